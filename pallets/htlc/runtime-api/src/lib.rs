@@ -0,0 +1,61 @@
+//! Runtime API definition for pallet-htlc.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+pub use pallet_htlc::{CannotCreateHtlc, HtlcMetrics, Stage, SwapCosts, SwapIntent, SwapRole};
+use sp_core::H256;
+use sp_runtime::DispatchResult;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	/// API to query the state of the HTLCs and swap intents of the pallet.
+	pub trait HtlcApi<AccountId, Balance, BlockNumber, Asset>
+	where
+		AccountId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+		Asset: Codec,
+	{
+		/// Aggregate health metrics of the escrows, e.g. for monitoring the
+		/// solvency of the held funds.
+		fn metrics() -> HtlcMetrics<Balance, BlockNumber>;
+
+		/// Check whether `who` can escrow `amount` and `safety_deposit` in a
+		/// new HTLC, or why not.
+		fn can_create_htlc(
+			who: AccountId,
+			amount: Balance,
+			safety_deposit: Balance,
+		) -> Result<(), CannotCreateHtlc<Balance>>;
+
+		/// Check whether `who` could withdraw the HTLC with the secret now, or
+		/// the error that the withdrawal would fail with.
+		fn validate_withdraw(htlc_id: H256, who: AccountId, secret: Vec<u8>) -> DispatchResult;
+
+		/// Check whether `who` could cancel the HTLC now, or the error that the
+		/// cancellation would fail with.
+		fn validate_cancel(htlc_id: H256, who: AccountId) -> DispatchResult;
+
+		/// The current stage of an active HTLC.
+		fn htlc_stage(htlc_id: H256) -> Option<Stage>;
+
+		/// A page of up to `limit` intents that resolvers can fill, with their
+		/// keys, following the intent `start_key`, so that resolvers can
+		/// rebuild their order books without replaying the events.
+		fn active_intents(
+			start_key: Option<H256>,
+			limit: u32,
+		) -> Vec<(H256, SwapIntent<AccountId, Balance, BlockNumber>)>;
+
+		/// The deposits and fees that `role` pays for a swap of `amount` of
+		/// the native token in the worst case, besides the amount itself,
+		/// with a maker that converts the settled funds into `asset` if any.
+		/// The transaction fees exclude the signatures, extensions and tips.
+		fn estimate_swap_costs(
+			amount: Balance,
+			asset: Option<Asset>,
+			role: SwapRole,
+		) -> SwapCosts<Balance>;
+	}
+}
@@ -0,0 +1,120 @@
+//! Price curves of the swap intents. Like the Dutch auctions of 1inch Fusion,
+//! the amount that the resolvers pay on the destination chain starts high and
+//! decays to the `dst_amount` of the intent, so that the first resolver that
+//! finds the price worth it fills the intent.
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{traits::ConstU32, BoundedVec};
+use scale_info::TypeInfo;
+use sp_runtime::{
+	helpers_128bit::multiply_by_rational_with_rounding,
+	traits::{AtLeast32BitUnsigned, Zero},
+	Rounding, SaturatedConversion,
+};
+
+/// Maximum number of points of a piecewise curve.
+pub const MAX_AUCTION_POINTS: u32 = 8;
+
+/// Points `(blocks after the start, amount)` of a piecewise curve.
+pub type AuctionPoints<Balance, BlockNumber> =
+	BoundedVec<(BlockNumber, Balance), ConstU32<MAX_AUCTION_POINTS>>;
+
+/// Amount paid on the destination chain over the life of an intent.
+#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug, Default)]
+pub enum AuctionCurve<Balance, BlockNumber> {
+	/// The resolvers always pay the `dst_amount` of the intent.
+	#[default]
+	Fixed,
+	/// Decays linearly from `start_amount` at block `start` to the
+	/// `dst_amount` of the intent `duration` blocks later.
+	Linear { start: BlockNumber, duration: BlockNumber, start_amount: Balance },
+	/// Decays linearly between `points`, like the segments of a Fusion
+	/// auction, from `start_amount` at block `start` to the `dst_amount` of
+	/// the intent `duration` blocks later.
+	Piecewise {
+		start: BlockNumber,
+		duration: BlockNumber,
+		start_amount: Balance,
+		points: AuctionPoints<Balance, BlockNumber>,
+	},
+}
+
+impl<Balance, BlockNumber> AuctionCurve<Balance, BlockNumber>
+where
+	Balance: AtLeast32BitUnsigned + Copy,
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+	/// Number of points that the curve is evaluated over.
+	pub fn points(&self) -> u32 {
+		match self {
+			Self::Piecewise { points, .. } => points.len() as u32,
+			_ => 0,
+		}
+	}
+
+	/// Whether the curve never goes below `floor` and never goes up again.
+	pub fn is_valid(&self, floor: Balance) -> bool {
+		match self {
+			Self::Fixed => true,
+			Self::Linear { duration, start_amount, .. } =>
+				!duration.is_zero() && *start_amount >= floor,
+			Self::Piecewise { duration, start_amount, points, .. } => {
+				let mut previous = (Zero::zero(), *start_amount);
+				for &(offset, amount) in points.iter().chain([(*duration, floor)].iter()) {
+					if offset <= previous.0 || amount > previous.1 {
+						return false;
+					}
+					previous = (offset, amount);
+				}
+				true
+			},
+		}
+	}
+
+	/// Amount paid at block `now` by a curve that ends at `floor`.
+	pub fn amount_at(&self, floor: Balance, now: BlockNumber) -> Balance {
+		match self {
+			Self::Fixed => floor,
+			Self::Linear { start, duration, start_amount } => interpolate(
+				(Zero::zero(), *start_amount),
+				(*duration, floor),
+				now.saturating_sub(*start),
+			)
+			.unwrap_or(floor),
+			Self::Piecewise { start, duration, start_amount, points } => {
+				let elapsed = now.saturating_sub(*start);
+				let mut previous = (Zero::zero(), *start_amount);
+				for &point in points.iter().chain([(*duration, floor)].iter()) {
+					if let Some(amount) = interpolate(previous, point, elapsed) {
+						return amount;
+					}
+					previous = point;
+				}
+				floor
+			},
+		}
+	}
+}
+
+/// Amount of the segment from `from` to `to` at `at`, rounded up in favour of
+/// the maker, or `None` if `at` is past the end of the segment.
+fn interpolate<Balance, BlockNumber>(
+	from: (BlockNumber, Balance),
+	to: (BlockNumber, Balance),
+	at: BlockNumber,
+) -> Option<Balance>
+where
+	Balance: AtLeast32BitUnsigned + Copy,
+	BlockNumber: AtLeast32BitUnsigned + Copy,
+{
+	if at >= to.0 {
+		return None;
+	}
+	let drop = from.1.saturating_sub(to.1).saturated_into::<u128>();
+	let remaining = (to.0 - at).saturated_into::<u128>();
+	let span = to.0.saturating_sub(from.0).saturated_into::<u128>();
+	let decayed = multiply_by_rational_with_rounding(drop, remaining, span, Rounding::Up)
+		.unwrap_or(drop)
+		.saturated_into::<Balance>();
+	Some(to.1.saturating_add(decayed))
+}
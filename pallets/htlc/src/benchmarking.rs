@@ -4,31 +4,605 @@ use super::*;
 
 #[allow(unused)]
 use crate::Pallet as Htlc;
+use crate::{
+	auction::{AuctionCurve, AuctionPoints, MAX_AUCTION_POINTS},
+	merkle::{self, MAX_PROOF_DEPTH},
+};
 use frame_benchmarking::v2::*;
-use frame_system::RawOrigin;
+use frame_support::{
+	traits::{
+		fungible::{Inspect, Mutate},
+		ConstU32, Get,
+	},
+	BoundedVec,
+};
+use frame_system::{pallet_prelude::BlockNumberFor, RawOrigin};
+use sp_core::{H160, H256};
+use sp_runtime::traits::{Saturating, Zero};
+
+/// A chain with SHA-256 hashlocks, the slower of the hash functions, and no
+/// timelock margins.
+const BENCH_CHAIN: ChainId = ChainId::MAX;
+
+fn set_up_chain<T: Config>() {
+	ChainProfiles::<T>::insert(
+		BENCH_CHAIN,
+		ChainProfile {
+			hash_function: HashFunction::Sha256,
+			address_format: AddressFormat::Any,
+			min_timelock_margin: Zero::zero(),
+			decimals: T::NativeDecimals::get(),
+		},
+	);
+}
+
+/// An account with the funds for any number of swaps.
+fn funded_account<T: Config>(name: &'static str) -> T::AccountId {
+	let who: T::AccountId = account(name, 0, 0);
+	let unit = T::NativeBalance::minimum_balance().max(1u32.into());
+	T::NativeBalance::set_balance(&who, unit.saturating_mul(u32::MAX.into()));
+	who
+}
+
+/// The amount of the swaps, well above the existential deposit.
+fn swap_amount<T: Config>() -> BalanceOf<T> {
+	T::NativeBalance::minimum_balance()
+		.max(1u32.into())
+		.saturating_mul(1_000u32.into())
+}
+
+/// The hashlock of the secret `index`.
+fn hashlock(index: u32) -> H256 {
+	HashFunction::Sha256.hash(&index.to_le_bytes())
+}
+
+/// An intent of `maker` on the benchmark chain, which resolvers may fill in
+/// part and which pays along an auction curve of `MAX_AUCTION_POINTS`.
+fn swap_intent<T: Config>(maker: &T::AccountId, index: u32) -> SwapIntentOf<T> {
+	let amount = swap_amount::<T>();
+	let points: AuctionPoints<BalanceOf<T>, BlockNumberFor<T>> = (1..=MAX_AUCTION_POINTS)
+		.map(|i| (i.into(), amount.saturating_add((MAX_AUCTION_POINTS + 1 - i).into())))
+		.collect::<Vec<_>>()
+		.try_into()
+		.expect("at most MAX_AUCTION_POINTS points; qed");
+	SwapIntent {
+		hashlock: hashlock(index),
+		maker: maker.clone(),
+		src_amount: amount,
+		dst_amount: amount,
+		dst_address: DstAddress::Evm(H160::repeat_byte(0x42)),
+		dst_chain: BENCH_CHAIN,
+		timeout_after_block: Some(10_000u32.into()),
+		nonce: index.into(),
+		auction: AuctionCurve::Piecewise {
+			start: 0u32.into(),
+			duration: (MAX_AUCTION_POINTS + 1).into(),
+			start_amount: amount.saturating_add((MAX_AUCTION_POINTS + 1).into()),
+			points,
+		},
+		flags: IntentFlags { allow_partial_fills: true, ..Default::default() },
+		epoch: 0,
+		denied_resolvers: Default::default(),
+		dst_decimals: None,
+		min_fill_amount: Zero::zero(),
+	}
+}
+
+/// Timelocks deployed at the current block, in the private withdrawal, whose
+/// later stages begin `offset` blocks after those of offset zero, so that
+/// HTLCs with different offsets are queued at different blocks.
+fn timelocks<T: Config>(offset: u32) -> Timelocks<BlockNumberFor<T>> {
+	let now = frame_system::Pallet::<T>::block_number();
+	Timelocks {
+		deployed_at: now,
+		withdrawal_after: now,
+		public_withdrawal_after: now.saturating_add((10 + offset).into()),
+		cancellation_after: now.saturating_add((100 + offset).into()),
+	}
+}
+
+/// The immutables of a destination HTLC of the order `order_hash` locked
+/// with `hashlock` from `taker` to `maker`.
+fn dst_immutables<T: Config>(
+	taker: &T::AccountId,
+	maker: &T::AccountId,
+	order_hash: H256,
+	hashlock: H256,
+	offset: u32,
+) -> ImmutablesOf<T> {
+	Immutables {
+		order_hash,
+		hashlock,
+		hash_function: HashFunction::Sha256,
+		maker: maker.clone(),
+		taker: taker.clone(),
+		amount: swap_amount::<T>(),
+		safety_deposit: T::MinSafetyDeposit::get(),
+		timelocks: timelocks::<T>(offset),
+	}
+}
+
+/// Create a destination HTLC of the order `order_hash` locked with
+/// `hashlock` from `taker` to `maker`, returning its id.
+fn escrow_dst_htlc<T: Config>(
+	taker: &T::AccountId,
+	maker: &T::AccountId,
+	order_hash: H256,
+	hashlock: H256,
+	offset: u32,
+) -> H256 {
+	let immutables = dst_immutables::<T>(taker, maker, order_hash, hashlock, offset);
+	Htlc::<T>::create_dst_htlc(
+		RawOrigin::Signed(taker.clone()).into(),
+		immutables.clone(),
+		immutables.timelocks.cancellation_after,
+		BENCH_CHAIN,
+	)
+	.expect("the taker is funded and the timelocks are valid; qed");
+	Htlc::<T>::hash_immutables(&immutables)
+}
+
+/// Note that `h` other HTLCs share `hashlock`.
+fn share_hashlock<T: Config>(hashlock: H256, h: u32) {
+	let htlc_ids: BoundedVec<H256, T::MaxHtlcsPerHashlock> = (0..h)
+		.map(|i| H256::from_low_u64_be(i.into()))
+		.collect::<Vec<_>>()
+		.try_into()
+		.expect("fewer than MaxHtlcsPerHashlock HTLCs; qed");
+	HashlockIndex::<T>::insert(hashlock, htlc_ids);
+}
+
+/// Add `f` legs to the order `order_hash`, recording it if it isn't yet.
+fn add_order_legs<T: Config>(order_hash: H256, f: u32) {
+	let legs: BoundedVec<H256, ConstU32<MAX_ORDER_LEGS>> = (0..f)
+		.map(|i| H256::from_low_u64_be(i.into()))
+		.collect::<Vec<_>>()
+		.try_into()
+		.expect("fewer than MAX_ORDER_LEGS legs; qed");
+	CrossChainOrders::<T>::mutate(order_hash, |record| match record {
+		Some(record) => record.htlcs = legs,
+		None =>
+			*record = Some(OrderRecord {
+				intent: None,
+				htlcs: legs,
+				counterparts: Default::default(),
+				status: OrderStatus::Open,
+			}),
+	});
+}
+
+/// Fill all of an intent, locked with a secret of `s` bytes and whose order
+/// has `f` other legs, with a source HTLC in its private withdrawal,
+/// returning its taker, its immutables and the secret.
+fn filled_intent<T: Config>(s: u32, f: u32) -> (T::AccountId, ImmutablesOf<T>, Vec<u8>) {
+	set_up_chain::<T>();
+	let maker = funded_account::<T>("maker");
+	let taker = funded_account::<T>("taker");
+	let secret = vec![0x42u8; s as usize];
+	let intent =
+		SwapIntent { hashlock: HashFunction::Sha256.hash(&secret), ..swap_intent::<T>(&maker, 0) };
+	Htlc::<T>::create_swap_intent(RawOrigin::Signed(maker.clone()).into(), intent)
+		.expect("the maker is funded and the intent is valid; qed");
+	let intent_key = Htlc::<T>::intent_key(&maker, 0);
+	add_order_legs::<T>(intent_key, f);
+	Htlc::<T>::create_src_htlc(
+		RawOrigin::Signed(taker.clone()).into(),
+		maker,
+		0,
+		timelocks::<T>(0),
+		T::MinSafetyDeposit::get(),
+	)
+	.expect("the taker is funded and the timelocks are valid; qed");
+	let htlc_id = match SwapIntents::<T>::get(intent_key).map(|stored| stored.status) {
+		Some(IntentStatus::InProgress { htlc_id, .. }) => htlc_id,
+		_ => panic!("the intent is filled; qed"),
+	};
+	let immutables = Htlcs::<T>::get(htlc_id).expect("the HTLC is created; qed").immutables;
+	(taker, immutables, secret)
+}
+
+/// Note that `r` other secrets were revealed in the current block.
+fn reveal_secrets<T: Config>(r: u32) {
+	let revealed: BoundedVec<(H256, H256), T::MaxRevealedSecretsPerBlock> = (0..r)
+		.map(|i| (H256::from_low_u64_be(i.into()), H256::zero()))
+		.collect::<Vec<_>>()
+		.try_into()
+		.expect("fewer than MaxRevealedSecretsPerBlock secrets; qed");
+	RevealedSecrets::<T>::insert(frame_system::Pallet::<T>::block_number(), revealed);
+}
+
+/// Assert that the HTLC with the immutables was withdrawn.
+fn assert_completed<T: Config>(immutables: &ImmutablesOf<T>) {
+	let htlc_id = Htlc::<T>::hash_immutables(immutables);
+	assert!(Htlcs::<T>::get(htlc_id).is_none_or(|htlc| htlc.status == HtlcStatus::Completed));
+}
 
 #[benchmarks]
 mod benchmarks {
 	use super::*;
 
+	/// Withdraw a source HTLC with a secret of `s` bytes, in a block in which
+	/// `r` other secrets were revealed, completing its intent and the order
+	/// of `f` other legs.
+	#[benchmark]
+	fn withdraw(
+		s: Linear<0, MAX_SECRET_LEN>,
+		r: Linear<0, { T::MaxRevealedSecretsPerBlock::get() - 1 }>,
+		f: Linear<0, { MAX_ORDER_LEGS - 1 }>,
+	) {
+		let (taker, immutables, secret) = filled_intent::<T>(s, f);
+		reveal_secrets::<T>(r);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(taker), immutables.clone(), secret);
+
+		assert_completed::<T>(&immutables);
+	}
+
+	/// `withdraw` publicly, rewarding the caller.
+	#[benchmark]
+	fn public_withdraw(
+		s: Linear<0, MAX_SECRET_LEN>,
+		r: Linear<0, { T::MaxRevealedSecretsPerBlock::get() - 1 }>,
+		f: Linear<0, { MAX_ORDER_LEGS - 1 }>,
+	) {
+		let (_, immutables, secret) = filled_intent::<T>(s, f);
+		frame_system::Pallet::<T>::set_block_number(immutables.timelocks.public_withdrawal_after);
+		reveal_secrets::<T>(r);
+		let caller = funded_account::<T>("caller");
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(caller), immutables.clone(), secret);
+
+		assert_completed::<T>(&immutables);
+	}
+
+	/// Compute the root of a Merkle tree from a leaf and a proof of `p` nodes
+	/// with SHA-256, the slower of the hash functions.
+	#[benchmark]
+	fn verify_proof(p: Linear<0, MAX_PROOF_DEPTH>) {
+		let proof: Vec<H256> = (0..p).map(|i| H256::from_low_u64_be(i.into())).collect();
+		let leaf = merkle::leaf(HashFunction::Sha256, 0, H256::repeat_byte(0x42));
+		let root;
+
+		#[block]
+		{
+			root = merkle::root(HashFunction::Sha256, leaf, &proof);
+		}
+
+		assert_eq!(root == leaf, p == 0);
+	}
+
+	/// Fill all of an intent with a source HTLC, whose hashlock `h` other
+	/// HTLCs share and whose order has `f` other legs.
+	#[benchmark]
+	fn create_src_htlc(
+		h: Linear<0, { T::MaxHtlcsPerHashlock::get() - 1 }>,
+		f: Linear<0, { MAX_ORDER_LEGS - 1 }>,
+	) {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		let resolver = funded_account::<T>("resolver");
+		let intent = swap_intent::<T>(&maker, 0);
+		let hashlock = intent.hashlock;
+		Htlc::<T>::create_swap_intent(RawOrigin::Signed(maker.clone()).into(), intent)
+			.expect("the maker is funded and the intent is valid; qed");
+		add_order_legs::<T>(Htlc::<T>::intent_key(&maker, 0), f);
+		share_hashlock::<T>(hashlock, h);
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(resolver.clone()),
+			maker,
+			0,
+			timelocks::<T>(0),
+			T::MinSafetyDeposit::get(),
+		);
+
+		assert_eq!(ResolverIntents::<T>::get(&resolver), 1);
+	}
+
+	/// Cancel a source HTLC that expired unbound to the destination leg,
+	/// forfeiting its safety deposit, and ending its intent and the order of
+	/// `f` other legs.
 	#[benchmark]
-	fn do_something() {
-		let value = 100u32.into();
-		let caller: T::AccountId = whitelisted_caller();
+	fn cancel(f: Linear<0, { MAX_ORDER_LEGS - 1 }>) {
+		let (taker, immutables, _) = filled_intent::<T>(0, f);
+		frame_system::Pallet::<T>::set_block_number(immutables.timelocks.cancellation_after);
+
 		#[extrinsic_call]
-		do_something(RawOrigin::Signed(caller), value);
+		_(RawOrigin::Signed(taker), immutables.clone());
 
-		assert_eq!(Something::<T>::get(), Some(value));
+		let htlc_id = Htlc::<T>::hash_immutables(&immutables);
+		assert!(Htlcs::<T>::get(htlc_id).is_none_or(|htlc| htlc.status == HtlcStatus::Cancelled));
 	}
 
+	/// Reserve an intent whose order has `f` other legs.
 	#[benchmark]
-	fn cause_error() {
-		Something::<T>::put(100u32);
-		let caller: T::AccountId = whitelisted_caller();
+	fn take_intent(f: Linear<0, { MAX_ORDER_LEGS - 1 }>) {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		let resolver = funded_account::<T>("resolver");
+		Htlc::<T>::create_swap_intent(
+			RawOrigin::Signed(maker.clone()).into(),
+			swap_intent::<T>(&maker, 0),
+		)
+		.expect("the maker is funded and the intent is valid; qed");
+		let intent_key = Htlc::<T>::intent_key(&maker, 0);
+		add_order_legs::<T>(intent_key, f);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(resolver.clone()), maker, 0);
+
+		assert_eq!(
+			SwapIntents::<T>::get(intent_key).map(|stored| stored.status),
+			Some(IntentStatus::Reserved { resolver })
+		);
+	}
+
+	/// Create a destination HTLC escrowing the swap amount of the taker,
+	/// whose hashlock `h` other HTLCs share and whose order has `f` other
+	/// legs.
+	#[benchmark]
+	fn create_dst_htlc(
+		h: Linear<0, { T::MaxHtlcsPerHashlock::get() - 1 }>,
+		f: Linear<0, { MAX_ORDER_LEGS - 1 }>,
+	) {
+		set_up_chain::<T>();
+		let maker: T::AccountId = account("maker", 0, 0);
+		let taker = funded_account::<T>("taker");
+		let immutables = dst_immutables::<T>(&taker, &maker, H256::repeat_byte(1), hashlock(0), 0);
+		share_hashlock::<T>(immutables.hashlock, h);
+		add_order_legs::<T>(immutables.order_hash, f);
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(taker),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after,
+			BENCH_CHAIN,
+		);
+
+		assert!(Htlcs::<T>::contains_key(Htlc::<T>::hash_immutables(&immutables)));
+	}
+
+	/// `create_dst_htlc` drawing the swap amount from the vault.
+	#[benchmark]
+	fn create_vault_dst_htlc(
+		h: Linear<0, { T::MaxHtlcsPerHashlock::get() - 1 }>,
+		f: Linear<0, { MAX_ORDER_LEGS - 1 }>,
+	) {
+		set_up_chain::<T>();
+		let maker: T::AccountId = account("maker", 0, 0);
+		let taker = funded_account::<T>("taker");
+		let provider = funded_account::<T>("provider");
+		let liquidity = swap_amount::<T>().saturating_mul(1_000u32.into());
+		Htlc::<T>::do_vault_deposit(provider, liquidity).expect("the provider is funded; qed");
+		Resolvers::<T>::insert(&taker, liquidity);
+		let immutables = dst_immutables::<T>(&taker, &maker, H256::repeat_byte(1), hashlock(0), 0);
+		share_hashlock::<T>(immutables.hashlock, h);
+		add_order_legs::<T>(immutables.order_hash, f);
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(taker),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after,
+			BENCH_CHAIN,
+		);
+
+		assert!(VaultDraws::<T>::contains_key(Htlc::<T>::hash_immutables(&immutables)));
+	}
+
+	/// Set the conversion of the swap amount of a destination HTLC. The
+	/// assets are opaque to the pallet, so the conversion is removed, which
+	/// writes the same key.
+	#[benchmark]
+	fn set_settlement_conversion() {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		let taker = funded_account::<T>("taker");
+		let htlc_id = escrow_dst_htlc::<T>(&taker, &maker, H256::repeat_byte(1), hashlock(0), 0);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(maker), htlc_id, None);
+
+		assert!(!SettlementConversions::<T>::contains_key(htlc_id));
+	}
+
+	/// Take a block of the auto-cancel queue holding `q` HTLCs.
+	#[benchmark]
+	fn auto_cancel_queue_block(q: Linear<0, { T::MaxCancellationsPerBlock::get() }>) {
+		let block: BlockNumberFor<T> = 1u32.into();
+		let queue: BoundedVec<H256, T::MaxCancellationsPerBlock> = (0..q)
+			.map(|i| H256::from_low_u64_be(i.into()))
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("at most MaxCancellationsPerBlock HTLCs; qed");
+		HtlcsByCancellationBlock::<T>::insert(block, queue);
+		let queue;
+
+		#[block]
+		{
+			queue = HtlcsByCancellationBlock::<T>::take(block);
+		}
+
+		assert_eq!(queue.len(), q as usize);
+	}
+
+	/// Take the `q` stage transitions queued at a block.
+	#[benchmark]
+	fn stage_events_block(q: Linear<0, { T::MaxCancellationsPerBlock::get() }>) {
+		let block: BlockNumberFor<T> = 1u32.into();
+		let transitions: BoundedVec<(H256, Stage), T::MaxCancellationsPerBlock> = (0..q)
+			.map(|i| (H256::from_low_u64_be(i.into()), Stage::PublicCancellation))
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("at most MaxCancellationsPerBlock transitions; qed");
+		StageTransitions::<T>::insert(block, transitions);
+		let transitions;
+
+		#[block]
+		{
+			transitions = StageTransitions::<T>::take(block);
+		}
+
+		assert_eq!(transitions.len(), q as usize);
+	}
+
+	/// Create a ladder of `n` intents, holding their funds at once.
+	#[benchmark]
+	fn create_swap_intents(n: Linear<1, { MAX_BATCH_INTENTS.min(T::MaxIntentsPerMaker::get()) }>) {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		let intents: BoundedVec<SwapIntentOf<T>, ConstU32<MAX_BATCH_INTENTS>> = (0..n)
+			.map(|i| swap_intent::<T>(&maker, i))
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("at most MAX_BATCH_INTENTS intents; qed");
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(maker.clone()), intents, 0);
+
+		assert_eq!(MakerIntents::<T>::decode_len(&maker), Some(n as usize));
+	}
+
+	/// Invalidate the intents of a maker with `n` open intents, cancelling
+	/// all of them.
+	#[benchmark]
+	fn cancel_all_intents(n: Linear<0, { T::MaxIntentsPerMaker::get() }>) {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		for i in 0..n {
+			Htlc::<T>::create_swap_intent(
+				RawOrigin::Signed(maker.clone()).into(),
+				swap_intent::<T>(&maker, i),
+			)
+			.expect("the maker is funded and the intent is valid; qed");
+		}
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(maker.clone()), 1);
+
+		assert!(!MakerIntents::<T>::contains_key(&maker));
+	}
+
+	/// Fill half of each of `n` intents along the last segment of their
+	/// auction curves, where each intent was already filled `f` times.
+	#[benchmark]
+	fn fill_intents(
+		n: Linear<
+			1,
+			{
+				MAX_BATCH_FILLS
+					.min(T::MaxIntentsPerMaker::get())
+					.min(T::MaxIntentsPerResolver::get())
+					.min(T::MaxCancellationsPerBlock::get())
+			},
+		>,
+		f: Linear<0, { MAX_ORDER_LEGS - 1 }>,
+	) {
+		set_up_chain::<T>();
+		frame_system::Pallet::<T>::set_block_number(MAX_AUCTION_POINTS.into());
+		let maker = funded_account::<T>("maker");
+		let resolver = funded_account::<T>("resolver");
+		let mut fills = BoundedVec::<_, ConstU32<MAX_BATCH_FILLS>>::new();
+		for i in 0..n {
+			Htlc::<T>::create_swap_intent(
+				RawOrigin::Signed(maker.clone()).into(),
+				swap_intent::<T>(&maker, i),
+			)
+			.expect("the maker is funded and the intent is valid; qed");
+			add_order_legs::<T>(Htlc::<T>::intent_key(&maker, i.into()), f);
+			let half = swap_amount::<T>() / 2u32.into();
+			fills
+				.try_push((maker.clone(), i.into(), half))
+				.expect("at most MAX_BATCH_FILLS; qed");
+		}
+
+		#[extrinsic_call]
+		_(
+			RawOrigin::Signed(resolver.clone()),
+			fills,
+			timelocks::<T>(0),
+			T::MinSafetyDeposit::get(),
+		);
+
+		assert_eq!(ResolverIntents::<T>::get(&resolver), n);
+	}
+
+	/// Cancel `n` expired destination HTLCs of the caller.
+	#[benchmark]
+	fn cancel_many(n: Linear<1, { MAX_BATCH_CANCELS.min(T::MaxCancellationsPerBlock::get()) }>) {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		let taker = funded_account::<T>("taker");
+		let htlc_ids: BoundedVec<H256, ConstU32<MAX_BATCH_CANCELS>> = (0..n)
+			.map(|i| {
+				escrow_dst_htlc::<T>(
+					&taker,
+					&maker,
+					H256::from_low_u64_be(i.into()),
+					hashlock(i),
+					i,
+				)
+			})
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("at most MAX_BATCH_CANCELS HTLCs; qed");
+		frame_system::Pallet::<T>::set_block_number(timelocks::<T>(n).cancellation_after);
+
+		#[extrinsic_call]
+		_(RawOrigin::Signed(taker), htlc_ids.clone());
+
+		for htlc_id in htlc_ids {
+			assert!(
+				Htlcs::<T>::get(htlc_id).is_none_or(|htlc| htlc.status == HtlcStatus::Cancelled)
+			);
+		}
+	}
+
+	/// Withdraw `n` destination HTLCs of an order with one secret of `s`
+	/// bytes.
+	#[benchmark]
+	fn withdraw_all_with_secret(
+		n: Linear<
+			1,
+			{
+				MAX_BATCH_WITHDRAWALS
+					.min(T::MaxHtlcsPerHashlock::get())
+					.min(T::MaxCancellationsPerBlock::get())
+			},
+		>,
+		s: Linear<0, MAX_SECRET_LEN>,
+	) {
+		set_up_chain::<T>();
+		let maker = funded_account::<T>("maker");
+		let taker = funded_account::<T>("taker");
+		let secret = vec![0x42u8; s as usize];
+		let order_hash = H256::repeat_byte(0x42);
+		let htlc_ids: BoundedVec<H256, ConstU32<MAX_BATCH_WITHDRAWALS>> = (0..n)
+			.map(|i| {
+				escrow_dst_htlc::<T>(
+					&taker,
+					&maker,
+					order_hash,
+					HashFunction::Sha256.hash(&secret),
+					i,
+				)
+			})
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("at most MAX_BATCH_WITHDRAWALS HTLCs; qed");
+
 		#[extrinsic_call]
-		cause_error(RawOrigin::Signed(caller));
+		_(RawOrigin::Signed(taker), secret, htlc_ids.clone());
 
-		assert_eq!(Something::<T>::get(), Some(101u32));
+		for htlc_id in htlc_ids {
+			assert!(
+				Htlcs::<T>::get(htlc_id).is_none_or(|htlc| htlc.status == HtlcStatus::Completed)
+			);
+		}
 	}
 
 	impl_benchmark_test_suite!(Htlc, crate::mock::new_test_ext(), crate::mock::Test);
@@ -0,0 +1,34 @@
+//! Conversions of the settled funds of the makers into local assets, e.g.
+//! through the pools of `pallet-asset-conversion`.
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::DispatchError;
+
+/// Asset into which the maker asked to convert the swap amount of a
+/// destination HTLC once it is withdrawn, and the least it accepts.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct SettlementConversion<Asset, Balance> {
+	pub asset: Asset,
+	pub min_out: Balance,
+}
+
+/// Converter of the native tokens paid out by the settlements.
+pub trait ConvertSettlement<AccountId, Balance, Asset> {
+	/// Swap `amount` of the native tokens of `who` into at least `min_out` of
+	/// `asset`, returning the amount received. The runtime implements it with
+	/// `Swap::swap_exact_tokens_for_tokens` of `pallet-asset-conversion`.
+	fn convert(
+		who: &AccountId,
+		amount: Balance,
+		asset: &Asset,
+		min_out: Balance,
+	) -> Result<Balance, DispatchError>;
+}
+
+/// Convert nothing, so the makers keep the native tokens.
+impl<AccountId, Balance, Asset> ConvertSettlement<AccountId, Balance, Asset> for () {
+	fn convert(_: &AccountId, _: Balance, _: &Asset, _: Balance) -> Result<Balance, DispatchError> {
+		Err(DispatchError::Unavailable)
+	}
+}
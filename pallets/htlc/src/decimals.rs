@@ -0,0 +1,30 @@
+//! Amounts of tokens with different decimals. The intents quote their
+//! `dst_amount` in the smallest unit of the token paid on the destination
+//! chain, e.g. with 6 decimals for USDC, while the native balances have the
+//! `NativeDecimals` of the runtime, so the amounts must be scaled before they
+//! are compared.
+
+use sp_runtime::traits::{AtLeast32BitUnsigned, Zero};
+
+/// Maximum number of decimals of an amount, as many as a `u128` holds.
+pub const MAX_DECIMALS: u8 = 38;
+
+/// `10^exponent`, unless it overflows the balance.
+fn power_of_ten<Balance: AtLeast32BitUnsigned>(exponent: u8) -> Option<Balance> {
+	(0..exponent).try_fold(Balance::one(), |power, _| power.checked_mul(&Balance::from(10u32)))
+}
+
+/// The `amount` with `from` decimals expressed with `to` decimals, rounded
+/// down, or `None` if it overflows the balance.
+pub fn normalize<Balance: AtLeast32BitUnsigned>(
+	amount: Balance,
+	from: u8,
+	to: u8,
+) -> Option<Balance> {
+	if to >= from {
+		amount.checked_mul(&power_of_ten(to - from)?)
+	} else {
+		// amounts smaller than the scale are worth nothing with `to` decimals
+		Some(power_of_ten(from - to).map_or_else(Zero::zero, |power| amount / power))
+	}
+}
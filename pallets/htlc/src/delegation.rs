@@ -0,0 +1,241 @@
+//! Registered resolvers and the stake delegated behind them. The delegated
+//! stake raises the credit limit of a resolver on the vault and its resolver
+//! tier; it is slashed whenever the resolver fails a fill or is slashed, and
+//! earns a share of the resolver earnings beyond its commission.
+
+use crate::{
+	BalanceOf, Config, CreditOf, DelegatedStakes, Delegations, Error, Event, HoldReason, Pallet,
+	ResolverCommissions, ResolverCooldowns, Resolvers, SwapIntentOf,
+};
+use codec::{Decode, Encode};
+use frame_support::{
+	ensure,
+	traits::{
+		fungible::{Balanced, BalancedHold, Mutate, MutateHold},
+		tokens::{Precision, Preservation},
+		Get, Imbalance,
+	},
+};
+use frame_system::pallet_prelude::OriginFor;
+use scale_info::TypeInfo;
+use sp_runtime::{traits::Zero, DispatchError, DispatchResult, Saturating};
+
+/// Stake of a delegator behind a resolver.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct Delegation<Balance, BlockNumber> {
+	/// Stake that backs the resolver.
+	pub bonded: Balance,
+	/// Stake undelegated and waiting for the `UndelegationDelay`.
+	pub unbonding: Balance,
+	/// Block from which the unbonding stake can be withdrawn.
+	pub unlock_at: BlockNumber,
+}
+
+/// Stake delegated to a resolver.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+pub struct DelegatedStake<Balance> {
+	/// Stake bonded by the delegators, excluding the unbonding stake.
+	pub bonded: Balance,
+	/// Number of delegators, bounded by the `MaxDelegators`.
+	pub delegators: u32,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Ensure that the origin is a registered resolver, signed or remote,
+	/// returning its account, like `origin::EnsureRegisteredResolver` but
+	/// with the `NotResolver` error.
+	pub fn ensure_registered_resolver(origin: OriginFor<T>) -> Result<T::AccountId, DispatchError> {
+		let who = Self::ensure_party(origin)?;
+		ensure!(Resolvers::<T>::contains_key(&who), Error::<T>::NotResolver);
+		Ok(who)
+	}
+
+	/// Ensure the maker did not deny `resolver` from filling the intent,
+	/// and that `resolver` is not cooling down.
+	pub(crate) fn ensure_resolver_allowed(
+		intent: &SwapIntentOf<T>,
+		resolver: &T::AccountId,
+	) -> DispatchResult {
+		ensure!(!intent.denied_resolvers.contains(resolver), Error::<T>::ResolverDenied);
+		let current_block = frame_system::Pallet::<T>::block_number();
+		ensure!(
+			ResolverCooldowns::<T>::get(resolver).is_none_or(|until| current_block >= until),
+			Error::<T>::ResolverCoolingDown
+		);
+		Ok(())
+	}
+
+	/// Bond `amount` of `delegator` behind a registered resolver.
+	pub(crate) fn do_delegate(
+		delegator: T::AccountId,
+		resolver: T::AccountId,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		ensure!(Resolvers::<T>::contains_key(&resolver), Error::<T>::NotResolver);
+		ensure!(!amount.is_zero(), Error::<T>::InsufficientDelegation);
+
+		let mut stake = DelegatedStakes::<T>::get(&resolver);
+		let mut delegation = match Delegations::<T>::get(&resolver, &delegator) {
+			Some(delegation) => delegation,
+			None => {
+				ensure!(stake.delegators < T::MaxDelegators::get(), Error::<T>::TooManyDelegators);
+				stake.delegators.saturating_inc();
+				Delegation {
+					bonded: Zero::zero(),
+					unbonding: Zero::zero(),
+					unlock_at: Zero::zero(),
+				}
+			},
+		};
+		T::NativeBalance::hold(&HoldReason::DelegatedStake.into(), &delegator, amount)
+			.map_err(|_| Error::<T>::InsufficientBalance)?;
+
+		delegation.bonded.saturating_accrue(amount);
+		stake.bonded.saturating_accrue(amount);
+		Delegations::<T>::insert(&resolver, &delegator, delegation);
+		DelegatedStakes::<T>::insert(&resolver, stake);
+
+		Self::deposit_event(Event::Delegated { delegator, resolver, amount });
+
+		Ok(())
+	}
+
+	/// Start unbonding `amount` of the stake of `delegator` behind a
+	/// resolver, restarting the delay of all its unbonding stake.
+	pub(crate) fn do_undelegate(
+		delegator: T::AccountId,
+		resolver: T::AccountId,
+		amount: BalanceOf<T>,
+	) -> DispatchResult {
+		let mut delegation = Delegations::<T>::get(&resolver, &delegator)
+			.ok_or(Error::<T>::InsufficientDelegation)?;
+		ensure!(
+			!amount.is_zero() && amount <= delegation.bonded,
+			Error::<T>::InsufficientDelegation
+		);
+
+		let unlock_at =
+			frame_system::Pallet::<T>::block_number().saturating_add(T::UndelegationDelay::get());
+		delegation.bonded.saturating_reduce(amount);
+		delegation.unbonding.saturating_accrue(amount);
+		delegation.unlock_at = unlock_at;
+		Delegations::<T>::insert(&resolver, &delegator, delegation);
+		DelegatedStakes::<T>::mutate(&resolver, |stake| stake.bonded.saturating_reduce(amount));
+
+		Self::deposit_event(Event::Undelegated { delegator, resolver, amount, unlock_at });
+
+		Ok(())
+	}
+
+	/// Release the unbonded stake of `delegator` behind a resolver, dropping
+	/// the delegation once nothing is bonded.
+	pub(crate) fn do_withdraw_undelegated(
+		delegator: T::AccountId,
+		resolver: T::AccountId,
+	) -> DispatchResult {
+		let mut delegation = Delegations::<T>::get(&resolver, &delegator)
+			.ok_or(Error::<T>::InsufficientDelegation)?;
+		ensure!(!delegation.unbonding.is_zero(), Error::<T>::InsufficientDelegation);
+		ensure!(
+			frame_system::Pallet::<T>::block_number() >= delegation.unlock_at,
+			Error::<T>::DelegationUnbonding
+		);
+
+		let amount = sp_std::mem::take(&mut delegation.unbonding);
+		T::NativeBalance::release(
+			&HoldReason::DelegatedStake.into(),
+			&delegator,
+			amount,
+			Precision::Exact,
+		)?;
+		if delegation.bonded.is_zero() {
+			Delegations::<T>::remove(&resolver, &delegator);
+			DelegatedStakes::<T>::mutate_exists(&resolver, |maybe_stake| {
+				if let Some(stake) = maybe_stake {
+					stake.delegators.saturating_dec();
+					if stake.delegators == 0 {
+						*maybe_stake = None;
+					}
+				}
+			});
+		} else {
+			Delegations::<T>::insert(&resolver, &delegator, delegation);
+		}
+
+		Self::deposit_event(Event::DelegationWithdrawn { delegator, resolver, amount });
+
+		Ok(())
+	}
+
+	/// Keep the resolver that failed a fill or was slashed from taking or
+	/// filling intents for the `ResolverCooldown`, and slash its
+	/// delegators.
+	pub(crate) fn penalize_resolver(resolver: &T::AccountId) {
+		let until =
+			frame_system::Pallet::<T>::block_number().saturating_add(T::ResolverCooldown::get());
+		ResolverCooldowns::<T>::insert(resolver, until);
+		Self::deposit_event(Event::ResolverCooldownStarted { resolver: resolver.clone(), until });
+
+		Self::slash_delegators(resolver);
+	}
+
+	/// Slash the `DelegationSlash` of the bonded and unbonding stake of
+	/// each delegator of the resolver into the insurance fund, or burn it
+	/// if the fund cannot receive it.
+	fn slash_delegators(resolver: &T::AccountId) {
+		let share = T::DelegationSlash::get();
+		let mut slashed = CreditOf::<T>::zero();
+		let mut bonded_slashed = BalanceOf::<T>::zero();
+		for (delegator, mut delegation) in Delegations::<T>::iter_prefix(resolver) {
+			let bonded = share.mul_floor(delegation.bonded);
+			let unbonding = share.mul_floor(delegation.unbonding);
+			let (credit, _) = T::NativeBalance::slash(
+				&HoldReason::DelegatedStake.into(),
+				&delegator,
+				bonded.saturating_add(unbonding),
+			);
+			slashed.subsume(credit);
+			delegation.bonded.saturating_reduce(bonded);
+			delegation.unbonding.saturating_reduce(unbonding);
+			bonded_slashed.saturating_accrue(bonded);
+			Delegations::<T>::insert(resolver, &delegator, delegation);
+		}
+		if slashed.peek().is_zero() {
+			return;
+		}
+		DelegatedStakes::<T>::mutate(resolver, |stake| {
+			stake.bonded.saturating_reduce(bonded_slashed)
+		});
+
+		let amount = slashed.peek();
+		let _ = T::NativeBalance::resolve(&Self::insurance_account(), slashed);
+		Self::deposit_event(Event::DelegatorsSlashed { resolver: resolver.clone(), amount });
+	}
+
+	/// Share `amount` that the resolver earned, beyond its commission,
+	/// with its delegators in proportion to their bonded stake. The shares
+	/// that cannot be paid stay with the resolver.
+	pub(crate) fn share_earnings(resolver: &T::AccountId, amount: BalanceOf<T>) {
+		let Some(commission) = ResolverCommissions::<T>::get(resolver) else { return };
+		let stake = DelegatedStakes::<T>::get(resolver);
+		if stake.bonded.is_zero() {
+			return;
+		}
+
+		let shared = amount.saturating_sub(commission.mul_ceil(amount));
+		let mut paid = BalanceOf::<T>::zero();
+		for (delegator, delegation) in Delegations::<T>::iter_prefix(resolver) {
+			let share = Self::mul_div(shared, delegation.bonded, stake.bonded);
+			if !share.is_zero() &&
+				T::NativeBalance::transfer(resolver, &delegator, share, Preservation::Preserve)
+					.is_ok()
+			{
+				paid.saturating_accrue(share);
+			}
+		}
+
+		if !paid.is_zero() {
+			Self::deposit_event(Event::EarningsShared { resolver: resolver.clone(), amount: paid });
+		}
+	}
+}
@@ -0,0 +1,23 @@
+//! Delivery of the settled funds of the makers to beneficiaries on other
+//! chains, e.g. with a reserve transfer or a teleport over XCM.
+
+use sp_runtime::DispatchError;
+
+/// Deliverer of the native tokens paid out by the settlements.
+pub trait DeliverSettlement<AccountId, Balance, Beneficiary> {
+	/// Send `amount` of the native tokens of `who` to `beneficiary`. The
+	/// runtime implements it with the transfers of `pallet-xcm`, taking a
+	/// `VersionedLocation` as the beneficiary.
+	fn deliver(
+		who: &AccountId,
+		amount: Balance,
+		beneficiary: &Beneficiary,
+	) -> Result<(), DispatchError>;
+}
+
+/// Deliver nothing, so the makers keep the native tokens.
+impl<AccountId, Balance, Beneficiary> DeliverSettlement<AccountId, Balance, Beneficiary> for () {
+	fn deliver(_: &AccountId, _: Balance, _: &Beneficiary) -> Result<(), DispatchError> {
+		Err(DispatchError::Unavailable)
+	}
+}
@@ -0,0 +1,454 @@
+//! Simulation of the `EscrowSrc` and `EscrowDst` contracts of the 1inch
+//! cross-chain swaps on an EVM chain, driven alongside the mock runtime so
+//! that both legs of a swap, the propagation of the secret and the races of
+//! the timelocks are covered by deterministic tests. Both chains share the
+//! block number of the mock as their clock, and the escrows hash the secrets
+//! with the `HashFunction` of the counterpart chain profile.
+
+use crate::{mock::*, *};
+use frame_support::assert_ok;
+use sp_core::{blake2_256, H160, H256};
+use sp_std::collections::btree_map::BTreeMap;
+
+const ALICE: u64 = 1;
+const RESOLVER_BOB: u64 = 2;
+const CHARLIE: u64 = 3;
+const SAFETY_DEPOSIT: u128 = 100;
+
+/// Leg of the swap that an escrow holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Side {
+	/// Holds the tokens of the maker for the taker, like `EscrowSrc`.
+	Src,
+	/// Holds the tokens of the taker for the maker, like `EscrowDst`.
+	Dst,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EscrowState {
+	Active,
+	Withdrawn,
+	Cancelled,
+}
+
+/// Timelocks of an escrow, as absolute blocks. Only source escrows have a
+/// public cancellation.
+#[derive(Clone, Copy, Debug)]
+struct EvmTimelocks {
+	withdrawal: u64,
+	public_withdrawal: u64,
+	cancellation: u64,
+	public_cancellation: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+struct EvmEscrow {
+	side: Side,
+	hashlock: H256,
+	maker: H160,
+	taker: H160,
+	amount: u128,
+	safety_deposit: u128,
+	timelocks: EvmTimelocks,
+	state: EscrowState,
+}
+
+/// Reverts of the simulated contracts.
+#[derive(Debug, PartialEq)]
+enum EvmError {
+	InvalidCaller,
+	InvalidSecret,
+	InvalidTime,
+	NotActive,
+	InsufficientBalance,
+}
+
+/// The EVM chain with the escrows deployed by the escrow factory.
+struct EvmChain {
+	hash_function: HashFunction,
+	balances: BTreeMap<H160, u128>,
+	escrows: Vec<EvmEscrow>,
+	/// Secrets in the `EscrowWithdrawal` logs, in order.
+	revealed: Vec<Vec<u8>>,
+}
+
+impl EvmChain {
+	fn new(hash_function: HashFunction) -> Self {
+		Self { hash_function, balances: BTreeMap::new(), escrows: Vec::new(), revealed: Vec::new() }
+	}
+
+	fn balance(&self, who: H160) -> u128 {
+		self.balances.get(&who).copied().unwrap_or_default()
+	}
+
+	fn fund(&mut self, who: H160, amount: u128) {
+		*self.balances.entry(who).or_default() += amount;
+	}
+
+	fn pay(&mut self, from: H160, to: H160, amount: u128) -> Result<(), EvmError> {
+		let balance = self.balance(from);
+		let rest = balance.checked_sub(amount).ok_or(EvmError::InsufficientBalance)?;
+		self.balances.insert(from, rest);
+		self.fund(to, amount);
+		Ok(())
+	}
+
+	/// The address of the escrow `id`, which holds its funds.
+	fn escrow_address(id: usize) -> H160 {
+		H160::from_slice(&blake2_256(&(id as u64).to_be_bytes())[..20])
+	}
+
+	/// Deploy an escrow as the resolver `taker`. A source escrow pulls the
+	/// amount from the maker, as the fill of its limit order, and a
+	/// destination escrow from the taker; the taker posts the safety deposit.
+	#[allow(clippy::too_many_arguments)]
+	fn deploy(
+		&mut self,
+		side: Side,
+		hashlock: H256,
+		maker: H160,
+		taker: H160,
+		amount: u128,
+		safety_deposit: u128,
+		timelocks: EvmTimelocks,
+	) -> Result<usize, EvmError> {
+		let id = self.escrows.len();
+		let escrow = Self::escrow_address(id);
+		let funder = match side {
+			Side::Src => maker,
+			Side::Dst => taker,
+		};
+		self.pay(funder, escrow, amount)?;
+		self.pay(taker, escrow, safety_deposit)?;
+		self.escrows.push(EvmEscrow {
+			side,
+			hashlock,
+			maker,
+			taker,
+			amount,
+			safety_deposit,
+			timelocks,
+			state: EscrowState::Active,
+		});
+		Ok(id)
+	}
+
+	fn active_escrow(&self, id: usize) -> Result<EvmEscrow, EvmError> {
+		let escrow = self.escrows[id].clone();
+		if escrow.state != EscrowState::Active {
+			return Err(EvmError::NotActive);
+		}
+		Ok(escrow)
+	}
+
+	/// `withdraw` or `publicWithdraw`: the amount goes to the taker of a
+	/// source escrow or the maker of a destination escrow, the safety
+	/// deposit to the caller, and the secret into the logs.
+	fn withdraw(
+		&mut self,
+		id: usize,
+		caller: H160,
+		secret: &[u8],
+		now: u64,
+	) -> Result<(), EvmError> {
+		let escrow = self.active_escrow(id)?;
+		if self.hash_function.hash(secret) != escrow.hashlock {
+			return Err(EvmError::InvalidSecret);
+		}
+		let start = if caller == escrow.taker {
+			escrow.timelocks.withdrawal
+		} else {
+			escrow.timelocks.public_withdrawal
+		};
+		if now < start || now >= escrow.timelocks.cancellation {
+			return Err(EvmError::InvalidTime);
+		}
+
+		let recipient = match escrow.side {
+			Side::Src => escrow.taker,
+			Side::Dst => escrow.maker,
+		};
+		self.pay(Self::escrow_address(id), recipient, escrow.amount)?;
+		self.pay(Self::escrow_address(id), caller, escrow.safety_deposit)?;
+		self.escrows[id].state = EscrowState::Withdrawn;
+		self.revealed.push(secret.to_vec());
+		Ok(())
+	}
+
+	/// `cancel` by the taker, or `publicCancel` of a source escrow by
+	/// anyone: the amount goes back to whoever deposited it, the safety
+	/// deposit to the caller.
+	fn cancel(&mut self, id: usize, caller: H160, now: u64) -> Result<(), EvmError> {
+		let escrow = self.active_escrow(id)?;
+		let start = if caller == escrow.taker {
+			escrow.timelocks.cancellation
+		} else {
+			escrow.timelocks.public_cancellation.ok_or(EvmError::InvalidCaller)?
+		};
+		if now < start {
+			return Err(EvmError::InvalidTime);
+		}
+
+		let refund = match escrow.side {
+			Side::Src => escrow.maker,
+			Side::Dst => escrow.taker,
+		};
+		self.pay(Self::escrow_address(id), refund, escrow.amount)?;
+		self.pay(Self::escrow_address(id), caller, escrow.safety_deposit)?;
+		self.escrows[id].state = EscrowState::Cancelled;
+		Ok(())
+	}
+}
+
+fn evm_address(who: u64) -> H160 {
+	let mut address = [0u8; 20];
+	address[12..20].copy_from_slice(&who.to_be_bytes());
+	H160::from(address)
+}
+
+/// Secrets revealed by the withdrawals on the mock runtime, in order, as a
+/// resolver watching the events learns them.
+fn revealed_on_substrate() -> Vec<Vec<u8>> {
+	System::events()
+		.into_iter()
+		.filter_map(|record| match record.event {
+			RuntimeEvent::HtlcEscrow(Event::HtlcWithdrawn { secret, .. }) => Some(secret),
+			_ => None,
+		})
+		.collect()
+}
+
+fn timelocks(deployed_at: u64) -> Timelocks<u64> {
+	Timelocks {
+		deployed_at,
+		withdrawal_after: deployed_at + 100,
+		public_withdrawal_after: deployed_at + 200,
+		cancellation_after: deployed_at + 300,
+	}
+}
+
+/// A swap from the EVM chain to the mock runtime: `EscrowSrc` holds the
+/// tokens of the maker there and a destination HTLC the swap amount here.
+/// Returns the id of the source escrow and the immutables of the HTLC.
+fn evm_to_substrate(evm: &mut EvmChain, secret: &[u8]) -> (usize, Immutables<u64, u128, u64>) {
+	let hashlock = HashFunction::Blake2_256.hash(secret);
+	evm.fund(evm_address(ALICE), 5_000);
+	evm.fund(evm_address(RESOLVER_BOB), 1_000);
+
+	let src = evm
+		.deploy(
+			Side::Src,
+			hashlock,
+			evm_address(ALICE),
+			evm_address(RESOLVER_BOB),
+			5_000,
+			SAFETY_DEPOSIT,
+			EvmTimelocks {
+				withdrawal: 11,
+				public_withdrawal: 121,
+				cancellation: 401,
+				public_cancellation: Some(501),
+			},
+		)
+		.unwrap();
+
+	let immutables = Immutables {
+		order_hash: H256(blake2_256(b"evm order")),
+		hashlock,
+		hash_function: HashFunction::Blake2_256,
+		maker: ALICE,
+		taker: RESOLVER_BOB,
+		amount: 1_000,
+		safety_deposit: SAFETY_DEPOSIT,
+		timelocks: timelocks(1),
+	};
+	assert_ok!(HtlcEscrow::create_dst_htlc(
+		RuntimeOrigin::signed(RESOLVER_BOB),
+		immutables.clone(),
+		evm.escrows[src].timelocks.cancellation,
+		GENERIC_CHAIN,
+	));
+
+	(src, immutables)
+}
+
+#[test]
+fn substrate_to_evm_swaps_settle_both_legs() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let mut evm = EvmChain::new(HashFunction::Blake2_256);
+		let secret = b"substrate to evm";
+		let hashlock = HashFunction::Blake2_256.hash(secret);
+
+		// the maker signs its intent here, the resolver escrows both legs
+		let nonce = 0;
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			SwapIntent {
+				hashlock,
+				maker: ALICE,
+				src_amount: 1_000,
+				dst_amount: 5_000,
+				dst_address: DstAddress::Evm(evm_address(ALICE)),
+				dst_chain: GENERIC_CHAIN,
+				timeout_after_block: Some(1_000),
+				nonce,
+				min_fill_amount: 0,
+				auction: Default::default(),
+				flags: Default::default(),
+				epoch: 0,
+				denied_resolvers: Default::default(),
+				dst_decimals: None,
+			},
+		));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			nonce,
+			timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		evm.fund(evm_address(RESOLVER_BOB), 6_000);
+		let dst = evm
+			.deploy(
+				Side::Dst,
+				hashlock,
+				evm_address(ALICE),
+				evm_address(RESOLVER_BOB),
+				5_000,
+				SAFETY_DEPOSIT,
+				EvmTimelocks {
+					withdrawal: 11,
+					public_withdrawal: 151,
+					cancellation: 251,
+					public_cancellation: None,
+				},
+			)
+			.unwrap();
+
+		// with both escrows finalized, the relayer shares the secret, and the
+		// resolver pays the maker on the EVM chain
+		System::set_block_number(101);
+		assert_eq!(
+			evm.withdraw(dst, evm_address(RESOLVER_BOB), b"wrong", 101),
+			Err(EvmError::InvalidSecret)
+		);
+		assert_ok!(evm.withdraw(dst, evm_address(RESOLVER_BOB), secret, 101));
+		assert_eq!(evm.balance(evm_address(ALICE)), 5_000);
+
+		// and takes the source HTLC here with the secret from the logs
+		let revealed = evm.revealed.last().unwrap().clone();
+		let source = Immutables {
+			order_hash: HtlcEscrow::intent_key(&ALICE, nonce),
+			hashlock,
+			hash_function: HashFunction::Blake2_256,
+			maker: ALICE,
+			taker: RESOLVER_BOB,
+			amount: 1_000,
+			safety_deposit: SAFETY_DEPOSIT,
+			timelocks: timelocks(1),
+		};
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), source, revealed));
+
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 - 1_000);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 + 1_000);
+		assert_eq!(evm.balance(evm_address(RESOLVER_BOB)), 1_000);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn evm_to_substrate_swaps_settle_both_legs() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let mut evm = EvmChain::new(HashFunction::Blake2_256);
+		let secret = b"evm to substrate";
+		let (src, destination) = evm_to_substrate(&mut evm, secret);
+
+		// the resolver pays the maker here, revealing the secret
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			destination,
+			secret.to_vec()
+		));
+
+		// and takes the tokens of the maker on the EVM chain with it
+		let revealed = revealed_on_substrate().pop().unwrap();
+		assert_ok!(evm.withdraw(src, evm_address(RESOLVER_BOB), &revealed, 101));
+
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + 1_000);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - 1_000);
+		assert_eq!(evm.balance(evm_address(ALICE)), 0);
+		assert_eq!(evm.balance(evm_address(RESOLVER_BOB)), 5_000 + 1_000);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn late_reveals_leave_the_resolver_time_on_the_source() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let mut evm = EvmChain::new(HashFunction::Blake2_256);
+		let secret = b"late reveal";
+		let (src, destination) = evm_to_substrate(&mut evm, secret);
+
+		// the destination HTLC must cancel before the source escrow
+		let late = Immutables {
+			order_hash: H256(blake2_256(b"late order")),
+			hashlock: HashFunction::Blake2_256.hash(b"late"),
+			..destination.clone()
+		};
+		assert_eq!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				late,
+				evm.escrows[src].timelocks.cancellation - 101,
+				GENERIC_CHAIN,
+			),
+			Err(Error::<Test>::InvalidTimelocks.into())
+		);
+
+		// the secret comes out in the last block of the public withdrawal here
+		System::set_block_number(300);
+		assert_ok!(HtlcEscrow::public_withdraw(
+			RuntimeOrigin::signed(CHARLIE),
+			destination,
+			secret.to_vec()
+		));
+
+		// the resolver cannot cancel the source escrow yet, and withdraws it
+		// with the secret before the maker can
+		let revealed = revealed_on_substrate().pop().unwrap();
+		assert_eq!(evm.cancel(src, evm_address(ALICE), 300), Err(EvmError::InvalidTime));
+		assert_ok!(evm.withdraw(src, evm_address(RESOLVER_BOB), &revealed, 300));
+		assert_eq!(evm.cancel(src, evm_address(RESOLVER_BOB), 401), Err(EvmError::NotActive));
+
+		assert_eq!(evm.balance(evm_address(RESOLVER_BOB)), 5_000 + 1_000);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + 1_000);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn unrevealed_swaps_are_refunded_on_both_chains() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let mut evm = EvmChain::new(HashFunction::Blake2_256);
+		let (src, destination) = evm_to_substrate(&mut evm, b"never revealed");
+
+		// the resolver gets its escrow back here first
+		System::set_block_number(301);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), destination));
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000);
+
+		// then the source escrow refunds the maker, by anyone once public
+		assert_eq!(evm.cancel(src, evm_address(CHARLIE), 401), Err(EvmError::InvalidTime));
+		assert_ok!(evm.cancel(src, evm_address(CHARLIE), 501));
+		assert_eq!(evm.balance(evm_address(ALICE)), 5_000);
+		assert_eq!(evm.balance(evm_address(CHARLIE)), SAFETY_DEPOSIT);
+		assert!(evm.revealed.is_empty());
+		assert!(revealed_on_substrate().is_empty());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
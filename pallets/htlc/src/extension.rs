@@ -0,0 +1,136 @@
+//! Transaction extension that checks the settlements of the HTLCs in the
+//! pool: the secret reveals go ahead of the other transactions, so that the
+//! time-critical settlements are not stuck behind spam during congestion, and
+//! the settlements that are bound to fail are not included at all, so that
+//! they cannot fill the blocks.
+
+use crate::{Call, Config, HtlcStatus, Htlcs, Pallet};
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use frame_support::{
+	pallet_prelude::TransactionSource, traits::IsSubType, weights::Weight, DefaultNoBound,
+};
+use scale_info::TypeInfo;
+use sp_core::{Get, H256};
+use sp_runtime::{
+	impl_tx_ext_default,
+	traits::{DispatchInfoOf, TransactionExtension, ValidateResult},
+	transaction_validity::{InvalidTransaction, ValidTransaction},
+};
+
+/// Raise the priority of the `withdraw` and `public_withdraw` transactions
+/// whose secret unlocks the active HTLC that they settle by the
+/// `SecretRevealPriority`, and reject the withdrawals and cancellations of
+/// HTLCs that do not exist, that are settled already or whose secret does
+/// not match.
+#[derive(Encode, Decode, DefaultNoBound, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckSettlements<T>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> core::fmt::Debug for CheckSettlements<T> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+		write!(f, "CheckSettlements")
+	}
+
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut core::fmt::Formatter) -> core::fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T: Config + Send + Sync> CheckSettlements<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+/// Secret that a withdrawal reveals, with the index and the proof of its
+/// leaf if it withdraws the fill of an intent in parts.
+type Reveal<'a> = (&'a [u8], Option<(u16, &'a [H256])>);
+
+/// The HTLC that `call` settles, with the secret that it reveals if it
+/// withdraws the HTLC.
+fn settlement<T: Config>(call: &Call<T>) -> Option<(H256, Option<Reveal<'_>>)> {
+	match call {
+		Call::withdraw { immutables, secret } |
+		Call::withdraw_to { immutables, secret, .. } |
+		Call::public_withdraw { immutables, secret } =>
+			Some((Pallet::<T>::hash_immutables(immutables), Some((secret, None)))),
+		Call::withdraw_with_proof { immutables, secret, index, proof } |
+		Call::public_withdraw_with_proof { immutables, secret, index, proof } =>
+			Some((Pallet::<T>::hash_immutables(immutables), Some((secret, Some((*index, proof)))))),
+		Call::withdraw_by_id { htlc_id, secret } |
+		Call::public_withdraw_by_id { htlc_id, secret } => Some((*htlc_id, Some((secret, None)))),
+		Call::cancel { immutables } |
+		Call::agree_to_cancel { immutables } |
+		Call::public_cancel { immutables } => Some((Pallet::<T>::hash_immutables(immutables), None)),
+		Call::cancel_by_id { htlc_id } |
+		Call::agree_to_cancel_by_id { htlc_id } |
+		Call::public_cancel_by_id { htlc_id } => Some((*htlc_id, None)),
+		_ => None,
+	}
+}
+
+/// Check that the HTLC `htlc_id` is active and that `secret`, if any,
+/// unlocks it.
+fn check_settlement<T: Config>(
+	htlc_id: H256,
+	reveal: Option<Reveal<'_>>,
+) -> Result<(), InvalidTransaction> {
+	let htlc = Htlcs::<T>::get(htlc_id).ok_or(InvalidTransaction::Call)?;
+	if htlc.status != HtlcStatus::Active {
+		return Err(InvalidTransaction::Stale);
+	}
+	match reveal {
+		Some((secret, proof))
+			if !Pallet::<T>::unlocks(htlc_id, &htlc, secret, proof).unwrap_or(false) =>
+			Err(InvalidTransaction::Call),
+		_ => Ok(()),
+	}
+}
+
+impl<T: Config + Send + Sync> TransactionExtension<<T as frame_system::Config>::RuntimeCall>
+	for CheckSettlements<T>
+where
+	<T as frame_system::Config>::RuntimeCall: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckSettlements";
+	type Implicit = ();
+	type Val = ();
+	type Pre = ();
+
+	fn weight(&self, call: &<T as frame_system::Config>::RuntimeCall) -> Weight {
+		match call.is_sub_type().and_then(settlement) {
+			// the fill index of the HTLC is read with a secret
+			Some((_, Some(_))) => T::DbWeight::get().reads(2),
+			Some(_) => T::DbWeight::get().reads(1),
+			None => Weight::zero(),
+		}
+	}
+
+	fn validate(
+		&self,
+		origin: <T as frame_system::Config>::RuntimeOrigin,
+		call: &<T as frame_system::Config>::RuntimeCall,
+		_info: &DispatchInfoOf<<T as frame_system::Config>::RuntimeCall>,
+		_len: usize,
+		_self_implicit: Self::Implicit,
+		_inherited_implication: &impl Encode,
+		_source: TransactionSource,
+	) -> ValidateResult<Self::Val, <T as frame_system::Config>::RuntimeCall> {
+		let priority = match call.is_sub_type().and_then(settlement) {
+			Some((htlc_id, reveal)) => {
+				check_settlement::<T>(htlc_id, reveal)?;
+				match reveal {
+					Some(_) => T::SecretRevealPriority::get(),
+					None => 0,
+				}
+			},
+			None => 0,
+		};
+		Ok((ValidTransaction { priority, ..Default::default() }, (), origin))
+	}
+
+	impl_tx_ext_default!(<T as frame_system::Config>::RuntimeCall; prepare);
+}
@@ -0,0 +1,106 @@
+//! Handlers of the safety deposits that are forfeited, so that each chain can
+//! route them to its own incentive policy without touching the settlement.
+
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use frame_support::traits::{
+	fungible::{Balanced, Credit},
+	OnUnbalanced, TryDrop,
+};
+use scale_info::TypeInfo;
+use sp_core::H256;
+
+/// Why a safety deposit was forfeited.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum ForfeitReason {
+	/// Anyone cancelled an HTLC that the taker left behind. The reward of the
+	/// caller is paid before the rest of the deposit is forfeited.
+	PublicCancellation,
+	/// The taker let a source HTLC expire without binding it to the escrow of
+	/// the destination leg.
+	Abandonment,
+}
+
+/// A safety deposit that was forfeited.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct Forfeit<AccountId> {
+	pub reason: ForfeitReason,
+	pub htlc_id: H256,
+	pub maker: AccountId,
+	pub taker: AccountId,
+	/// The account that settled the HTLC, unless it was the taker or the
+	/// HTLC was settled automatically.
+	pub caller: Option<AccountId>,
+}
+
+/// Handler of the forfeited safety deposits, in the style of `OnUnbalanced`.
+pub trait OnForfeit<AccountId, Credit> {
+	/// Take the `credit` of the `forfeit`.
+	fn on_forfeit(forfeit: &Forfeit<AccountId>, credit: Credit);
+}
+
+/// Burn the forfeited deposits.
+impl<AccountId, Credit> OnForfeit<AccountId, Credit> for () {
+	fn on_forfeit(_: &Forfeit<AccountId>, _: Credit) {}
+}
+
+/// Hand the forfeited deposits to an `OnUnbalanced` handler, e.g. the treasury.
+pub struct ForfeitTo<H>(PhantomData<H>);
+
+impl<AccountId, Credit: TryDrop, H: OnUnbalanced<Credit>> OnForfeit<AccountId, Credit>
+	for ForfeitTo<H>
+{
+	fn on_forfeit(_: &Forfeit<AccountId>, credit: Credit) {
+		H::on_unbalanced(credit);
+	}
+}
+
+/// Pay the forfeited deposits to the maker, burning them if it cannot
+/// receive them.
+pub struct ForfeitToMaker<F>(PhantomData<F>);
+
+impl<AccountId, F: Balanced<AccountId>> OnForfeit<AccountId, Credit<AccountId, F>>
+	for ForfeitToMaker<F>
+{
+	fn on_forfeit(forfeit: &Forfeit<AccountId>, credit: Credit<AccountId, F>) {
+		let _ = F::resolve(&forfeit.maker, credit);
+	}
+}
+
+/// Pay the forfeited deposits to the caller that settled the HTLC, leaving
+/// them to `Else` if there is none or it cannot receive them.
+pub struct ForfeitToCaller<F, Else = ()>(PhantomData<(F, Else)>);
+
+impl<AccountId, F, Else> OnForfeit<AccountId, Credit<AccountId, F>> for ForfeitToCaller<F, Else>
+where
+	F: Balanced<AccountId>,
+	Else: OnForfeit<AccountId, Credit<AccountId, F>>,
+{
+	fn on_forfeit(forfeit: &Forfeit<AccountId>, credit: Credit<AccountId, F>) {
+		let credit = match &forfeit.caller {
+			Some(caller) => match F::resolve(caller, credit) {
+				Ok(()) => return,
+				Err(credit) => credit,
+			},
+			None => credit,
+		};
+		Else::on_forfeit(forfeit, credit);
+	}
+}
+
+/// Leave the deposits forfeited in public cancellations to `P` and the ones
+/// of abandoned source HTLCs to `A`.
+pub struct SplitForfeits<P, A>(PhantomData<(P, A)>);
+
+impl<AccountId, Credit, P, A> OnForfeit<AccountId, Credit> for SplitForfeits<P, A>
+where
+	P: OnForfeit<AccountId, Credit>,
+	A: OnForfeit<AccountId, Credit>,
+{
+	fn on_forfeit(forfeit: &Forfeit<AccountId>, credit: Credit) {
+		match forfeit.reason {
+			ForfeitReason::PublicCancellation => P::on_forfeit(forfeit, credit),
+			ForfeitReason::Abandonment => A::on_forfeit(forfeit, credit),
+		}
+	}
+}
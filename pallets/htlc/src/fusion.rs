@@ -0,0 +1,146 @@
+//! Helpers for the 1inch Fusion and cross-chain swap contracts, so that the
+//! Polkadot leg of a swap can be matched with the order signed on the EVM
+//! chain and with the escrow deployed there.
+
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_core::{H160, H256, U256};
+use sp_io::{crypto::secp256k1_ecdsa_recover, hashing::keccak_256};
+use sp_std::prelude::*;
+
+/// Name of the EIP-712 domain of the 1inch Aggregation Router.
+pub const DOMAIN_NAME: &[u8] = b"1inch Aggregation Router";
+/// Version of the EIP-712 domain of the 1inch Aggregation Router.
+pub const DOMAIN_VERSION: &[u8] = b"6";
+
+const DOMAIN_TYPE: &[u8] =
+	b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const ORDER_TYPE: &[u8] = b"Order(uint256 salt,address maker,address receiver,address makerAsset,address takerAsset,uint256 makingAmount,uint256 takingAmount,uint256 makerTraits)";
+
+/// Order of the 1inch Limit Order Protocol, which Fusion orders are built on.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct FusionOrder {
+	pub salt: U256,
+	pub maker: H160,
+	pub receiver: H160,
+	pub maker_asset: H160,
+	pub taker_asset: H160,
+	pub making_amount: U256,
+	pub taking_amount: U256,
+	pub maker_traits: U256,
+}
+
+/// The deployment of the 1inch Aggregation Router that the order is for.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct FusionDomain {
+	pub chain_id: U256,
+	pub verifying_contract: H160,
+}
+
+fn encode_address(address: &H160) -> [u8; 32] {
+	let mut word = [0u8; 32];
+	word[12..].copy_from_slice(address.as_bytes());
+	word
+}
+
+impl FusionDomain {
+	/// The EIP-712 domain separator.
+	pub fn separator(&self) -> H256 {
+		let mut encoded = Vec::with_capacity(5 * 32);
+		encoded.extend_from_slice(&keccak_256(DOMAIN_TYPE));
+		encoded.extend_from_slice(&keccak_256(DOMAIN_NAME));
+		encoded.extend_from_slice(&keccak_256(DOMAIN_VERSION));
+		encoded.extend_from_slice(&self.chain_id.to_big_endian());
+		encoded.extend_from_slice(&encode_address(&self.verifying_contract));
+		H256(keccak_256(&encoded))
+	}
+}
+
+impl FusionOrder {
+	/// The EIP-712 struct hash of the order.
+	pub fn struct_hash(&self) -> H256 {
+		let mut encoded = Vec::with_capacity(9 * 32);
+		encoded.extend_from_slice(&keccak_256(ORDER_TYPE));
+		encoded.extend_from_slice(&self.salt.to_big_endian());
+		encoded.extend_from_slice(&encode_address(&self.maker));
+		encoded.extend_from_slice(&encode_address(&self.receiver));
+		encoded.extend_from_slice(&encode_address(&self.maker_asset));
+		encoded.extend_from_slice(&encode_address(&self.taker_asset));
+		encoded.extend_from_slice(&self.making_amount.to_big_endian());
+		encoded.extend_from_slice(&self.taking_amount.to_big_endian());
+		encoded.extend_from_slice(&self.maker_traits.to_big_endian());
+		H256(keccak_256(&encoded))
+	}
+
+	/// The order hash, i.e. the EIP-712 digest that the maker signs.
+	pub fn hash(&self, domain: &FusionDomain) -> H256 {
+		let mut encoded = Vec::with_capacity(2 + 2 * 32);
+		encoded.extend_from_slice(b"\x19\x01");
+		encoded.extend_from_slice(domain.separator().as_bytes());
+		encoded.extend_from_slice(self.struct_hash().as_bytes());
+		H256(keccak_256(&encoded))
+	}
+}
+
+/// Recover the EVM address that signed the digest, accepting both the `0/1`
+/// and the `27/28` recovery ids.
+pub fn recover_signer(digest: &H256, signature: &[u8; 65]) -> Option<H160> {
+	let mut signature = *signature;
+	if signature[64] >= 27 {
+		signature[64] -= 27;
+	}
+	let public = secp256k1_ecdsa_recover(&signature, digest.as_fixed_bytes()).ok()?;
+	Some(H160::from_slice(&keccak_256(&public)[12..]))
+}
+
+/// Immutables of an escrow of the 1inch cross-chain swap contracts, whose
+/// hash is the salt of the escrow deployment.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct EvmImmutables {
+	pub order_hash: H256,
+	pub hashlock: H256,
+	pub maker: H160,
+	pub taker: H160,
+	pub token: H160,
+	pub amount: U256,
+	pub safety_deposit: U256,
+	/// The packed timelocks of the escrow.
+	pub timelocks: U256,
+}
+
+impl EvmImmutables {
+	/// The hash of the ABI-encoded immutables, as in `ImmutablesLib.hash`.
+	pub fn hash(&self) -> H256 {
+		let mut encoded = Vec::with_capacity(8 * 32);
+		encoded.extend_from_slice(self.order_hash.as_bytes());
+		encoded.extend_from_slice(self.hashlock.as_bytes());
+		encoded.extend_from_slice(&encode_address(&self.maker));
+		encoded.extend_from_slice(&encode_address(&self.taker));
+		encoded.extend_from_slice(&encode_address(&self.token));
+		encoded.extend_from_slice(&self.amount.to_big_endian());
+		encoded.extend_from_slice(&self.safety_deposit.to_big_endian());
+		encoded.extend_from_slice(&self.timelocks.to_big_endian());
+		H256(keccak_256(&encoded))
+	}
+}
+
+/// Address of the escrow that the factory deploys with CREATE2 as an EIP-1167
+/// minimal proxy of the implementation, salted with the hash of the immutables.
+pub fn escrow_address(factory: &H160, implementation: &H160, immutables: &EvmImmutables) -> H160 {
+	let mut init_code = Vec::with_capacity(55);
+	init_code.extend_from_slice(&[
+		0x3d, 0x60, 0x2d, 0x80, 0x60, 0x0a, 0x3d, 0x39, 0x81, 0xf3, 0x36, 0x3d, 0x3d, 0x37, 0x3d,
+		0x3d, 0x3d, 0x36, 0x3d, 0x73,
+	]);
+	init_code.extend_from_slice(implementation.as_bytes());
+	init_code.extend_from_slice(&[
+		0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3,
+	]);
+
+	let mut encoded = Vec::with_capacity(1 + 20 + 2 * 32);
+	encoded.push(0xff);
+	encoded.extend_from_slice(factory.as_bytes());
+	encoded.extend_from_slice(immutables.hash().as_bytes());
+	encoded.extend_from_slice(&keccak_256(&init_code));
+	H160::from_slice(&keccak_256(&encoded)[12..])
+}
@@ -2,37 +2,119 @@
 
 pub use pallet::*;
 
+pub mod auction;
+pub mod conversion;
+pub mod decimals;
+pub mod delegation;
+pub mod delivery;
+pub mod extension;
+pub mod forfeit;
+pub mod fusion;
+pub mod merkle;
+pub mod migrations;
+pub mod optimistic;
+pub mod origin;
+pub mod quotes;
+pub mod receipt;
+pub mod resolver;
+pub mod revive;
+pub mod validation;
+pub mod vault;
+
+pub mod weights;
+pub use weights::*;
+
+pub use xcm::latest::Location;
+
 #[cfg(test)]
 mod mock;
 
 #[cfg(test)]
+#[allow(unused_imports, clippy::needless_borrows_for_generic_args)]
 mod tests;
 
+#[cfg(test)]
+mod proptests;
+
+#[cfg(test)]
+mod evm_sim;
+
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
 #[frame_support::pallet(dev_mode)]
 pub mod pallet {
 	use frame_support::{
-		dispatch::{GetDispatchInfo, RawOrigin},
+		dispatch::{DispatchClass, GetDispatchInfo},
 		pallet_prelude::*,
+		storage::with_storage_layer,
 		traits::{
 			fungible,
-			fungible::{Mutate, MutateHold},
-			tokens::{Precision, Preservation},
+			fungible::{Balanced, BalancedHold, Inspect, Mutate, MutateHold},
+			tokens::{Fortitude, Precision, Preservation},
+			Contains, Imbalance, OnUnbalanced, UnixTime,
 		},
+		PalletId,
 	};
 	use frame_system::pallet_prelude::*;
+
+	use crate::{
+		auction::AuctionCurve,
+		conversion::{ConvertSettlement, SettlementConversion},
+		decimals,
+		delegation::{DelegatedStake, Delegation},
+		delivery::DeliverSettlement,
+		forfeit::{Forfeit, ForfeitReason, OnForfeit},
+		fusion::{escrow_address, recover_signer, EvmImmutables, FusionDomain, FusionOrder},
+		merkle,
+		optimistic::VerifySettlement,
+		quotes::{QuoteBook, QuoteOf},
+		receipt::{IssueReceipt, SwapReceipt},
+		validation::{self, ImmutablesError, ValidatedImmutables},
+		vault::{ResolverCredit, VaultState},
+		WeightInfo,
+	};
+	use serde::{Deserialize, Serialize};
 	use sp_core::{H160, H256};
-	use sp_io::hashing::blake2_256;
-	use sp_runtime::traits::{BlakeTwo256, Dispatchable, Hash};
+	use sp_io::hashing::{blake2_256, sha2_256};
+	use sp_runtime::{
+		traits::{
+			AccountIdConversion, AtLeast32BitUnsigned, BlakeTwo256, Dispatchable, Hash, One,
+			TrailingZeroInput, UniqueSaturatedFrom, UniqueSaturatedInto, Zero,
+		},
+		Perbill, Saturating, TokenError,
+	};
 	use sp_std::prelude::*;
+	use xcm::latest::Location;
+	use xcm_executor::traits::ConvertLocation;
+
+	#[cfg(any(feature = "try-runtime", test))]
+	use sp_runtime::TryRuntimeError;
 
 	pub type BalanceOf<T> = <<T as Config>::NativeBalance as fungible::Inspect<
 		<T as frame_system::Config>::AccountId,
 	>>::Balance;
 
+	pub type CreditOf<T> =
+		fungible::Credit<<T as frame_system::Config>::AccountId, <T as Config>::NativeBalance>;
+
+	pub type SwapIntentOf<T> =
+		SwapIntent<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+	pub type StoredSwapIntentOf<T> =
+		StoredSwapIntent<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+	pub type ImmutablesOf<T> =
+		Immutables<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+	pub type HtlcOf<T> =
+		Htlc<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+	/// The in-code storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(7);
+
 	#[pallet::pallet]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
@@ -45,9 +127,16 @@ pub mod pallet {
 			+ fungible::Mutate<Self::AccountId>
 			+ fungible::hold::Inspect<Self::AccountId, Reason = Self::RuntimeHoldReason>
 			+ fungible::hold::Mutate<Self::AccountId, Reason = Self::RuntimeHoldReason>
+			+ fungible::Balanced<Self::AccountId>
+			+ fungible::hold::Balanced<Self::AccountId>
 			+ fungible::freeze::Inspect<Self::AccountId>
 			+ fungible::freeze::Mutate<Self::AccountId>;
 
+		/// Number of decimals of the native balances, to which the intents
+		/// normalize their `dst_amount`.
+		#[pallet::constant]
+		type NativeDecimals: Get<u8>;
+
 		type RuntimeCall: Parameter
 			+ Dispatchable<RuntimeOrigin = Self::RuntimeOrigin>
 			+ GetDispatchInfo;
@@ -55,10 +144,278 @@ pub mod pallet {
 		/// Reason for which funds are held.
 		type RuntimeHoldReason: From<HoldReason>;
 
+		/// Weight information for the hooks and the batch calls of this pallet.
+		type WeightInfo: WeightInfo;
+
+		/// How the escrowed funds are kept.
+		#[pallet::constant]
+		type EscrowMode: Get<EscrowMode>;
+
+		/// Whether to store only a commitment to the destination HTLCs, like
+		/// the EVM escrows, trading calldata for state: their parties supply
+		/// the immutables with each action, checked against the id of the
+		/// HTLC. Committed HTLCs are not indexed by hashlock or order, nor
+		/// cancelled automatically, and are settled one at a time.
+		#[pallet::constant]
+		type CommitmentOnly: Get<bool>;
+
+		/// Identifier of the pallet, from which the escrow accounts are derived.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+
+		/// Handler of the safety deposits that are forfeited, e.g. the
+		/// treasury, the maker or a burn.
+		type ForfeitHandler: OnForfeit<Self::AccountId, CreditOf<Self>>;
+
 		/// Minimum safety deposit that should be kept when a resolver
 		/// creates a HTLC.
 		#[pallet::constant]
 		type MinSafetyDeposit: Get<BalanceOf<Self>>;
+
+		/// Safety deposit that the maker posts with each swap intent, which is
+		/// forfeited to the resolver if the maker lets the source HTLC expire,
+		/// or cancels the intent, without revealing the secret once the
+		/// resolver bound it to the escrow on the other chain. Zero disables
+		/// the maker deposit.
+		#[pallet::constant]
+		type MakerSafetyDeposit: Get<BalanceOf<Self>>;
+
+		/// Number of blocks after a resolver takes an intent during which the
+		/// maker cannot cancel it, so that the resolver is not stranded with
+		/// the funds it escrowed on the other chain.
+		#[pallet::constant]
+		type IntentCancellationDelay: Get<BlockNumberFor<Self>>;
+
+		/// Bond that a resolver posts to reserve an intent before filling it,
+		/// which is forfeited to the maker if the resolver does not fill it.
+		#[pallet::constant]
+		type ReservationBond: Get<BalanceOf<Self>>;
+
+		/// Deposit held from the maker of an intent without a timeout for the
+		/// storage it uses, until the intent is cancelled or filled.
+		#[pallet::constant]
+		type GoodTilCancelDeposit: Get<BalanceOf<Self>>;
+
+		/// Maximum number of open intents of a maker, which hold its funds
+		/// until they are filled, cancelled or expire.
+		#[pallet::constant]
+		type MaxIntentsPerMaker: Get<u32>;
+
+		/// Maximum number of intents that a resolver holds reserved or in
+		/// progress at once, so that no resolver stalls the whole order book.
+		#[pallet::constant]
+		type MaxIntentsPerResolver: Get<u32>;
+
+		/// Number of blocks after a resolver takes an intent within which it
+		/// must fill it, after which another registered resolver can take the
+		/// intent over.
+		#[pallet::constant]
+		type FillDeadline: Get<BlockNumberFor<Self>>;
+
+		/// Number of blocks during which a resolver that forfeited its bond or
+		/// safety deposit cannot take or fill intents.
+		#[pallet::constant]
+		type ResolverCooldown: Get<BlockNumberFor<Self>>;
+
+		/// Number of blocks after which the stake undelegated from a resolver
+		/// can be withdrawn, during which it is still exposed to slashing.
+		#[pallet::constant]
+		type UndelegationDelay: Get<BlockNumberFor<Self>>;
+
+		/// Share of the stake delegated to a resolver that is slashed, along
+		/// with each delegator, whenever the resolver is.
+		#[pallet::constant]
+		type DelegationSlash: Get<Perbill>;
+
+		/// Maximum number of delegators backing each resolver.
+		#[pallet::constant]
+		type MaxDelegators: Get<u32>;
+
+		/// Number of blocks over which the settled volume of an account counts
+		/// towards its `FeeTiers`, tracked in `VOLUME_BUCKETS` buckets.
+		#[pallet::constant]
+		type VolumeWindow: Get<BlockNumberFor<Self>>;
+
+		/// Number of blocks after a taker reserves a destination HTLC within
+		/// which it must activate it with the swap amount.
+		#[pallet::constant]
+		type DstActivationPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Verifier of the challenges to the optimistic settlements of source
+		/// HTLCs; `()` disables them.
+		type SettlementVerifier: VerifySettlement<
+			Self::AccountId,
+			BalanceOf<Self>,
+			BlockNumberFor<Self>,
+		>;
+
+		/// Bond that the taker posts to claim a source HTLC optimistically,
+		/// which goes to whoever proves the claim wrong.
+		#[pallet::constant]
+		type OptimisticClaimBond: Get<BalanceOf<Self>>;
+
+		/// Number of blocks during which an optimistic claim can be challenged.
+		#[pallet::constant]
+		type ChallengeWindow: Get<BlockNumberFor<Self>>;
+
+		/// Priority added by the `CheckSettlements` extension to the
+		/// withdrawals that reveal the right secret.
+		#[pallet::constant]
+		type SecretRevealPriority: Get<TransactionPriority>;
+
+		/// Issues the receipts of the settled swaps, e.g. NFTs minted with
+		/// `MintReceipts`; `()` issues none.
+		type ReceiptIssuer: IssueReceipt<Self::AccountId, BalanceOf<Self>>;
+
+		/// Local asset into which the makers can convert their settled funds.
+		type ConversionAsset: Parameter;
+
+		/// Converts the settled funds of the makers that asked for it, e.g.
+		/// through `pallet-asset-conversion`; `()` converts nothing.
+		type SettlementConverter: ConvertSettlement<
+			Self::AccountId,
+			BalanceOf<Self>,
+			Self::ConversionAsset,
+		>;
+
+		/// Beneficiary on another chain to which the makers can deliver their
+		/// settled funds, e.g. an XCM `Location`.
+		type RemoteBeneficiary: Parameter;
+
+		/// Delivers the settled funds of the makers that asked for it, e.g.
+		/// with `pallet-xcm`; `()` delivers nothing.
+		type SettlementDelivery: DeliverSettlement<
+			Self::AccountId,
+			BalanceOf<Self>,
+			Self::RemoteBeneficiary,
+		>;
+
+		/// Number of blocks after `cancellation_after` from which anyone can
+		/// cancel an HTLC that the taker left behind.
+		#[pallet::constant]
+		type PublicCancellationDelay: Get<BlockNumberFor<Self>>;
+
+		/// Share of the safety deposit that is paid to the caller of a public
+		/// cancellation; the rest is forfeited to the `ForfeitHandler`.
+		#[pallet::constant]
+		type PublicCancellationReward: Get<Perbill>;
+
+		/// Share of the reward of a public withdrawal or cancellation that is
+		/// credited to the watchtower of the HTLC, when it called it itself.
+		#[pallet::constant]
+		type WatchtowerShare: Get<Perbill>;
+
+		/// Fee that a resolver owes to the liquidity providers of the vault for
+		/// the funds it drew, once the escrow funded by them is withdrawn.
+		#[pallet::constant]
+		type VaultFee: Get<Perbill>;
+
+		/// Part of the vault fees and of the forfeited safety deposits that
+		/// goes to the insurance fund, which compensates the makers and takers
+		/// harmed by bugs or failed resolvers.
+		#[pallet::constant]
+		type InsuranceShare: Get<Perbill>;
+
+		/// Maximum share of the vault that the resolvers can draw.
+		#[pallet::constant]
+		type MaxVaultUtilization: Get<Perbill>;
+
+		/// Maximum number of withdrawals from the vault waiting for the drawn
+		/// funds to come back.
+		#[pallet::constant]
+		type MaxQueuedVaultWithdrawals: Get<u32>;
+
+		/// Number of blocks after its deployment from which the
+		/// `GovernanceOrigin` can sweep an HTLC that is still active back to
+		/// its depositors.
+		#[pallet::constant]
+		type EmergencySweepPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Number of blocks for which the holds of an account that no active
+		/// HTLC accounts for any more stay unclaimed before anyone can sweep
+		/// them to the `UnclaimedHandler`.
+		#[pallet::constant]
+		type EscheatmentPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Handler of the unclaimed holds that are swept, e.g. the treasury.
+		type UnclaimedHandler: OnUnbalanced<CreditOf<Self>>;
+
+		/// Maximum number of overdue HTLCs that are cancelled automatically at
+		/// the start of each block.
+		#[pallet::constant]
+		type MaxAutoCancelsPerBlock: Get<u32>;
+
+		/// Maximum number of HTLCs that can be queued for automatic
+		/// cancellation, or for stage events, at the same block.
+		#[pallet::constant]
+		type MaxCancellationsPerBlock: Get<u32>;
+
+		/// Whether to emit `StageEntered` when an HTLC opens for the public
+		/// withdrawal and the public cancellation, so that watchtowers can be
+		/// event-driven. The HTLCs are queued at creation only if enabled.
+		#[pallet::constant]
+		type StageEvents: Get<bool>;
+
+		/// Number of blocks before the `cancellation_after` of an active HTLC
+		/// at which `SettlementAtRisk` is emitted, so that the monitoring of
+		/// the resolvers alerts their operators before the funds revert. Zero
+		/// disables the warnings.
+		#[pallet::constant]
+		type SettlementWarningPeriod: Get<BlockNumberFor<Self>>;
+
+		/// Maximum number of blocks between the creation of an HTLC and its
+		/// `cancellation_after`.
+		#[pallet::constant]
+		type MaxTimelockHorizon: Get<BlockNumberFor<Self>>;
+
+		/// Source of the UNIX time, with which the cancellation timestamps of
+		/// the source HTLCs on EVM chains are converted to blocks.
+		type UnixTime: UnixTime;
+
+		/// Expected number of milliseconds between blocks.
+		#[pallet::constant]
+		type ExpectedBlockTime: Get<u64>;
+
+		/// Number of seconds by which the destination HTLCs are cancelled
+		/// before the cancellation timestamp of their source, to absorb the
+		/// drift of the block times and of the clocks of the chains.
+		#[pallet::constant]
+		type TimestampSafetyMargin: Get<u64>;
+
+		/// Filter of the accounts that are allowed to take part in a swap, such
+		/// as a KYC registry or a sanctions list. Both the maker and the taker
+		/// are checked when a HTLC is created and when it is withdrawn.
+		type ComplianceFilter: Contains<Self::AccountId>;
+
+		/// Origin allowed to manage the pallet-wide settings, such as the
+		/// list of blocked accounts.
+		type GovernanceOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Origin of the remote parties, such as the resolvers of other
+		/// parachains dispatching through XCM, succeeding with their
+		/// `Location`, e.g. `pallet_xcm::EnsureXcm`.
+		type RemoteOrigin: EnsureOrigin<Self::RuntimeOrigin, Success = Location>;
+
+		/// Converter of the `Location` of a remote party to the account that
+		/// acts as its maker or taker, so that it needs no local signer.
+		type LocationToAccount: ConvertLocation<Self::AccountId>;
+
+		/// Maximum number of secrets that can be revealed in a single block.
+		#[pallet::constant]
+		type MaxRevealedSecretsPerBlock: Get<u32>;
+
+		/// Number of blocks for which the revealed secrets of a block are kept
+		/// in `RevealedSecrets` before they are pruned.
+		#[pallet::constant]
+		type RevealedSecretsRetention: Get<BlockNumberFor<Self>>;
+
+		/// Maximum number of HTLCs that can share the same hashlock.
+		#[pallet::constant]
+		type MaxHtlcsPerHashlock: Get<u32>;
+
+		/// Submits the fills of the auto-resolver of the off-chain worker.
+		#[cfg(feature = "auto-resolver")]
+		type AutoResolver: crate::resolver::SubmitFill<Self>;
 	}
 
 	/// Reason options for held funds.
@@ -73,15 +430,190 @@ pub mod pallet {
 		/// Amount held from the maker for each swap intent.
 		#[codec(index = 2)]
 		MakerSwapIntentAmount,
+		/// The safety deposit of the maker for each swap intent.
+		#[codec(index = 3)]
+		MakerSafetyDeposit,
+		/// The bond of the resolver that reserved a swap intent.
+		#[codec(index = 4)]
+		ReservationBond,
+		/// The storage deposit of the maker for a good-til-cancel intent.
+		#[codec(index = 5)]
+		IntentStorageDeposit,
+		/// The bond of the taker that claimed a source HTLC optimistically.
+		#[codec(index = 6)]
+		OptimisticClaimBond,
+		/// The stake delegated to a resolver.
+		#[codec(index = 7)]
+		DelegatedStake,
 	}
 
-	/// Immutable parameters of the HTLC, similar to 1inch IBaseEscrow.Immutables
+	/// How the escrowed funds of the HTLCs and swap intents are kept.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Copy, Debug)]
+	pub enum EscrowMode {
+		/// Held on the accounts of the depositors with the `HoldReason`s.
+		Holds,
+		/// Transferred to an account derived from the HTLC or intent, like the
+		/// clone-per-escrow contracts on EVM chains. Each escrowed amount must
+		/// be zero or at least the existential deposit.
+		SubAccounts,
+		/// Transferred to the account of the pallet, which pools the escrowed
+		/// funds of all HTLCs and intents under the same rule.
+		PalletAccount,
+	}
+
+	/// Hash function used to compute the hashlock from the secret.
+	#[derive(
+		Encode,
+		Decode,
+		TypeInfo,
+		MaxEncodedLen,
+		Eq,
+		PartialEq,
+		Clone,
+		Copy,
+		Debug,
+		Serialize,
+		Deserialize,
+	)]
+	pub enum HashFunction {
+		Blake2_256,
+		Sha256,
+	}
+
+	impl HashFunction {
+		/// Hash the secret with the hash function.
+		pub fn hash(&self, secret: &[u8]) -> H256 {
+			match self {
+				HashFunction::Blake2_256 => BlakeTwo256::hash(secret),
+				HashFunction::Sha256 => H256(sha2_256(secret)),
+			}
+		}
+	}
+
+	/// Maximum length of the secrets, so that hashing them has a bounded
+	/// weight.
+	pub const MAX_SECRET_LEN: u32 = 256;
+
+	/// Identifier of a chain on the other side of the swaps.
+	pub type ChainId = u32;
+
+	/// Format of the destination addresses accepted for a chain.
+	#[derive(
+		Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Copy, Debug, Serialize, Deserialize,
+	)]
+	pub enum AddressFormat {
+		/// Any well-formed address.
+		Any,
+		/// 20-byte EVM accounts.
+		Evm,
+		/// Bitcoin script hashes and segwit outputs.
+		Bitcoin,
+		/// 32-byte public keys.
+		PublicKey,
+	}
+
+	impl AddressFormat {
+		/// Whether the address is in this format.
+		pub fn accepts(&self, address: &DstAddress) -> bool {
+			match self {
+				AddressFormat::Any => true,
+				AddressFormat::Evm => matches!(address, DstAddress::Evm(_)),
+				AddressFormat::Bitcoin => matches!(
+					address,
+					DstAddress::BitcoinScriptHash(_) | DstAddress::BitcoinWitness { .. }
+				),
+				AddressFormat::PublicKey => matches!(address, DstAddress::PublicKey(_)),
+			}
+		}
+	}
+
+	/// Differences of a chain on the other side of the swaps that are
+	/// consulted when creating the intents and HTLCs.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+	pub struct ChainProfile<BlockNumber> {
+		/// Hash function of the hashlocks, which must be available on both chains.
+		pub hash_function: HashFunction,
+		/// Format of the destination addresses on the chain.
+		pub address_format: AddressFormat,
+		/// Minimum number of blocks between the withdrawal and the cancellation
+		/// of the HTLCs, and between the cancellation of a destination HTLC and
+		/// of its source on the chain, to account for its confirmation times.
+		pub min_timelock_margin: BlockNumber,
+		/// Number of decimals of the amounts on the chain, with which the
+		/// intents quote their `dst_amount` unless they set `dst_decimals`.
+		pub decimals: u8,
+	}
+
+	/// Timelocks recommended for the swaps with a chain, as offsets from the
+	/// deployment of the HTLCs.
 	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct TimelockTemplate {
+		pub withdrawal_offset: u32,
+		pub public_withdrawal_offset: u32,
+		pub cancellation_offset: u32,
+	}
+
+	impl TimelockTemplate {
+		/// Whether the stages of the template follow each other.
+		pub fn is_valid(&self) -> bool {
+			self.withdrawal_offset <= self.public_withdrawal_offset &&
+				self.public_withdrawal_offset <= self.cancellation_offset &&
+				self.withdrawal_offset < self.cancellation_offset
+		}
+
+		/// The timelocks of an HTLC deployed at `deployed_at`.
+		pub fn timelocks_at<BlockNumber: AtLeast32BitUnsigned + Copy>(
+			&self,
+			deployed_at: BlockNumber,
+		) -> Timelocks<BlockNumber> {
+			CompactTimelocks {
+				deployed_at,
+				withdrawal_offset: self.withdrawal_offset,
+				public_withdrawal_offset: self.public_withdrawal_offset,
+				cancellation_offset: self.cancellation_offset,
+			}
+			.expand()
+		}
+	}
+
+	/// Timelocks recommended by the `GovernanceOrigin` for the swaps with
+	/// each chain of the `ChainProfiles`.
+	#[pallet::storage]
+	pub type TimelockTemplates<T: Config> =
+		StorageMap<_, Twox64Concat, ChainId, TimelockTemplate, OptionQuery>;
+
+	/// Profiles of the chains that this chain can swap with, managed by the
+	/// `GovernanceOrigin`.
+	#[pallet::storage]
+	pub type ChainProfiles<T: Config> =
+		StorageMap<_, Twox64Concat, ChainId, ChainProfile<BlockNumberFor<T>>, OptionQuery>;
+
+	#[pallet::genesis_config]
+	#[derive(frame_support::DefaultNoBound)]
+	pub struct GenesisConfig<T: Config> {
+		/// Profiles of the chains that the chain can swap with from its
+		/// genesis.
+		pub chain_profiles: Vec<(ChainId, ChainProfile<BlockNumberFor<T>>)>,
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> BuildGenesisConfig for GenesisConfig<T> {
+		fn build(&self) {
+			for (chain_id, profile) in &self.chain_profiles {
+				ChainProfiles::<T>::insert(chain_id, profile);
+			}
+		}
+	}
+
+	/// Immutable parameters of the HTLC, similar to 1inch IBaseEscrow.Immutables
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug)]
 	pub struct Immutables<AccountId, Balance, BlockNumber> {
 		/// Hash of the cross chain order.
 		pub order_hash: H256,
 		/// Hash of the maker's secret.
 		pub hashlock: H256,
+		/// Hash function of the hashlock.
+		pub hash_function: HashFunction,
 		/// The maker of the swap (on source chain).
 		pub maker: AccountId,
 		/// The resolver who will complete the swap.
@@ -94,9 +626,10 @@ pub mod pallet {
 		pub timelocks: Timelocks<BlockNumber>,
 	}
 
-	/// Timelock configuration, similar to 1inch TimelocksLib. Store the number
-	/// of seconds from the time the escrow contract is deployed.
-	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	/// Timelock configuration, similar to 1inch TimelocksLib. Stored HTLCs
+	/// keep them as `CompactTimelocks`, with the number of blocks from the
+	/// time the escrow contract is deployed.
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug)]
 	pub struct Timelocks<BlockNumber> {
 		/// Block when the HTLC was deployed.
 		pub deployed_at: BlockNumber,
@@ -108,6 +641,60 @@ pub mod pallet {
 		pub cancellation_after: BlockNumber,
 	}
 
+	/// Encoding of the `Timelocks` with `u32` offsets from the deployment,
+	/// which keeps the stored HTLCs and their proofs small.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct CompactTimelocks<BlockNumber> {
+		pub deployed_at: BlockNumber,
+		pub withdrawal_offset: u32,
+		pub public_withdrawal_offset: u32,
+		pub cancellation_offset: u32,
+	}
+
+	impl<BlockNumber: AtLeast32BitUnsigned + Copy> Timelocks<BlockNumber> {
+		/// The compact form of the timelocks, if none of them is before the
+		/// deployment nor more than `u32::MAX` blocks after it.
+		pub fn compact(&self) -> Option<CompactTimelocks<BlockNumber>> {
+			let offset = |at: BlockNumber| {
+				at.checked_sub(&self.deployed_at).and_then(|offset| offset.try_into().ok())
+			};
+			Some(CompactTimelocks {
+				deployed_at: self.deployed_at,
+				withdrawal_offset: offset(self.withdrawal_after)?,
+				public_withdrawal_offset: offset(self.public_withdrawal_after)?,
+				cancellation_offset: offset(self.cancellation_after)?,
+			})
+		}
+	}
+
+	impl<BlockNumber: AtLeast32BitUnsigned + Copy> CompactTimelocks<BlockNumber> {
+		/// The timelocks at their blocks.
+		pub fn expand(&self) -> Timelocks<BlockNumber> {
+			let at = |offset: u32| self.deployed_at.saturating_add(offset.into());
+			Timelocks {
+				deployed_at: self.deployed_at,
+				withdrawal_after: at(self.withdrawal_offset),
+				public_withdrawal_after: at(self.public_withdrawal_offset),
+				cancellation_after: at(self.cancellation_offset),
+			}
+		}
+	}
+
+	/// Stage of the lifetime of an HTLC, derived from its timelocks.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+	pub enum Stage {
+		/// Waiting for the finality of the escrow on the other chain.
+		FinalityLock,
+		/// Only the taker can withdraw.
+		PrivateWithdrawal,
+		/// Anyone who knows the secret can withdraw.
+		PublicWithdrawal,
+		/// Only the taker can cancel.
+		Cancellation,
+		/// Anyone can cancel, after the `PublicCancellationDelay`.
+		PublicCancellation,
+	}
+
 	/// The status of a HTLC guards against malicious actors who aim to
 	/// take incorrect actions.
 	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
@@ -125,12 +712,190 @@ pub mod pallet {
 		Destination,
 	}
 
+	/// The first version of the `Immutables`.
+	pub type ImmutablesV1<AccountId, Balance, BlockNumber> =
+		Immutables<AccountId, Balance, BlockNumber>;
+
+	/// The second version of the `Immutables`, with `CompactTimelocks`. The
+	/// ids of the HTLCs are still the hashes of their `Immutables`.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct ImmutablesV2<AccountId, Balance, BlockNumber> {
+		pub order_hash: H256,
+		pub hashlock: H256,
+		pub hash_function: HashFunction,
+		pub maker: AccountId,
+		pub taker: AccountId,
+		pub amount: Balance,
+		pub safety_deposit: Balance,
+		pub timelocks: CompactTimelocks<BlockNumber>,
+	}
+
+	/// The `Immutables` of any version, so that fields can be added to them
+	/// without rewriting the stored HTLCs at once, nor upgrading the clients
+	/// that decode them in lock-step. The stored immutables of every version
+	/// decode, and are encoded in the latest one that holds them: in the
+	/// first one if their timelocks have no compact form.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub enum VersionedImmutables<AccountId, Balance, BlockNumber> {
+		#[codec(index = 1)]
+		V1(ImmutablesV1<AccountId, Balance, BlockNumber>),
+		#[codec(index = 2)]
+		V2(ImmutablesV2<AccountId, Balance, BlockNumber>),
+	}
+
+	impl<AccountId, Balance, BlockNumber: AtLeast32BitUnsigned + Copy>
+		VersionedImmutables<AccountId, Balance, BlockNumber>
+	{
+		/// The immutables in the latest version.
+		pub fn into_latest(self) -> Immutables<AccountId, Balance, BlockNumber> {
+			match self {
+				VersionedImmutables::V1(immutables) => immutables,
+				VersionedImmutables::V2(immutables) => Immutables {
+					order_hash: immutables.order_hash,
+					hashlock: immutables.hashlock,
+					hash_function: immutables.hash_function,
+					maker: immutables.maker,
+					taker: immutables.taker,
+					amount: immutables.amount,
+					safety_deposit: immutables.safety_deposit,
+					timelocks: immutables.timelocks.expand(),
+				},
+			}
+		}
+	}
+
+	impl<AccountId, Balance, BlockNumber: AtLeast32BitUnsigned + Copy>
+		From<Immutables<AccountId, Balance, BlockNumber>>
+		for VersionedImmutables<AccountId, Balance, BlockNumber>
+	{
+		fn from(immutables: Immutables<AccountId, Balance, BlockNumber>) -> Self {
+			let Some(timelocks) = immutables.timelocks.compact() else {
+				return VersionedImmutables::V1(immutables);
+			};
+			VersionedImmutables::V2(ImmutablesV2 {
+				order_hash: immutables.order_hash,
+				hashlock: immutables.hashlock,
+				hash_function: immutables.hash_function,
+				maker: immutables.maker,
+				taker: immutables.taker,
+				amount: immutables.amount,
+				safety_deposit: immutables.safety_deposit,
+				timelocks,
+			})
+		}
+	}
+
+	/// `ImmutablesV2` encoded without a copy.
+	#[derive(Encode)]
+	struct ImmutablesV2Ref<'a, AccountId, Balance, BlockNumber> {
+		order_hash: &'a H256,
+		hashlock: &'a H256,
+		hash_function: &'a HashFunction,
+		maker: &'a AccountId,
+		taker: &'a AccountId,
+		amount: &'a Balance,
+		safety_deposit: &'a Balance,
+		timelocks: CompactTimelocks<BlockNumber>,
+	}
+
+	/// The `VersionedImmutables` of an HTLC, encoded without a copy.
+	#[derive(Encode)]
+	enum VersionedImmutablesRef<'a, AccountId, Balance, BlockNumber> {
+		#[codec(index = 1)]
+		V1(&'a ImmutablesV1<AccountId, Balance, BlockNumber>),
+		#[codec(index = 2)]
+		V2(ImmutablesV2Ref<'a, AccountId, Balance, BlockNumber>),
+	}
+
 	/// The information for each HTLC that needs to be stored on-chain.
-	#[derive(Encode, Decode, TypeInfo)]
+	/// Encoded as `StoredHtlc`, with versioned immutables.
 	pub struct Htlc<AccountId, Balance, BlockNumber> {
 		pub immutables: Immutables<AccountId, Balance, BlockNumber>,
 		pub status: HtlcStatus,
 		pub htlc_type: HtlcType,
+		/// Chain of the other leg of the swap.
+		pub counterpart_chain: ChainId,
+	}
+
+	/// Encoding of the `Htlc` in storage.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct StoredHtlc<AccountId, Balance, BlockNumber> {
+		pub immutables: VersionedImmutables<AccountId, Balance, BlockNumber>,
+		pub status: HtlcStatus,
+		pub htlc_type: HtlcType,
+		pub counterpart_chain: ChainId,
+	}
+
+	impl<AccountId, Balance, BlockNumber: AtLeast32BitUnsigned + Copy>
+		StoredHtlc<AccountId, Balance, BlockNumber>
+	{
+		/// The HTLC with its immutables in the latest version.
+		pub fn into_latest(self) -> Htlc<AccountId, Balance, BlockNumber> {
+			Htlc {
+				immutables: self.immutables.into_latest(),
+				status: self.status,
+				htlc_type: self.htlc_type,
+				counterpart_chain: self.counterpart_chain,
+			}
+		}
+	}
+
+	impl<AccountId: Encode, Balance: Encode, BlockNumber: AtLeast32BitUnsigned + Copy + Encode>
+		Encode for Htlc<AccountId, Balance, BlockNumber>
+	{
+		fn size_hint(&self) -> usize {
+			1 + self.immutables.size_hint() +
+				self.status.size_hint() +
+				self.htlc_type.size_hint() +
+				self.counterpart_chain.size_hint()
+		}
+
+		fn encode_to<W: codec::Output + ?Sized>(&self, dest: &mut W) {
+			let immutables = &self.immutables;
+			match immutables.timelocks.compact() {
+				Some(timelocks) => VersionedImmutablesRef::V2(ImmutablesV2Ref {
+					order_hash: &immutables.order_hash,
+					hashlock: &immutables.hashlock,
+					hash_function: &immutables.hash_function,
+					maker: &immutables.maker,
+					taker: &immutables.taker,
+					amount: &immutables.amount,
+					safety_deposit: &immutables.safety_deposit,
+					timelocks,
+				})
+				.encode_to(dest),
+				None => VersionedImmutablesRef::V1(immutables).encode_to(dest),
+			}
+			self.status.encode_to(dest);
+			self.htlc_type.encode_to(dest);
+			self.counterpart_chain.encode_to(dest);
+		}
+	}
+
+	impl<AccountId: Encode, Balance: Encode, BlockNumber: AtLeast32BitUnsigned + Copy + Encode>
+		codec::EncodeLike for Htlc<AccountId, Balance, BlockNumber>
+	{
+	}
+
+	impl<AccountId: Decode, Balance: Decode, BlockNumber: AtLeast32BitUnsigned + Copy + Decode>
+		Decode for Htlc<AccountId, Balance, BlockNumber>
+	{
+		fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+			StoredHtlc::decode(input).map(StoredHtlc::into_latest)
+		}
+	}
+
+	impl<
+			AccountId: TypeInfo + 'static,
+			Balance: TypeInfo + 'static,
+			BlockNumber: TypeInfo + 'static,
+		> TypeInfo for Htlc<AccountId, Balance, BlockNumber>
+	{
+		type Identity = StoredHtlc<AccountId, Balance, BlockNumber>;
+
+		fn type_info() -> scale_info::Type {
+			StoredHtlc::<AccountId, Balance, BlockNumber>::type_info()
+		}
 	}
 
 	#[pallet::storage]
@@ -142,58 +907,846 @@ pub mod pallet {
 		OptionQuery,
 	>;
 
-	/// Keep track of the swap intent data of a maker. This can/should be
-	/// part of another pallet (such as a limit order protocol pallet) or stored
+	/// Commitment to a destination HTLC of the `CommitmentOnly` mode, whose
+	/// immutables hash to its id.
 	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
-	pub struct SwapIntent<AccountId, Balance, BlockNumber> {
-		pub hashlock: H256,
-		/// Account that intents to swap
-		pub maker: AccountId,
-		/// Amount they own and want to provide
-		pub src_amount: Balance,
-		/// Amount they own and want to receive
-		pub dst_amount: Balance,
-		/// Address on the destination chain
-		pub dst_address: H160,
-		pub timeout_after_block: BlockNumber,
-		pub nonce: u64,
+	pub struct HtlcCommitment {
+		pub status: HtlcStatus,
+		/// Chain of the source leg of the swap.
+		pub counterpart_chain: ChainId,
 	}
 
-	/// Enum to keep track of the state of each swap intent submitted
-	/// by the maker. We should remove intents after they are completed
-	/// or cancelled and keep track of the hash/nonce of the ones that
-	/// have already been part of the chain. This is an improvement
-	/// over the current implementation that should be implemented.
+	/// The commitments to the destination HTLCs that are not stored in
+	/// `Htlcs`, by the id of the HTLC.
+	#[pallet::storage]
+	pub type HtlcCommitments<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, HtlcCommitment, OptionQuery>;
+
+	/// Destination HTLC reserved by the taker with its safety deposit, which
+	/// is created once the taker activates it with the swap amount.
 	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
-	pub enum IntentStatus<AccountId> {
-		/// Intent is active and available for resolvers
-		Active,
-		/// Intent is being fulfilled (resolver created source HTLC)
-		InProgress { resolver: AccountId, htlc_id: H256 },
-		/// Intent has been completed successfully
-		Completed,
-		/// Intent was cancelled by maker
-		Cancelled,
-		/// Intent expired without fulfillment
-		Expired,
+	pub struct PendingDstHtlc<AccountId, Balance, BlockNumber> {
+		pub immutables: Immutables<AccountId, Balance, BlockNumber>,
+		pub src_cancellation_block: BlockNumber,
+		pub src_chain: ChainId,
+		/// Whether the swap amount is drawn from the resolver vault.
+		pub from_vault: bool,
+		/// Last block to activate the HTLC.
+		pub activate_before: BlockNumber,
 	}
 
+	/// Destination HTLCs pending activation, expired in `on_initialize`
+	/// after their `activate_before`.
+	#[pallet::storage]
+	pub type PendingDstHtlcs<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		PendingDstHtlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// Claim of the taker that the destination leg of a source HTLC settled.
 	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
-	pub struct StoredSwapIntent<AccountId, Balance, BlockNumber> {
-		pub intent: SwapIntent<AccountId, Balance, BlockNumber>,
-		pub status: IntentStatus<AccountId>,
-		pub created_at: BlockNumber,
+	pub struct OptimisticClaim<AccountId, Balance, BlockNumber> {
+		pub claimant: AccountId,
+		pub bond: Balance,
+		/// Last block to challenge the claim.
+		pub challenge_until: BlockNumber,
 	}
 
+	/// Optimistic claims of the source HTLCs that are not finalized yet.
 	#[pallet::storage]
-	pub type SwapIntents<T: Config> = StorageMap<
+	pub type OptimisticClaims<T: Config> = StorageMap<
 		_,
 		Blake2_128Concat,
 		H256,
-		StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		OptimisticClaim<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
 		OptionQuery,
 	>;
 
+	/// Index of the HTLCs that share a hashlock, so that anyone who learns a
+	/// secret can find every escrow that it unlocks.
+	#[pallet::storage]
+	pub type HashlockIndex<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, BoundedVec<H256, T::MaxHtlcsPerHashlock>, ValueQuery>;
+
+	/// The key of the active swap intent that uses each hashlock.
+	#[pallet::storage]
+	pub type IntentHashlocks<T: Config> = StorageMap<_, Blake2_128Concat, H256, H256, OptionQuery>;
+
+	/// Quotes posted by the resolvers against each active intent, one per
+	/// resolver, until the intent is taken or closed.
+	#[pallet::storage]
+	pub type IntentQuotes<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, QuoteBook<T>, ValueQuery>;
+
+	/// The quote that the maker accepted for each reserved intent. Its
+	/// resolver pays the quoted `dst_amount` instead of the price of the
+	/// auction when it fills the intent.
+	#[pallet::storage]
+	pub type AcceptedQuotes<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, QuoteOf<T>, OptionQuery>;
+
+	/// Intent templates of each maker by their id, up to
+	/// `MAX_INTENT_TEMPLATES` per maker.
+	#[pallet::storage]
+	pub type IntentTemplates<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Twox64Concat,
+		u32,
+		IntentTemplateOf<T>,
+		OptionQuery,
+	>;
+
+	/// Address of the escrow on an EVM chain that mirrors each source HTLC,
+	/// so that on-chain logic and indexers can bind the two legs of a swap.
+	#[pallet::storage]
+	pub type CounterpartEscrows<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, H160, OptionQuery>;
+
+	/// Where the taker deployed the escrow of the other leg of an HTLC on an
+	/// EVM chain.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct CounterpartReference {
+		/// Hash of the transaction that deployed the escrow.
+		pub tx_hash: H256,
+		/// Address of the escrow contract.
+		pub escrow: H160,
+		/// Block of the transaction on the EVM chain.
+		pub block_number: u64,
+	}
+
+	/// Evidence of the escrows of the other legs of the HTLCs recorded by
+	/// their takers, so that the makers and watchtowers can check that the
+	/// other leg exists before revealing the secret.
+	#[pallet::storage]
+	pub type CounterpartReferences<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, CounterpartReference, OptionQuery>;
+
+	/// The hashlock of the upstream leg of the HTLCs of swaps routed through
+	/// an intermediary chain, e.g. of the B -> C leg of a swap A -> B -> C.
+	#[pallet::storage]
+	pub type ParentHashlocks<T: Config> = StorageMap<_, Blake2_128Concat, H256, H256, OptionQuery>;
+
+	/// Maximum number of HTLCs of an order on this chain, e.g. the fills of
+	/// a swap intent and the destination legs with its `order_hash`.
+	pub const MAX_ORDER_LEGS: u32 = 16;
+
+	/// Where an order is in its lifecycle, across its legs on this chain.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub enum OrderStatus {
+		/// The swap intent waits for a resolver.
+		Open,
+		/// An HTLC of the order is active.
+		InProgress,
+		/// An HTLC of the order was withdrawn.
+		Completed,
+		/// The order was cancelled or expired without being settled.
+		Cancelled,
+	}
+
+	/// Everything this chain knows of an order, so that a single query tells
+	/// where it is in its lifecycle.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct OrderRecord<AccountId> {
+		/// Status of the swap intent, if the order was posted on this chain.
+		pub intent: Option<IntentStatus<AccountId>>,
+		/// The source and destination HTLCs of the order on this chain.
+		pub htlcs: BoundedVec<H256, ConstU32<MAX_ORDER_LEGS>>,
+		/// The escrows of the other legs, bound to or recorded for the HTLCs.
+		pub counterparts: BoundedVec<(H256, H160), ConstU32<MAX_ORDER_LEGS>>,
+		pub status: OrderStatus,
+	}
+
+	/// The orders by their `order_hash`, which is the key of the swap intent
+	/// of the orders posted on this chain.
+	#[pallet::storage]
+	pub type CrossChainOrders<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, OrderRecord<T::AccountId>, OptionQuery>;
+
+	/// The HTLCs to cancel automatically once each block is reached, i.e. at
+	/// their `cancellation_after`.
+	#[pallet::storage]
+	pub type HtlcsByCancellationBlock<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<H256, T::MaxCancellationsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// The raw key of `SwapIntents` from which `on_idle` continues looking
+	/// for timed out intents.
+	#[pallet::storage]
+	pub type IntentExpiryCursor<T: Config> = StorageValue<_, Vec<u8>, OptionQuery>;
+
+	/// HTLCs by the block at which they enter a stage that is relevant to
+	/// third parties, for the `StageEntered` events.
+	#[pallet::storage]
+	pub type StageTransitions<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<(H256, Stage), T::MaxCancellationsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// HTLCs by the block at which their settlement is at risk, for the
+	/// `SettlementAtRisk` events.
+	#[pallet::storage]
+	pub type SettlementWarnings<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<H256, T::MaxCancellationsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// The next block of `HtlcsByCancellationBlock` to process.
+	#[pallet::storage]
+	pub type AutoCancelCursor<T: Config> = StorageValue<_, BlockNumberFor<T>, OptionQuery>;
+
+	/// Amount of safety deposits that a sponsor is willing to post for a
+	/// resolver, reduced while the deposits are escrowed.
+	#[pallet::storage]
+	pub type SponsorAllowances<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		BalanceOf<T>,
+		ValueQuery,
+	>;
+
+	/// Sponsor that a resolver chose to post the safety deposits of the HTLCs
+	/// it creates.
+	#[pallet::storage]
+	pub type Sponsors<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+	/// Sponsor that posted the safety deposit of an active HTLC.
+	#[pallet::storage]
+	pub type DepositSponsors<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, T::AccountId, OptionQuery>;
+
+	/// Resolvers registered by the `GovernanceOrigin` to draw on the vault,
+	/// with their credit limits.
+	#[pallet::storage]
+	pub type Resolvers<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, OptionQuery>;
+
+	/// Block until which each resolver that failed a fill or was slashed
+	/// cannot take or fill intents.
+	#[pallet::storage]
+	pub type ResolverCooldowns<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BlockNumberFor<T>, OptionQuery>;
+
+	/// Stake of each delegator behind each resolver.
+	#[pallet::storage]
+	pub type Delegations<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		Delegation<BalanceOf<T>, BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// Stake delegated to each resolver, which raises its credit limit.
+	#[pallet::storage]
+	pub type DelegatedStakes<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, DelegatedStake<BalanceOf<T>>, ValueQuery>;
+
+	/// Maximum number of active HTLCs that a watchtower watches at once.
+	pub const MAX_WATCHTOWER_DUTIES: u32 = 64;
+
+	/// Bookkeeping of a watchtower.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+	pub struct WatchtowerInfo<Balance> {
+		/// Number of HTLCs that the watchtower watches.
+		pub duties: u32,
+		/// Rewards of the public actions earned by the watchtower.
+		pub rewards: Balance,
+	}
+
+	/// Accounts that opted in to be assigned to watch HTLCs by their parties,
+	/// for a share of the rewards of their public withdrawal or cancellation.
+	#[pallet::storage]
+	pub type Watchtowers<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, WatchtowerInfo<BalanceOf<T>>, OptionQuery>;
+
+	/// Watchtower assigned to each active HTLC, until the HTLC settles.
+	#[pallet::storage]
+	pub type WatchtowerDuties<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, T::AccountId, OptionQuery>;
+
+	/// Share of its earnings that each resolver keeps before the rest is
+	/// shared with its delegators. Resolvers without one keep all of them.
+	#[pallet::storage]
+	pub type ResolverCommissions<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, Perbill, OptionQuery>;
+
+	/// Volume settled by each account over the `VolumeWindow`, by bucket
+	/// start block, oldest first.
+	#[pallet::storage]
+	pub type RecentVolumes<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<(BlockNumberFor<T>, BalanceOf<T>), ConstU32<VOLUME_BUCKETS>>,
+		ValueQuery,
+	>;
+
+	/// Discounts of the vault fee by the volume settled over the
+	/// `VolumeWindow`, in increasing order of the volume.
+	#[pallet::storage]
+	pub type FeeTiers<T: Config> = StorageValue<_, FeeTiersOf<T>, ValueQuery>;
+
+	/// Reduced safety deposits and vault fees of the registered resolvers by
+	/// the stake delegated to them, in increasing order of the stake.
+	#[pallet::storage]
+	pub type ResolverTiers<T: Config> = StorageValue<_, ResolverTiersOf<T>, ValueQuery>;
+
+	/// Funds of the vault used by each resolver.
+	#[pallet::storage]
+	pub type ResolverCredits<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, ResolverCredit<BalanceOf<T>>, ValueQuery>;
+
+	/// Aggregates of the swaps settled by each account, e.g. for loyalty
+	/// programs or fee tiers.
+	#[pallet::storage]
+	pub type AccountStats<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		SwapStats<BalanceOf<T>, BlockNumberFor<T>>,
+		ValueQuery,
+	>;
+
+	/// Accounting of the resolver vault.
+	#[pallet::storage]
+	pub type Vault<T: Config> = StorageValue<_, VaultState<BalanceOf<T>>, ValueQuery>;
+
+	/// Shares of the vault held by each liquidity provider.
+	#[pallet::storage]
+	pub type VaultShares<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+	/// Shares of the vault waiting to be redeemed, in order, as the drawn funds
+	/// come back.
+	#[pallet::storage]
+	pub type VaultWithdrawalQueue<T: Config> = StorageValue<
+		_,
+		BoundedVec<(T::AccountId, BalanceOf<T>), T::MaxQueuedVaultWithdrawals>,
+		ValueQuery,
+	>;
+
+	/// Active HTLCs whose swap amount was drawn from the vault.
+	#[pallet::storage]
+	pub type VaultDraws<T: Config> = StorageMap<_, Blake2_128Concat, H256, (), OptionQuery>;
+
+	/// The amounts that could not be released from the holds of the HTLCs and
+	/// intents, e.g. because another pallet slashed them, by the account and
+	/// reason of the hold, waiting for governance to clear them.
+	#[pallet::storage]
+	pub type EscrowDeficits<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		Blake2_128Concat,
+		(T::AccountId, HoldReason),
+		BalanceOf<T>,
+		ValueQuery,
+	>;
+
+	/// The amount that the taker added to the safety deposit of an active HTLC
+	/// on top of the one in its immutables.
+	#[pallet::storage]
+	pub type SafetyDepositTopUps<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, BalanceOf<T>, OptionQuery>;
+
+	/// The conversion that the maker of an active destination HTLC asked for
+	/// once it is withdrawn.
+	#[pallet::storage]
+	pub type SettlementConversions<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		SettlementConversion<T::ConversionAsset, BalanceOf<T>>,
+		OptionQuery,
+	>;
+
+	/// The beneficiary on another chain to which the maker of an active
+	/// destination HTLC asked to deliver the swap amount once it is withdrawn.
+	#[pallet::storage]
+	pub type SettlementDeliveries<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, T::RemoteBeneficiary, OptionQuery>;
+
+	/// What the holds of the swap amounts and safety deposits of an account
+	/// kept beyond its active HTLCs once a terminal HTLC or intent released
+	/// them, by the key of the HTLC or intent, with the block since which it
+	/// is unclaimed.
+	#[pallet::storage]
+	pub type ResidualHolds<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		Blake2_128Concat,
+		(T::AccountId, HoldReason),
+		(BalanceOf<T>, BlockNumberFor<T>),
+		OptionQuery,
+	>;
+
+	/// Swap amounts and safety deposits that the active and pending HTLCs
+	/// hold from an account.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+	pub struct ExpectedHold<Balance> {
+		pub swap_amounts: Balance,
+		pub safety_deposits: Balance,
+	}
+
+	/// What the HTLCs hold from each account, kept with the holds of the
+	/// escrow, so that its residual holds are known without a scan of the
+	/// HTLCs. The shortfalls of partial releases stay in it until their
+	/// `EscrowDeficits` are cleared, and the `ResidualHolds` until they are
+	/// swept or reconciled.
+	#[pallet::storage]
+	pub type ExpectedHolds<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, ExpectedHold<BalanceOf<T>>, ValueQuery>;
+
+	/// The party that agreed first to cancel an active HTLC early, waiting for
+	/// the agreement of the other party.
+	#[pallet::storage]
+	pub type CancelAgreements<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, T::AccountId, OptionQuery>;
+
+	/// Compact record of a settled HTLC that is kept after the full `Htlc`
+	/// has been pruned, so the history of the swaps remains verifiable.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct ArchivedHtlc<Balance, BlockNumber> {
+		/// Terminal status of the HTLC.
+		pub status: HtlcStatus,
+		/// Hash of the maker's secret.
+		pub hashlock: H256,
+		/// Amount of tokens that was swapped.
+		pub amount: Balance,
+		/// Block when the HTLC was archived.
+		pub archived_at: BlockNumber,
+	}
+
+	#[pallet::storage]
+	pub type ArchivedHtlcs<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		ArchivedHtlc<BalanceOf<T>, BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// The `(hashlock, htlc_id)` pairs whose secret was revealed in each block,
+	/// so that relayers can cheaply prove that a secret was revealed by a
+	/// given block. A hashlock is noted once per block, with the first HTLC
+	/// that revealed it. Entries are pruned after `RevealedSecretsRetention`
+	/// blocks.
+	#[pallet::storage]
+	pub type RevealedSecrets<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		BlockNumberFor<T>,
+		BoundedVec<(H256, H256), T::MaxRevealedSecretsPerBlock>,
+		ValueQuery,
+	>;
+
+	/// Maximum length of a raw destination address.
+	pub const MAX_RAW_ADDRESS_LEN: u32 = 64;
+
+	/// Address of the maker on the destination chain, which is not
+	/// necessarily an EVM chain.
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug)]
+	pub enum DstAddress {
+		/// 20-byte account of an EVM chain.
+		Evm(H160),
+		/// Hash of a Bitcoin redeem script (P2SH).
+		BitcoinScriptHash(H160),
+		/// Bitcoin segwit output, i.e. the payload of a bech32/bech32m address.
+		BitcoinWitness { version: u8, program: BoundedVec<u8, ConstU32<40>> },
+		/// 32-byte public key, e.g. a Solana account.
+		PublicKey([u8; 32]),
+		/// Address in any other format.
+		Raw(BoundedVec<u8, ConstU32<MAX_RAW_ADDRESS_LEN>>),
+	}
+
+	impl DstAddress {
+		/// Check that the address is well-formed for its format.
+		pub fn is_valid(&self) -> bool {
+			match self {
+				DstAddress::BitcoinWitness { version, program } => match version {
+					0 => program.len() == 20 || program.len() == 32,
+					1..=16 => program.len() >= 2,
+					_ => false,
+				},
+				DstAddress::Raw(bytes) => !bytes.is_empty(),
+				_ => true,
+			}
+		}
+	}
+
+	/// Keep track of the swap intent data of a maker. This can/should be
+	/// part of another pallet (such as a limit order protocol pallet) or stored
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug)]
+	pub struct SwapIntent<AccountId, Balance, BlockNumber> {
+		pub hashlock: H256,
+		/// Account that intents to swap
+		pub maker: AccountId,
+		/// Amount they own and want to provide
+		pub src_amount: Balance,
+		/// Amount they own and want to receive
+		pub dst_amount: Balance,
+		/// Address on the destination chain
+		pub dst_address: DstAddress,
+		/// The destination chain
+		pub dst_chain: ChainId,
+		/// Last block to fill the intent, or none to keep it open until it is
+		/// cancelled
+		pub timeout_after_block: Option<BlockNumber>,
+		pub nonce: u64,
+		/// Curve of the amount paid on the destination chain, which decays to
+		/// `dst_amount`
+		pub auction: AuctionCurve<Balance, BlockNumber>,
+		/// Smallest amount of a partial fill, so that resolvers can't
+		/// fragment the intent with dust fills
+		pub min_fill_amount: Balance,
+		/// How resolvers may fill the intent
+		pub flags: IntentFlags,
+		/// Epoch of the maker, below which `cancel_all_intents` invalidates
+		/// the intent
+		pub epoch: u32,
+		/// Resolvers that may not take or fill the intent
+		pub denied_resolvers: DeniedResolvers<AccountId>,
+		/// Number of decimals of the token paid on the destination chain, or
+		/// none for the `decimals` of its chain profile
+		pub dst_decimals: Option<u8>,
+	}
+
+	/// Reusable parameters of the intents of a maker, from which
+	/// `create_intent_from_template` opens recurring swaps.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct IntentTemplate<Balance> {
+		pub src_amount: Balance,
+		pub dst_amount: Balance,
+		pub dst_address: DstAddress,
+		pub dst_chain: ChainId,
+		pub dst_decimals: Option<u8>,
+		/// Number of blocks that the intents stay open, or none to keep them
+		/// open until they are cancelled
+		pub validity: Option<u32>,
+		pub flags: IntentFlags,
+		pub min_fill_amount: Balance,
+	}
+
+	pub type IntentTemplateOf<T> = IntentTemplate<BalanceOf<T>>;
+
+	/// Maximum number of intent templates per maker.
+	pub const MAX_INTENT_TEMPLATES: u32 = 16;
+
+	/// Maximum number of resolvers that a maker denies per intent.
+	pub const MAX_DENIED_RESOLVERS: u32 = 16;
+
+	/// Maximum number of intents that a resolver fills in one batch.
+	pub const MAX_BATCH_FILLS: u32 = 16;
+
+	/// Maximum number of HTLCs that a taker cancels in one batch.
+	pub const MAX_BATCH_CANCELS: u32 = 32;
+
+	/// Maximum number of HTLCs that a taker withdraws with one secret.
+	pub const MAX_BATCH_WITHDRAWALS: u32 = 16;
+
+	/// Maximum number of intents that a maker creates in one batch.
+	pub const MAX_BATCH_INTENTS: u32 = 16;
+
+	/// Number of buckets in which the volume of an account is tracked over
+	/// the `VolumeWindow`.
+	pub const VOLUME_BUCKETS: u32 = 30;
+
+	/// Maximum number of `FeeTiers`.
+	pub const MAX_FEE_TIERS: u32 = 8;
+
+	pub type FeeTiersOf<T> = BoundedVec<FeeTier<BalanceOf<T>>, ConstU32<MAX_FEE_TIERS>>;
+
+	/// Maximum number of `ResolverTiers`.
+	pub const MAX_RESOLVER_TIERS: u32 = 8;
+
+	pub type ResolverTiersOf<T> =
+		BoundedVec<ResolverTier<BalanceOf<T>>, ConstU32<MAX_RESOLVER_TIERS>>;
+
+	/// Resolvers denied by the maker of an intent.
+	pub type DeniedResolvers<AccountId> = BoundedVec<AccountId, ConstU32<MAX_DENIED_RESOLVERS>>;
+
+	/// Fill policy of a swap intent, after the `MakerTraits` of the 1inch
+	/// Limit Order Protocol.
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Copy, Debug)]
+	pub struct IntentFlags {
+		/// The intent is filled by the first resolver that takes it or not at
+		/// all, so it can't be taken over.
+		pub fill_or_kill: bool,
+		/// The intent can be filled again after a stalled fill is taken over.
+		pub allow_multiple_fills: bool,
+		/// A resolver may fill part of the intent, of at least its
+		/// `min_fill_amount`, which leaves the rest to the maker.
+		pub allow_partial_fills: bool,
+	}
+
+	impl Default for IntentFlags {
+		fn default() -> Self {
+			Self { fill_or_kill: false, allow_multiple_fills: true, allow_partial_fills: false }
+		}
+	}
+
+	impl<AccountId, Balance, BlockNumber> SwapIntent<AccountId, Balance, BlockNumber>
+	where
+		Balance: AtLeast32BitUnsigned + Copy,
+		BlockNumber: AtLeast32BitUnsigned + Copy,
+	{
+		/// Amount paid on the destination chain by a resolver filling the
+		/// intent at block `now`.
+		pub fn dst_amount_at(&self, now: BlockNumber) -> Balance {
+			self.auction.amount_at(self.dst_amount, now)
+		}
+
+		/// Whether the intent timed out at block `now`.
+		pub fn is_expired_at(&self, now: BlockNumber) -> bool {
+			self.timeout_after_block.is_some_and(|timeout| now > timeout)
+		}
+	}
+
+	/// Enum to keep track of the state of each swap intent submitted
+	/// by the maker. We should remove intents after they are completed
+	/// or cancelled and keep track of the hash/nonce of the ones that
+	/// have already been part of the chain. This is an improvement
+	/// over the current implementation that should be implemented.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub enum IntentStatus<AccountId> {
+		/// Intent is active and available for resolvers
+		Active,
+		/// Intent is reserved by a resolver that is escrowing the funds on the
+		/// other chain
+		Reserved { resolver: AccountId },
+		/// Intent is being fulfilled (resolver created source HTLC)
+		InProgress { resolver: AccountId, htlc_id: H256 },
+		/// Intent has been completed successfully
+		Completed,
+		/// Intent was cancelled by maker
+		Cancelled,
+		/// Intent expired without fulfillment
+		Expired,
+	}
+
+	impl<AccountId> IntentStatus<AccountId> {
+		/// Whether the intent is open: active, reserved or in progress.
+		pub fn is_open(&self) -> bool {
+			matches!(self, Self::Active | Self::Reserved { .. } | Self::InProgress { .. })
+		}
+
+		/// The resolver that holds the intent, reserved or in progress.
+		pub fn resolver(&self) -> Option<&AccountId> {
+			match self {
+				Self::Reserved { resolver } | Self::InProgress { resolver, .. } => Some(resolver),
+				_ => None,
+			}
+		}
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		pub intent: SwapIntent<AccountId, Balance, BlockNumber>,
+		pub status: IntentStatus<AccountId>,
+		pub created_at: BlockNumber,
+		/// Safety deposit held from the maker.
+		pub maker_deposit: Balance,
+		/// Block when a resolver took the intent.
+		pub taken_at: Option<BlockNumber>,
+		/// Bond held from the resolver that reserved the intent.
+		pub reservation_bond: Balance,
+		/// Number of source HTLCs that filled the intent.
+		pub fills: u32,
+		/// Storage deposit held from the maker of a good-til-cancel intent.
+		pub storage_deposit: Balance,
+	}
+
+	#[pallet::storage]
+	pub type SwapIntents<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		H256,
+		StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		OptionQuery,
+	>;
+
+	/// Parts of an intent that is filled in parts, with the secrets of the
+	/// Merkle tree whose root is its hashlock.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Copy, Debug)]
+	pub struct MultiFill<Balance> {
+		/// Number of parts, with a secret each and one more for the fill
+		/// that completes the intent.
+		pub parts: u16,
+		/// Amount of the intent that its fills took, withdrawn, cancelled or
+		/// in progress.
+		pub filled: Balance,
+	}
+
+	/// Intents that are filled in parts.
+	#[pallet::storage]
+	pub type MultiFillIntents<T: Config> =
+		StorageMap<_, Blake2_128Concat, H256, MultiFill<BalanceOf<T>>, OptionQuery>;
+
+	/// Index of the secret that withdraws each source HTLC that fills an
+	/// intent in parts.
+	#[pallet::storage]
+	pub type FillIndexes<T: Config> = StorageMap<_, Blake2_128Concat, H256, u16, OptionQuery>;
+
+	/// Current epoch of each maker. Open intents of earlier epochs can no
+	/// longer be taken or filled.
+	#[pallet::storage]
+	pub type MakerEpochs<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// Keys of the open intents of each maker, bounded by the
+	/// `MaxIntentsPerMaker`.
+	#[pallet::storage]
+	pub type MakerIntents<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<H256, T::MaxIntentsPerMaker>,
+		ValueQuery,
+	>;
+
+	/// Number of intents that each resolver holds reserved or in progress,
+	/// bounded by the `MaxIntentsPerResolver`.
+	#[pallet::storage]
+	pub type ResolverIntents<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// Aggregate health metrics of the HTLCs and swap intents, exposed
+	/// through the runtime API for monitoring the escrow solvency.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+	pub struct HtlcMetrics<Balance, BlockNumber> {
+		/// Number of active HTLCs.
+		pub active_htlcs: u32,
+		/// Amount held for the swaps of the active destination HTLCs.
+		pub held_swap_amount: Balance,
+		/// Amount held for the safety deposits of the active HTLCs.
+		pub held_safety_deposit: Balance,
+		/// Amount held from the makers for the active, reserved and in
+		/// progress intents.
+		pub held_maker_intent_amount: Balance,
+		/// Amount held for the safety deposits of the makers.
+		pub held_maker_safety_deposit: Balance,
+		/// Amount held for the reservation bonds of the resolvers.
+		pub held_reservation_bond: Balance,
+		/// Amount held for the bonds of the optimistic claims.
+		pub held_claim_bond: Balance,
+		/// Number of intents per status.
+		pub active_intents: u32,
+		pub in_progress_intents: u32,
+		pub completed_intents: u32,
+		pub cancelled_intents: u32,
+		pub expired_intents: u32,
+		/// Number of blocks since the oldest active HTLC was deployed.
+		pub oldest_active_htlc_age: Option<BlockNumber>,
+	}
+
+	/// Swaps settled by an account as maker and as taker, with their volume
+	/// in the native token.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+	pub struct SwapStats<Balance, BlockNumber> {
+		pub maker_swaps: u32,
+		pub maker_volume: Balance,
+		pub taker_swaps: u32,
+		pub taker_volume: Balance,
+		/// Block of the last swap settled by the account.
+		pub last_active: BlockNumber,
+	}
+
+	/// Discount of the vault fee for the accounts that settled at least
+	/// `min_volume` over the `VolumeWindow`.
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug)]
+	pub struct FeeTier<Balance> {
+		pub min_volume: Balance,
+		pub discount: Perbill,
+	}
+
+	/// Terms of the registered resolvers backed by at least `min_stake` of
+	/// delegated stake.
+	#[derive(Encode, Decode, TypeInfo, MaxEncodedLen, Eq, PartialEq, Clone, Debug)]
+	pub struct ResolverTier<Balance> {
+		pub min_stake: Balance,
+		/// Discount of the `MinSafetyDeposit` of their HTLCs.
+		pub deposit_discount: Perbill,
+		/// Discount of the vault fee, unless their `FeeTiers` give more.
+		pub fee_discount: Perbill,
+	}
+
+	/// Reason why an account cannot escrow the funds of a new HTLC, as
+	/// reported by `can_create_htlc`.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub enum CannotCreateHtlc<Balance> {
+		/// The account is in the `Blocked` list.
+		Blocked,
+		/// The account is not allowed by the `ComplianceFilter`.
+		NotCompliant,
+		/// The safety deposit is below the `MinSafetyDeposit`.
+		SafetyDepositTooLow { minimum: Balance },
+		/// The free balance does not cover the amount and the safety deposit.
+		InsufficientBalance { required: Balance, balance: Balance },
+		/// Part of the balance is locked or frozen, e.g. because it is staked.
+		Frozen { required: Balance, available: Balance },
+		/// The account would be left below the existential deposit.
+		ExistentialDeposit { required: Balance, available: Balance },
+	}
+
+	/// Side of a swap whose costs are estimated by `estimate_swap_costs`.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Copy, Debug)]
+	pub enum SwapRole {
+		/// The maker, who creates the intent and withdraws the destination HTLC.
+		Maker,
+		/// The taker, who reserves the intent and escrows both HTLCs.
+		Taker,
+	}
+
+	/// Costs of a swap in the native token, besides the swap amount, so that
+	/// wallets can show them before the user signs.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+	pub struct SwapCosts<Balance> {
+		/// Safety deposits and bonds held until the swap is settled.
+		pub safety_deposit: Balance,
+		/// Deposit held for the storage of an intent without a timeout.
+		pub storage_deposit: Balance,
+		/// Fee owed to the vault if the swap amount is drawn from it.
+		pub protocol_fee: Balance,
+		/// Weight of the extrinsics that the role submits in the worst case,
+		/// with their base weight.
+		pub weight: Weight,
+		/// Fee for the `weight`.
+		pub weight_fee: Balance,
+		/// Encoded length of the calls of those extrinsics, without their
+		/// signatures and extensions.
+		pub length: u32,
+		/// Fee for the `length`.
+		pub length_fee: Balance,
+	}
+
+	/// Accounts that are not allowed to create or settle HTLCs and swap
+	/// intents, nor move funds through the vault, delegations, sponsorships,
+	/// safety deposits or challenges, managed by the `GovernanceOrigin`.
+	#[pallet::storage]
+	pub type Blocked<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, (), OptionQuery>;
+
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
@@ -202,671 +1755,5683 @@ pub mod pallet {
 			htlc_id: H256,
 			hashlock: H256,
 			maker: T::AccountId,
-			taker: T::AccountId,
+			taker: T::AccountId,
+			amount: BalanceOf<T>,
+			safety_deposit: BalanceOf<T>,
+		},
+		/// Destination HTLC reserved by the taker, who must activate it
+		/// before `activate_before`.
+		DstHtlcReserved { htlc_id: H256, taker: T::AccountId, activate_before: BlockNumberFor<T> },
+
+		/// Reserved destination HTLC that was not activated in time, whose
+		/// safety deposit was refunded.
+		DstHtlcReservationExpired { htlc_id: H256 },
+
+		/// HTLC withdrawn.
+		HtlcWithdrawn {
+			htlc_id: H256,
+			secret: Vec<u8>,
+			amount: BalanceOf<T>,
+			beneficiary: T::AccountId,
+			safety_deposit_recipient: T::AccountId,
+		},
+		/// Taker claimed the source HTLC, asserting that the destination leg
+		/// settled, unless challenged until `challenge_until`.
+		OptimisticClaimSubmitted {
+			htlc_id: H256,
+			claimant: T::AccountId,
+			challenge_until: BlockNumberFor<T>,
+		},
+
+		/// Optimistic claim proven wrong by `challenger`, who got the bond.
+		OptimisticClaimRejected { htlc_id: H256, challenger: T::AccountId, bond: BalanceOf<T> },
+
+		/// Source HTLC settled to the taker by an unchallenged claim.
+		HtlcSettledOptimistically { htlc_id: H256, amount: BalanceOf<T> },
+
+		/// Unchallenged claim dropped with its bond returned, because a party
+		/// of the HTLC is no longer allowed to swap. The HTLC is settled with
+		/// the secret or cancelled as usual.
+		OptimisticClaimDropped { htlc_id: H256, claimant: T::AccountId },
+
+		/// HTLC cancelled.
+		HtlcCancelled { htlc_id: H256, refund_recipient: T::AccountId },
+
+		/// HTLC of a batch that could not be cancelled.
+		HtlcCancellationFailed { htlc_id: H256, error: DispatchError },
+
+		/// HTLC of a batch that could not be withdrawn.
+		HtlcWithdrawalFailed { htlc_id: H256, error: DispatchError },
+
+		/// Source HTLC created for a swap to Bitcoin, with the parameters that
+		/// the resolver needs to lock the mirroring script HTLC on Bitcoin.
+		BitcoinCounterpartRequested {
+			htlc_id: H256,
+			payment_hash: H256,
+			recipient: DstAddress,
+			amount: BalanceOf<T>,
+			cancellation_after: BlockNumberFor<T>,
+		},
+
+		/// Maker or taker agreed to cancel an HTLC before its timelocks expire.
+		EarlyCancellationAgreed { htlc_id: H256, who: T::AccountId },
+
+		/// Dormant HTLC swept back to its depositors by governance.
+		HtlcSwept { htlc_id: H256 },
+
+		/// Governance reconciled the holds of an HTLC, cancelling it if it was
+		/// still active.
+		HtlcReconciled { htlc_id: H256 },
+
+		/// A hold that no active HTLC accounts for was released.
+		OrphanedHoldReleased { who: T::AccountId, reason: HoldReason, amount: BalanceOf<T> },
+
+		/// The terminal HTLC or intent `key` left a hold of `who` behind, which
+		/// can be swept after the `EscheatmentPeriod` unless it is reconciled.
+		ResidualHoldNoted { key: H256, who: T::AccountId, reason: HoldReason, amount: BalanceOf<T> },
+
+		/// The unclaimed hold that `key` left behind was swept to the
+		/// `UnclaimedHandler`.
+		ResidualHoldEscheated {
+			key: H256,
+			who: T::AccountId,
+			reason: HoldReason,
+			amount: BalanceOf<T>,
+		},
+
+		/// Caller of a public cancellation rewarded from the safety deposit.
+		PublicCancellationRewarded { htlc_id: H256, caller: T::AccountId, reward: BalanceOf<T> },
+
+		/// Swap intent created by maker, with the complete order so that the
+		/// resolvers can quote from the events alone.
+		SwapIntentCreated { intent_key: H256, intent: SwapIntentOf<T> },
+
+		/// Swap intent.
+		SwapIntentCancelled {
+			maker: T::AccountId,
+			nonce: u64,
+			src_amount: BalanceOf<T>,
+			dst_amount: BalanceOf<T>,
+			dst_address: DstAddress,
+			hashlock: H256,
+		},
+
+		/// Swap intent timed out before any resolver filled it.
+		SwapIntentExpired { maker: T::AccountId, nonce: u64 },
+
+		/// Swap intent created to be filled in `parts` parts, with the
+		/// secrets of the Merkle tree whose root is its hashlock.
+		MultiFillIntentCreated { intent_key: H256, parts: u16 },
+
+		/// Source HTLC filled the intent in part, to be withdrawn with the
+		/// secret `index` of its tree.
+		IntentPartFilled { intent_key: H256, htlc_id: H256, index: u16 },
+
+		/// The fill of an intent that is filled in parts was settled with
+		/// some of the intent left, which is open again.
+		IntentReopened { intent_key: H256, filled: BalanceOf<T> },
+
+		/// Active HTLC opened for the public withdrawal or cancellation.
+		StageEntered { htlc_id: H256, stage: Stage },
+
+		/// Active HTLC within the `SettlementWarningPeriod` of its
+		/// cancellation.
+		SettlementAtRisk {
+			htlc_id: H256,
+			taker: T::AccountId,
+			cancellation_after: BlockNumberFor<T>,
+		},
+
+		/// Intent template of a maker set, or removed if none.
+		IntentTemplateSet {
+			maker: T::AccountId,
+			template_id: u32,
+			template: Option<IntentTemplateOf<T>>,
+		},
+
+		/// Sponsor allowed to post safety deposits for a resolver.
+		SponsorAllowanceSet {
+			sponsor: T::AccountId,
+			resolver: T::AccountId,
+			allowance: BalanceOf<T>,
+		},
+
+		/// Resolver chose the sponsor of its safety deposits.
+		SponsorSet { resolver: T::AccountId, sponsor: Option<T::AccountId> },
+
+		/// Safety deposit of the HTLC posted by a sponsor.
+		SafetyDepositSponsored { htlc_id: H256, sponsor: T::AccountId },
+
+		/// Taker increased the safety deposit of the HTLC to `safety_deposit`.
+		SafetyDepositToppedUp { htlc_id: H256, amount: BalanceOf<T>, safety_deposit: BalanceOf<T> },
+
+		/// Resolver allowed to draw on the vault up to the credit limit.
+		ResolverRegistered { who: T::AccountId, credit_limit: BalanceOf<T> },
+
+		/// Resolver no longer allowed to draw on the vault.
+		ResolverDeregistered { who: T::AccountId },
+
+		/// Stake delegated to the resolver.
+		Delegated { delegator: T::AccountId, resolver: T::AccountId, amount: BalanceOf<T> },
+
+		/// Stake undelegated from the resolver, withdrawable from `unlock_at`.
+		Undelegated {
+			delegator: T::AccountId,
+			resolver: T::AccountId,
+			amount: BalanceOf<T>,
+			unlock_at: BlockNumberFor<T>,
+		},
+
+		/// Unbonding stake released to the delegator.
+		DelegationWithdrawn {
+			delegator: T::AccountId,
+			resolver: T::AccountId,
+			amount: BalanceOf<T>,
+		},
+
+		/// Stake of the delegators of the resolver slashed with it.
+		DelegatorsSlashed { resolver: T::AccountId, amount: BalanceOf<T> },
+
+		/// The resolver keeps `commission` of its earnings, sharing the rest
+		/// with its delegators.
+		ResolverCommissionSet { resolver: T::AccountId, commission: Option<Perbill> },
+
+		/// Earnings of the resolver shared with its delegators.
+		EarningsShared { resolver: T::AccountId, amount: BalanceOf<T> },
+
+		/// The discounts of the vault fee by volume were replaced.
+		FeeTiersSet { tiers: FeeTiersOf<T> },
+
+		/// The terms of the resolvers by delegated stake were replaced.
+		ResolverTiersSet { tiers: ResolverTiersOf<T> },
+
+		/// Liquidity provider deposited funds into the vault for shares.
+		VaultDeposited { who: T::AccountId, amount: BalanceOf<T>, shares: BalanceOf<T> },
+
+		/// Liquidity provider redeemed shares of the vault for funds.
+		VaultWithdrawn { who: T::AccountId, amount: BalanceOf<T>, shares: BalanceOf<T> },
+
+		/// Shares of the vault queued for redemption until the drawn funds come
+		/// back.
+		VaultWithdrawalQueued { who: T::AccountId, shares: BalanceOf<T> },
+
+		/// Swap amount of the HTLC drawn from the vault.
+		VaultDrawn { htlc_id: H256, resolver: T::AccountId, amount: BalanceOf<T> },
+
+		/// Withdrawn HTLC funded by the vault left the resolver owing the swap
+		/// amount and the fee.
+		VaultDebtIncurred {
+			htlc_id: H256,
+			resolver: T::AccountId,
+			amount: BalanceOf<T>,
+			fee: BalanceOf<T>,
+		},
+
+		/// Resolver repaid part of its debt to the vault.
+		VaultRepaid { resolver: T::AccountId, amount: BalanceOf<T> },
+
+		/// Fees or forfeited deposits paid into the insurance fund.
+		InsuranceFunded { amount: BalanceOf<T> },
+
+		/// Compensation paid out of the insurance fund by governance.
+		InsurancePaidOut { who: T::AccountId, amount: BalanceOf<T> },
+
+		/// The hold of an HTLC or intent `key` was short of the escrowed
+		/// amount, so only what was left of it was released.
+		PartialRelease {
+			key: H256,
+			who: T::AccountId,
+			reason: HoldReason,
+			expected: BalanceOf<T>,
+			released: BalanceOf<T>,
+		},
+
+		/// Governance cleared the deficit that a partial release left for the
+		/// HTLC or intent `key`.
+		EscrowDeficitCleared {
+			key: H256,
+			who: T::AccountId,
+			reason: HoldReason,
+			amount: BalanceOf<T>,
+		},
+
+		/// Terminal HTLC moved to the archive.
+		HtlcArchived { htlc_id: H256 },
+
+		/// The maker of a destination HTLC set the conversion of its swap
+		/// amount, or removed it.
+		SettlementConversionSet {
+			htlc_id: H256,
+			conversion: Option<SettlementConversion<T::ConversionAsset, BalanceOf<T>>>,
+		},
+
+		/// The swap amount withdrawn to the maker was converted.
+		SettlementConverted {
+			htlc_id: H256,
+			asset: T::ConversionAsset,
+			amount_in: BalanceOf<T>,
+			amount_out: BalanceOf<T>,
+		},
+
+		/// The swap amount could not be converted and stays with the maker in
+		/// the native token.
+		SettlementConversionFailed { htlc_id: H256, error: DispatchError },
+
+		/// The maker of a destination HTLC set the beneficiary on another chain
+		/// of its swap amount, or removed it.
+		SettlementDeliverySet { htlc_id: H256, beneficiary: Option<T::RemoteBeneficiary> },
+
+		/// The swap amount withdrawn to the maker was delivered to its
+		/// beneficiary on another chain.
+		SettlementDelivered {
+			htlc_id: H256,
+			beneficiary: T::RemoteBeneficiary,
+			amount: BalanceOf<T>,
+		},
+
+		/// The swap amount could not be delivered and stays with the maker.
+		SettlementDeliveryFailed { htlc_id: H256, error: DispatchError },
+
+		/// Account added to the blocklist.
+		AccountBlocked { who: T::AccountId },
+
+		/// Account removed from the blocklist.
+		AccountUnblocked { who: T::AccountId },
+
+		/// Intent reserved by the resolver, who posted `bond`.
+		IntentTaken { maker: T::AccountId, nonce: u64, resolver: T::AccountId, bond: BalanceOf<T> },
+
+		/// Quote posted by a resolver against an intent, replacing its
+		/// previous one.
+		QuotePosted {
+			intent_key: H256,
+			resolver: T::AccountId,
+			dst_amount: BalanceOf<T>,
+			expires_at: BlockNumberFor<T>,
+		},
+
+		/// Account registered as a watchtower.
+		WatchtowerRegistered { who: T::AccountId },
+
+		/// Watchtower deregistered.
+		WatchtowerDeregistered { who: T::AccountId },
+
+		/// Watchtower assigned to watch an HTLC.
+		WatchtowerAssigned { htlc_id: H256, watchtower: T::AccountId },
+
+		/// Watchtower released from watching an HTLC.
+		WatchtowerReleased { htlc_id: H256, watchtower: T::AccountId },
+
+		/// Share of the reward of a public action paid to the watchtower of
+		/// the HTLC.
+		WatchtowerRewarded { htlc_id: H256, watchtower: T::AccountId, amount: BalanceOf<T> },
+
+		/// Quote of a resolver withdrawn, or pushed out by a better one.
+		QuoteWithdrawn { intent_key: H256, resolver: T::AccountId },
+
+		/// Quote accepted by the maker, who reserved the intent for the
+		/// resolver.
+		QuoteAccepted { intent_key: H256, resolver: T::AccountId, dst_amount: BalanceOf<T> },
+
+		/// Open intents of `maker` from epochs before `before_epoch`
+		/// invalidated.
+		IntentsInvalidated { maker: T::AccountId, before_epoch: u32 },
+
+		/// Stalled intent taken over by `resolver` from `previous`, who
+		/// forfeited its bond to `resolver`.
+		IntentTakenOver {
+			maker: T::AccountId,
+			nonce: u64,
+			resolver: T::AccountId,
+			previous: T::AccountId,
+			forfeited: BalanceOf<T>,
+		},
+
+		/// Bond of the resolver that reserved the intent forfeited to the
+		/// maker, as the resolver did not fill it.
+		ReservationBondForfeited {
+			intent_key: H256,
+			resolver: T::AccountId,
+			maker: T::AccountId,
+			amount: BalanceOf<T>,
+		},
+
+		/// Safety deposit of the taker of a source HTLC that it abandoned
+		/// forfeited to the `ForfeitHandler`.
+		SafetyDepositForfeited { htlc_id: H256, amount: BalanceOf<T> },
+
+		/// The resolver cannot take or fill intents until block `until`.
+		ResolverCooldownStarted { resolver: T::AccountId, until: BlockNumberFor<T> },
+
+		/// Safety deposit of the maker forfeited to the resolver of the intent.
+		MakerDepositForfeited {
+			intent_key: H256,
+			maker: T::AccountId,
+			resolver: T::AccountId,
+			amount: BalanceOf<T>,
+		},
+
+		/// Source HTLC bound to the escrow that mirrors it on an EVM chain.
+		CounterpartEscrowBound { htlc_id: H256, escrow: H160 },
+
+		/// The taker recorded where it deployed the escrow of the other leg.
+		CounterpartReferenceRecorded { htlc_id: H256, reference: CounterpartReference },
+
+		/// The HTLC is the downstream leg of the active HTLC `upstream`.
+		ParentHashlockDeclared { htlc_id: H256, parent_hashlock: H256, upstream: H256 },
+
+		/// HTLC proven to correspond to a 1inch Fusion order signed by the maker.
+		FusionOrderVerified { htlc_id: H256, order_hash: H256, maker: H160 },
+
+		/// Profile of a chain set.
+		ChainProfileSet { chain_id: ChainId, profile: ChainProfile<BlockNumberFor<T>> },
+
+		/// Timelocks recommended for a chain set, or removed with `None`.
+		TimelockTemplateSet { chain_id: ChainId, template: Option<TimelockTemplate> },
+
+		/// Profile of a chain removed.
+		ChainProfileRemoved { chain_id: ChainId },
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// Error name example. Should be descriptive.
+		NoneValue,
+
+		/// Invalid caller for the operation.
+		InvalidCaller,
+
+		/// Invalid timelock configuration.
+		InvalidTimelocks,
+
+		/// The swap amount of the HTLC is zero.
+		ZeroAmount,
+
+		/// The hashlock of the HTLC is zero.
+		ZeroHashlock,
+
+		/// The provided immutables do not match the ones stored.
+		InvalidImmutables,
+
+		/// The hash of the provided secret does not match the hashlock of the contract.
+		InvalidSecret,
+
+		/// The secret is longer than the `MAX_SECRET_LEN`.
+		SecretTooLong,
+
+		/// Cannot lock funds as the caller has insufficient balance.
+		InsufficientBalance,
+
+		/// The withdrawal was attempted too early and it's not allowed
+		/// based on the current timelock configuration.
+		EarlyWithdrawal,
+
+		/// The public withdrawal was attempted too early and it's not allowed
+		/// based on the current timelock configuration.
+		EarlyPublicWithdrawal,
+
+		/// The cancellation was attempted too early and it's not allowed
+		/// based on the current timelock configuration.
+		EarlyCancellation,
+
+		/// The maker attempted to cancel an intent before the
+		/// `IntentCancellationDelay` passed since a resolver took it.
+		EarlyIntentCancellation,
+
+		/// The public cancellation was attempted too early and it's not allowed
+		/// based on the current timelock configuration.
+		EarlyPublicCancellation,
+
+		/// The cancellation of the HTLC is beyond the `MaxTimelockHorizon`.
+		TimelockTooFar,
+
+		/// The cancellation timestamp of the source HTLC has passed or is
+		/// within the `TimestampSafetyMargin`, e.g. a block number given as a
+		/// timestamp.
+		SrcCancellationPassed,
+
+		/// The cancellation timestamp of the source HTLC is beyond the
+		/// `MaxTimelockHorizon`, e.g. in milliseconds instead of seconds.
+		SrcCancellationTooFar,
+
+		/// Too many HTLCs are already queued for cancellation at the block.
+		CancellationQueueFull,
+
+		/// Too many HTLCs already enter a stage at the block.
+		StageQueueFull,
+
+		/// Too many HTLCs are already at risk at the block.
+		SettlementWarningQueueFull,
+
+		/// The maker has no intent template with the id.
+		NoIntentTemplate,
+
+		/// The intent template does not open valid intents.
+		InvalidIntentTemplate,
+
+		/// The maker already has `MAX_INTENT_TEMPLATES` intent templates.
+		TooManyIntentTemplates,
+
+		/// The safety deposit exceeds the allowance of the sponsor.
+		SponsorAllowanceExceeded,
+
+		/// The safety deposit cannot be topped up by nothing.
+		InvalidTopUp,
+
+		/// The account is not a registered resolver.
+		NotResolver,
+
+		/// The draw exceeds the credit limit of the resolver.
+		CreditLimitExceeded,
+
+		/// The resolver is backed by the maximum number of delegators.
+		TooManyDelegators,
+
+		/// The delegator has less stake behind the resolver.
+		InsufficientDelegation,
+
+		/// The undelegated stake is still unbonding.
+		DelegationUnbonding,
+
+		/// The fee tiers are not in strictly increasing order of the volume.
+		FeeTiersNotSorted,
+
+		/// The resolver tiers are not in strictly increasing order of the
+		/// stake.
+		ResolverTiersNotSorted,
+
+		/// The vault does not hold enough funds that are not drawn.
+		VaultIlliquid,
+
+		/// The account does not hold enough shares of the vault.
+		InsufficientShares,
+
+		/// The amount is too small to be deposited in or repaid to the vault.
+		InvalidVaultAmount,
+
+		/// The draw would take the vault beyond the `MaxVaultUtilization`.
+		VaultUtilizationExceeded,
+
+		/// Too many withdrawals from the vault are already queued.
+		VaultWithdrawalQueueFull,
+
+		/// The amount is below the existential deposit of an escrow account.
+		EscrowBelowMinimum,
+
+		/// The HTLC has not been active long enough to be swept.
+		EarlySweep,
+
+		/// The HTLC or intent left no hold of the account behind.
+		NoResidualHold,
+
+		/// The residual hold is not unclaimed for the `EscheatmentPeriod` yet.
+		EarlyEscheatment,
+
+		/// No partial release left a deficit for the hold.
+		NoEscrowDeficit,
+
+		/// The caller already agreed to cancel the HTLC.
+		AlreadyAgreed,
+
+		/// The withdrawal was attempted too late and it's not allowed
+		/// based on the current timelock configuration.
+		LateWithdrawal,
+
+		/// The public withdrawal was attempted too late and it's not allowed
+		/// based on the current timelock configuration.
+		LatePublicWithdrawal,
+
+		/// HTLC already exists.
+		HtlcAlreadyExists,
+
+		/// No destination HTLC is pending activation with this id.
+		DstHtlcNotPending,
+
+		/// The activation period of the reserved destination HTLC is over.
+		DstActivationExpired,
+
+		/// The runtime cannot verify challenges, so it accepts no optimistic
+		/// claims.
+		OptimisticSettlementDisabled,
+
+		/// The source HTLC is already claimed optimistically.
+		AlreadyClaimed,
+
+		/// The challenge window would not end before the cancellation.
+		LateOptimisticClaim,
+
+		/// No optimistic claim of the source HTLC.
+		NoOptimisticClaim,
+
+		/// The challenge window of the claim is over.
+		ChallengeWindowClosed,
+
+		/// The claim can still be challenged.
+		ChallengeWindowOpen,
+
+		/// The HTLC has an unchallenged optimistic claim, which settles it to
+		/// the taker instead.
+		OptimisticClaimPending,
+
+		/// The proof does not show that the destination leg did not settle.
+		InvalidSettlementProof,
+
+		/// HTLC does not exists.
+		HtlcDoesNotExist,
+
+		/// HTLC is not active.
+		HtlcNotActive,
+
+		/// HTLC is still active and cannot be archived.
+		HtlcStillActive,
+
+		/// The hash function of the hashlock does not match the chain profile.
+		InvalidHashFunction,
+
+		/// The timelocks do not leave the margin required by the chain profile.
+		InsufficientTimelockMargin,
+
+		/// No profile is registered for the chain.
+		UnknownChain,
+
+		/// No timelocks are recommended for the chain.
+		NoTimelockTemplate,
+
+		/// The destination token has more than `MAX_DECIMALS`, or the
+		/// `dst_amount` of the intent overflows with the `NativeDecimals`.
+		InvalidDecimals,
+
+		/// The HTLC is already bound to an escrow on the other chain.
+		CounterpartAlreadyBound,
+
+		/// The immutables of the escrow on the other chain do not match the HTLC.
+		CounterpartMismatch,
+
+		/// No other active HTLC is locked with the parent hashlock.
+		UpstreamHtlcNotFound,
+
+		/// A downstream leg must be cancellable before its upstream leg.
+		TimelockNotShorterThanUpstream,
+
+		/// The hash of the order does not match the order hash of the HTLC.
+		OrderHashMismatch,
+
+		/// The order is not signed by its maker.
+		InvalidOrderSignature,
+
+		/// The destination address is malformed.
+		InvalidDstAddress,
+
+		/// Intent already exists.
+		IntentAlreadyExists,
+
+		/// The maker has `MaxIntentsPerMaker` open intents.
+		TooManyIntents,
+
+		/// Intent does not already exists.
+		IntentDoesNotExists,
+
+		/// Intent is not active.
+		IntentNotActive,
+
+		/// The quote is below the `dst_amount` of the intent, or expires in
+		/// the past or after the intent.
+		InvalidQuote,
+
+		/// The quote book of the intent is full of better quotes.
+		QuoteBookFull,
+
+		/// The resolver has no live quote against the intent.
+		QuoteNotFound,
+
+		/// The account is not a registered watchtower.
+		NotWatchtower,
+
+		/// The caller is already a registered watchtower.
+		AlreadyWatchtower,
+
+		/// The watchtower still watches HTLCs.
+		WatchtowerOnDuty,
+
+		/// Another watchtower already watches the HTLC.
+		HtlcAlreadyWatched,
+
+		/// The watchtower watches `MAX_WATCHTOWER_DUTIES` HTLCs already.
+		TooManyDuties,
+
+		/// The caller does not watch the HTLC.
+		NotWatching,
+
+		/// Intent is reserved by another resolver.
+		IntentReserved,
+
+		/// The resolver of the intent still has time to fill it.
+		FillNotStalled,
+
+		/// The `min_fill_amount` of the intent is above its `src_amount`.
+		InvalidMinFillAmount,
+
+		/// The maker does not allow partial fills of the intent.
+		PartialFillsNotAllowed,
+
+		/// The partial fill is below the `min_fill_amount` of the intent.
+		FillBelowMinimum,
+
+		/// An intent that is filled in parts needs some parts, and flags that
+		/// allow partial and multiple fills.
+		InvalidFillParts,
+
+		/// The fill ends in the same part of the intent as the previous one,
+		/// whose secret may be revealed already.
+		FillIndexReused,
+
+		/// The secret is not of the index of the fill of the HTLC.
+		InvalidFillIndex,
+
+		/// The intent was already filled and doesn't allow multiple fills.
+		IntentAlreadyFilled,
+
+		/// The intent is fill-or-kill and can't be taken over.
+		FillOrKillIntent,
+
+		/// The resolver holds the maximum number of intents in progress.
+		TooManyIntentsInProgress,
+
+		/// A fill must take some of the intent and not more than all of it.
+		InvalidFillAmount,
+
+		/// The epoch is below the current epoch of the maker.
+		InvalidEpoch,
+
+		/// The maker invalidated the epoch of the intent.
+		IntentInvalidated,
+
+		/// The maker denied the resolver from taking or filling the intent.
+		ResolverDenied,
+
+		/// The resolver failed a fill or was slashed recently.
+		ResolverCoolingDown,
+
+		/// Intent expired.
+		IntentExpired,
+
+		/// The auction curve of the intent goes below its `dst_amount` or up
+		/// again.
+		InvalidAuction,
+
+		/// The hashlock is already used by an active HTLC or swap intent of
+		/// another order.
+		HashlockInUse,
+
+		/// The maximum number of HTLCs sharing this hashlock was reached.
+		TooManyHtlcsForHashlock,
+
+		/// The maximum number of HTLCs of the order was reached.
+		TooManyOrderLegs,
+
+		/// The maximum number of secrets revealed in this block was reached.
+		TooManyRevealedSecrets,
+
+		/// A higher value of a safety deposit is required.
+		HigherSafetyDepositRequired,
+
+		/// The maker or the taker is rejected by the compliance filter.
+		AccountNotCompliant,
+
+		/// The account is in the blocklist.
+		AccountBlocked,
+
+		/// The account is already in the blocklist.
+		AlreadyBlocked,
+
+		/// The account is not in the blocklist.
+		NotBlocked,
+
+		/// The swap amount of the HTLC is already converted or delivered
+		/// elsewhere.
+		SettlementAlreadyRouted,
+
+		/// The location of the remote party has no account on this chain.
+		UnconvertibleLocation,
+	}
+
+	impl<T> From<ImmutablesError> for Error<T> {
+		fn from(error: ImmutablesError) -> Self {
+			match error {
+				ImmutablesError::ZeroAmount => Error::<T>::ZeroAmount,
+				ImmutablesError::ZeroHashlock => Error::<T>::ZeroHashlock,
+				ImmutablesError::TimelocksOutOfOrder |
+				ImmutablesError::TimelocksTooLong |
+				ImmutablesError::CancelledAfterSource => Error::<T>::InvalidTimelocks,
+			}
+		}
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+			// prune the revealed secrets that fell out of the retention window
+			let retention = T::RevealedSecretsRetention::get();
+			if n > retention {
+				RevealedSecrets::<T>::remove(n - retention);
+			}
+
+			T::WeightInfo::on_initialize_base()
+				.saturating_add(Self::emit_stage_events(n))
+				.saturating_add(Self::emit_settlement_warnings(n))
+				.saturating_add(Self::auto_cancel_overdue(n))
+		}
+
+		fn on_idle(n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			Self::expire_timed_out_intents(n, remaining_weight)
+		}
+
+		#[cfg(feature = "auto-resolver")]
+		fn offchain_worker(n: BlockNumberFor<T>) {
+			crate::resolver::run::<T>(n);
+		}
+
+		fn integrity_test() {
+			// the automatic cancellations must always fit in a block
+			let max_auto_cancels = T::MaxAutoCancelsPerBlock::get() as u64;
+			let max_queue_depth = T::MaxCancellationsPerBlock::get();
+			let worst_case = T::WeightInfo::on_initialize_base()
+				.saturating_add(
+					T::WeightInfo::stage_events_block(max_queue_depth)
+						.saturating_add(
+							T::WeightInfo::stage_event().saturating_mul(max_queue_depth.into()),
+						)
+						.saturating_mul(2),
+				)
+				.saturating_add(
+					T::WeightInfo::auto_cancel_queue_block(max_queue_depth)
+						.saturating_add(T::WeightInfo::auto_cancel_htlc())
+						.saturating_mul(max_auto_cancels),
+				);
+			assert!(
+				worst_case.all_lte(T::BlockWeights::get().max_block),
+				"MaxAutoCancelsPerBlock cancellations do not fit in a block"
+			);
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+			let htlcs = Htlcs::<T>::iter_keys().count() as u32;
+			let intents = SwapIntents::<T>::iter_keys().count() as u32;
+			Ok((htlcs, intents).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+			let (htlcs, intents): (u32, u32) =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			// migrations must not lose any HTLC or intent
+			ensure!(
+				Htlcs::<T>::iter_keys().count() as u32 == htlcs,
+				"the number of HTLCs changed during the upgrade"
+			);
+			ensure!(
+				SwapIntents::<T>::iter_keys().count() as u32 == intents,
+				"the number of swap intents changed during the upgrade"
+			);
+			ensure!(
+				Pallet::<T>::on_chain_storage_version() == STORAGE_VERSION,
+				"the storage version was not updated by the migrations"
+			);
+
+			Self::do_try_state()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), TryRuntimeError> {
+			Self::do_try_state()
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		///////
+		// Calls for destination HTLCs
+
+		/// Create a destination HTLC escrowing the swap amount of the caller,
+		/// its taker, for the maker. The weight assumes that the maximum of
+		/// other HTLCs share its hashlock and that its order has its maximum
+		/// of legs.
+		#[pallet::call_index(0)]
+		#[pallet::weight(Pallet::<T>::dst_weight(false))]
+		pub fn create_dst_htlc(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			src_cancellation_block: BlockNumberFor<T>,
+			src_chain: ChainId,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_create_dst_htlc(who, immutables, src_cancellation_block, src_chain, false)
+		}
+
+		/// `create_dst_htlc` with the cancellation of the source HTLC as a UNIX
+		/// timestamp in seconds, as EVM chains report it, instead of a block.
+		#[pallet::call_index(59)]
+		#[pallet::weight(Pallet::<T>::dst_weight(false).saturating_add(T::DbWeight::get().reads(1)))]
+		pub fn create_dst_htlc_with_src_timestamp(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			src_cancellation_timestamp: u64,
+			src_chain: ChainId,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			let src_cancellation_block = Self::block_before_timestamp(src_cancellation_timestamp)?;
+			Self::do_create_dst_htlc(who, immutables, src_cancellation_block, src_chain, false)
+		}
+
+		/// Create a destination HTLC whose swap amount is drawn from the
+		/// resolver vault, within the credit limit of the registered resolver.
+		#[pallet::call_index(21)]
+		#[pallet::weight(Pallet::<T>::dst_weight(true))]
+		pub fn create_vault_dst_htlc(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			src_cancellation_block: BlockNumberFor<T>,
+			src_chain: ChainId,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_create_dst_htlc(who, immutables, src_cancellation_block, src_chain, true)
+		}
+
+		/// Reserve a destination HTLC with only its safety deposit, so that the
+		/// taker does not lock the swap amount while the source leg confirms.
+		/// The taker activates it within the `DstActivationPeriod`, or the
+		/// reservation expires and the safety deposit is refunded.
+		#[pallet::call_index(29)]
+		pub fn reserve_dst_htlc(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			src_cancellation_block: BlockNumberFor<T>,
+			src_chain: ChainId,
+			from_vault: bool,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			let htlc_id =
+				Self::validate_dst_htlc(&who, &immutables, src_cancellation_block, src_chain)?;
+
+			Self::lock_safety_deposit(htlc_id, &who, immutables.safety_deposit)?;
+
+			// expire the reservation in the first block after its deadline
+			let activate_before = frame_system::Pallet::<T>::block_number()
+				.saturating_add(T::DstActivationPeriod::get());
+			Self::schedule_auto_cancel(activate_before.saturating_add(One::one()), htlc_id)?;
+			PendingDstHtlcs::<T>::insert(
+				htlc_id,
+				PendingDstHtlc {
+					immutables,
+					src_cancellation_block,
+					src_chain,
+					from_vault,
+					activate_before,
+				},
+			);
+
+			Self::deposit_event(Event::DstHtlcReserved { htlc_id, taker: who, activate_before });
+
+			Ok(())
+		}
+
+		/// Activate a reserved destination HTLC, locking the swap amount.
+		#[pallet::call_index(30)]
+		pub fn activate_dst_htlc(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let pending =
+				PendingDstHtlcs::<T>::take(htlc_id).ok_or(Error::<T>::DstHtlcNotPending)?;
+			ensure!(who == pending.immutables.taker, Error::<T>::InvalidCaller);
+			ensure!(
+				frame_system::Pallet::<T>::block_number() <= pending.activate_before,
+				Error::<T>::DstActivationExpired
+			);
+
+			// the safety deposit is locked again with the swap amount
+			Self::refund_safety_deposit(htlc_id, &Self::pending_dst_htlc(&pending))?;
+			Self::do_create_dst_htlc(
+				who,
+				pending.immutables,
+				pending.src_cancellation_block,
+				pending.src_chain,
+				pending.from_vault,
+			)
+		}
+
+		/// Withdraw an HTLC as its taker with the secret, once its private
+		/// withdrawal opens. The weight assumes that the block revealed its
+		/// maximum of other secrets and that the order has its maximum of
+		/// legs, which are only known at the withdrawal.
+		#[pallet::call_index(1)]
+		#[pallet::weight(Pallet::<T>::withdraw_weight(secret.len() as u32, false))]
+		pub fn withdraw(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: Vec<u8>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_withdraw(who, immutables, secret, None, None)
+		}
+
+		/// `withdraw` a source HTLC as its taker, paying the swap amount to
+		/// `target` instead, e.g. an exchange deposit address, like
+		/// `EscrowSrc.withdrawTo`. The safety deposit still goes back to
+		/// whoever posted it.
+		#[pallet::call_index(40)]
+		#[pallet::weight(Pallet::<T>::withdraw_weight(secret.len() as u32, false))]
+		pub fn withdraw_to(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: Vec<u8>,
+			target: T::AccountId,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_withdraw(who, immutables, secret, None, Some(target))
+		}
+
+		/// Withdraw an HTLC with the secret on behalf of its taker, once its
+		/// public withdrawal opens, for the safety deposit. The weight is that
+		/// of `withdraw`.
+		#[pallet::call_index(2)]
+		#[pallet::weight(Pallet::<T>::withdraw_weight(secret.len() as u32, true))]
+		pub fn public_withdraw(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: Vec<u8>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_public_withdraw(who, immutables, secret, None)
+		}
+
+		/// `withdraw` a source HTLC that fills an intent in parts, with the
+		/// secret `index` of the Merkle tree whose root is the hashlock and
+		/// the `proof` of its leaf. The index must be that of the fill.
+		#[pallet::call_index(71)]
+		#[pallet::weight(
+			Pallet::<T>::withdraw_weight(secret.len() as u32, false)
+				.saturating_add(T::WeightInfo::verify_proof(proof.len() as u32))
+		)]
+		pub fn withdraw_with_proof(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: Vec<u8>,
+			index: u16,
+			proof: merkle::MerkleProof,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_withdraw(who, immutables, secret, Some((index, &proof)), None)
+		}
+
+		/// `public_withdraw` a source HTLC that fills an intent in parts, like
+		/// `withdraw_with_proof`.
+		#[pallet::call_index(72)]
+		#[pallet::weight(
+			Pallet::<T>::withdraw_weight(secret.len() as u32, true)
+				.saturating_add(T::WeightInfo::verify_proof(proof.len() as u32))
+		)]
+		pub fn public_withdraw_with_proof(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: Vec<u8>,
+			index: u16,
+			proof: merkle::MerkleProof,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::do_public_withdraw(who, immutables, secret, Some((index, &proof)))
+		}
+
+		/// Claim the funds of a source HTLC as its taker without revealing the
+		/// secret, asserting that the destination leg settled. The taker posts
+		/// the `OptimisticClaimBond`, which goes to whoever proves within the
+		/// `ChallengeWindow` that the secret was never revealed.
+		#[pallet::call_index(31)]
+		pub fn claim_optimistically(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			ensure!(T::SettlementVerifier::enabled(), Error::<T>::OptimisticSettlementDisabled);
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(htlc.htlc_type == HtlcType::Source, Error::<T>::InvalidImmutables);
+			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+			ensure!(!OptimisticClaims::<T>::contains_key(htlc_id), Error::<T>::AlreadyClaimed);
+
+			// ensure both parties are still allowed to swap
+			Self::ensure_compliant(&htlc.immutables.maker)?;
+			Self::ensure_compliant(&who)?;
+
+			// the claim is settled before the maker can cancel the HTLC
+			let current_block = frame_system::Pallet::<T>::block_number();
+			let timelocks = &htlc.immutables.timelocks;
+			ensure!(
+				Self::current_stage(timelocks, current_block) >= Stage::PrivateWithdrawal,
+				Error::<T>::EarlyWithdrawal
+			);
+			let challenge_until = current_block.saturating_add(T::ChallengeWindow::get());
+			ensure!(
+				challenge_until < timelocks.cancellation_after,
+				Error::<T>::LateOptimisticClaim
+			);
+
+			let bond = T::OptimisticClaimBond::get();
+			Self::escrow_lock(HoldReason::OptimisticClaimBond, htlc_id, &who, bond)
+				.map_err(|_| Error::<T>::InsufficientBalance)?;
+			OptimisticClaims::<T>::insert(
+				htlc_id,
+				OptimisticClaim { claimant: who.clone(), bond, challenge_until },
+			);
+
+			Self::deposit_event(Event::OptimisticClaimSubmitted {
+				htlc_id,
+				claimant: who,
+				challenge_until,
+			});
+
+			Ok(())
+		}
+
+		/// Challenge the optimistic claim of a source HTLC with a `proof` that
+		/// its destination leg did not settle, checked by the
+		/// `SettlementVerifier`. The caller gets the bond of the claimant, and
+		/// the HTLC is settled with the secret or cancelled as usual.
+		#[pallet::call_index(32)]
+		pub fn challenge_claim(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			proof: Vec<u8>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			// blocked accounts cannot collect the bond
+			Self::ensure_not_blocked(&who)?;
+
+			let claim = OptimisticClaims::<T>::get(htlc_id).ok_or(Error::<T>::NoOptimisticClaim)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() <= claim.challenge_until,
+				Error::<T>::ChallengeWindowClosed
+			);
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(
+				T::SettlementVerifier::verify_unsettled(htlc_id, &htlc.immutables, &proof),
+				Error::<T>::InvalidSettlementProof
+			);
+
+			OptimisticClaims::<T>::remove(htlc_id);
+			Self::escrow_pay(
+				HoldReason::OptimisticClaimBond,
+				htlc_id,
+				&claim.claimant,
+				&who,
+				claim.bond,
+			)?;
+
+			Self::deposit_event(Event::OptimisticClaimRejected {
+				htlc_id,
+				challenger: who,
+				bond: claim.bond,
+			});
+
+			Ok(())
+		}
+
+		/// Settle a source HTLC to its taker once the challenge window of its
+		/// optimistic claim is over, returning the bond. Anyone can call this.
+		/// If the HTLC was settled in the meantime, only the bond is returned.
+		/// An HTLC that expires with an unchallenged claim is settled by the
+		/// claim instead of cancelled.
+		#[pallet::call_index(33)]
+		pub fn finalize_claim(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			Self::ensure_party(origin)?;
+
+			let claim = OptimisticClaims::<T>::get(htlc_id).ok_or(Error::<T>::NoOptimisticClaim)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() > claim.challenge_until,
+				Error::<T>::ChallengeWindowOpen
+			);
+
+			Self::finalize_optimistic_claim(htlc_id, claim)
+		}
+
+		/// Cancel an HTLC as its taker once its cancellation opens. The weight
+		/// assumes that the order has its maximum of legs.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::cancel(MAX_ORDER_LEGS - 1))]
+		pub fn cancel(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			// Validation phase
+
+			// validate HTLC exists
+			let htlc_id = Self::hash_immutables(&immutables);
+			let htlc = Self::supplied_htlc(htlc_id, &immutables)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+
+			// verify immutables match
+			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+
+			// verify the parties and the timing
+			Self::ensure_can_cancel(htlc_id, &htlc, &who, false)?;
+
+			// Canellation phase
+			Self::cancel_htlc(htlc_id, htlc, IntentStatus::Expired)
+		}
+
+		/// Cancel several expired HTLCs of the caller at once, e.g. to clean
+		/// up after an outage. The HTLCs that can't be cancelled are skipped,
+		/// with an `HtlcCancellationFailed` event each.
+		#[pallet::call_index(47)]
+		#[pallet::weight(T::WeightInfo::cancel_many(htlc_ids.len() as u32))]
+		pub fn cancel_many(
+			origin: OriginFor<T>,
+			htlc_ids: BoundedVec<H256, ConstU32<MAX_BATCH_CANCELS>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			for htlc_id in htlc_ids {
+				// each cancellation either completes or leaves no trace
+				let cancelled = with_storage_layer(|| {
+					let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+					ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+					Self::ensure_can_cancel(htlc_id, &htlc, &who, false)?;
+					Self::cancel_htlc(htlc_id, htlc, IntentStatus::Expired)
+				});
+				if let Err(error) = cancelled {
+					Self::deposit_event(Event::HtlcCancellationFailed { htlc_id, error });
+				}
+			}
+
+			Ok(())
+		}
+
+		/// Withdraw several HTLCs of the caller that unlock with the same
+		/// secret, e.g. the escrows into which an order was split, revealing it
+		/// once. The HTLCs that can't be withdrawn, including those locked
+		/// with another hashlock, are skipped, with an `HtlcWithdrawalFailed`
+		/// event each.
+		#[pallet::call_index(56)]
+		#[pallet::weight(T::WeightInfo::withdraw_all_with_secret(
+			htlc_ids.len() as u32,
+			secret.len() as u32,
+		))]
+		pub fn withdraw_all_with_secret(
+			origin: OriginFor<T>,
+			secret: Vec<u8>,
+			htlc_ids: BoundedVec<H256, ConstU32<MAX_BATCH_WITHDRAWALS>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			for htlc_id in htlc_ids {
+				// each withdrawal either completes or leaves no trace
+				let withdrawn = with_storage_layer(|| {
+					let immutables = Self::stored_immutables(htlc_id)?;
+					Self::do_withdraw(who.clone(), immutables, secret.clone(), None, None)
+				});
+				if let Err(error) = withdrawn {
+					Self::deposit_event(Event::HtlcWithdrawalFailed { htlc_id, error });
+				}
+			}
+
+			Ok(())
+		}
+
+		/// Agree to cancel an active HTLC before `cancellation_after`, e.g. when
+		/// the deal fell through off-chain. Once both the maker and the taker
+		/// agree, the HTLC is cancelled immediately and everyone is refunded.
+		#[pallet::call_index(15)]
+		pub fn agree_to_cancel(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			// validate HTLC exists
+			let htlc_id = Self::hash_immutables(&immutables);
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+
+			// verify immutables match
+			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+
+			// only the parties of the swap can agree
+			ensure!(
+				who == htlc.immutables.maker || who == htlc.immutables.taker,
+				Error::<T>::InvalidCaller
+			);
+
+			// ensure none of the parties is blocked
+			Self::ensure_not_blocked(&htlc.immutables.maker)?;
+			Self::ensure_not_blocked(&htlc.immutables.taker)?;
+
+			// wait for the other party
+			let agreed = CancelAgreements::<T>::get(htlc_id);
+			ensure!(agreed.as_ref() != Some(&who), Error::<T>::AlreadyAgreed);
+			Self::deposit_event(Event::EarlyCancellationAgreed { htlc_id, who: who.clone() });
+			if agreed.is_none() {
+				CancelAgreements::<T>::insert(htlc_id, who);
+				return Ok(());
+			}
+			CancelAgreements::<T>::remove(htlc_id);
+
+			// neither party is at fault, so the maker keeps its safety deposit
+			Self::cancel_htlc(htlc_id, htlc, IntentStatus::Cancelled)
+		}
+
+		/// Cancel an HTLC that the taker did not cancel in time; the caller is
+		/// rewarded with a share of the safety deposit and the rest is slashed.
+		#[pallet::call_index(14)]
+		pub fn public_cancel(
+			origin: OriginFor<T>,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			// validate HTLC exists
+			let htlc_id = Self::hash_immutables(&immutables);
+			let mut htlc = Self::supplied_htlc(htlc_id, &immutables)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+
+			// verify immutables match
+			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+
+			// verify the parties and the timing
+			Self::ensure_can_cancel(htlc_id, &htlc, &who, true)?;
+
+			// the refund releases the watchtower of the HTLC
+			let watchtower = Self::rewarded_watchtower(htlc_id, &who);
+			let refund_recipient = Self::refund_htlc(htlc_id, &htlc, IntentStatus::Expired)?;
+
+			// the caller gets its share of the safety deposit and the rest is
+			// forfeited
+			let amount = Self::take_safety_deposit(htlc_id, &htlc);
+			let safety_deposit = Self::escrow_slash(
+				HoldReason::SafetyDeposit,
+				htlc_id,
+				&Self::forfeit_sponsorship(htlc_id, &htlc),
+				amount,
+			)?;
+			let reward = T::PublicCancellationReward::get().mul_floor(amount);
+			let (mut reward_credit, slashed) = safety_deposit.split(reward);
+			let share = T::WatchtowerShare::get().mul_floor(reward);
+			if let Some(watchtower) = watchtower.filter(|_| !share.is_zero()) {
+				let (share_credit, rest) = reward_credit.split(share);
+				reward_credit = rest;
+				T::NativeBalance::resolve(&watchtower, share_credit)
+					.map_err(|_| TokenError::BelowMinimum)?;
+				Self::note_watchtower_reward(htlc_id, watchtower, share);
+			}
+			let reward = reward_credit.peek();
+			if !reward.is_zero() {
+				T::NativeBalance::resolve(&who, reward_credit)
+					.map_err(|_| TokenError::BelowMinimum)?;
+			}
+			Self::forfeit(
+				ForfeitReason::PublicCancellation,
+				htlc_id,
+				&htlc,
+				Some(who.clone()),
+				slashed,
+			);
+
+			// update HTLC
+			htlc.status = HtlcStatus::Cancelled;
+			Self::store_htlc(htlc_id, &htlc);
+
+			Self::deposit_event(Event::HtlcCancelled { htlc_id, refund_recipient });
+			Self::deposit_event(Event::PublicCancellationRewarded { htlc_id, caller: who, reward });
+
+			Ok(())
+		}
+
+		/// Register the caller as a watchtower, which watches HTLCs for a
+		/// share of the rewards of their public withdrawal or cancellation.
+		#[pallet::call_index(63)]
+		pub fn register_watchtower(origin: OriginFor<T>) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&who)?;
+
+			ensure!(!Watchtowers::<T>::contains_key(&who), Error::<T>::AlreadyWatchtower);
+			Watchtowers::<T>::insert(&who, WatchtowerInfo::default());
+
+			Self::deposit_event(Event::WatchtowerRegistered { who });
+
+			Ok(())
+		}
+
+		/// Deregister the caller as a watchtower, once it watches no HTLC.
+		#[pallet::call_index(64)]
+		pub fn deregister_watchtower(origin: OriginFor<T>) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let info = Watchtowers::<T>::get(&who).ok_or(Error::<T>::NotWatchtower)?;
+			ensure!(info.duties == 0, Error::<T>::WatchtowerOnDuty);
+			Watchtowers::<T>::remove(&who);
+
+			Self::deposit_event(Event::WatchtowerDeregistered { who });
+
+			Ok(())
+		}
+
+		/// Assign `watchtower` to watch the active HTLC `htlc_id` as its only
+		/// watchtower, until it settles. Only the maker or the taker of the
+		/// HTLC, or governance, assign it.
+		#[pallet::call_index(65)]
+		pub fn watch_htlc(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			watchtower: T::AccountId,
+		) -> DispatchResult {
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			if T::GovernanceOrigin::ensure_origin(origin.clone()).is_err() {
+				let who = Self::ensure_party(origin)?;
+				ensure!(
+					who == htlc.immutables.maker || who == htlc.immutables.taker,
+					Error::<T>::InvalidCaller
+				);
+			}
+
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(!WatchtowerDuties::<T>::contains_key(htlc_id), Error::<T>::HtlcAlreadyWatched);
+			Watchtowers::<T>::try_mutate(&watchtower, |info| -> DispatchResult {
+				let info = info.as_mut().ok_or(Error::<T>::NotWatchtower)?;
+				ensure!(info.duties < MAX_WATCHTOWER_DUTIES, Error::<T>::TooManyDuties);
+				info.duties.saturating_inc();
+				Ok(())
+			})?;
+			WatchtowerDuties::<T>::insert(htlc_id, &watchtower);
+
+			Self::deposit_event(Event::WatchtowerAssigned { htlc_id, watchtower });
+
+			Ok(())
+		}
+
+		/// Stop watching the HTLC `htlc_id`.
+		#[pallet::call_index(66)]
+		pub fn unwatch_htlc(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			ensure!(
+				WatchtowerDuties::<T>::get(htlc_id).as_ref() == Some(&who),
+				Error::<T>::NotWatching
+			);
+			Self::release_watchtower(htlc_id);
+
+			Ok(())
+		}
+
+		/// `withdraw` the HTLC `htlc_id` with its stored immutables.
+		#[pallet::call_index(35)]
+		#[pallet::weight(Pallet::<T>::withdraw_weight(secret.len() as u32, false))]
+		pub fn withdraw_by_id(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			secret: Vec<u8>,
+		) -> DispatchResult {
+			Self::withdraw(origin, Self::stored_immutables(htlc_id)?, secret)
+		}
+
+		/// `public_withdraw` the HTLC `htlc_id` with its stored immutables.
+		#[pallet::call_index(36)]
+		#[pallet::weight(Pallet::<T>::withdraw_weight(secret.len() as u32, true))]
+		pub fn public_withdraw_by_id(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			secret: Vec<u8>,
+		) -> DispatchResult {
+			Self::public_withdraw(origin, Self::stored_immutables(htlc_id)?, secret)
+		}
+
+		/// `cancel` the HTLC `htlc_id` with its stored immutables.
+		#[pallet::call_index(37)]
+		#[pallet::weight(T::WeightInfo::cancel(MAX_ORDER_LEGS - 1))]
+		pub fn cancel_by_id(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			Self::cancel(origin, Self::stored_immutables(htlc_id)?)
+		}
+
+		/// `agree_to_cancel` the HTLC `htlc_id` with its stored immutables.
+		#[pallet::call_index(38)]
+		pub fn agree_to_cancel_by_id(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			Self::agree_to_cancel(origin, Self::stored_immutables(htlc_id)?)
+		}
+
+		/// `public_cancel` the HTLC `htlc_id` with its stored immutables.
+		#[pallet::call_index(39)]
+		pub fn public_cancel_by_id(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			Self::public_cancel(origin, Self::stored_immutables(htlc_id)?)
+		}
+
+		/// Allow a resolver to have its safety deposits posted by the caller, up
+		/// to `allowance` at a time. A zero allowance revokes the sponsorship.
+		#[pallet::call_index(17)]
+		pub fn set_sponsor_allowance(
+			origin: OriginFor<T>,
+			resolver: T::AccountId,
+			allowance: BalanceOf<T>,
+		) -> DispatchResult {
+			let sponsor = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&sponsor)?;
+
+			SponsorAllowances::<T>::set(&sponsor, &resolver, allowance);
+
+			Self::deposit_event(Event::SponsorAllowanceSet { sponsor, resolver, allowance });
+
+			Ok(())
+		}
+
+		/// Choose the sponsor that posts the safety deposits of the HTLCs that
+		/// the caller creates, or post them again itself with `None`.
+		#[pallet::call_index(18)]
+		pub fn set_sponsor(origin: OriginFor<T>, sponsor: Option<T::AccountId>) -> DispatchResult {
+			let resolver = Self::ensure_party(origin)?;
+
+			Sponsors::<T>::set(&resolver, sponsor.clone());
+
+			Self::deposit_event(Event::SponsorSet { resolver, sponsor });
+
+			Ok(())
+		}
+
+		/// Allow a resolver to draw on the vault up to `credit_limit`, or change
+		/// its limit.
+		#[pallet::call_index(19)]
+		pub fn register_resolver(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			credit_limit: BalanceOf<T>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			Resolvers::<T>::insert(&who, credit_limit);
+
+			Self::deposit_event(Event::ResolverRegistered { who, credit_limit });
+
+			Ok(())
+		}
+
+		/// Stop a resolver from drawing on the vault; its debt stays owed.
+		#[pallet::call_index(20)]
+		pub fn deregister_resolver(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(Resolvers::<T>::contains_key(&who), Error::<T>::NotResolver);
+			Resolvers::<T>::remove(&who);
+
+			Self::deposit_event(Event::ResolverDeregistered { who });
+
+			Ok(())
+		}
+
+		/// Delegate stake behind a registered resolver, raising its credit
+		/// limit by as much. The stake is slashed by the `DelegationSlash`
+		/// whenever the resolver fails a fill or is slashed.
+		#[pallet::call_index(49)]
+		pub fn delegate(
+			origin: OriginFor<T>,
+			resolver: T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let delegator = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&delegator)?;
+
+			Self::do_delegate(delegator, resolver, amount)
+		}
+
+		/// Start unbonding `amount` of the stake behind a resolver, which can
+		/// be withdrawn after the `UndelegationDelay`. Undelegating again
+		/// restarts the delay of all the unbonding stake.
+		#[pallet::call_index(50)]
+		pub fn undelegate(
+			origin: OriginFor<T>,
+			resolver: T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let delegator = Self::ensure_party(origin)?;
+
+			Self::do_undelegate(delegator, resolver, amount)
+		}
+
+		/// Release the stake undelegated from a resolver once it unbonded.
+		#[pallet::call_index(51)]
+		pub fn withdraw_undelegated(
+			origin: OriginFor<T>,
+			resolver: T::AccountId,
+		) -> DispatchResult {
+			let delegator = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&delegator)?;
+
+			Self::do_withdraw_undelegated(delegator, resolver)
+		}
+
+		/// Set the share of its earnings that a registered resolver keeps,
+		/// sharing the rest with its delegators in proportion to their bonded
+		/// stake, or keep all of them with `None`.
+		#[pallet::call_index(52)]
+		pub fn set_resolver_commission(
+			origin: OriginFor<T>,
+			commission: Option<Perbill>,
+		) -> DispatchResult {
+			let resolver = Self::ensure_registered_resolver(origin)?;
+
+			ResolverCommissions::<T>::set(&resolver, commission);
+
+			Self::deposit_event(Event::ResolverCommissionSet { resolver, commission });
+
+			Ok(())
+		}
+
+		/// Deposit funds into the vault in exchange for shares of it.
+		#[pallet::call_index(22)]
+		pub fn vault_deposit(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&who)?;
+
+			Self::do_vault_deposit(who, amount)
+		}
+
+		/// Redeem shares of the vault for their part of its funds. If the funds
+		/// are drawn by the resolvers, or other withdrawals wait before, the
+		/// shares are queued and redeemed as the drawn funds come back.
+		#[pallet::call_index(23)]
+		pub fn vault_withdraw(origin: OriginFor<T>, shares: BalanceOf<T>) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&who)?;
+
+			Self::do_vault_withdraw(who, shares)
+		}
+
+		/// Repay up to `amount` of the debt of the caller to the vault.
+		#[pallet::call_index(24)]
+		pub fn repay_vault(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&who)?;
+
+			Self::do_repay_vault(who, amount)
+		}
+
+		/// Compensate `who` out of the insurance fund, e.g. for the funds it
+		/// lost to a bug of the pallet or to a resolver that failed.
+		#[pallet::call_index(34)]
+		pub fn pay_insurance(
+			origin: OriginFor<T>,
+			who: T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			Self::pay_from(&Self::insurance_account(), &who, amount)?;
+
+			Self::deposit_event(Event::InsurancePaidOut { who, amount });
+
+			Ok(())
+		}
+
+		/// Convert the swap amount of an active destination HTLC of the caller
+		/// into `conversion.asset` as soon as it is withdrawn, or keep it in
+		/// the native token with `None`. If the conversion fails, e.g. because
+		/// it returns less than `conversion.min_out`, the withdrawal still
+		/// pays the native tokens.
+		#[pallet::call_index(41)]
+		#[pallet::weight(T::WeightInfo::set_settlement_conversion())]
+		pub fn set_settlement_conversion(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			conversion: Option<SettlementConversion<T::ConversionAsset, BalanceOf<T>>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(htlc.htlc_type == HtlcType::Destination, Error::<T>::InvalidImmutables);
+			ensure!(who == htlc.immutables.maker, Error::<T>::InvalidCaller);
+			ensure!(
+				conversion.is_none() || !SettlementDeliveries::<T>::contains_key(htlc_id),
+				Error::<T>::SettlementAlreadyRouted
+			);
+
+			SettlementConversions::<T>::set(htlc_id, conversion.clone());
+
+			Self::deposit_event(Event::SettlementConversionSet { htlc_id, conversion });
+
+			Ok(())
+		}
+
+		/// Deliver the swap amount of an active destination HTLC of the caller
+		/// to `beneficiary` on another chain as soon as it is withdrawn, e.g.
+		/// with a reserve transfer over XCM, or keep it here with `None`. If
+		/// the delivery fails, the withdrawal still pays the maker here.
+		#[pallet::call_index(42)]
+		pub fn set_settlement_delivery(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			beneficiary: Option<T::RemoteBeneficiary>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(htlc.htlc_type == HtlcType::Destination, Error::<T>::InvalidImmutables);
+			ensure!(who == htlc.immutables.maker, Error::<T>::InvalidCaller);
+			ensure!(
+				beneficiary.is_none() || !SettlementConversions::<T>::contains_key(htlc_id),
+				Error::<T>::SettlementAlreadyRouted
+			);
+
+			SettlementDeliveries::<T>::set(htlc_id, beneficiary.clone());
+
+			Self::deposit_event(Event::SettlementDeliverySet { htlc_id, beneficiary });
+
+			Ok(())
+		}
+
+		/// Add `amount` to the safety deposit of an active HTLC of the caller.
+		/// The top-up is posted by whoever posted the safety deposit, so a
+		/// sponsored deposit is topped up from the allowance of the sponsor.
+		#[pallet::call_index(25)]
+		pub fn top_up_safety_deposit(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&who)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+			ensure!(!amount.is_zero(), Error::<T>::InvalidTopUp);
+
+			let depositor = match DepositSponsors::<T>::get(htlc_id) {
+				Some(sponsor) => {
+					Self::spend_sponsor_allowance(&sponsor, &who, amount)?;
+					sponsor
+				},
+				None => who,
+			};
+			Self::escrow_lock(HoldReason::SafetyDeposit, htlc_id, &depositor, amount)
+				.map_err(|_| Error::<T>::InsufficientBalance)?;
+
+			let top_up = SafetyDepositTopUps::<T>::get(htlc_id).unwrap_or_default();
+			SafetyDepositTopUps::<T>::insert(htlc_id, top_up.saturating_add(amount));
+
+			Self::deposit_event(Event::SafetyDepositToppedUp {
+				htlc_id,
+				amount,
+				safety_deposit: Self::safety_deposit(htlc_id, &htlc),
+			});
+
+			Ok(())
+		}
+
+		///////
+		// Calls for Swap intents
+
+		/// Open an intent of the caller, holding its amount and deposits. The
+		/// weight is that of a ladder of one intent.
+		#[pallet::call_index(4)]
+		#[pallet::weight(T::WeightInfo::create_swap_intents(1))]
+		pub fn create_swap_intent(
+			origin: OriginFor<T>,
+			intent: SwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let (intent_key, stored_intent) = Self::open_intent(&who, intent)?;
+			Self::lock_intent_funds(&who, intent_key, &stored_intent)?;
+
+			Self::deposit_event(Event::SwapIntentCreated {
+				intent_key,
+				intent: stored_intent.intent,
+			});
+
+			Ok(())
+		}
+
+		/// Create a ladder of intents at once, with the nonces `first_nonce`,
+		/// `first_nonce + 1` and so on, whatever the nonces of the intents.
+		/// With holds, the funds of all the intents are held at once.
+		#[pallet::call_index(48)]
+		#[pallet::weight(T::WeightInfo::create_swap_intents(intents.len() as u32))]
+		pub fn create_swap_intents(
+			origin: OriginFor<T>,
+			intents: BoundedVec<SwapIntentOf<T>, ConstU32<MAX_BATCH_INTENTS>>,
+			first_nonce: u64,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let opened = (first_nonce..)
+				.zip(intents)
+				.map(|(nonce, intent)| Self::open_intent(&who, SwapIntent { nonce, ..intent }))
+				.collect::<Result<Vec<_>, _>>()?;
+
+			if T::EscrowMode::get() == EscrowMode::Holds {
+				let (mut amount, mut maker_deposit, mut storage_deposit) =
+					(BalanceOf::<T>::zero(), BalanceOf::<T>::zero(), BalanceOf::<T>::zero());
+				for (_, stored_intent) in &opened {
+					amount.saturating_accrue(stored_intent.intent.src_amount);
+					maker_deposit.saturating_accrue(stored_intent.maker_deposit);
+					storage_deposit.saturating_accrue(stored_intent.storage_deposit);
+				}
+				for (reason, amount) in [
+					(HoldReason::MakerSwapIntentAmount, amount),
+					(HoldReason::MakerSafetyDeposit, maker_deposit),
+					(HoldReason::IntentStorageDeposit, storage_deposit),
+				] {
+					T::NativeBalance::hold(&reason.into(), &who, amount)
+						.map_err(|_| Error::<T>::InsufficientBalance)?;
+				}
+			} else {
+				for (intent_key, stored_intent) in &opened {
+					Self::lock_intent_funds(&who, *intent_key, stored_intent)?;
+				}
+			}
+
+			for (intent_key, stored_intent) in opened {
+				Self::deposit_event(Event::SwapIntentCreated {
+					intent_key,
+					intent: stored_intent.intent,
+				});
+			}
+
+			Ok(())
+		}
+
+		/// Set the intent template `template_id` of the caller, or remove it
+		/// if none.
+		#[pallet::call_index(67)]
+		pub fn set_intent_template(
+			origin: OriginFor<T>,
+			template_id: u32,
+			template: Option<IntentTemplateOf<T>>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			match &template {
+				Some(template) => {
+					let profile = Self::chain_profile(template.dst_chain)?;
+					ensure!(
+						template.dst_address.is_valid() &&
+							profile.address_format.accepts(&template.dst_address) &&
+							!template.src_amount.is_zero() &&
+							!template.dst_amount.is_zero() &&
+							template.min_fill_amount <= template.src_amount &&
+							template.validity != Some(0),
+						Error::<T>::InvalidIntentTemplate
+					);
+					ensure!(
+						IntentTemplates::<T>::contains_key(&who, template_id) ||
+							IntentTemplates::<T>::iter_key_prefix(&who).count() <
+								MAX_INTENT_TEMPLATES as usize,
+						Error::<T>::TooManyIntentTemplates
+					);
+					IntentTemplates::<T>::insert(&who, template_id, template);
+				},
+				None => IntentTemplates::<T>::remove(&who, template_id),
+			}
+
+			Self::deposit_event(Event::IntentTemplateSet { maker: who, template_id, template });
+
+			Ok(())
+		}
+
+		/// Open an intent from the intent template `template_id` of the
+		/// caller, locked with `hashlock`, from the current block.
+		#[pallet::call_index(68)]
+		pub fn create_intent_from_template(
+			origin: OriginFor<T>,
+			template_id: u32,
+			hashlock: H256,
+			nonce: u64,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let template =
+				IntentTemplates::<T>::get(&who, template_id).ok_or(Error::<T>::NoIntentTemplate)?;
+			let current_block = frame_system::Pallet::<T>::block_number();
+			let intent = SwapIntent {
+				hashlock,
+				maker: who.clone(),
+				src_amount: template.src_amount,
+				dst_amount: template.dst_amount,
+				dst_address: template.dst_address,
+				dst_chain: template.dst_chain,
+				timeout_after_block: template
+					.validity
+					.map(|validity| current_block.saturating_add(validity.into())),
+				nonce,
+				auction: AuctionCurve::Fixed,
+				min_fill_amount: template.min_fill_amount,
+				flags: template.flags,
+				epoch: MakerEpochs::<T>::get(&who),
+				denied_resolvers: Default::default(),
+				dst_decimals: template.dst_decimals,
+			};
+
+			let (intent_key, stored_intent) = Self::open_intent(&who, intent)?;
+			Self::lock_intent_funds(&who, intent_key, &stored_intent)?;
+
+			Self::deposit_event(Event::SwapIntentCreated {
+				intent_key,
+				intent: stored_intent.intent,
+			});
+
+			Ok(())
+		}
+
+		/// Create a swap intent that is filled in `parts` parts, whose
+		/// hashlock is the root of the Merkle tree of `parts + 1` secrets.
+		/// Each fill is withdrawn with the secret of the part that the amount
+		/// filled up to it reaches, or the last one if it completes the
+		/// intent, and the proof of its leaf.
+		/// A fill that is cancelled keeps its part, whose amount goes back to
+		/// the maker.
+		#[pallet::call_index(70)]
+		pub fn create_multi_fill_intent(
+			origin: OriginFor<T>,
+			intent: SwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			parts: u16,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let flags = intent.flags;
+			ensure!(
+				parts > 0 &&
+					flags.allow_partial_fills &&
+					flags.allow_multiple_fills &&
+					!flags.fill_or_kill,
+				Error::<T>::InvalidFillParts
+			);
+
+			let (intent_key, stored_intent) = Self::open_intent(&who, intent)?;
+			Self::lock_intent_funds(&who, intent_key, &stored_intent)?;
+			MultiFillIntents::<T>::insert(intent_key, MultiFill { parts, filled: Zero::zero() });
+
+			Self::deposit_event(Event::SwapIntentCreated {
+				intent_key,
+				intent: stored_intent.intent,
+			});
+			Self::deposit_event(Event::MultiFillIntentCreated { intent_key, parts });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(5)]
+		pub fn cancel_swap_intent(origin: OriginFor<T>, nonce: u64) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			// ensure the maker is not blocked
+			Self::ensure_not_blocked(&who)?;
+
+			// generate the key for the map and check it doesn't already exist
+			let intent_key = Self::intent_key(&who, nonce);
+			let stored_intent =
+				SwapIntents::<T>::get(intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+
+			// ensure the maker cancels the intent to swap
+			ensure!(who == stored_intent.intent.maker, Error::<T>::InvalidCaller);
+
+			// an intent that a resolver is filling is cancelled with its source
+			// HTLC, once the resolver had time to settle the swap
+			if let IntentStatus::InProgress { htlc_id, .. } = stored_intent.status {
+				return Self::cancel_taken_intent(who, nonce, stored_intent, htlc_id);
+			}
+
+			// ensure we cannot cancel an already cancelled intent, and that a
+			// reserved one had time to be filled
+			match stored_intent.status {
+				IntentStatus::Active => {},
+				IntentStatus::Reserved { .. } => {
+					Self::ensure_intent_cancellation_delay(&stored_intent)?;
+					Self::settle_reservation_bond(intent_key, &stored_intent)?;
+				},
+				_ => return Err(Error::<T>::IntentNotActive.into()),
+			}
+
+			Self::cancel_open_intent(intent_key, stored_intent)
+		}
+
+		/// Reserve the intent of `maker` for the caller, holding the
+		/// `ReservationBond`, so that no other resolver fills it while the
+		/// caller escrows the funds on the other chain. The weight assumes
+		/// that the order has its maximum of legs.
+		#[pallet::call_index(26)]
+		#[pallet::weight(T::WeightInfo::take_intent(MAX_ORDER_LEGS - 1))]
+		pub fn take_intent(
+			origin: OriginFor<T>,
+			maker: T::AccountId,
+			nonce: u64,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let intent_key = Self::intent_key(&maker, nonce);
+			let mut stored_intent =
+				SwapIntents::<T>::get(intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+			ensure!(stored_intent.status == IntentStatus::Active, Error::<T>::IntentNotActive);
+			Self::ensure_intent_epoch(&stored_intent.intent)?;
+
+			// ensure both parties are allowed to swap
+			Self::ensure_compliant(&maker)?;
+			Self::ensure_compliant(&who)?;
+			Self::ensure_resolver_allowed(&stored_intent.intent, &who)?;
+
+			// ensure the intent hasn't expired
+			let current_block = frame_system::Pallet::<T>::block_number();
+			ensure!(!stored_intent.intent.is_expired_at(current_block), Error::<T>::IntentExpired);
+
+			let bond = T::ReservationBond::get();
+			Self::escrow_lock(HoldReason::ReservationBond, intent_key, &who, bond)
+				.map_err(|_| Error::<T>::InsufficientBalance)?;
+
+			let status = IntentStatus::Reserved { resolver: who.clone() };
+			Self::move_intent_hold(&stored_intent.status, &status)?;
+			stored_intent.status = status;
+			stored_intent.taken_at = Some(current_block);
+			stored_intent.reservation_bond = bond;
+			SwapIntents::<T>::insert(intent_key, &stored_intent);
+			IntentQuotes::<T>::remove(intent_key);
+			Self::track_order(intent_key);
+
+			Self::deposit_event(Event::IntentTaken { maker, nonce, resolver: who, bond });
+
+			Ok(())
+		}
+
+		/// Post a quote against the active intent of `maker`, offering to pay
+		/// `dst_amount` on the destination chain until `expires_at`. A full
+		/// quote book drops its expired quotes, then its lowest one if the new
+		/// quote is higher.
+		#[pallet::call_index(60)]
+		pub fn post_quote(
+			origin: OriginFor<T>,
+			maker: T::AccountId,
+			nonce: u64,
+			dst_amount: BalanceOf<T>,
+			expires_at: BlockNumberFor<T>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			Self::do_post_quote(who, maker, nonce, dst_amount, expires_at)
+		}
+
+		/// Withdraw the quote of the caller against the intent of `maker`.
+		#[pallet::call_index(61)]
+		pub fn withdraw_quote(
+			origin: OriginFor<T>,
+			maker: T::AccountId,
+			nonce: u64,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			Self::do_withdraw_quote(who, maker, nonce)
+		}
+
+		/// Accept the live quote of `resolver` against the active intent of
+		/// the caller, reserving the intent for the resolver at the quoted
+		/// price. No bond is held, as the resolver does not sign the call, but
+		/// a resolver that lets the reservation lapse cools down like one that
+		/// forfeits its bond.
+		#[pallet::call_index(62)]
+		pub fn accept_quote(
+			origin: OriginFor<T>,
+			nonce: u64,
+			resolver: T::AccountId,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			Self::do_accept_quote(who, nonce, resolver)
+		}
+
+		/// Take over the intent of `maker` from a resolver that did not fill it
+		/// within the `FillDeadline`. The caller must be a registered resolver;
+		/// it reserves the intent with a new bond and gets the bond of the
+		/// previous resolver. A source HTLC that the previous resolver left
+		/// behind is cancelled, once its timelocks allow it, without returning
+		/// the funds of the maker.
+		#[pallet::call_index(27)]
+		pub fn take_over_intent(
+			origin: OriginFor<T>,
+			maker: T::AccountId,
+			nonce: u64,
+		) -> DispatchResult {
+			let who = Self::ensure_registered_resolver(origin)?;
+
+			let intent_key = Self::intent_key(&maker, nonce);
+			let mut stored_intent =
+				SwapIntents::<T>::get(intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+			let (previous, stalled_htlc) = match &stored_intent.status {
+				IntentStatus::Reserved { resolver } => (resolver.clone(), None),
+				IntentStatus::InProgress { resolver, htlc_id } =>
+					(resolver.clone(), Some(*htlc_id)),
+				_ => return Err(Error::<T>::IntentNotActive.into()),
+			};
+			ensure!(who != previous, Error::<T>::InvalidCaller);
+			Self::ensure_intent_epoch(&stored_intent.intent)?;
+
+			// ensure the maker lets another resolver fill the intent
+			let flags = stored_intent.intent.flags;
+			ensure!(!flags.fill_or_kill, Error::<T>::FillOrKillIntent);
+			ensure!(
+				stalled_htlc.is_none() || flags.allow_multiple_fills,
+				Error::<T>::IntentAlreadyFilled
+			);
+			// the stalled fill of an intent in parts took its part, so it is
+			// cancelled through its timelocks instead, which opens the intent
+			ensure!(
+				stalled_htlc.is_none() || !MultiFillIntents::<T>::contains_key(intent_key),
+				Error::<T>::IntentAlreadyFilled
+			);
+
+			// ensure both parties are allowed to swap
+			Self::ensure_compliant(&maker)?;
+			Self::ensure_compliant(&who)?;
+			Self::ensure_resolver_allowed(&stored_intent.intent, &who)?;
+
+			// ensure the intent hasn't expired and the fill is stalled
+			let current_block = frame_system::Pallet::<T>::block_number();
+			ensure!(!stored_intent.intent.is_expired_at(current_block), Error::<T>::IntentExpired);
+			let taken_at = stored_intent.taken_at.unwrap_or(stored_intent.created_at);
+			ensure!(
+				current_block >= taken_at.saturating_add(T::FillDeadline::get()),
+				Error::<T>::FillNotStalled
+			);
+
+			if let Some(htlc_id) = stalled_htlc {
+				Self::abandon_fill(htlc_id)?;
+			}
+
+			let forfeited = stored_intent.reservation_bond;
+			Self::escrow_pay(HoldReason::ReservationBond, intent_key, &previous, &who, forfeited)?;
+			Self::penalize_resolver(&previous);
+			Self::share_earnings(&who, forfeited);
+			let bond = T::ReservationBond::get();
+			Self::escrow_lock(HoldReason::ReservationBond, intent_key, &who, bond)
+				.map_err(|_| Error::<T>::InsufficientBalance)?;
+
+			let status = IntentStatus::Reserved { resolver: who.clone() };
+			Self::move_intent_hold(&stored_intent.status, &status)?;
+			stored_intent.status = status;
+			stored_intent.taken_at = Some(current_block);
+			stored_intent.reservation_bond = bond;
+			SwapIntents::<T>::insert(intent_key, &stored_intent);
+			AcceptedQuotes::<T>::remove(intent_key);
+			Self::track_order(intent_key);
+
+			Self::deposit_event(Event::IntentTakenOver {
+				maker,
+				nonce,
+				resolver: who,
+				previous,
+				forfeited,
+			});
+
+			Ok(())
+		}
+
+		/// Invalidate all the open intents of the caller from epochs before
+		/// `before_epoch`, so that no resolver takes or fills them, and cancel
+		/// those that no resolver holds, returning their funds to the caller.
+		/// Intents that a resolver already reserved or is filling survive, as
+		/// the resolver may have escrowed the funds on the other chain; only
+		/// that resolver can still fill them, and the maker cancels them once
+		/// the `IntentCancellationDelay` passed. The caller pays for the
+		/// intents it has open, at most `MaxIntentsPerMaker`.
+		#[pallet::call_index(28)]
+		#[pallet::weight(T::WeightInfo::cancel_all_intents(T::MaxIntentsPerMaker::get()))]
+		#[allow(clippy::useless_conversion)]
+		pub fn cancel_all_intents(
+			origin: OriginFor<T>,
+			before_epoch: u32,
+		) -> DispatchResultWithPostInfo {
+			let who = Self::ensure_party(origin)?;
+			Self::ensure_not_blocked(&who)?;
+
+			ensure!(before_epoch > MakerEpochs::<T>::get(&who), Error::<T>::InvalidEpoch);
+			MakerEpochs::<T>::insert(&who, before_epoch);
+
+			// at most `MaxIntentsPerMaker` intents are open
+			let open_intents = MakerIntents::<T>::get(&who);
+			let actual_weight = T::WeightInfo::cancel_all_intents(open_intents.len() as u32);
+			for intent_key in open_intents {
+				let Some(stored_intent) = SwapIntents::<T>::get(intent_key) else {
+					continue;
+				};
+				if stored_intent.status == IntentStatus::Active &&
+					stored_intent.intent.epoch < before_epoch
+				{
+					Self::cancel_open_intent(intent_key, stored_intent)?;
+				}
+			}
+
+			Self::deposit_event(Event::IntentsInvalidated { maker: who, before_epoch });
+
+			Ok(Some(actual_weight).into())
+		}
+
+		///////
+		// Calls for source HTLCs
+
+		#[pallet::call_index(6)]
+		#[pallet::weight(Pallet::<T>::fill_weight())]
+		pub fn create_src_htlc(
+			origin: OriginFor<T>,
+			maker: T::AccountId,
+			nonce: u64,
+			timelocks: Timelocks<BlockNumberFor<T>>,
+			safety_deposit: BalanceOf<T>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+			Self::fill_intent(who, maker, nonce, None, timelocks, safety_deposit)
+		}
+
+		/// `create_src_htlc` with the timelocks recommended for the destination
+		/// chain of the intent, from the current block.
+		#[pallet::call_index(58)]
+		#[pallet::weight(Pallet::<T>::fill_weight().saturating_add(T::DbWeight::get().reads(1)))]
+		pub fn create_src_htlc_with_template(
+			origin: OriginFor<T>,
+			maker: T::AccountId,
+			nonce: u64,
+			safety_deposit: BalanceOf<T>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let stored_intent = SwapIntents::<T>::get(Self::intent_key(&maker, nonce))
+				.ok_or(Error::<T>::IntentDoesNotExists)?;
+			let template = TimelockTemplates::<T>::get(stored_intent.intent.dst_chain)
+				.ok_or(Error::<T>::NoTimelockTemplate)?;
+			let timelocks = template.timelocks_at(frame_system::Pallet::<T>::block_number());
+
+			Self::fill_intent(who, maker, nonce, None, timelocks, safety_deposit)
+		}
+
+		/// Fill several intents at once, each with a source HTLC of `amount`
+		/// of the intent, the same timelocks and safety deposit, e.g. to sweep
+		/// an auction. Only the intents that allow partial fills take less
+		/// than all of their amount, and no less than their `min_fill_amount`.
+		/// Either all the intents are filled or none.
+		///
+		/// The weight assumes that each order already has its maximum of
+		/// `MAX_ORDER_LEGS` legs, which are only known once filled.
+		#[pallet::call_index(46)]
+		#[pallet::weight(T::WeightInfo::fill_intents(fills.len() as u32, MAX_ORDER_LEGS - 1))]
+		pub fn fill_intents(
+			origin: OriginFor<T>,
+			fills: BoundedVec<(T::AccountId, u64, BalanceOf<T>), ConstU32<MAX_BATCH_FILLS>>,
+			timelocks: Timelocks<BlockNumberFor<T>>,
+			safety_deposit: BalanceOf<T>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			for (maker, nonce, amount) in fills {
+				Self::fill_intent(
+					who.clone(),
+					maker,
+					nonce,
+					Some(amount),
+					timelocks.clone(),
+					safety_deposit,
+				)?;
+			}
+
+			Ok(())
+		}
+
+		/// Bind an active source HTLC to the escrow deployed with CREATE2 by the
+		/// 1inch escrow factory on an EVM chain. Only the resolver can bind it.
+		#[pallet::call_index(13)]
+		pub fn bind_counterpart_escrow(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			factory: H160,
+			implementation: H160,
+			counterpart: Box<EvmImmutables>,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+			ensure!(htlc.htlc_type == HtlcType::Source, Error::<T>::InvalidImmutables);
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(
+				!CounterpartEscrows::<T>::contains_key(htlc_id),
+				Error::<T>::CounterpartAlreadyBound
+			);
+
+			// both legs must be unlocked by the same secret
+			ensure!(
+				counterpart.hashlock == htlc.immutables.hashlock,
+				Error::<T>::CounterpartMismatch
+			);
+
+			let escrow = escrow_address(&factory, &implementation, &counterpart);
+			CounterpartEscrows::<T>::insert(htlc_id, escrow);
+			Self::track_counterpart(htlc.immutables.order_hash, htlc_id, escrow)?;
+
+			Self::deposit_event(Event::CounterpartEscrowBound { htlc_id, escrow });
+
+			Ok(())
+		}
+
+		/// Record the transaction, address and block of the escrow that the
+		/// taker deployed for the other leg of an active HTLC, e.g. again after
+		/// a reorg of the other chain. It must be the escrow that a source
+		/// HTLC is bound to, if any.
+		#[pallet::call_index(44)]
+		pub fn record_counterpart_reference(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			reference: CounterpartReference,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			if let Some(escrow) = CounterpartEscrows::<T>::get(htlc_id) {
+				ensure!(reference.escrow == escrow, Error::<T>::CounterpartMismatch);
+			}
+
+			CounterpartReferences::<T>::insert(htlc_id, &reference);
+			Self::track_counterpart(htlc.immutables.order_hash, htlc_id, reference.escrow)?;
+
+			Self::deposit_event(Event::CounterpartReferenceRecorded { htlc_id, reference });
+
+			Ok(())
+		}
+
+		/// Declare that an active HTLC is the downstream leg of a swap routed
+		/// through this chain, whose upstream leg is the active HTLC locked
+		/// with `parent_hashlock`. The downstream leg must be cancellable
+		/// strictly before the upstream one, so that the intermediary can
+		/// still withdraw upstream once the secret is revealed downstream.
+		#[pallet::call_index(45)]
+		pub fn declare_parent_hashlock(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			parent_hashlock: H256,
+		) -> DispatchResult {
+			let who = Self::ensure_party(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+
+			let upstream =
+				Self::ensure_shorter_than_upstream(htlc_id, &htlc.immutables, parent_hashlock)?;
+			ParentHashlocks::<T>::insert(htlc_id, parent_hashlock);
+
+			Self::deposit_event(Event::ParentHashlockDeclared {
+				htlc_id,
+				parent_hashlock,
+				upstream,
+			});
+
+			Ok(())
+		}
+
+		/// Move a completed or cancelled HTLC to the compact archive; anyone
+		/// can call this to free the storage used by the full HTLC.
+		#[pallet::call_index(9)]
+		pub fn prune_htlc(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			Self::ensure_party(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status != HtlcStatus::Active, Error::<T>::HtlcStillActive);
+
+			let archived = ArchivedHtlc {
+				status: htlc.status,
+				hashlock: htlc.immutables.hashlock,
+				amount: htlc.immutables.amount,
+				archived_at: frame_system::Pallet::<T>::block_number(),
+			};
+
+			Htlcs::<T>::remove(htlc_id);
+			HashlockIndex::<T>::mutate_exists(htlc.immutables.hashlock, |maybe_ids| {
+				if let Some(ids) = maybe_ids {
+					ids.retain(|id| *id != htlc_id);
+					if ids.is_empty() {
+						*maybe_ids = None;
+					}
+				}
+			});
+			CounterpartEscrows::<T>::remove(htlc_id);
+			CounterpartReferences::<T>::remove(htlc_id);
+			FillIndexes::<T>::remove(htlc_id);
+			ParentHashlocks::<T>::remove(htlc_id);
+			CancelAgreements::<T>::remove(htlc_id);
+			ArchivedHtlcs::<T>::insert(htlc_id, archived);
+
+			Self::deposit_event(Event::HtlcArchived { htlc_id });
+
+			Ok(())
+		}
+
+		/// Prove that the HTLC corresponds to a 1inch Fusion order signed by
+		/// its maker on the EVM chain.
+		#[pallet::call_index(12)]
+		pub fn verify_fusion_order(
+			origin: OriginFor<T>,
+			htlc_id: H256,
+			order: Box<FusionOrder>,
+			domain: FusionDomain,
+			signature: [u8; 65],
+		) -> DispatchResult {
+			Self::ensure_party(origin)?;
+
+			let order_hash = Self::check_fusion_order(htlc_id, &order, &domain, &signature)?;
+
+			Self::deposit_event(Event::FusionOrderVerified {
+				htlc_id,
+				order_hash,
+				maker: order.maker,
+			});
+
+			Ok(())
+		}
+
+		///////
+		// Governance calls
+
+		#[pallet::call_index(7)]
+		pub fn block_account(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(!Blocked::<T>::contains_key(&who), Error::<T>::AlreadyBlocked);
+			Blocked::<T>::insert(&who, ());
+
+			Self::deposit_event(Event::AccountBlocked { who });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(8)]
+		pub fn unblock_account(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(Blocked::<T>::contains_key(&who), Error::<T>::NotBlocked);
+			Blocked::<T>::remove(&who);
+
+			Self::deposit_event(Event::AccountUnblocked { who });
+
+			Ok(())
+		}
+
+		/// Refund an HTLC that stayed active for the `EmergencySweepPeriod` to
+		/// its depositors, so that orphaned holds do not accumulate.
+		#[pallet::call_index(16)]
+		pub fn sweep_htlc(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+
+			let current_block = frame_system::Pallet::<T>::block_number();
+			ensure!(
+				current_block >=
+					htlc.immutables
+						.timelocks
+						.deployed_at
+						.saturating_add(T::EmergencySweepPeriod::get()),
+				Error::<T>::EarlySweep
+			);
+
+			Self::cancel_htlc(htlc_id, htlc, IntentStatus::Cancelled)?;
+			Self::deposit_event(Event::HtlcSwept { htlc_id });
+
+			Ok(())
+		}
+
+		/// Cancel an HTLC that got stuck, whatever its timelocks, releasing
+		/// what is left of its holds, and release the holds of the swap
+		/// amounts and safety deposits of its parties that no active HTLC
+		/// accounts for any more, with the holds that the HTLC or intent
+		/// `htlc_id` left behind.
+		#[pallet::call_index(43)]
+		pub fn force_reconcile(origin: OriginFor<T>, htlc_id: H256) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			let htlc = Htlcs::<T>::get(htlc_id);
+			ensure!(
+				htlc.is_some() || ResidualHolds::<T>::iter_prefix(htlc_id).next().is_some(),
+				Error::<T>::HtlcDoesNotExist
+			);
+			if let Some(htlc) = htlc {
+				let parties = [
+					htlc.immutables.maker.clone(),
+					htlc.immutables.taker.clone(),
+					Self::swap_funder(htlc_id, &htlc),
+					DepositSponsors::<T>::get(htlc_id)
+						.unwrap_or_else(|| htlc.immutables.taker.clone()),
+				];
+				if htlc.status == HtlcStatus::Active {
+					Self::cancel_htlc(htlc_id, htlc, IntentStatus::Cancelled)?;
+				}
+
+				if T::EscrowMode::get() == EscrowMode::Holds {
+					let mut released = Vec::new();
+					for who in parties {
+						if !released.contains(&who) {
+							Self::release_orphaned_holds(&who)?;
+							released.push(who);
+						}
+					}
+				}
+			}
+			Self::release_residual_holds(htlc_id)?;
+
+			Self::deposit_event(Event::HtlcReconciled { htlc_id });
+
+			Ok(())
+		}
+
+		/// Replace the discounts of the vault fee that accounts get by the
+		/// volume they settled over the `VolumeWindow`.
+		#[pallet::call_index(53)]
+		pub fn set_fee_tiers(origin: OriginFor<T>, tiers: FeeTiersOf<T>) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				tiers.windows(2).all(|pair| pair[0].min_volume < pair[1].min_volume),
+				Error::<T>::FeeTiersNotSorted
+			);
+			FeeTiers::<T>::put(&tiers);
+
+			Self::deposit_event(Event::FeeTiersSet { tiers });
+
+			Ok(())
+		}
+
+		/// Replace the terms that registered resolvers get by the stake
+		/// delegated to them.
+		#[pallet::call_index(54)]
+		pub fn set_resolver_tiers(
+			origin: OriginFor<T>,
+			tiers: ResolverTiersOf<T>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(
+				tiers.windows(2).all(|pair| pair[0].min_stake < pair[1].min_stake),
+				Error::<T>::ResolverTiersNotSorted
+			);
+			ResolverTiers::<T>::put(&tiers);
+
+			Self::deposit_event(Event::ResolverTiersSet { tiers });
+
+			Ok(())
+		}
+
+		/// Sweep the hold of `who` for `reason` that the terminal HTLC or
+		/// intent `key` left behind to the `UnclaimedHandler`, once it stayed
+		/// unclaimed for the `EscheatmentPeriod` since it was noted, unless
+		/// governance reconciled it back to `who` in the meantime.
+		#[pallet::call_index(55)]
+		pub fn escheat_residual_hold(
+			origin: OriginFor<T>,
+			key: H256,
+			who: T::AccountId,
+			reason: HoldReason,
+		) -> DispatchResult {
+			Self::ensure_party(origin)?;
+
+			let (amount, since) = ResidualHolds::<T>::get(key, (who.clone(), reason))
+				.ok_or(Error::<T>::NoResidualHold)?;
+			ensure!(
+				frame_system::Pallet::<T>::block_number() >=
+					since.saturating_add(T::EscheatmentPeriod::get()),
+				Error::<T>::EarlyEscheatment
+			);
+
+			ResidualHolds::<T>::remove(key, (who.clone(), reason));
+			Self::track_expected_hold(reason, &who, |held| held.saturating_reduce(amount));
+			let (credit, _) = T::NativeBalance::slash(&reason.into(), &who, amount);
+			let amount = credit.peek();
+			T::UnclaimedHandler::on_unbalanced(credit);
+
+			Self::deposit_event(Event::ResidualHoldEscheated { key, who, reason, amount });
+
+			Ok(())
+		}
+
+		/// Clear the deficit that a partial release of the hold of `who` for
+		/// `reason` left for the HTLC or intent `key`, once it is remediated,
+		/// with the shortfall that the `ExpectedHolds` of `who` kept for it.
+		/// What the remediation restored of the hold is left behind by `key`.
+		#[pallet::call_index(69)]
+		pub fn clear_escrow_deficit(
+			origin: OriginFor<T>,
+			key: H256,
+			who: T::AccountId,
+			reason: HoldReason,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			let amount = EscrowDeficits::<T>::take(key, (who.clone(), reason));
+			ensure!(!amount.is_zero(), Error::<T>::NoEscrowDeficit);
+			Self::track_expected_hold(reason, &who, |held| held.saturating_reduce(amount));
+			Self::note_residual_hold(reason, key, &who, amount);
+
+			Self::deposit_event(Event::EscrowDeficitCleared { key, who, reason, amount });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(10)]
+		pub fn set_chain_profile(
+			origin: OriginFor<T>,
+			chain_id: ChainId,
+			profile: ChainProfile<BlockNumberFor<T>>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ChainProfiles::<T>::insert(chain_id, &profile);
+
+			Self::deposit_event(Event::ChainProfileSet { chain_id, profile });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(11)]
+		pub fn remove_chain_profile(origin: OriginFor<T>, chain_id: ChainId) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(ChainProfiles::<T>::contains_key(chain_id), Error::<T>::UnknownChain);
+			ChainProfiles::<T>::remove(chain_id);
+			TimelockTemplates::<T>::remove(chain_id);
+
+			Self::deposit_event(Event::ChainProfileRemoved { chain_id });
+
+			Ok(())
+		}
+
+		/// Recommend the timelocks of the swaps with a registered chain, which
+		/// `create_src_htlc_with_template` applies, or stop with `None`.
+		#[pallet::call_index(57)]
+		pub fn set_timelock_template(
+			origin: OriginFor<T>,
+			chain_id: ChainId,
+			template: Option<TimelockTemplate>,
+		) -> DispatchResult {
+			T::GovernanceOrigin::ensure_origin(origin)?;
+
+			ensure!(ChainProfiles::<T>::contains_key(chain_id), Error::<T>::UnknownChain);
+			ensure!(
+				template.as_ref().is_none_or(TimelockTemplate::is_valid),
+				Error::<T>::InvalidTimelocks
+			);
+			TimelockTemplates::<T>::set(chain_id, template.clone());
+
+			Self::deposit_event(Event::TimelockTemplateSet { chain_id, template });
+
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Generate unique ID from immutables. The immutables of the blake2
+		/// hashlocks are hashed without their `hash_function`, so that the
+		/// HTLCs from before it keep their ids.
+		pub fn hash_immutables(
+			immutables: &Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> H256 {
+			let encoded = match immutables.hash_function {
+				HashFunction::Blake2_256 => (
+					&immutables.order_hash,
+					&immutables.hashlock,
+					&immutables.maker,
+					&immutables.taker,
+					&immutables.amount,
+					&immutables.safety_deposit,
+					&immutables.timelocks,
+				)
+					.encode(),
+				HashFunction::Sha256 => immutables.encode(),
+			};
+			BlakeTwo256::hash(&encoded)
+		}
+
+		/// Check whether `who` can escrow `amount` and `safety_deposit` in a new
+		/// HTLC with the configured `EscrowMode`, so that a resolver learns why
+		/// before the escrow fails, e.g. because most of its balance is staked.
+		pub fn can_create_htlc(
+			who: &T::AccountId,
+			amount: BalanceOf<T>,
+			safety_deposit: BalanceOf<T>,
+		) -> Result<(), CannotCreateHtlc<BalanceOf<T>>> {
+			ensure!(!Blocked::<T>::contains_key(who), CannotCreateHtlc::Blocked);
+			ensure!(T::ComplianceFilter::contains(who), CannotCreateHtlc::NotCompliant);
+
+			let minimum = Self::min_safety_deposit(who);
+			ensure!(safety_deposit >= minimum, CannotCreateHtlc::SafetyDepositTooLow { minimum });
+
+			let required = amount.saturating_add(safety_deposit);
+			let balance = T::NativeBalance::balance(who);
+			ensure!(
+				required <= balance,
+				CannotCreateHtlc::InsufficientBalance { required, balance }
+			);
+
+			// holds may overlap the freezes, the transfers to the escrow
+			// accounts may not
+			let (liquid, spendable) = match T::EscrowMode::get() {
+				EscrowMode::Holds => (
+					T::NativeBalance::reducible_balance(
+						who,
+						Preservation::Expendable,
+						Fortitude::Force,
+					),
+					T::NativeBalance::reducible_balance(
+						who,
+						Preservation::Protect,
+						Fortitude::Force,
+					),
+				),
+				EscrowMode::SubAccounts | EscrowMode::PalletAccount => (
+					T::NativeBalance::reducible_balance(
+						who,
+						Preservation::Expendable,
+						Fortitude::Polite,
+					),
+					T::NativeBalance::reducible_balance(
+						who,
+						Preservation::Preserve,
+						Fortitude::Polite,
+					),
+				),
+			};
+			ensure!(required <= liquid, CannotCreateHtlc::Frozen { required, available: liquid });
+			ensure!(
+				required <= spendable,
+				CannotCreateHtlc::ExistentialDeposit { required, available: spendable }
+			);
+
+			Ok(())
+		}
+
+		/// Estimate the costs of a swap of `amount` for `role` in the worst case,
+		/// in which the maker leaves no timeout on its intent and converts the
+		/// settled funds into `asset` if any, the taker draws from the vault and
+		/// every HTLC is settled by its own extrinsic. The fees of the weight
+		/// and length of the extrinsics are left to `weight_to_fee` and
+		/// `length_to_fee`, e.g. the transaction payment of the runtime, and
+		/// exclude their signatures, extensions and tips.
+		pub fn estimate_swap_costs(
 			amount: BalanceOf<T>,
-			safety_deposit: BalanceOf<T>,
-		},
-		/// HTLC withdrawn.
-		HtlcWithdrawn {
+			asset: Option<T::ConversionAsset>,
+			role: SwapRole,
+			weight_to_fee: impl FnOnce(Weight) -> BalanceOf<T>,
+			length_to_fee: impl FnOnce(u32) -> BalanceOf<T>,
+		) -> SwapCosts<BalanceOf<T>> {
+			// Every call is prefixed by the indices of the pallet and the call.
+			let call_len = |args: usize| args.saturating_add(2);
+			let immutables_len = ImmutablesOf::<T>::max_encoded_len();
+			let balance_len = BalanceOf::<T>::max_encoded_len();
+			let settlement_weight = Self::withdraw_weight(MAX_SECRET_LEN, false)
+				.max(T::WeightInfo::cancel(MAX_ORDER_LEGS - 1));
+			let settlement_len = call_len(
+				immutables_len
+					.saturating_add(codec::Compact(MAX_SECRET_LEN).encoded_size())
+					.saturating_add(MAX_SECRET_LEN as usize),
+			);
+
+			let (safety_deposit, storage_deposit, protocol_fee, calls) = match role {
+				// create_swap_intent, set_settlement_conversion for an asset and
+				// the settlement of the destination HTLC
+				SwapRole::Maker => {
+					let mut calls = vec![
+						(
+							T::WeightInfo::create_swap_intents(1),
+							call_len(SwapIntentOf::<T>::max_encoded_len()),
+						),
+						(settlement_weight, settlement_len),
+					];
+					if let Some(asset) = asset {
+						let conversion = Some(SettlementConversion { asset, min_out: amount });
+						calls.push((
+							T::WeightInfo::set_settlement_conversion(),
+							call_len(
+								H256::max_encoded_len().saturating_add(conversion.encoded_size()),
+							),
+						));
+					}
+					(
+						T::MakerSafetyDeposit::get(),
+						T::GoodTilCancelDeposit::get(),
+						Zero::zero(),
+						calls,
+					)
+				},
+				// take_intent, create_src_htlc, create_vault_dst_htlc and the
+				// settlements of both HTLCs
+				SwapRole::Taker => (
+					T::MinSafetyDeposit::get()
+						.saturating_mul(2u32.into())
+						.saturating_add(T::ReservationBond::get()),
+					Zero::zero(),
+					T::VaultFee::get().mul_ceil(amount),
+					vec![
+						(
+							T::WeightInfo::take_intent(MAX_ORDER_LEGS - 1),
+							call_len(T::AccountId::max_encoded_len().saturating_add(8)),
+						),
+						(
+							Self::fill_weight(),
+							call_len(
+								T::AccountId::max_encoded_len()
+									.saturating_add(8)
+									.saturating_add(
+										Timelocks::<BlockNumberFor<T>>::max_encoded_len(),
+									)
+									.saturating_add(balance_len),
+							),
+						),
+						(
+							Self::dst_weight(true),
+							call_len(
+								immutables_len
+									.saturating_add(BlockNumberFor::<T>::max_encoded_len())
+									.saturating_add(ChainId::max_encoded_len()),
+							),
+						),
+						(settlement_weight, settlement_len),
+						(settlement_weight, settlement_len),
+					],
+				),
+			};
+
+			let base_extrinsic = T::BlockWeights::get().get(DispatchClass::Normal).base_extrinsic;
+			let (weight, length) =
+				calls.into_iter().fold((Weight::zero(), 0usize), |(weight, length), (w, l)| {
+					(
+						weight.saturating_add(base_extrinsic).saturating_add(w),
+						length.saturating_add(l),
+					)
+				});
+			let length = length as u32;
+
+			SwapCosts {
+				safety_deposit,
+				storage_deposit,
+				protocol_fee,
+				weight,
+				weight_fee: weight_to_fee(weight),
+				length,
+				length_fee: length_to_fee(length),
+			}
+		}
+
+		/// The stage of an HTLC with the timelocks at block `now`.
+		pub fn current_stage(
+			timelocks: &Timelocks<BlockNumberFor<T>>,
+			now: BlockNumberFor<T>,
+		) -> Stage {
+			if now < timelocks.withdrawal_after {
+				Stage::FinalityLock
+			} else if now < timelocks.public_withdrawal_after {
+				Stage::PrivateWithdrawal
+			} else if now < timelocks.cancellation_after {
+				Stage::PublicWithdrawal
+			} else if now <
+				timelocks.cancellation_after.saturating_add(T::PublicCancellationDelay::get())
+			{
+				Stage::Cancellation
+			} else {
+				Stage::PublicCancellation
+			}
+		}
+
+		/// The current stage of an active HTLC.
+		pub fn htlc_stage(htlc_id: H256) -> Option<Stage> {
+			let htlc = Htlcs::<T>::get(htlc_id).filter(|htlc| htlc.status == HtlcStatus::Active)?;
+			Some(Self::current_stage(
+				&htlc.immutables.timelocks,
+				frame_system::Pallet::<T>::block_number(),
+			))
+		}
+
+		/// Check whether `who` could withdraw the HTLC with the secret now,
+		/// returning the error that `withdraw`, or `public_withdraw` if `who`
+		/// is not the taker, would fail with.
+		pub fn validate_withdraw(
 			htlc_id: H256,
+			who: &T::AccountId,
+			secret: &[u8],
+		) -> DispatchResult {
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			Self::ensure_can_withdraw(
+				htlc_id,
+				&htlc,
+				who,
+				secret,
+				None,
+				*who != htlc.immutables.taker,
+			)
+		}
+
+		/// Check whether `who` could cancel the HTLC now, returning the error
+		/// that `cancel`, or `public_cancel` if `who` is not the taker, would
+		/// fail with.
+		pub fn validate_cancel(htlc_id: H256, who: &T::AccountId) -> DispatchResult {
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			Self::ensure_can_cancel(htlc_id, &htlc, who, *who != htlc.immutables.taker)
+		}
+
+		/// A page of up to `limit` intents that resolvers can fill, with their
+		/// keys, following the intent `start_key` or from the first one. The
+		/// key of the last intent continues with the next page.
+		pub fn active_intents(start_key: Option<H256>, limit: u32) -> Vec<(H256, SwapIntentOf<T>)> {
+			let current_block = frame_system::Pallet::<T>::block_number();
+			let intents = match start_key {
+				Some(key) => SwapIntents::<T>::iter_from(SwapIntents::<T>::hashed_key_for(key)),
+				None => SwapIntents::<T>::iter(),
+			};
+			intents
+				.filter(|(_, stored_intent)| {
+					stored_intent.status == IntentStatus::Active &&
+						!stored_intent.intent.is_expired_at(current_block) &&
+						Self::ensure_intent_epoch(&stored_intent.intent).is_ok()
+				})
+				.map(|(intent_key, stored_intent)| (intent_key, stored_intent.intent))
+				.take(limit as usize)
+				.collect()
+		}
+
+		/// Compute the aggregate metrics over all the stored HTLCs and intents.
+		pub fn metrics() -> HtlcMetrics<BalanceOf<T>, BlockNumberFor<T>> {
+			let current_block = frame_system::Pallet::<T>::block_number();
+			let mut metrics = HtlcMetrics::<BalanceOf<T>, BlockNumberFor<T>>::default();
+
+			for (htlc_id, htlc) in
+				Htlcs::<T>::iter().filter(|(_, htlc)| htlc.status == HtlcStatus::Active)
+			{
+				metrics.active_htlcs.saturating_inc();
+				if htlc.htlc_type == HtlcType::Destination {
+					metrics.held_swap_amount.saturating_accrue(htlc.immutables.amount);
+				}
+				metrics
+					.held_safety_deposit
+					.saturating_accrue(Self::safety_deposit(htlc_id, &htlc));
+
+				let age = current_block.saturating_sub(htlc.immutables.timelocks.deployed_at);
+				if metrics.oldest_active_htlc_age.is_none_or(|oldest| age > oldest) {
+					metrics.oldest_active_htlc_age = Some(age);
+				}
+			}
+
+			for (intent_key, stored_intent) in SwapIntents::<T>::iter() {
+				match stored_intent.status {
+					IntentStatus::Active => metrics.active_intents.saturating_inc(),
+					IntentStatus::Reserved { .. } | IntentStatus::InProgress { .. } =>
+						metrics.in_progress_intents.saturating_inc(),
+					IntentStatus::Completed => metrics.completed_intents.saturating_inc(),
+					IntentStatus::Cancelled => metrics.cancelled_intents.saturating_inc(),
+					IntentStatus::Expired => metrics.expired_intents.saturating_inc(),
+				}
+				if matches!(
+					stored_intent.status,
+					IntentStatus::Active |
+						IntentStatus::Reserved { .. } |
+						IntentStatus::InProgress { .. }
+				) {
+					metrics
+						.held_maker_intent_amount
+						.saturating_accrue(Self::held_intent_amount(intent_key, &stored_intent));
+					metrics
+						.held_maker_safety_deposit
+						.saturating_accrue(stored_intent.maker_deposit);
+					metrics.held_reservation_bond.saturating_accrue(stored_intent.reservation_bond);
+				}
+			}
+
+			for claim in OptimisticClaims::<T>::iter_values() {
+				metrics.held_claim_bond.saturating_accrue(claim.bond);
+			}
+
+			metrics
+		}
+
+		/// Check the consistency of the HTLCs, the swap intents and their
+		/// indexes.
+		#[cfg(any(feature = "try-runtime", test))]
+		pub fn do_try_state() -> Result<(), TryRuntimeError> {
+			for (watchtower, info) in Watchtowers::<T>::iter() {
+				let duties =
+					WatchtowerDuties::<T>::iter_values().filter(|who| *who == watchtower).count();
+				ensure!(duties == info.duties as usize, "watchtower duties are miscounted");
+			}
+			ensure!(
+				WatchtowerDuties::<T>::iter_values().all(Watchtowers::<T>::contains_key),
+				"unregistered watchtower on duty"
+			);
+
+			for (hashlock, htlc_ids) in HashlockIndex::<T>::iter() {
+				// the active escrows of a hashlock belong to a single order
+				let mut order = IntentHashlocks::<T>::get(hashlock);
+				for htlc_id in htlc_ids {
+					let htlc = Htlcs::<T>::get(htlc_id).ok_or("indexed HTLC does not exist")?;
+					ensure!(htlc.immutables.hashlock == hashlock, "HTLC indexed by wrong hashlock");
+					if htlc.status != HtlcStatus::Active {
+						continue;
+					}
+					let order_hash = *order.get_or_insert(htlc.immutables.order_hash);
+					ensure!(
+						htlc.immutables.order_hash == order_hash,
+						"active escrows of different orders use the same hashlock"
+					);
+				}
+			}
+
+			for (htlc_id, htlc) in Htlcs::<T>::iter() {
+				ensure!(
+					HashlockIndex::<T>::get(htlc.immutables.hashlock).contains(&htlc_id),
+					"HTLC is missing from the hashlock index"
+				);
+			}
+
+			for (htlc_id, agreed) in CancelAgreements::<T>::iter() {
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("agreed HTLC does not exist")?;
+				ensure!(
+					agreed == htlc.immutables.maker || agreed == htlc.immutables.taker,
+					"cancellation agreed by a third party"
+				);
+			}
+
+			// the outstanding funds of the vault are drawn or owed by resolvers
+			let mut drawn = BalanceOf::<T>::zero();
+			for htlc_id in VaultDraws::<T>::iter_keys() {
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("vault-funded HTLC does not exist")?;
+				ensure!(htlc.status == HtlcStatus::Active, "vault-funded HTLC is not active");
+				drawn.saturating_accrue(htlc.immutables.amount);
+			}
+			let (credit_drawn, credit_owed) = ResolverCredits::<T>::iter_values().fold(
+				(BalanceOf::<T>::zero(), BalanceOf::<T>::zero()),
+				|(drawn, owed), credit| {
+					let owed_to_vault = credit.owed.saturating_sub(credit.insurance_owed);
+					(drawn.saturating_add(credit.drawn), owed.saturating_add(owed_to_vault))
+				},
+			);
+			ensure!(drawn == credit_drawn, "vault draws do not match the resolver credits");
+			ensure!(
+				Vault::<T>::get().outstanding == credit_drawn.saturating_add(credit_owed),
+				"outstanding vault funds do not match the resolver credits"
+			);
+
+			let queued_shares = VaultWithdrawalQueue::<T>::get()
+				.iter()
+				.fold(BalanceOf::<T>::zero(), |queued, (_, shares)| queued.saturating_add(*shares));
+			ensure!(
+				Vault::<T>::get().queued_shares == queued_shares,
+				"queued vault shares do not match the withdrawal queue"
+			);
+
+			for htlc_id in DepositSponsors::<T>::iter_keys() {
+				if PendingDstHtlcs::<T>::contains_key(htlc_id) {
+					continue;
+				}
+				if let Some(commitment) = HtlcCommitments::<T>::get(htlc_id) {
+					ensure!(
+						commitment.status == HtlcStatus::Active,
+						"sponsored HTLC is not active"
+					);
+					continue;
+				}
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("sponsored HTLC does not exist")?;
+				ensure!(htlc.status == HtlcStatus::Active, "sponsored HTLC is not active");
+			}
+
+			for htlc_id in PendingDstHtlcs::<T>::iter_keys() {
+				ensure!(!Htlcs::<T>::contains_key(htlc_id), "pending HTLC already exists");
+			}
+
+			for htlc_id in HtlcCommitments::<T>::iter_keys() {
+				ensure!(!Htlcs::<T>::contains_key(htlc_id), "committed HTLC is also stored");
+			}
+
+			if T::EscrowMode::get() == EscrowMode::Holds {
+				let mut expected = sp_std::collections::btree_map::BTreeMap::new();
+				let active = Htlcs::<T>::iter()
+					.filter(|(_, htlc)| htlc.status == HtlcStatus::Active)
+					.flat_map(|(htlc_id, htlc)| Self::expected_holds(htlc_id, &htlc));
+				let pending = PendingDstHtlcs::<T>::iter()
+					.map(|(htlc_id, pending)| Self::expected_pending_hold(htlc_id, &pending));
+				for (who, reason, amount) in active.chain(pending) {
+					let hold: &mut ExpectedHold<BalanceOf<T>> = expected.entry(who).or_default();
+					match reason {
+						HoldReason::SwapAmount => hold.swap_amounts.saturating_accrue(amount),
+						_ => hold.safety_deposits.saturating_accrue(amount),
+					}
+				}
+				// the expected holds cover the HTLCs, and beyond them only the
+				// shortfalls of the partial releases and the residual holds,
+				// unless the holds of committed HTLCs, whose amounts are not
+				// stored, are among them
+				let mut shortfalls = BalanceOf::<T>::zero();
+				for (who, tracked) in ExpectedHolds::<T>::iter() {
+					let hold = expected.remove(&who).unwrap_or_default();
+					ensure!(
+						tracked.swap_amounts >= hold.swap_amounts &&
+							tracked.safety_deposits >= hold.safety_deposits,
+						"expected holds do not cover the active and pending HTLCs"
+					);
+					shortfalls.saturating_accrue(tracked.swap_amounts - hold.swap_amounts);
+					shortfalls.saturating_accrue(tracked.safety_deposits - hold.safety_deposits);
+				}
+				ensure!(
+					expected.values().all(|hold| *hold == ExpectedHold::default()),
+					"expected holds do not cover the active and pending HTLCs"
+				);
+				let deficits = EscrowDeficits::<T>::iter_values()
+					.chain(ResidualHolds::<T>::iter_values().map(|(residual, _)| residual))
+					.fold(BalanceOf::<T>::zero(), |total, deficit| total.saturating_add(deficit));
+				let committed = HtlcCommitments::<T>::iter_values()
+					.any(|commitment| commitment.status == HtlcStatus::Active);
+				ensure!(
+					committed || shortfalls <= deficits,
+					"expected holds exceed the escrow deficits"
+				);
+			}
+
+			for htlc_id in ResidualHolds::<T>::iter_keys().map(|(key, _)| key) {
+				ensure!(
+					Htlcs::<T>::get(htlc_id).is_none_or(|htlc| htlc.status != HtlcStatus::Active),
+					"active HTLC left a hold behind"
+				);
+			}
+
+			for htlc_id in SafetyDepositTopUps::<T>::iter_keys() {
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("topped up HTLC does not exist")?;
+				ensure!(htlc.status == HtlcStatus::Active, "topped up HTLC is not active");
+			}
+
+			for htlc_id in SettlementConversions::<T>::iter_keys() {
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("converted HTLC does not exist")?;
+				ensure!(htlc.status == HtlcStatus::Active, "converted HTLC is not active");
+				ensure!(
+					htlc.htlc_type == HtlcType::Destination,
+					"converted HTLC is not a destination"
+				);
+			}
+
+			for htlc_id in SettlementDeliveries::<T>::iter_keys() {
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("delivered HTLC does not exist")?;
+				ensure!(htlc.status == HtlcStatus::Active, "delivered HTLC is not active");
+				ensure!(
+					htlc.htlc_type == HtlcType::Destination,
+					"delivered HTLC is not a destination"
+				);
+				ensure!(
+					!SettlementConversions::<T>::contains_key(htlc_id),
+					"delivered HTLC is also converted"
+				);
+			}
+
+			for (order_hash, record) in CrossChainOrders::<T>::iter() {
+				for htlc_id in &record.htlcs {
+					ensure!(
+						Htlcs::<T>::get(htlc_id).map_or(
+							ArchivedHtlcs::<T>::contains_key(htlc_id),
+							|htlc| htlc.immutables.order_hash == order_hash
+						),
+						"order leg does not exist"
+					);
+				}
+			}
+			for (htlc_id, htlc) in Htlcs::<T>::iter() {
+				ensure!(
+					CrossChainOrders::<T>::get(htlc.immutables.order_hash)
+						.is_some_and(|record| record.htlcs.contains(&htlc_id)),
+					"HTLC is not a leg of its order"
+				);
+			}
+
+			for htlc_id in ParentHashlocks::<T>::iter_keys() {
+				ensure!(Htlcs::<T>::contains_key(htlc_id), "downstream HTLC does not exist");
+			}
+
+			for htlc_id in CounterpartReferences::<T>::iter_keys() {
+				ensure!(Htlcs::<T>::contains_key(htlc_id), "referenced HTLC does not exist");
+			}
+
+			for htlc_id in CounterpartEscrows::<T>::iter_keys() {
+				let htlc = Htlcs::<T>::get(htlc_id).ok_or("bound HTLC does not exist")?;
+				ensure!(htlc.htlc_type == HtlcType::Source, "bound HTLC is not a source");
+			}
+
+			let mut delegated_stakes = sp_std::collections::btree_map::BTreeMap::new();
+			for (resolver, _, delegation) in Delegations::<T>::iter() {
+				let stake: &mut DelegatedStake<BalanceOf<T>> =
+					delegated_stakes.entry(resolver).or_default();
+				stake.bonded.saturating_accrue(delegation.bonded);
+				stake.delegators.saturating_inc();
+			}
+			ensure!(
+				DelegatedStakes::<T>::iter()
+					.collect::<sp_std::collections::btree_map::BTreeMap<_, _>>() ==
+					delegated_stakes,
+				"delegated stakes are miscounted"
+			);
+
+			ensure!(
+				FeeTiers::<T>::get()
+					.windows(2)
+					.all(|pair| pair[0].min_volume < pair[1].min_volume),
+				"fee tiers are not sorted"
+			);
+			ensure!(
+				ResolverTiers::<T>::get()
+					.windows(2)
+					.all(|pair| pair[0].min_stake < pair[1].min_stake),
+				"resolver tiers are not sorted"
+			);
+			for volumes in RecentVolumes::<T>::iter_values() {
+				ensure!(
+					volumes.windows(2).all(|pair| pair[0].0 < pair[1].0),
+					"volume buckets are not sorted"
+				);
+			}
+
+			let mut resolver_intents = sp_std::collections::btree_map::BTreeMap::new();
+			for stored_intent in SwapIntents::<T>::iter_values() {
+				if let Some(resolver) = stored_intent.status.resolver() {
+					resolver_intents
+						.entry(resolver.clone())
+						.and_modify(|n: &mut u32| n.saturating_inc())
+						.or_insert(1);
+				}
+			}
+			ensure!(
+				ResolverIntents::<T>::iter()
+					.collect::<sp_std::collections::btree_map::BTreeMap<_, _>>() ==
+					resolver_intents,
+				"intents of the resolvers are miscounted"
+			);
+
+			let mut open_intents = sp_std::collections::btree_map::BTreeMap::new();
+			for (hashlock, intent_key) in IntentHashlocks::<T>::iter() {
+				let stored_intent =
+					SwapIntents::<T>::get(intent_key).ok_or("indexed intent does not exist")?;
+				open_intents
+					.entry(stored_intent.intent.maker.clone())
+					.or_insert_with(Vec::new)
+					.push(intent_key);
+				ensure!(
+					stored_intent.intent.hashlock == hashlock,
+					"intent indexed by wrong hashlock"
+				);
+				ensure!(
+					matches!(
+						stored_intent.status,
+						IntentStatus::Active |
+							IntentStatus::Reserved { .. } |
+							IntentStatus::InProgress { .. }
+					),
+					"indexed intent is not active"
+				);
+			}
+			for keys in open_intents.values_mut() {
+				keys.sort();
+			}
+			ensure!(
+				MakerIntents::<T>::iter()
+					.map(|(maker, keys)| {
+						let mut keys = keys.into_inner();
+						keys.sort();
+						(maker, keys)
+					})
+					.collect::<sp_std::collections::btree_map::BTreeMap<_, _>>() ==
+					open_intents,
+				"open intents of the makers are not indexed"
+			);
+
+			for (intent_key, stored_intent) in SwapIntents::<T>::iter() {
+				match stored_intent.status {
+					IntentStatus::Active | IntentStatus::Reserved { .. } => ensure!(
+						IntentHashlocks::<T>::get(stored_intent.intent.hashlock) ==
+							Some(intent_key),
+						"active intent is missing from the hashlock index"
+					),
+					IntentStatus::InProgress { htlc_id, .. } => {
+						let htlc = Htlcs::<T>::get(htlc_id).ok_or("filling HTLC does not exist")?;
+						ensure!(htlc.status == HtlcStatus::Active, "filling HTLC is not active");
+						ensure!(htlc.htlc_type == HtlcType::Source, "filling HTLC is not a source");
+					},
+					_ => {},
+				}
+			}
+
+			for (intent_key, quote) in AcceptedQuotes::<T>::iter() {
+				let stored_intent =
+					SwapIntents::<T>::get(intent_key).ok_or("quoted intent does not exist")?;
+				ensure!(
+					stored_intent.status == IntentStatus::Reserved { resolver: quote.resolver },
+					"accepted quote of an intent that its resolver does not reserve"
+				);
+			}
+
+			Ok(())
+		}
+
+		/// Weight of a withdrawal with a secret of `s` bytes, in a block that
+		/// revealed its maximum of other secrets, of an order with its maximum
+		/// of legs.
+		fn withdraw_weight(s: u32, public: bool) -> Weight {
+			let r = T::MaxRevealedSecretsPerBlock::get().saturating_sub(1);
+			let f = MAX_ORDER_LEGS - 1;
+			if public {
+				T::WeightInfo::public_withdraw(s, r, f)
+			} else {
+				T::WeightInfo::withdraw(s, r, f)
+			}
+		}
+
+		/// Weight of a destination HTLC whose hashlock the maximum of other
+		/// HTLCs share, of an order with its maximum of legs.
+		fn dst_weight(from_vault: bool) -> Weight {
+			let h = T::MaxHtlcsPerHashlock::get().saturating_sub(1);
+			let f = MAX_ORDER_LEGS - 1;
+			if from_vault {
+				T::WeightInfo::create_vault_dst_htlc(h, f)
+			} else {
+				T::WeightInfo::create_dst_htlc(h, f)
+			}
+		}
+
+		/// Weight of a fill of an intent whose hashlock the maximum of other
+		/// HTLCs share, of an order with its maximum of legs.
+		fn fill_weight() -> Weight {
+			T::WeightInfo::create_src_htlc(
+				T::MaxHtlcsPerHashlock::get().saturating_sub(1),
+				MAX_ORDER_LEGS - 1,
+			)
+		}
+
+		/// Withdraw an HTLC as its taker, with the proof of the secret of a
+		/// fill of an intent in parts, paying the swap amount of a source HTLC
+		/// to `target` if any.
+		fn do_withdraw(
+			who: T::AccountId,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
 			secret: Vec<u8>,
-			amount: BalanceOf<T>,
-			beneficiary: T::AccountId,
-			safety_deposit_recipient: T::AccountId,
-		},
-		/// HTLC cancelled.
-		HtlcCancelled { htlc_id: H256, refund_recipient: T::AccountId },
+			proof: Option<(u16, &[H256])>,
+			target: Option<T::AccountId>,
+		) -> DispatchResult {
+			// Validation phase
 
-		/// Swap intent created by maker.
-		SwapIntentCreated {
-			maker: T::AccountId,
-			nonce: u64,
-			src_amount: BalanceOf<T>,
-			dst_amount: BalanceOf<T>,
-			dst_address: H160,
-			hashlock: H256,
-		},
+			// validate HTLC exists
+			let htlc_id = Self::hash_immutables(&immutables);
+			let mut htlc = Self::supplied_htlc(htlc_id, &immutables)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
 
-		/// Swap intent.
-		SwapIntentCancelled {
-			maker: T::AccountId,
-			nonce: u64,
-			src_amount: BalanceOf<T>,
-			dst_amount: BalanceOf<T>,
-			dst_address: H160,
-			hashlock: H256,
-		},
-	}
+			// verify immutables match
+			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
 
-	#[pallet::error]
-	pub enum Error<T> {
-		/// Error name example. Should be descriptive.
-		NoneValue,
+			// verify the secret, the parties and the timing
+			Self::ensure_can_withdraw(htlc_id, &htlc, &who, &secret, proof, false)?;
+
+			// only the funds of a source HTLC can be sent elsewhere
+			ensure!(
+				target.is_none() || htlc.htlc_type == HtlcType::Source,
+				Error::<T>::InvalidImmutables
+			);
+			if let Some(target) = &target {
+				Self::ensure_compliant(target)?;
+			}
+
+			// Withdrawal phase
+
+			let beneficiary = match htlc.htlc_type {
+				HtlcType::Destination => {
+					// Destination HTLC: EVM -> Polkadot
+					// Resolver (taker) deposited funds for maker
+					// Funds go: taker -> maker
+					Self::escrow_pay(
+						HoldReason::SwapAmount,
+						htlc_id,
+						&Self::swap_funder(htlc_id, &htlc),
+						&htlc.immutables.maker,
+						htlc.immutables.amount,
+					)?;
+					Self::settle_vault_draw(htlc_id, &htlc, false);
+
+					htlc.immutables.maker.clone()
+				},
+
+				HtlcType::Source => {
+					// Destination HTLC: Polkadot -> EVM
+					// Maker deposited funds for taker
+					// Funds go: maker -> taker, or the target of the taker
+					let target = target.unwrap_or_else(|| htlc.immutables.taker.clone());
+					Self::escrow_pay(
+						HoldReason::MakerSwapIntentAmount,
+						htlc.immutables.order_hash,
+						&htlc.immutables.maker,
+						&target,
+						htlc.immutables.amount,
+					)?;
+
+					// the intent filled by this HTLC is now completed, or open
+					// for its next part
+					Self::settle_intent_fill(htlc_id, &htlc, IntentStatus::Completed)?;
+
+					target
+				},
+			};
+
+			// Safety deposit back to taker
+			let safety_deposit_recipient = Self::refund_safety_deposit(htlc_id, &htlc)?;
+
+			// update HTLC
+			htlc.status = HtlcStatus::Completed;
+			Self::store_htlc(htlc_id, &htlc);
+
+			// index the revealed secret for the current block
+			Self::note_revealed_secret(htlc.immutables.hashlock, htlc_id)?;
+			Self::note_settlement(htlc_id, &htlc, beneficiary.clone());
+
+			// emit event that shows the unhashed secret to the public
+			Self::deposit_event(Event::HtlcWithdrawn {
+				htlc_id,
+				secret,
+				amount: immutables.amount,
+				beneficiary,
+				safety_deposit_recipient,
+			});
+
+			Ok(())
+		}
+
+		/// Withdraw an HTLC publicly once its public withdrawal opens, with
+		/// the proof of the secret of a fill of an intent in parts, rewarding
+		/// the caller with the safety deposit.
+		fn do_public_withdraw(
+			who: T::AccountId,
+			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: Vec<u8>,
+			proof: Option<(u16, &[H256])>,
+		) -> DispatchResult {
+			// Validation phase
+
+			// validate HTLC exists
+			let htlc_id = Self::hash_immutables(&immutables);
+			let mut htlc = Self::supplied_htlc(htlc_id, &immutables)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+
+			// verify immutables match
+			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+
+			// verify the secret, the parties and the timing
+			Self::ensure_can_withdraw(htlc_id, &htlc, &who, &secret, proof, true)?;
+
+			// Withdrawal phase
+
+			let beneficiary = match htlc.htlc_type {
+				HtlcType::Destination => {
+					// Destination HTLC: EVM -> Polkadot
+					// Resolver (taker) deposited funds for maker
+					// Funds go: taker -> maker
+					Self::escrow_pay(
+						HoldReason::SwapAmount,
+						htlc_id,
+						&Self::swap_funder(htlc_id, &htlc),
+						&htlc.immutables.maker,
+						htlc.immutables.amount,
+					)?;
+					Self::settle_vault_draw(htlc_id, &htlc, false);
+
+					htlc.immutables.maker.clone()
+				},
+
+				HtlcType::Source => {
+					// Destination HTLC: Polkadot -> EVM
+					// Maker deposited funds for taker
+					// Funds go: maker -> taker
+					Self::escrow_pay(
+						HoldReason::MakerSwapIntentAmount,
+						htlc.immutables.order_hash,
+						&htlc.immutables.maker,
+						&htlc.immutables.taker,
+						htlc.immutables.amount,
+					)?;
+
+					// the intent filled by this HTLC is now completed, or open
+					// for its next part
+					Self::settle_intent_fill(htlc_id, &htlc, IntentStatus::Completed)?;
+
+					htlc.immutables.taker.clone()
+				},
+			};
+
+			// release safety deposit to the caller, crediting its share to the
+			// watchtower of the HTLC if the watchtower is the caller
+			let depositor = Self::forfeit_sponsorship(htlc_id, &htlc);
+			let safety_deposit = Self::take_safety_deposit(htlc_id, &htlc);
+			let share = match Self::rewarded_watchtower(htlc_id, &who) {
+				Some(watchtower) => {
+					let share = T::WatchtowerShare::get().mul_floor(safety_deposit);
+					Self::escrow_pay(
+						HoldReason::SafetyDeposit,
+						htlc_id,
+						&depositor,
+						&watchtower,
+						share,
+					)?;
+					Self::note_watchtower_reward(htlc_id, watchtower, share);
+					share
+				},
+				None => Zero::zero(),
+			};
+			Self::escrow_pay(
+				HoldReason::SafetyDeposit,
+				htlc_id,
+				&depositor,
+				&who,
+				safety_deposit.saturating_sub(share),
+			)?;
+
+			// update HTLC
+			htlc.status = HtlcStatus::Completed;
+			Self::store_htlc(htlc_id, &htlc);
+
+			// index the revealed secret for the current block
+			Self::note_revealed_secret(htlc.immutables.hashlock, htlc_id)?;
+			Self::note_settlement(htlc_id, &htlc, beneficiary.clone());
+
+			// emit event that shows the unhashed secret to the public
+			Self::deposit_event(Event::HtlcWithdrawn {
+				htlc_id,
+				secret,
+				amount: immutables.amount,
+				beneficiary,
+				safety_deposit_recipient: who,
+			});
 
-		/// Invalid caller for the operation.
-		InvalidCaller,
+			Ok(())
+		}
 
-		/// Invalid timelock configuration.
-		InvalidTimelocks,
+		/// Whether the secret, with the proof of its leaf for the fill of an
+		/// intent in parts, unlocks the HTLC `htlc_id`.
+		pub(crate) fn unlocks(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			secret: &[u8],
+			proof: Option<(u16, &[H256])>,
+		) -> Result<bool, Error<T>> {
+			let hash_function = htlc.immutables.hash_function;
+			let secret_hash = hash_function.hash(secret);
+			let hashlock = match (FillIndexes::<T>::get(htlc_id), proof) {
+				(None, None) => secret_hash,
+				(Some(fill_index), Some((index, proof))) => {
+					ensure!(index == fill_index, Error::<T>::InvalidFillIndex);
+					merkle::root(
+						hash_function,
+						merkle::leaf(hash_function, index, secret_hash),
+						proof,
+					)
+				},
+				_ => return Ok(false),
+			};
+			Ok(htlc.immutables.hashlock == hashlock)
+		}
 
-		/// The provided immutables do not match the ones stored.
-		InvalidImmutables,
+		/// Checks of the private and public withdrawals of an active HTLC.
+		fn ensure_can_withdraw(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			who: &T::AccountId,
+			secret: &[u8],
+			proof: Option<(u16, &[H256])>,
+			public: bool,
+		) -> DispatchResult {
+			// verify secret hash matches the one stored in the lock
+			ensure!(secret.len() <= MAX_SECRET_LEN as usize, Error::<T>::SecretTooLong);
+			ensure!(Self::unlocks(htlc_id, htlc, secret, proof)?, Error::<T>::InvalidSecret);
 
-		/// The hash of the provided secret does not match the hashlock of the contract.
-		InvalidSecret,
+			// ensure both parties are still allowed to swap
+			Self::ensure_compliant(&htlc.immutables.maker)?;
+			Self::ensure_compliant(&htlc.immutables.taker)?;
 
-		/// Cannot lock funds as the caller has insufficient balance.
-		InsufficientBalance,
+			// the taker withdraws privately, anyone else publicly
+			ensure!((*who == htlc.immutables.taker) != public, Error::<T>::InvalidCaller);
 
-		/// The withdrawal was attempted too early and it's not allowed
-		/// based on the current timelock configuration.
-		EarlyWithdrawal,
+			// check the timing is valid for the withdrawal
+			let stage = Self::current_stage(
+				&htlc.immutables.timelocks,
+				frame_system::Pallet::<T>::block_number(),
+			);
+			if public {
+				Self::ensure_not_blocked(who)?;
+				ensure!(stage >= Stage::PublicWithdrawal, Error::<T>::EarlyPublicWithdrawal);
+				ensure!(stage < Stage::Cancellation, Error::<T>::LatePublicWithdrawal);
+			} else {
+				ensure!(stage >= Stage::PrivateWithdrawal, Error::<T>::EarlyWithdrawal);
+				ensure!(stage < Stage::Cancellation, Error::<T>::LateWithdrawal);
+			}
 
-		/// The public withdrawal was attempted too early and it's not allowed
-		/// based on the current timelock configuration.
-		EarlyPublicWithdrawal,
+			Ok(())
+		}
 
-		/// The cancellation was attempted too early and it's not allowed
-		/// based on the current timelock configuration.
-		EarlyCancellation,
+		/// Checks of the private and public cancellations of an active HTLC.
+		fn ensure_can_cancel(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			who: &T::AccountId,
+			public: bool,
+		) -> DispatchResult {
+			// the taker cancels privately, anyone else publicly; only the caller
+			// is checked, so that a blocked party does not strand the refund of
+			// the other one
+			ensure!((*who == htlc.immutables.taker) != public, Error::<T>::InvalidCaller);
+			Self::ensure_not_blocked(who)?;
+
+			// check the timing is valid for the cancellation
+			let stage = Self::current_stage(
+				&htlc.immutables.timelocks,
+				frame_system::Pallet::<T>::block_number(),
+			);
+			if public {
+				ensure!(stage >= Stage::PublicCancellation, Error::<T>::EarlyPublicCancellation);
+			} else {
+				ensure!(stage >= Stage::Cancellation, Error::<T>::EarlyCancellation);
+			}
 
-		/// The withdrawal was attempted too late and it's not allowed
-		/// based on the current timelock configuration.
-		LateWithdrawal,
+			// an unchallenged claim settles the HTLC to the taker instead
+			ensure!(
+				!OptimisticClaims::<T>::contains_key(htlc_id),
+				Error::<T>::OptimisticClaimPending
+			);
 
-		/// The public withdrawal was attempted too late and it's not allowed
-		/// based on the current timelock configuration.
-		LatePublicWithdrawal,
+			Ok(())
+		}
 
-		/// HTLC already exists.
-		HtlcAlreadyExists,
+		/// Validate and store the intent of `who`, before its funds are held.
+		fn open_intent(
+			who: &T::AccountId,
+			intent: SwapIntentOf<T>,
+		) -> Result<(H256, StoredSwapIntentOf<T>), DispatchError> {
+			// ensure the maker creates the intent to swap
+			ensure!(*who == intent.maker, Error::<T>::InvalidCaller);
 
-		/// HTLC does not exists.
-		HtlcDoesNotExist,
+			// ensure the maker is allowed to swap
+			Self::ensure_compliant(who)?;
 
-		/// HTLC is not active.
-		HtlcNotActive,
+			let profile = Self::chain_profile(intent.dst_chain)?;
+			ensure!(
+				intent.dst_address.is_valid() &&
+					profile.address_format.accepts(&intent.dst_address),
+				Error::<T>::InvalidDstAddress
+			);
+			ensure!(intent.auction.is_valid(intent.dst_amount), Error::<T>::InvalidAuction);
+			ensure!(intent.min_fill_amount <= intent.src_amount, Error::<T>::InvalidMinFillAmount);
+			ensure!(intent.epoch >= MakerEpochs::<T>::get(who), Error::<T>::InvalidEpoch);
+			Self::normalized_dst_amount(&intent)?;
 
-		/// Intent already exists.
-		IntentAlreadyExists,
+			// generate the key for the map and check it doesn't already exist
+			let intent_key = Self::intent_key(who, intent.nonce);
+			ensure!(!SwapIntents::<T>::contains_key(intent_key), Error::<T>::IntentAlreadyExists);
 
-		/// Intent does not already exists.
-		IntentDoesNotExists,
+			// ensure the hashlock is not used by the escrows of another order
+			Self::ensure_hashlock_unused(&intent.hashlock, intent_key)?;
 
-		/// Intent is not active.
-		IntentNotActive,
+			// ensure the maker stays within its open intents
+			let open_intents = MakerIntents::<T>::decode_len(who).unwrap_or_default();
+			ensure!(
+				(open_intents as u32) < T::MaxIntentsPerMaker::get(),
+				Error::<T>::TooManyIntents
+			);
 
-		/// Intent expired.
-		IntentExpired,
+			let current_block = frame_system::Pallet::<T>::block_number();
+			let maker_deposit = T::MakerSafetyDeposit::get();
+			let storage_deposit = match intent.timeout_after_block {
+				Some(_) => Zero::zero(),
+				None => T::GoodTilCancelDeposit::get(),
+			};
+			let stored_intent = StoredSwapIntent {
+				intent: intent.clone(),
+				status: IntentStatus::Active,
+				created_at: current_block,
+				maker_deposit,
+				taken_at: None,
+				reservation_bond: Zero::zero(),
+				fills: 0,
+				storage_deposit,
+			};
 
-		/// A higher value of a safety deposit is required.
-		HigherSafetyDepositRequired,
-	}
+			SwapIntents::<T>::insert(intent_key, &stored_intent);
+			IntentHashlocks::<T>::insert(intent.hashlock, intent_key);
+			Self::track_order(intent_key);
+			MakerIntents::<T>::try_append(who, intent_key)
+				.map_err(|_| Error::<T>::TooManyIntents)?;
 
-	#[pallet::call]
-	impl<T: Config> Pallet<T> {
-		///////
-		/// Calls for destination HTLCs
+			Ok((intent_key, stored_intent))
+		}
 
-		#[pallet::call_index(0)]
-		pub fn create_dst_htlc(
-			origin: OriginFor<T>,
-			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
-			src_cancellation_timestamp: BlockNumberFor<T>,
+		/// Hold the swap amount and the deposits of the intent `intent_key`.
+		fn lock_intent_funds(
+			who: &T::AccountId,
+			intent_key: H256,
+			stored_intent: &StoredSwapIntentOf<T>,
 		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+			Self::escrow_lock(
+				HoldReason::MakerSwapIntentAmount,
+				intent_key,
+				who,
+				stored_intent.intent.src_amount,
+			)
+			.map_err(|_| Error::<T>::InsufficientBalance)?;
 
-			// ensure the taker creates the escrow
-			ensure!(who == immutables.taker, Error::<T>::InvalidCaller);
+			Self::escrow_lock(
+				HoldReason::MakerSafetyDeposit,
+				intent_key,
+				who,
+				stored_intent.maker_deposit,
+			)
+			.map_err(|_| Error::<T>::InsufficientBalance)?;
+			Self::escrow_lock(
+				HoldReason::IntentStorageDeposit,
+				intent_key,
+				who,
+				stored_intent.storage_deposit,
+			)
+			.map_err(|_| Error::<T>::InsufficientBalance)?;
 
-			let min_safety_deposit: BalanceOf<T> = T::MinSafetyDeposit::get().into();
+			Ok(())
+		}
 
+		/// Fill the intent `nonce` of `maker` with a source HTLC of `amount`
+		/// of it, or all of it by default, taken by `who`.
+		fn fill_intent(
+			who: T::AccountId,
+			maker: T::AccountId,
+			nonce: u64,
+			amount: Option<BalanceOf<T>>,
+			timelocks: Timelocks<BlockNumberFor<T>>,
+			safety_deposit: BalanceOf<T>,
+		) -> DispatchResult {
 			ensure!(
-				immutables.safety_deposit >= min_safety_deposit,
+				safety_deposit >= Self::min_safety_deposit(&who),
 				Error::<T>::HigherSafetyDepositRequired
 			);
 
-			let current_block = frame_system::Pallet::<T>::block_number();
-			let mut updated_immutables = immutables.clone();
-			updated_immutables.timelocks.deployed_at = current_block;
-
-			// ensure cancellation time aligns with source chain cancellation
+			// generate the key for the map and check it doesn't already exist
+			let intent_key = Self::intent_key(&maker, nonce);
+			let mut stored_intent =
+				SwapIntents::<T>::get(intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+
+			// ensure the intent is open, or reserved by the caller, who may
+			// have escrowed the funds on the other chain already
+			match &stored_intent.status {
+				IntentStatus::Active => Self::ensure_intent_epoch(&stored_intent.intent)?,
+				IntentStatus::Reserved { resolver } => {
+					ensure!(*resolver == who, Error::<T>::IntentReserved)
+				},
+				_ => return Err(Error::<T>::IntentNotActive.into()),
+			}
 			ensure!(
-				updated_immutables.timelocks.cancellation_after <= src_cancellation_timestamp,
-				Error::<T>::InvalidTimelocks
+				stored_intent.fills == 0 || stored_intent.intent.flags.allow_multiple_fills,
+				Error::<T>::IntentAlreadyFilled
 			);
 
+			// ensure both parties are allowed to swap
+			Self::ensure_compliant(&stored_intent.intent.maker)?;
+			Self::ensure_compliant(&who)?;
+			Self::ensure_resolver_allowed(&stored_intent.intent, &who)?;
+
+			// ensure the intent hasn't expired
+			let current_block = frame_system::Pallet::<T>::block_number();
+			ensure!(!stored_intent.intent.is_expired_at(current_block), Error::<T>::IntentExpired);
+
 			// validate timelock sequence (withdrawal < public_withdrawal < cancellation)
+			validation::check_timelocks(&timelocks, None).map_err(Error::<T>::from)?;
+			let profile = Self::chain_profile(stored_intent.intent.dst_chain)?;
+
+			// a partial fill leaves the rest of the intent to the maker once
+			// it is settled, which only the makers who opt in allow, or to
+			// the next fill of an intent that is filled in parts
+			let src_amount = stored_intent.intent.src_amount;
+			let multi_fill = MultiFillIntents::<T>::get(intent_key);
+			let unfilled = Self::unfilled_amount(intent_key, &stored_intent);
+			let amount = amount.unwrap_or(unfilled);
+			ensure!(!amount.is_zero() && amount <= unfilled, Error::<T>::InvalidFillAmount);
 			ensure!(
-				updated_immutables.timelocks.withdrawal_after <=
-					updated_immutables.timelocks.public_withdrawal_after &&
-					updated_immutables.timelocks.public_withdrawal_after <=
-						updated_immutables.timelocks.cancellation_after,
-				Error::<T>::InvalidTimelocks
+				amount == src_amount || !stored_intent.intent.flags.fill_or_kill,
+				Error::<T>::FillOrKillIntent
 			);
+			ensure!(
+				amount == src_amount || stored_intent.intent.flags.allow_partial_fills,
+				Error::<T>::PartialFillsNotAllowed
+			);
+			ensure!(
+				amount == unfilled || amount >= stored_intent.intent.min_fill_amount,
+				Error::<T>::FillBelowMinimum
+			);
+			let fill_index = multi_fill
+				.map(|multi_fill| {
+					merkle::fill_index(src_amount, multi_fill.parts, multi_fill.filled, amount)
+						.ok_or(Error::<T>::FillIndexReused)
+				})
+				.transpose()?;
+			Self::ensure_timelock_margins(&profile, &timelocks, None)?;
+
+			let immutables = Immutables {
+				order_hash: intent_key,
+				hashlock: stored_intent.intent.hashlock,
+				hash_function: profile.hash_function,
+				maker: stored_intent.intent.maker.clone(),
+				taker: who.clone(),
+				amount,
+				safety_deposit,
+				timelocks,
+			};
+
+			// ensure the hashlock is not used by the escrows of another order;
+			// the intent that is being filled and its other fills use the same
+			Self::ensure_hashlock_unused(&immutables.hashlock, intent_key)?;
 
 			// ensure HTLC doesn't already exist
 			let htlc_id = Self::hash_immutables(&immutables);
-			ensure!(!Htlcs::<T>::contains_key(&htlc_id), Error::<T>::HtlcAlreadyExists);
-
-			// hold the required funds for the swap and then the safety deposit
-			T::NativeBalance::hold(&HoldReason::SwapAmount.into(), &who, updated_immutables.amount)
-				.map_err(|_| Error::<T>::InsufficientBalance)?;
+			ensure!(
+				!Htlcs::<T>::contains_key(htlc_id) && !ArchivedHtlcs::<T>::contains_key(htlc_id),
+				Error::<T>::HtlcAlreadyExists
+			);
 
-			T::NativeBalance::hold(
-				&HoldReason::SafetyDeposit.into(),
-				&who,
-				updated_immutables.safety_deposit,
-			)
-			.map_err(|_| Error::<T>::InsufficientBalance)?;
+			// hold the required safety deposit for the swap from the taker
+			Self::lock_safety_deposit(htlc_id, &who, immutables.safety_deposit)?;
 
 			let htlc = Htlc {
 				immutables: immutables.clone(),
 				status: HtlcStatus::Active,
-				htlc_type: HtlcType::Destination,
+				htlc_type: HtlcType::Source,
+				counterpart_chain: stored_intent.intent.dst_chain,
 			};
 
-			Htlcs::<T>::insert(&htlc_id, &htlc);
+			Htlcs::<T>::insert(htlc_id, &htlc);
+			Self::index_hashlock(htlc.immutables.hashlock, htlc_id)?;
+			Self::schedule_auto_cancel(htlc.immutables.timelocks.cancellation_after, htlc_id)?;
+			Self::schedule_stage_events(&htlc.immutables.timelocks, htlc_id)?;
+			Self::schedule_settlement_warning(
+				htlc.immutables.timelocks.cancellation_after,
+				htlc_id,
+			)?;
+
+			// the intent is now being filled by the resolver
+			let status = IntentStatus::InProgress { resolver: who.clone(), htlc_id };
+			Self::move_intent_hold(&stored_intent.status, &status)?;
+			stored_intent.status = status;
+			stored_intent.taken_at.get_or_insert(current_block);
+			stored_intent.fills.saturating_inc();
+			SwapIntents::<T>::insert(intent_key, &stored_intent);
+			IntentQuotes::<T>::remove(intent_key);
+			Self::track_order_leg(intent_key, htlc_id)?;
+
+			// the fill of an intent in parts is withdrawn with the secret of
+			// the part that it reaches
+			if let Some(index) = fill_index {
+				FillIndexes::<T>::insert(htlc_id, index);
+				MultiFillIntents::<T>::mutate(intent_key, |multi_fill| {
+					if let Some(multi_fill) = multi_fill {
+						multi_fill.filled.saturating_accrue(amount);
+					}
+				});
+				Self::deposit_event(Event::IntentPartFilled { intent_key, htlc_id, index });
+			}
+
+			// the resolver pays the price that the maker accepted from it, or
+			// else the price of the auction, when it fills the intent
+			let price = match AcceptedQuotes::<T>::take(intent_key) {
+				Some(quote) if quote.resolver == who => quote.dst_amount,
+				_ => stored_intent.intent.dst_amount_at(current_block),
+			};
+			let dst_amount = Perbill::from_rational(amount, src_amount).mul_floor(price);
 
 			Self::deposit_event(Event::HtlcCreated {
 				htlc_id,
-				hashlock: immutables.hashlock,
-				maker: immutables.maker,
-				taker: immutables.taker,
-				amount: immutables.amount,
-				safety_deposit: updated_immutables.safety_deposit,
+				hashlock: stored_intent.intent.hashlock,
+				maker: stored_intent.intent.maker,
+				taker: who,
+				amount,
+				safety_deposit,
+			});
+
+			// the resolver mirrors swaps to Bitcoin with a script HTLC
+			if profile.address_format == AddressFormat::Bitcoin {
+				Self::deposit_event(Event::BitcoinCounterpartRequested {
+					htlc_id,
+					payment_hash: stored_intent.intent.hashlock,
+					recipient: stored_intent.intent.dst_address.clone(),
+					amount: dst_amount,
+					cancellation_after: htlc.immutables.timelocks.cancellation_after,
+				});
+			}
+
+			Ok(())
+		}
+
+		/// Refund the maker the part of the intent that the source HTLC left
+		/// unfilled, before the intent is finalized with it.
+		fn release_unfilled(
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let intent_key = htlc.immutables.order_hash;
+			let Some(stored_intent) = SwapIntents::<T>::get(intent_key) else {
+				return Ok(());
+			};
+			// the fills of an intent in parts are all settled by now
+			let unfilled = match MultiFillIntents::<T>::get(intent_key) {
+				Some(_) => Self::unfilled_amount(intent_key, &stored_intent),
+				None => stored_intent.intent.src_amount.saturating_sub(htlc.immutables.amount),
+			};
+			Self::escrow_refund(
+				HoldReason::MakerSwapIntentAmount,
+				intent_key,
+				&htlc.immutables.maker,
+				unfilled,
+			)
+		}
+
+		/// The amount of the intent that its fills did not take yet, all of
+		/// it but for an intent that is filled in parts.
+		pub(crate) fn unfilled_amount(
+			intent_key: H256,
+			stored_intent: &StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> BalanceOf<T> {
+			let filled = MultiFillIntents::<T>::get(intent_key)
+				.map_or_else(Zero::zero, |multi_fill| multi_fill.filled);
+			stored_intent.intent.src_amount.saturating_sub(filled)
+		}
+
+		/// The amount held for an open intent: what its fills did not take,
+		/// with the fill in progress of an intent that is filled in parts.
+		fn held_intent_amount(
+			intent_key: H256,
+			stored_intent: &StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> BalanceOf<T> {
+			let unfilled = Self::unfilled_amount(intent_key, stored_intent);
+			match stored_intent.status {
+				IntentStatus::InProgress { htlc_id, .. }
+					if FillIndexes::<T>::contains_key(htlc_id) =>
+					Htlcs::<T>::get(htlc_id)
+						.map_or(unfilled, |htlc| unfilled.saturating_add(htlc.immutables.amount)),
+				_ => unfilled,
+			}
+		}
+
+		/// Settle the intent that the source HTLC filled with `status`: open
+		/// it again for the next fill if it is filled in parts, some of it is
+		/// left before it expires and the fill was not cancelled with the
+		/// intent, or else finalize it, refunding the maker what is left
+		/// unfilled.
+		fn settle_intent_fill(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			status: IntentStatus<T::AccountId>,
+		) -> DispatchResult {
+			let intent_key = htlc.immutables.order_hash;
+			if let Some(mut stored_intent) = SwapIntents::<T>::get(intent_key).filter(|stored| {
+				MultiFillIntents::<T>::contains_key(intent_key) &&
+					status != IntentStatus::Cancelled &&
+					!stored.intent.is_expired_at(frame_system::Pallet::<T>::block_number()) &&
+					!Self::unfilled_amount(intent_key, stored).is_zero() &&
+					matches!(
+						stored.status,
+						IntentStatus::InProgress { htlc_id: filling, .. } if filling == htlc_id
+					)
+			}) {
+				Self::settle_reservation_bond(intent_key, &stored_intent)?;
+				Self::move_intent_hold(&stored_intent.status, &IntentStatus::Active)?;
+				stored_intent.status = IntentStatus::Active;
+				stored_intent.reservation_bond = Zero::zero();
+				stored_intent.taken_at = None;
+				SwapIntents::<T>::insert(intent_key, &stored_intent);
+				Self::track_order(intent_key);
+
+				let filled = stored_intent
+					.intent
+					.src_amount
+					.saturating_sub(Self::unfilled_amount(intent_key, &stored_intent));
+				Self::deposit_event(Event::IntentReopened { intent_key, filled });
+				return Ok(());
+			}
+
+			Self::release_unfilled(htlc)?;
+			Self::finalize_intent(intent_key, status)
+		}
+
+		/// Pay the safety deposit of the maker to the resolver of the intent.
+		fn forfeit_maker_deposit(
+			intent_key: H256,
+			maker: &T::AccountId,
+			resolver: &T::AccountId,
+			maker_deposit: BalanceOf<T>,
+		) -> DispatchResult {
+			if maker_deposit.is_zero() {
+				return Ok(());
+			}
+			Self::escrow_pay(
+				HoldReason::MakerSafetyDeposit,
+				intent_key,
+				maker,
+				resolver,
+				maker_deposit,
+			)?;
+			Self::deposit_event(Event::MakerDepositForfeited {
+				intent_key,
+				maker: maker.clone(),
+				resolver: resolver.clone(),
+				amount: maker_deposit,
+			});
+			Self::share_earnings(resolver, maker_deposit);
+			Ok(())
+		}
+
+		/// Move the intent that was filled by a source HTLC to its final
+		/// status and release its hashlock.
+		fn finalize_intent(intent_key: H256, status: IntentStatus<T::AccountId>) -> DispatchResult {
+			let Some(mut stored_intent) = SwapIntents::<T>::get(intent_key) else {
+				return Ok(());
+			};
+			IntentHashlocks::<T>::remove(stored_intent.intent.hashlock);
+			IntentQuotes::<T>::remove(intent_key);
+			AcceptedQuotes::<T>::remove(intent_key);
+			if matches!(
+				stored_intent.status,
+				IntentStatus::Active |
+					IntentStatus::Reserved { .. } |
+					IntentStatus::InProgress { .. }
+			) {
+				Self::close_intent(&stored_intent.intent.maker, intent_key);
+			}
+
+			// the maker gets the safety deposit back, unless the swap expired
+			// after a resolver bound the source HTLC to the destination leg
+			let maker = &stored_intent.intent.maker;
+			let maker_deposit = stored_intent.maker_deposit;
+			match (&status, &stored_intent.status) {
+				(IntentStatus::Expired, IntentStatus::InProgress { resolver, htlc_id })
+					if CounterpartEscrows::<T>::contains_key(htlc_id) =>
+					Self::forfeit_maker_deposit(intent_key, maker, resolver, maker_deposit)?,
+				_ => Self::escrow_refund(
+					HoldReason::MakerSafetyDeposit,
+					intent_key,
+					maker,
+					maker_deposit,
+				)?,
+			}
+
+			Self::settle_reservation_bond(intent_key, &stored_intent)?;
+			Self::escrow_refund(
+				HoldReason::IntentStorageDeposit,
+				intent_key,
+				maker,
+				stored_intent.storage_deposit,
+			)?;
+
+			Self::move_intent_hold(&stored_intent.status, &status)?;
+			stored_intent.status = status;
+			SwapIntents::<T>::insert(intent_key, &stored_intent);
+			Self::track_order(intent_key);
+
+			Ok(())
+		}
+
+		/// Move an intent from the resolver that holds it with the status
+		/// `from` to the one that holds it with the status `to`, if another,
+		/// within the `MaxIntentsPerResolver` of the latter.
+		pub(crate) fn move_intent_hold(
+			from: &IntentStatus<T::AccountId>,
+			to: &IntentStatus<T::AccountId>,
+		) -> DispatchResult {
+			let (from, to) = (from.resolver(), to.resolver());
+			if from == to {
+				return Ok(());
+			}
+			if let Some(resolver) = from {
+				ResolverIntents::<T>::mutate_exists(resolver, |held| {
+					*held = held.map(|held| held.saturating_sub(1)).filter(|held| *held > 0);
+				});
+			}
+			if let Some(resolver) = to {
+				ResolverIntents::<T>::try_mutate(resolver, |held| {
+					ensure!(
+						*held < T::MaxIntentsPerResolver::get(),
+						Error::<T>::TooManyIntentsInProgress
+					);
+					held.saturating_inc();
+					Ok::<_, DispatchError>(())
+				})?;
+			}
+			Ok(())
+		}
+
+		/// The immutables of the HTLC `htlc_id`, for the calls that act on it
+		/// by its id.
+		fn stored_immutables(htlc_id: H256) -> Result<ImmutablesOf<T>, DispatchError> {
+			Htlcs::<T>::get(htlc_id)
+				.map(|htlc| htlc.immutables)
+				.ok_or(Error::<T>::HtlcDoesNotExist.into())
+		}
+
+		/// The HTLC `htlc_id` with the `immutables` supplied to act on it, from
+		/// its record or else from its commitment.
+		fn supplied_htlc(
+			htlc_id: H256,
+			immutables: &ImmutablesOf<T>,
+		) -> Result<HtlcOf<T>, DispatchError> {
+			if let Some(htlc) = Htlcs::<T>::get(htlc_id) {
+				return Ok(htlc);
+			}
+			let commitment =
+				HtlcCommitments::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			Ok(Htlc {
+				immutables: immutables.clone(),
+				status: commitment.status,
+				htlc_type: HtlcType::Destination,
+				counterpart_chain: commitment.counterpart_chain,
+			})
+		}
+
+		/// Store the HTLC that an action settled, in its record, bringing its
+		/// order up to date, or else in its commitment.
+		fn store_htlc(htlc_id: H256, htlc: &HtlcOf<T>) {
+			let committed = HtlcCommitments::<T>::mutate(htlc_id, |commitment| {
+				commitment.as_mut().map(|commitment| commitment.status = htlc.status.clone())
+			});
+			if committed.is_none() {
+				Htlcs::<T>::insert(htlc_id, htlc);
+				Self::track_order(htlc.immutables.order_hash);
+			}
+		}
+
+		/// Remove an open intent of `maker` that ends from its `MakerIntents`.
+		fn close_intent(maker: &T::AccountId, intent_key: H256) {
+			MakerIntents::<T>::mutate_exists(maker, |open_intents| {
+				if let Some(keys) = open_intents {
+					keys.retain(|key| *key != intent_key);
+				}
+				open_intents.take_if(|keys| keys.is_empty());
+			});
+		}
+
+		/// Cancel an active intent, or a reserved one whose bond is settled,
+		/// returning its funds to the maker.
+		fn cancel_open_intent(
+			intent_key: H256,
+			mut stored_intent: StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let maker = stored_intent.intent.maker.clone();
+			Self::move_intent_hold(&stored_intent.status, &IntentStatus::Cancelled)?;
+			stored_intent.status = IntentStatus::Cancelled;
+			SwapIntents::<T>::insert(intent_key, &stored_intent);
+			Self::track_order(intent_key);
+			IntentHashlocks::<T>::remove(stored_intent.intent.hashlock);
+			IntentQuotes::<T>::remove(intent_key);
+			AcceptedQuotes::<T>::remove(intent_key);
+			Self::close_intent(&maker, intent_key);
+
+			Self::escrow_refund(
+				HoldReason::MakerSwapIntentAmount,
+				intent_key,
+				&maker,
+				Self::unfilled_amount(intent_key, &stored_intent),
+			)?;
+			Self::escrow_refund(
+				HoldReason::MakerSafetyDeposit,
+				intent_key,
+				&maker,
+				stored_intent.maker_deposit,
+			)?;
+			Self::escrow_refund(
+				HoldReason::IntentStorageDeposit,
+				intent_key,
+				&maker,
+				stored_intent.storage_deposit,
+			)?;
+
+			Self::deposit_event(Event::SwapIntentCancelled {
+				maker,
+				nonce: stored_intent.intent.nonce,
+				src_amount: stored_intent.intent.src_amount,
+				dst_amount: stored_intent.intent.dst_amount,
+				dst_address: stored_intent.intent.dst_address,
+				hashlock: stored_intent.intent.hashlock,
+			});
+
+			Ok(())
+		}
+
+		/// Release the reservation bond of an intent that ends: it is
+		/// forfeited to the maker, and the resolver cools down, if the resolver
+		/// never filled the intent, and it is returned to the resolver
+		/// otherwise.
+		fn settle_reservation_bond(
+			intent_key: H256,
+			stored_intent: &StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			let bond = stored_intent.reservation_bond;
+			let maker = &stored_intent.intent.maker;
+			match &stored_intent.status {
+				// a resolver that reserved the intent without a bond, e.g. by
+				// an accepted quote, still cools down
+				IntentStatus::Reserved { resolver } if bond.is_zero() =>
+					Self::penalize_resolver(resolver),
+				_ if bond.is_zero() => {},
+				IntentStatus::Reserved { resolver } => {
+					Self::escrow_pay(
+						HoldReason::ReservationBond,
+						intent_key,
+						resolver,
+						maker,
+						bond,
+					)?;
+					Self::deposit_event(Event::ReservationBondForfeited {
+						intent_key,
+						resolver: resolver.clone(),
+						maker: maker.clone(),
+						amount: bond,
+					});
+					Self::penalize_resolver(resolver);
+				},
+				IntentStatus::InProgress { resolver, .. } =>
+					Self::escrow_refund(HoldReason::ReservationBond, intent_key, resolver, bond)?,
+				_ => {},
+			}
+
+			Ok(())
+		}
+
+		/// The watchtower of the HTLC that is credited its share of the reward
+		/// of a public action, if it is the `caller`; an HTLC rescued by
+		/// another account rewards only that account.
+		fn rewarded_watchtower(htlc_id: H256, caller: &T::AccountId) -> Option<T::AccountId> {
+			WatchtowerDuties::<T>::get(htlc_id).filter(|watchtower| watchtower == caller)
+		}
+
+		fn note_watchtower_reward(htlc_id: H256, watchtower: T::AccountId, amount: BalanceOf<T>) {
+			Watchtowers::<T>::mutate_extant(&watchtower, |info| {
+				info.rewards.saturating_accrue(amount)
 			});
+			Self::deposit_event(Event::WatchtowerRewarded { htlc_id, watchtower, amount });
+		}
+
+		/// Release the watchtower of the HTLC from watching it.
+		fn release_watchtower(htlc_id: H256) {
+			if let Some(watchtower) = WatchtowerDuties::<T>::take(htlc_id) {
+				Watchtowers::<T>::mutate_extant(&watchtower, |info| info.duties.saturating_dec());
+				Self::deposit_event(Event::WatchtowerReleased { htlc_id, watchtower });
+			}
+		}
+
+		/// Ensure that the origin is signed, or is a remote party, returning
+		/// the account that takes part in the swaps on its behalf.
+		pub fn ensure_party(origin: OriginFor<T>) -> Result<T::AccountId, DispatchError> {
+			match T::RemoteOrigin::try_origin(origin) {
+				Ok(location) => Self::location_account(&location)
+					.ok_or(Error::<T>::UnconvertibleLocation.into()),
+				Err(origin) => Ok(ensure_signed(origin)?),
+			}
+		}
+
+		/// The account of the remote party at `location`, to be named as
+		/// the maker or taker of its swaps.
+		pub fn location_account(location: &Location) -> Option<T::AccountId> {
+			T::LocationToAccount::convert_location(location)
+		}
 
+		/// Ensure the maker did not invalidate the epoch of the intent.
+		pub(crate) fn ensure_intent_epoch(intent: &SwapIntentOf<T>) -> DispatchResult {
+			ensure!(
+				intent.epoch >= MakerEpochs::<T>::get(&intent.maker),
+				Error::<T>::IntentInvalidated
+			);
 			Ok(())
 		}
 
-		#[pallet::call_index(1)]
-		pub fn withdraw(
-			origin: OriginFor<T>,
-			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
-			secret: Vec<u8>,
+		/// Ensure the `IntentCancellationDelay` passed since a resolver took
+		/// the intent.
+		fn ensure_intent_cancellation_delay(
+			stored_intent: &StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
 		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+			let taken_at = stored_intent.taken_at.unwrap_or(stored_intent.created_at);
+			ensure!(
+				frame_system::Pallet::<T>::block_number() >=
+					taken_at.saturating_add(T::IntentCancellationDelay::get()),
+				Error::<T>::EarlyIntentCancellation
+			);
 
-			// Validation phase
+			Ok(())
+		}
 
-			// validate HTLC exists
-			let htlc_id = Self::hash_immutables(&immutables);
-			let mut htlc = Htlcs::<T>::get(&htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+		/// Cancel an intent that was taken by a resolver for its maker `who`,
+		/// along with the source HTLC `htlc_id` that fills it. The maker is
+		/// held to the `IntentCancellationDelay` and to the timelocks of the
+		/// HTLC, so the resolver can still withdraw once the secret is out.
+		fn cancel_taken_intent(
+			who: T::AccountId,
+			nonce: u64,
+			mut stored_intent: StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			htlc_id: H256,
+		) -> DispatchResult {
+			Self::ensure_intent_cancellation_delay(&stored_intent)?;
+
+			let current_block = frame_system::Pallet::<T>::block_number();
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
 			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			ensure!(
+				Self::current_stage(&htlc.immutables.timelocks, current_block) >=
+					Stage::Cancellation,
+				Error::<T>::EarlyCancellation
+			);
 
-			// verify immutables match
-			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+			// the maker forfeits its safety deposit if the resolver bound the
+			// source HTLC to the destination leg, as if it let the HTLC expire
+			if let IntentStatus::InProgress { resolver, .. } = &stored_intent.status {
+				if CounterpartEscrows::<T>::contains_key(htlc_id) {
+					let intent_key = Self::intent_key(&who, nonce);
+					Self::forfeit_maker_deposit(
+						intent_key,
+						&who,
+						resolver,
+						stored_intent.maker_deposit,
+					)?;
+					stored_intent.maker_deposit = Zero::zero();
+					SwapIntents::<T>::insert(intent_key, &stored_intent);
+				}
+			}
+			Self::cancel_htlc(htlc_id, htlc, IntentStatus::Cancelled)?;
 
-			// verify secret hash matches the one stored in the lock
-			let secret_hash = BlakeTwo256::hash(&secret);
-			ensure!(htlc.immutables.hashlock == secret_hash, Error::<T>::InvalidSecret);
+			Self::deposit_event(Event::SwapIntentCancelled {
+				maker: who,
+				nonce,
+				src_amount: stored_intent.intent.src_amount,
+				dst_amount: stored_intent.intent.dst_amount,
+				dst_address: stored_intent.intent.dst_address,
+				hashlock: stored_intent.intent.hashlock,
+			});
 
-			// verify taker is the caller of the external
-			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+			Ok(())
+		}
 
-			// check the timing is valid for the withdrawal
-			let current_block = frame_system::Pallet::<T>::block_number();
-			ensure!(
-				current_block >= htlc.immutables.timelocks.withdrawal_after,
-				Error::<T>::EarlyWithdrawal
-			);
+		/// Cancel the source HTLC of a stalled fill, refunding the safety
+		/// deposit to whoever posted it. The swap amount stays escrowed for
+		/// the intent, which is filled again.
+		fn abandon_fill(htlc_id: H256) -> DispatchResult {
+			let mut htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
 			ensure!(
-				current_block < htlc.immutables.timelocks.cancellation_after,
-				Error::<T>::LateWithdrawal
+				Self::current_stage(
+					&htlc.immutables.timelocks,
+					frame_system::Pallet::<T>::block_number(),
+				) >= Stage::Cancellation,
+				Error::<T>::EarlyCancellation
 			);
 
-			// Withdrawal phase
-
-			let beneficiary;
+			Self::refund_safety_deposit(htlc_id, &htlc)?;
+			Self::release_watchtower(htlc_id);
 
-			match htlc.htlc_type {
-				HtlcType::Destination => {
-					// Destination HTLC: EVM -> Polkadot
-					// Resolver (taker) deposited funds for maker
-					// Funds go: taker -> maker
-					T::NativeBalance::release(
-						&HoldReason::SwapAmount.into(),
-						&htlc.immutables.taker,
-						htlc.immutables.amount,
-						Precision::Exact,
-					)?;
+			htlc.status = HtlcStatus::Cancelled;
+			Htlcs::<T>::insert(htlc_id, &htlc);
+			Self::track_order(htlc.immutables.order_hash);
 
-					T::NativeBalance::transfer(
-						&htlc.immutables.taker,
-						&htlc.immutables.maker,
-						htlc.immutables.amount,
-						Preservation::Preserve,
-					)?;
+			Self::deposit_event(Event::HtlcCancelled {
+				htlc_id,
+				refund_recipient: htlc.immutables.maker,
+			});
 
-					beneficiary = htlc.immutables.maker.clone();
-				},
+			Ok(())
+		}
 
-				HtlcType::Source => {
-					// Destination HTLC: Polkadot -> EVM
-					// Maker deposited funds for taker
-					// Funds go: maker -> taker
-					T::NativeBalance::release(
-						&HoldReason::MakerSwapIntentAmount.into(),
-						&htlc.immutables.maker,
-						htlc.immutables.amount,
-						Precision::Exact,
-					)?;
+		/// Return the bond of an unchallenged optimistic claim and settle its
+		/// source HTLC to the taker, as for a withdrawal, if it is still
+		/// active. The claim is dropped instead if a party is no longer
+		/// allowed to swap, so that the bond isn't locked with the HTLC.
+		fn finalize_optimistic_claim(
+			htlc_id: H256,
+			claim: OptimisticClaim<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			OptimisticClaims::<T>::remove(htlc_id);
+			Self::escrow_refund(
+				HoldReason::OptimisticClaimBond,
+				htlc_id,
+				&claim.claimant,
+				claim.bond,
+			)?;
 
-					T::NativeBalance::transfer(
-						&htlc.immutables.maker,
-						&htlc.immutables.taker,
-						htlc.immutables.amount,
-						Preservation::Preserve,
-					)?;
+			let Some(mut htlc) = Htlcs::<T>::get(htlc_id) else { return Ok(()) };
+			if htlc.status != HtlcStatus::Active {
+				return Ok(());
+			}
 
-					beneficiary = htlc.immutables.taker.clone();
-				},
+			// ensure both parties are still allowed to swap, as for a withdrawal
+			if Self::ensure_compliant(&htlc.immutables.maker)
+				.and_then(|_| Self::ensure_compliant(&htlc.immutables.taker))
+				.is_err()
+			{
+				Self::deposit_event(Event::OptimisticClaimDropped {
+					htlc_id,
+					claimant: claim.claimant,
+				});
+				return Ok(());
 			}
 
-			// Safety deposit back to taker
-			T::NativeBalance::release(
-				&HoldReason::SafetyDeposit.into(),
+			// Maker deposited funds for taker
+			// Funds go: maker -> taker
+			Self::escrow_pay(
+				HoldReason::MakerSwapIntentAmount,
+				htlc.immutables.order_hash,
+				&htlc.immutables.maker,
 				&htlc.immutables.taker,
-				htlc.immutables.safety_deposit,
-				Precision::Exact,
+				htlc.immutables.amount,
 			)?;
+			Self::settle_intent_fill(htlc_id, &htlc, IntentStatus::Completed)?;
+			Self::refund_safety_deposit(htlc_id, &htlc)?;
 
-			// update HTLC
 			htlc.status = HtlcStatus::Completed;
-			Htlcs::<T>::insert(&htlc_id, &htlc);
+			Htlcs::<T>::insert(htlc_id, &htlc);
+			Self::track_order(htlc.immutables.order_hash);
+			Self::note_settlement(htlc_id, &htlc, htlc.immutables.taker.clone());
 
-			// emit event that shows the unhashed secret to the public
-			Self::deposit_event(Event::HtlcWithdrawn {
+			Self::deposit_event(Event::HtlcSettledOptimistically {
 				htlc_id,
-				secret,
-				amount: immutables.amount,
-				beneficiary,
-				safety_deposit_recipient: who,
+				amount: htlc.immutables.amount,
 			});
 
 			Ok(())
 		}
 
-		#[pallet::call_index(2)]
-		pub fn public_withdraw(
-			origin: OriginFor<T>,
+		/// Cancel an active HTLC, refunding the swap amount to whoever locked
+		/// it and the safety deposit to whoever posted it. The safety deposit
+		/// of a source HTLC that expired is forfeited to the maker instead, if
+		/// the taker never bound it to the escrow of the destination leg.
+		fn cancel_htlc(
+			htlc_id: H256,
+			mut htlc: Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			intent_status: IntentStatus<T::AccountId>,
+		) -> DispatchResult {
+			// a claim that is left, e.g. of an HTLC cancelled by agreement,
+			// returns its bond
+			if let Some(claim) = OptimisticClaims::<T>::take(htlc_id) {
+				Self::escrow_refund(
+					HoldReason::OptimisticClaimBond,
+					htlc_id,
+					&claim.claimant,
+					claim.bond,
+				)?;
+			}
+
+			let abandoned = htlc.htlc_type == HtlcType::Source &&
+				intent_status == IntentStatus::Expired &&
+				!CounterpartEscrows::<T>::contains_key(htlc_id);
+			let refund_recipient = Self::refund_htlc(htlc_id, &htlc, intent_status)?;
+
+			if abandoned {
+				let amount = Self::take_safety_deposit(htlc_id, &htlc);
+				let safety_deposit = Self::escrow_slash(
+					HoldReason::SafetyDeposit,
+					htlc_id,
+					&Self::forfeit_sponsorship(htlc_id, &htlc),
+					amount,
+				)?;
+				Self::forfeit(ForfeitReason::Abandonment, htlc_id, &htlc, None, safety_deposit);
+				Self::deposit_event(Event::SafetyDepositForfeited { htlc_id, amount });
+				Self::penalize_resolver(&htlc.immutables.taker);
+			} else {
+				// release safety deposit to the taker
+				Self::refund_safety_deposit(htlc_id, &htlc)?;
+			}
+
+			// update HTLC
+			htlc.status = HtlcStatus::Cancelled;
+			Self::store_htlc(htlc_id, &htlc);
+
+			Self::deposit_event(Event::HtlcCancelled { htlc_id, refund_recipient });
+
+			Ok(())
+		}
+
+		/// Create a destination HTLC for the taker `who`, with the swap amount
+		/// drawn from the vault if `from_vault`.
+		fn do_create_dst_htlc(
+			who: T::AccountId,
 			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
-			secret: Vec<u8>,
+			src_cancellation_block: BlockNumberFor<T>,
+			src_chain: ChainId,
+			from_vault: bool,
 		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+			let htlc_id =
+				Self::validate_dst_htlc(&who, &immutables, src_cancellation_block, src_chain)?;
+
+			// hold the required funds for the swap, from the vault if the
+			// resolver draws on it, and then the safety deposit
+			let funder = if from_vault {
+				Self::draw_from_vault(&who, htlc_id, immutables.amount)?
+			} else {
+				who.clone()
+			};
+			Self::escrow_lock(HoldReason::SwapAmount, htlc_id, &funder, immutables.amount)
+				.map_err(|_| Error::<T>::InsufficientBalance)?;
 
-			// Validation phase
+			Self::lock_safety_deposit(htlc_id, &who, immutables.safety_deposit)?;
+
+			// only the commitment is stored, unless the vault accounts for the
+			// swap amount of the HTLC
+			if T::CommitmentOnly::get() && !from_vault {
+				HtlcCommitments::<T>::insert(
+					htlc_id,
+					HtlcCommitment { status: HtlcStatus::Active, counterpart_chain: src_chain },
+				);
+			} else {
+				let htlc = Htlc {
+					immutables: immutables.clone(),
+					status: HtlcStatus::Active,
+					htlc_type: HtlcType::Destination,
+					counterpart_chain: src_chain,
+				};
+
+				Htlcs::<T>::insert(htlc_id, &htlc);
+				Self::index_hashlock(htlc.immutables.hashlock, htlc_id)?;
+				Self::track_order_leg(htlc.immutables.order_hash, htlc_id)?;
+				Self::schedule_auto_cancel(htlc.immutables.timelocks.cancellation_after, htlc_id)?;
+				Self::schedule_stage_events(&htlc.immutables.timelocks, htlc_id)?;
+				Self::schedule_settlement_warning(
+					htlc.immutables.timelocks.cancellation_after,
+					htlc_id,
+				)?;
+			}
 
-			// validate HTLC exists
-			let htlc_id = Self::hash_immutables(&immutables);
-			let mut htlc = Htlcs::<T>::get(&htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
-			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+			Self::deposit_event(Event::HtlcCreated {
+				htlc_id,
+				hashlock: immutables.hashlock,
+				maker: immutables.maker,
+				taker: immutables.taker,
+				amount: immutables.amount,
+				safety_deposit: immutables.safety_deposit,
+			});
 
-			// verify immutables match
-			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+			Ok(())
+		}
 
-			// verify secret hash matches the one stored in the lock
-			let secret_hash = BlakeTwo256::hash(&secret);
-			ensure!(htlc.immutables.hashlock == secret_hash, Error::<T>::InvalidSecret);
+		/// The block expected to be the last before the UNIX `timestamp`, in
+		/// seconds, less the `TimestampSafetyMargin`.
+		pub fn block_before_timestamp(timestamp: u64) -> Result<BlockNumberFor<T>, DispatchError> {
+			let now = T::UnixTime::now().as_secs();
+			let deadline = timestamp
+				.checked_sub(T::TimestampSafetyMargin::get())
+				.filter(|deadline| *deadline > now)
+				.ok_or(Error::<T>::SrcCancellationPassed)?;
+
+			// round down, so that the block is before the deadline
+			let blocks = deadline.saturating_sub(now).saturating_mul(1_000) /
+				T::ExpectedBlockTime::get().max(1);
+			let horizon: u64 = T::MaxTimelockHorizon::get().unique_saturated_into();
+			ensure!(blocks <= horizon, Error::<T>::SrcCancellationTooFar);
+
+			Ok(frame_system::Pallet::<T>::block_number()
+				.saturating_add(BlockNumberFor::<T>::unique_saturated_from(blocks)))
+		}
+
+		/// Validate a destination HTLC that the taker `who` creates, returning
+		/// its id.
+		fn validate_dst_htlc(
+			who: &T::AccountId,
+			immutables: &Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			src_cancellation_block: BlockNumberFor<T>,
+			src_chain: ChainId,
+		) -> Result<H256, DispatchError> {
+			// ensure the taker creates the escrow
+			ensure!(*who == immutables.taker, Error::<T>::InvalidCaller);
 
-			// Verify taker is not the caller of the external; anyone else
-			// can call this function. The check here is not as important as
-			// the check of the complementary condition in the `withdraw`
-			// function.
-			ensure!(who != htlc.immutables.taker, Error::<T>::InvalidCaller);
+			// ensure both parties are allowed to swap
+			Self::ensure_compliant(&immutables.maker)?;
+			Self::ensure_compliant(&immutables.taker)?;
 
-			// check the timing is valid for the public withdrawal
-			let current_block = frame_system::Pallet::<T>::block_number();
 			ensure!(
-				current_block >= htlc.immutables.timelocks.public_withdrawal_after,
-				Error::<T>::EarlyPublicWithdrawal
+				immutables.safety_deposit >= Self::min_safety_deposit(who),
+				Error::<T>::HigherSafetyDepositRequired
 			);
+
+			// validate the submitted timelock sequence, which must end before the
+			// cancellation on the source chain, and the non-zero fields
+			let mut updated_immutables =
+				ValidatedImmutables::new(immutables.clone(), Some(&src_cancellation_block))
+					.map_err(Error::<T>::from)?
+					.into_inner();
+			updated_immutables.timelocks.deployed_at = frame_system::Pallet::<T>::block_number();
+
+			// ensure the hashlock matches the source chain and the timelocks
+			// leave the required margin for it
+			let profile = Self::chain_profile(src_chain)?;
 			ensure!(
-				current_block < htlc.immutables.timelocks.cancellation_after,
-				Error::<T>::LatePublicWithdrawal
+				immutables.hash_function == profile.hash_function,
+				Error::<T>::InvalidHashFunction
 			);
+			Self::ensure_timelock_margins(
+				&profile,
+				&updated_immutables.timelocks,
+				Some(src_cancellation_block),
+			)?;
 
-			// Withdrawal phase
-
-			let beneficiary;
+			// ensure the hashlock is not used by the escrows of another order
+			Self::ensure_hashlock_unused(&immutables.hashlock, immutables.order_hash)?;
 
-			match htlc.htlc_type {
-				HtlcType::Destination => {
-					// Destination HTLC: EVM -> Polkadot
-					// Resolver (taker) deposited funds for maker
-					// Funds go: taker -> maker
-					T::NativeBalance::release(
-						&HoldReason::SwapAmount.into(),
-						&htlc.immutables.taker,
-						htlc.immutables.amount,
-						Precision::Exact,
-					)?;
+			// ensure HTLC doesn't already exist, nor is pending activation
+			let htlc_id = Self::hash_immutables(immutables);
+			ensure!(
+				!Htlcs::<T>::contains_key(htlc_id) &&
+					!ArchivedHtlcs::<T>::contains_key(htlc_id) &&
+					!HtlcCommitments::<T>::contains_key(htlc_id) &&
+					!PendingDstHtlcs::<T>::contains_key(htlc_id),
+				Error::<T>::HtlcAlreadyExists
+			);
 
-					T::NativeBalance::transfer(
-						&htlc.immutables.taker,
-						&htlc.immutables.maker,
-						htlc.immutables.amount,
-						Preservation::Preserve,
-					)?;
+			Ok(htlc_id)
+		}
 
-					beneficiary = htlc.immutables.maker.clone();
-				},
+		/// Refund the safety deposit of the destination HTLC `htlc_id`, if it
+		/// was not activated by block `now`.
+		fn expire_pending_dst_htlc(htlc_id: H256, now: BlockNumberFor<T>) -> DispatchResult {
+			let Some(pending) = PendingDstHtlcs::<T>::get(htlc_id) else { return Ok(()) };
+			if now <= pending.activate_before {
+				return Ok(());
+			}
+			PendingDstHtlcs::<T>::remove(htlc_id);
+			Self::refund_safety_deposit(htlc_id, &Self::pending_dst_htlc(&pending))?;
 
-				HtlcType::Source => {
-					// Destination HTLC: Polkadot -> EVM
-					// Maker deposited funds for taker
-					// Funds go: maker -> taker
-					T::NativeBalance::release(
-						&HoldReason::MakerSwapIntentAmount.into(),
-						&htlc.immutables.maker,
-						htlc.immutables.amount,
-						Precision::Exact,
-					)?;
+			Self::deposit_event(Event::DstHtlcReservationExpired { htlc_id });
 
-					T::NativeBalance::transfer(
-						&htlc.immutables.maker,
-						&htlc.immutables.taker,
-						htlc.immutables.amount,
-						Preservation::Preserve,
-					)?;
+			Ok(())
+		}
 
-					beneficiary = htlc.immutables.taker.clone();
-				},
+		/// The destination HTLC that `pending` reserves.
+		fn pending_dst_htlc(
+			pending: &PendingDstHtlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>> {
+			Htlc {
+				immutables: pending.immutables.clone(),
+				status: HtlcStatus::Active,
+				htlc_type: HtlcType::Destination,
+				counterpart_chain: pending.src_chain,
 			}
+		}
 
-			// release safety deposit to the take
-			T::NativeBalance::release(
-				&HoldReason::SafetyDeposit.into(),
-				&htlc.immutables.taker,
-				htlc.immutables.safety_deposit,
-				Precision::Exact,
-			)?;
-
-			T::NativeBalance::transfer(
-				&htlc.immutables.taker,
-				&who,
-				htlc.immutables.safety_deposit,
-				Preservation::Preserve,
-			)?;
+		/// Count the HTLC `htlc_id` that settled to `beneficiary` in the
+		/// statistics of its parties and issue its receipt.
+		fn note_settlement(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			beneficiary: T::AccountId,
+		) {
+			Self::release_watchtower(htlc_id);
+			let now = frame_system::Pallet::<T>::block_number();
+			let amount = htlc.immutables.amount;
+			AccountStats::<T>::mutate(&htlc.immutables.maker, |stats| {
+				stats.maker_swaps.saturating_inc();
+				stats.maker_volume.saturating_accrue(amount);
+				stats.last_active = now;
+			});
+			AccountStats::<T>::mutate(&htlc.immutables.taker, |stats| {
+				stats.taker_swaps.saturating_inc();
+				stats.taker_volume.saturating_accrue(amount);
+				stats.last_active = now;
+			});
+			Self::note_volume(&htlc.immutables.maker, amount);
+			Self::note_volume(&htlc.immutables.taker, amount);
 
-			// update HTLC
-			htlc.status = HtlcStatus::Completed;
-			Htlcs::<T>::insert(&htlc_id, &htlc);
+			if let Some(conversion) = SettlementConversions::<T>::take(htlc_id) {
+				Self::convert_settlement(htlc_id, &beneficiary, amount, conversion);
+			}
+			if let Some(remote) = SettlementDeliveries::<T>::take(htlc_id) {
+				Self::deliver_settlement(htlc_id, &beneficiary, amount, remote);
+			}
 
-			// emit event that shows the unhashed secret to the public
-			Self::deposit_event(Event::HtlcWithdrawn {
+			let counterpart_amount = match htlc.htlc_type {
+				HtlcType::Source => SwapIntents::<T>::get(htlc.immutables.order_hash)
+					.map(|stored_intent| stored_intent.intent.dst_amount),
+				HtlcType::Destination => None,
+			};
+			T::ReceiptIssuer::issue(&SwapReceipt {
 				htlc_id,
-				secret,
-				amount: immutables.amount,
 				beneficiary,
-				safety_deposit_recipient: who,
+				amount: htlc.immutables.amount,
+				counterpart_amount,
+				counterpart_chain: htlc.counterpart_chain,
+			});
+		}
+
+		/// Convert the swap amount paid to the maker of a destination HTLC,
+		/// leaving it in the native token if the conversion fails.
+		fn convert_settlement(
+			htlc_id: H256,
+			maker: &T::AccountId,
+			amount: BalanceOf<T>,
+			conversion: SettlementConversion<T::ConversionAsset, BalanceOf<T>>,
+		) {
+			let SettlementConversion { asset, min_out } = conversion;
+			let converted = with_storage_layer(|| {
+				T::SettlementConverter::convert(maker, amount, &asset, min_out)
+			});
+			match converted {
+				Ok(amount_out) => Self::deposit_event(Event::SettlementConverted {
+					htlc_id,
+					asset,
+					amount_in: amount,
+					amount_out,
+				}),
+				Err(error) =>
+					Self::deposit_event(Event::SettlementConversionFailed { htlc_id, error }),
+			}
+		}
+
+		/// Deliver the swap amount paid to the maker of a destination HTLC to
+		/// its beneficiary on another chain, leaving it with the maker if the
+		/// delivery fails.
+		fn deliver_settlement(
+			htlc_id: H256,
+			maker: &T::AccountId,
+			amount: BalanceOf<T>,
+			beneficiary: T::RemoteBeneficiary,
+		) {
+			let delivered =
+				with_storage_layer(|| T::SettlementDelivery::deliver(maker, amount, &beneficiary));
+			match delivered {
+				Ok(()) =>
+					Self::deposit_event(Event::SettlementDelivered { htlc_id, beneficiary, amount }),
+				Err(error) =>
+					Self::deposit_event(Event::SettlementDeliveryFailed { htlc_id, error }),
+			}
+		}
+
+		/// Start of the volume bucket of block `now` and the first block of
+		/// the `VolumeWindow` that ends at it.
+		fn volume_bucket(now: BlockNumberFor<T>) -> (BlockNumberFor<T>, BlockNumberFor<T>) {
+			let window = T::VolumeWindow::get();
+			let bucket_length = (window / VOLUME_BUCKETS.into()).max(One::one());
+			(now.saturating_sub(now % bucket_length), now.saturating_sub(window))
+		}
+
+		/// Add `amount` to the volume of `who` in the current bucket, dropping
+		/// the buckets that left the `VolumeWindow`.
+		fn note_volume(who: &T::AccountId, amount: BalanceOf<T>) {
+			let (bucket, window_start) =
+				Self::volume_bucket(frame_system::Pallet::<T>::block_number());
+			RecentVolumes::<T>::mutate_exists(who, |maybe_volumes| {
+				let mut volumes = maybe_volumes.take().unwrap_or_default();
+				volumes.retain(|(start, _)| *start > window_start);
+				match volumes.last_mut() {
+					Some((start, volume)) if *start == bucket => volume.saturating_accrue(amount),
+					_ => {
+						if volumes.is_full() {
+							volumes.remove(0);
+						}
+						let _ = volumes.try_push((bucket, amount));
+					},
+				}
+				*maybe_volumes = Some(volumes);
 			});
+		}
+
+		/// Volume settled by `who` as maker or taker over the `VolumeWindow`.
+		pub fn rolling_volume(who: &T::AccountId) -> BalanceOf<T> {
+			let (_, window_start) = Self::volume_bucket(frame_system::Pallet::<T>::block_number());
+			RecentVolumes::<T>::get(who)
+				.into_iter()
+				.filter(|(start, _)| *start > window_start)
+				.fold(Zero::zero(), |total: BalanceOf<T>, (_, volume)| total.saturating_add(volume))
+		}
+
+		/// Discount of the vault fee of the highest of the `FeeTiers` that
+		/// the rolling volume of `who` reaches.
+		pub fn fee_discount(who: &T::AccountId) -> Perbill {
+			let volume = Self::rolling_volume(who);
+			FeeTiers::<T>::get()
+				.into_iter()
+				.take_while(|tier| tier.min_volume <= volume)
+				.last()
+				.map_or(Perbill::zero(), |tier| tier.discount)
+		}
+
+		/// The highest of the `ResolverTiers` that the stake delegated to
+		/// `who` reaches, if it is a registered resolver.
+		pub fn resolver_tier(who: &T::AccountId) -> Option<ResolverTier<BalanceOf<T>>> {
+			if !Resolvers::<T>::contains_key(who) {
+				return None;
+			}
+			let stake = DelegatedStakes::<T>::get(who).bonded;
+			ResolverTiers::<T>::get()
+				.into_iter()
+				.take_while(|tier| tier.min_stake <= stake)
+				.last()
+		}
+
+		/// Safety deposit that `who` posts at least in its HTLCs, reduced by
+		/// its resolver tier.
+		pub fn min_safety_deposit(who: &T::AccountId) -> BalanceOf<T> {
+			let minimum = T::MinSafetyDeposit::get();
+			let discount =
+				Self::resolver_tier(who).map_or(Perbill::zero(), |tier| tier.deposit_discount);
+			minimum.saturating_sub(discount.mul_floor(minimum))
+		}
+
+		/// Escrow the safety deposit of a new HTLC from the sponsor of the
+		/// resolver, if it has one, or from the resolver.
+		fn lock_safety_deposit(
+			htlc_id: H256,
+			resolver: &T::AccountId,
+			safety_deposit: BalanceOf<T>,
+		) -> DispatchResult {
+			let Some(sponsor) = Sponsors::<T>::get(resolver) else {
+				return Self::escrow_lock(
+					HoldReason::SafetyDeposit,
+					htlc_id,
+					resolver,
+					safety_deposit,
+				)
+				.map_err(|_| Error::<T>::InsufficientBalance.into());
+			};
+			Self::spend_sponsor_allowance(&sponsor, resolver, safety_deposit)?;
+			Self::escrow_lock(HoldReason::SafetyDeposit, htlc_id, &sponsor, safety_deposit)
+				.map_err(|_| Error::<T>::InsufficientBalance)?;
+			DepositSponsors::<T>::insert(htlc_id, &sponsor);
+
+			Self::deposit_event(Event::SafetyDepositSponsored { htlc_id, sponsor });
 
 			Ok(())
 		}
 
-		#[pallet::call_index(3)]
-		pub fn cancel(
-			origin: OriginFor<T>,
-			immutables: Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		/// Take `amount` from the allowance of `sponsor` for `resolver`.
+		fn spend_sponsor_allowance(
+			sponsor: &T::AccountId,
+			resolver: &T::AccountId,
+			amount: BalanceOf<T>,
 		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+			Self::ensure_compliant(sponsor)?;
 
-			// Validation phase
+			SponsorAllowances::<T>::try_mutate(sponsor, resolver, |allowance| {
+				*allowance =
+					allowance.checked_sub(&amount).ok_or(Error::<T>::SponsorAllowanceExceeded)?;
+				Ok(())
+			})
+		}
 
-			// validate HTLC exists
-			let htlc_id = Self::hash_immutables(&immutables);
-			let mut htlc = Htlcs::<T>::get(&htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
-			ensure!(htlc.status == HtlcStatus::Active, Error::<T>::HtlcNotActive);
+		/// The safety deposit held for an HTLC, including any top-ups.
+		pub fn safety_deposit(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> BalanceOf<T> {
+			let top_up = SafetyDepositTopUps::<T>::get(htlc_id).unwrap_or_default();
+			htlc.immutables.safety_deposit.saturating_add(top_up)
+		}
 
-			// verify immutables match
-			ensure!(htlc.immutables == immutables, Error::<T>::InvalidImmutables);
+		/// The safety deposit of an HTLC that is being settled, forgetting
+		/// its top-ups.
+		fn take_safety_deposit(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> BalanceOf<T> {
+			let safety_deposit = Self::safety_deposit(htlc_id, htlc);
+			SafetyDepositTopUps::<T>::remove(htlc_id);
+			safety_deposit
+		}
 
-			// Verify taker is not the caller of the external; anyone else
-			// can call this function. The check here is not as important as
-			// the check of the complementary condition in the `withdraw`
-			// function.
-			ensure!(who == htlc.immutables.taker, Error::<T>::InvalidCaller);
+		/// Refund the safety deposit of a settled HTLC to whoever posted it,
+		/// restoring the allowance of the sponsor, and return the recipient.
+		fn refund_safety_deposit(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> Result<T::AccountId, DispatchError> {
+			let safety_deposit = Self::take_safety_deposit(htlc_id, htlc);
+			let depositor = match DepositSponsors::<T>::take(htlc_id) {
+				Some(sponsor) => {
+					SponsorAllowances::<T>::mutate(&sponsor, &htlc.immutables.taker, |allowance| {
+						allowance.saturating_accrue(safety_deposit)
+					});
+					sponsor
+				},
+				None => htlc.immutables.taker.clone(),
+			};
+			Self::escrow_refund(HoldReason::SafetyDeposit, htlc_id, &depositor, safety_deposit)?;
+
+			Ok(depositor)
+		}
+
+		/// Leave the forfeited safety deposit of an HTLC to the
+		/// `ForfeitHandler`.
+		fn forfeit(
+			reason: ForfeitReason,
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			caller: Option<T::AccountId>,
+			credit: CreditOf<T>,
+		) {
+			let forfeit = Forfeit {
+				reason,
+				htlc_id,
+				maker: htlc.immutables.maker.clone(),
+				taker: htlc.immutables.taker.clone(),
+				caller,
+			};
 
-			// check the timing is valid for the public withdrawal
-			let current_block = frame_system::Pallet::<T>::block_number();
-			ensure!(
-				current_block >= htlc.immutables.timelocks.cancellation_after,
-				Error::<T>::EarlyCancellation
-			);
+			// the insurance fund takes its share first, unless the account
+			// cannot receive it
+			let insurance = T::InsuranceShare::get().mul_floor(credit.peek());
+			let (insurance, credit) = credit.split(insurance);
+			let amount = insurance.peek();
+			let credit = match T::NativeBalance::resolve(&Self::insurance_account(), insurance) {
+				Ok(()) => {
+					if !amount.is_zero() {
+						Self::deposit_event(Event::InsuranceFunded { amount });
+					}
+					credit
+				},
+				Err(insurance) => credit.merge(insurance),
+			};
+			T::ForfeitHandler::on_forfeit(&forfeit, credit);
+		}
 
-			// Canellation phase
-			let refund_recipient;
+		/// Forget the sponsor of an HTLC whose safety deposit is forfeited, and
+		/// return whoever posted the deposit.
+		fn forfeit_sponsorship(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> T::AccountId {
+			DepositSponsors::<T>::take(htlc_id).unwrap_or_else(|| htlc.immutables.taker.clone())
+		}
 
-			match htlc.htlc_type {
+		/// Refund the swap amount of a cancelled HTLC to whoever locked it and
+		/// finalize the intent that it fills, returning the refund recipient.
+		/// The safety deposit is left on hold.
+		fn refund_htlc(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+			intent_status: IntentStatus<T::AccountId>,
+		) -> Result<T::AccountId, DispatchError> {
+			Self::release_watchtower(htlc_id);
+			Ok(match htlc.htlc_type {
 				HtlcType::Destination => {
 					// Destination HTLC: EVM -> Polkadot
 					// Resolver (taker) deposited funds for maker
-					// Funds go back to taker
-					T::NativeBalance::release(
-						&HoldReason::SwapAmount.into(),
-						&htlc.immutables.taker,
+					// Funds go back to taker, or to the vault
+					Self::escrow_refund(
+						HoldReason::SwapAmount,
+						htlc_id,
+						&Self::swap_funder(htlc_id, htlc),
 						htlc.immutables.amount,
-						Precision::Exact,
 					)?;
+					Self::settle_vault_draw(htlc_id, htlc, true);
+					SettlementConversions::<T>::remove(htlc_id);
+					SettlementDeliveries::<T>::remove(htlc_id);
 
-					// release safety deposit to the take
-					T::NativeBalance::release(
-						&HoldReason::SafetyDeposit.into(),
-						&htlc.immutables.taker,
-						htlc.immutables.safety_deposit,
-						Precision::Exact,
-					)?;
-
-					refund_recipient = htlc.immutables.taker.clone();
+					htlc.immutables.taker.clone()
 				},
 
 				HtlcType::Source => {
 					// Destination HTLC: Polkadot -> EVM
 					// Maker deposited funds for taker
 					// Funds go back to maker
-					T::NativeBalance::release(
-						&HoldReason::MakerSwapIntentAmount.into(),
+					Self::escrow_refund(
+						HoldReason::MakerSwapIntentAmount,
+						htlc.immutables.order_hash,
 						&htlc.immutables.maker,
 						htlc.immutables.amount,
-						Precision::Exact,
 					)?;
 
-					// release safety deposit to the take
-					T::NativeBalance::release(
-						&HoldReason::SafetyDeposit.into(),
-						&htlc.immutables.taker,
-						htlc.immutables.safety_deposit,
-						Precision::Exact,
-					)?;
+					// the intent filled by this HTLC ends without a swap, or
+					// is open for its next part
+					Self::settle_intent_fill(htlc_id, htlc, intent_status)?;
 
-					refund_recipient = htlc.immutables.maker.clone();
+					htlc.immutables.maker.clone()
 				},
-			}
+			})
+		}
 
-			// update HTLC
-			htlc.status = HtlcStatus::Cancelled;
-			Htlcs::<T>::insert(&htlc_id, &htlc);
+		/// Check that the order hash of the HTLC is the EIP-712 hash of the
+		/// Fusion order and that the order is signed by its maker, returning
+		/// the order hash.
+		pub fn check_fusion_order(
+			htlc_id: H256,
+			order: &FusionOrder,
+			domain: &FusionDomain,
+			signature: &[u8; 65],
+		) -> Result<H256, DispatchError> {
+			let htlc = Htlcs::<T>::get(htlc_id).ok_or(Error::<T>::HtlcDoesNotExist)?;
+
+			let order_hash = order.hash(domain);
+			ensure!(htlc.immutables.order_hash == order_hash, Error::<T>::OrderHashMismatch);
+			ensure!(
+				recover_signer(&order_hash, signature) == Some(order.maker),
+				Error::<T>::InvalidOrderSignature
+			);
 
-			// emit event that shows the unhashed secret to the public
-			Self::deposit_event(Event::HtlcCancelled { htlc_id, refund_recipient });
+			Ok(order_hash)
+		}
 
-			Ok(())
+		/// The profile of a chain that this chain can swap with.
+		pub fn chain_profile(
+			chain_id: ChainId,
+		) -> Result<ChainProfile<BlockNumberFor<T>>, DispatchError> {
+			ChainProfiles::<T>::get(chain_id).ok_or_else(|| Error::<T>::UnknownChain.into())
 		}
 
-		///////
-		/// Calls for Swap intents
+		/// Number of decimals of the token that the intent is paid in on the
+		/// destination chain.
+		pub fn dst_decimals(intent: &SwapIntentOf<T>) -> Result<u8, DispatchError> {
+			let decimals = match intent.dst_decimals {
+				Some(decimals) => decimals,
+				None => Self::chain_profile(intent.dst_chain)?.decimals,
+			};
+			ensure!(decimals <= decimals::MAX_DECIMALS, Error::<T>::InvalidDecimals);
+			Ok(decimals)
+		}
 
-		#[pallet::call_index(4)]
-		pub fn create_swap_intent(
-			origin: OriginFor<T>,
-			intent: SwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		/// The `dst_amount` of the intent with the `NativeDecimals`, to compare
+		/// it with its `src_amount`.
+		pub fn normalized_dst_amount(
+			intent: &SwapIntentOf<T>,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			decimals::normalize(
+				intent.dst_amount,
+				Self::dst_decimals(intent)?,
+				T::NativeDecimals::get(),
+			)
+			.ok_or_else(|| Error::<T>::InvalidDecimals.into())
+		}
+
+		/// Ensure that the timelocks leave the margins required by the chain
+		/// profile, also with respect to the cancellation on the source chain
+		/// for destination HTLCs.
+		fn ensure_timelock_margins(
+			profile: &ChainProfile<BlockNumberFor<T>>,
+			timelocks: &Timelocks<BlockNumberFor<T>>,
+			src_cancellation: Option<BlockNumberFor<T>>,
 		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+			let margin = profile.min_timelock_margin;
+			ensure!(
+				timelocks.cancellation_after.saturating_sub(timelocks.withdrawal_after) >= margin,
+				Error::<T>::InsufficientTimelockMargin
+			);
+			if let Some(src_cancellation) = src_cancellation {
+				ensure!(
+					src_cancellation.saturating_sub(timelocks.cancellation_after) >= margin,
+					Error::<T>::InsufficientTimelockMargin
+				);
+			}
+			Ok(())
+		}
 
-			// ensure the maker creates the intent to swap
-			ensure!(who == intent.maker, Error::<T>::InvalidCaller);
+		/// Ensure that no active HTLC or swap intent of another order than
+		/// `order_hash` uses `hashlock`. The escrows that an order is split
+		/// into share its hashlock, as the intent and its partial fills do, and
+		/// the intent of an order is keyed by its `order_hash`.
+		pub(crate) fn ensure_hashlock_unused(hashlock: &H256, order_hash: H256) -> DispatchResult {
+			let other_order = HashlockIndex::<T>::get(hashlock).iter().any(|htlc_id| {
+				Htlcs::<T>::get(htlc_id).is_some_and(|htlc| {
+					htlc.status == HtlcStatus::Active && htlc.immutables.order_hash != order_hash
+				})
+			});
+			ensure!(!other_order, Error::<T>::HashlockInUse);
 
-			// generate the key for the map and check it doesn't already exist
-			let intent_key = Self::intent_key(&who, intent.nonce);
-			ensure!(!SwapIntents::<T>::contains_key(&intent_key), Error::<T>::IntentAlreadyExists);
+			if let Some(intent_key) = IntentHashlocks::<T>::get(hashlock) {
+				ensure!(intent_key == order_hash, Error::<T>::HashlockInUse);
+			}
 
-			let current_block = frame_system::Pallet::<T>::block_number();
-			let stored_intent = StoredSwapIntent {
-				intent: intent.clone(),
-				status: IntentStatus::Active,
-				created_at: current_block,
-			};
+			Ok(())
+		}
 
-			SwapIntents::<T>::insert(&intent_key, &stored_intent);
+		/// Ensure that the HTLC `htlc_id` is cancellable strictly before the
+		/// active HTLC locked with `parent_hashlock`, returning the id of the
+		/// latter.
+		pub fn ensure_shorter_than_upstream(
+			htlc_id: H256,
+			immutables: &ImmutablesOf<T>,
+			parent_hashlock: H256,
+		) -> Result<H256, DispatchError> {
+			let (upstream_id, upstream) = HashlockIndex::<T>::get(parent_hashlock)
+				.into_iter()
+				.filter(|upstream_id| *upstream_id != htlc_id)
+				.find_map(|upstream_id| {
+					Htlcs::<T>::get(upstream_id)
+						.filter(|upstream| upstream.status == HtlcStatus::Active)
+						.map(|upstream| (upstream_id, upstream))
+				})
+				.ok_or(Error::<T>::UpstreamHtlcNotFound)?;
+			ensure!(
+				immutables.timelocks.cancellation_after <
+					upstream.immutables.timelocks.cancellation_after,
+				Error::<T>::TimelockNotShorterThanUpstream
+			);
+			Ok(upstream_id)
+		}
 
-			T::NativeBalance::hold(
-				&HoldReason::MakerSwapIntentAmount.into(),
-				&who,
-				intent.src_amount,
-			)
-			.map_err(|_| Error::<T>::InsufficientBalance)?;
+		/// The account that keeps the funds escrowed for the HTLC or intent
+		/// `key`, or `None` if they are held on the accounts of the depositors.
+		fn escrow_account(key: H256) -> Option<T::AccountId> {
+			match T::EscrowMode::get() {
+				EscrowMode::Holds => None,
+				EscrowMode::SubAccounts => Some(Self::sub_account(key)),
+				EscrowMode::PalletAccount => Some(Self::account_id()),
+			}
+		}
 
-			Self::deposit_event(Event::SwapIntentCreated {
-				maker: who,
-				nonce: intent.nonce,
-				src_amount: intent.src_amount,
-				dst_amount: intent.dst_amount,
-				dst_address: intent.dst_address,
-				hashlock: intent.hashlock,
-			});
+		/// The account of the pallet, which pools the escrowed funds in the
+		/// `PalletAccount` mode.
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account_truncating()
+		}
 
+		/// The account derived from the HTLC or intent `key`.
+		pub fn sub_account(key: H256) -> T::AccountId {
+			let entropy = (T::PalletId::get(), key).using_encoded(blake2_256);
+			Decode::decode(&mut TrailingZeroInput::new(entropy.as_ref()))
+				.expect("infinite length input; no invalid inputs for type; qed")
+		}
+
+		/// Escrow `amount` of `who` for the HTLC or intent `key`.
+		fn escrow_lock(
+			reason: HoldReason,
+			key: H256,
+			who: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let Some(account) = Self::escrow_account(key) else {
+				T::NativeBalance::hold(&reason.into(), who, amount)?;
+				Self::expect_hold(who, reason, amount);
+				return Ok(());
+			};
+			if amount.is_zero() {
+				return Ok(());
+			}
+			ensure!(amount >= T::NativeBalance::minimum_balance(), Error::<T>::EscrowBelowMinimum);
+			T::NativeBalance::transfer(who, &account, amount, Preservation::Preserve)?;
 			Ok(())
 		}
 
-		#[pallet::call_index(5)]
-		pub fn cancel_swap_intent(origin: OriginFor<T>, nonce: u64) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+		/// Return `amount` escrowed by `who` for the HTLC or intent `key`.
+		fn escrow_refund(
+			reason: HoldReason,
+			key: H256,
+			who: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			match Self::escrow_account(key) {
+				None => {
+					Self::release_held(reason, key, who, amount)?;
+					Ok(())
+				},
+				Some(account) => Self::pay_from(&account, who, amount),
+			}
+		}
 
-			// generate the key for the map and check it doesn't already exist
-			let intent_key = Self::intent_key(&who, nonce);
-			let mut stored_intent =
-				SwapIntents::<T>::get(&intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+		/// Pay `amount` escrowed by `who` for the HTLC or intent `key` to `to`.
+		fn escrow_pay(
+			reason: HoldReason,
+			key: H256,
+			who: &T::AccountId,
+			to: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			match Self::escrow_account(key) {
+				None => {
+					let released = Self::release_held(reason, key, who, amount)?;
+					T::NativeBalance::transfer(who, to, released, Preservation::Preserve)?;
+					Ok(())
+				},
+				Some(account) => Self::pay_from(&account, to, amount),
+			}
+		}
 
-			// ensure we cannot cancel an already cancelled intent
-			ensure!(stored_intent.status == IntentStatus::Active, Error::<T>::IntentNotActive);
+		/// Release `amount` held from `who` for the HTLC or intent `key`, or
+		/// what is left of it if the hold was reduced behind the back of the
+		/// pallet, so that the escrow does not get stuck. The shortfall is
+		/// recorded in the `EscrowDeficits`. Returns the released amount.
+		fn release_held(
+			reason: HoldReason,
+			key: H256,
+			who: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> Result<BalanceOf<T>, DispatchError> {
+			let hold_reason = reason.into();
+			if T::NativeBalance::release(&hold_reason, who, amount, Precision::Exact).is_ok() {
+				Self::track_expected_hold(reason, who, |held| held.saturating_reduce(amount));
+				Self::note_residual_hold(reason, key, who, amount);
+				return Ok(amount);
+			}
 
-			// ensure the maker cancels the intent to swap
-			ensure!(who == stored_intent.intent.maker, Error::<T>::InvalidCaller);
+			let released =
+				T::NativeBalance::release(&hold_reason, who, amount, Precision::BestEffort)?;
+			// the shortfall stays expected until the deficit is cleared
+			Self::track_expected_hold(reason, who, |held| held.saturating_reduce(released));
+			EscrowDeficits::<T>::mutate(key, (who.clone(), reason), |deficit| {
+				deficit.saturating_accrue(amount.saturating_sub(released))
+			});
+			Self::deposit_event(Event::PartialRelease {
+				key,
+				who: who.clone(),
+				reason,
+				expected: amount,
+				released,
+			});
+			Ok(released)
+		}
 
-			stored_intent.status = IntentStatus::Cancelled;
-			SwapIntents::<T>::insert(&intent_key, &stored_intent);
+		/// Release the holds of the swap amounts and safety deposits of `who`
+		/// beyond those of its active HTLCs.
+		fn release_orphaned_holds(who: &T::AccountId) -> DispatchResult {
+			for (reason, amount) in Self::orphaned_holds(who) {
+				if amount.is_zero() {
+					continue;
+				}
+				T::NativeBalance::release(&reason.into(), who, amount, Precision::Exact)?;
+				Self::deposit_event(Event::OrphanedHoldReleased {
+					who: who.clone(),
+					reason,
+					amount,
+				});
+			}
 
-			T::NativeBalance::release(
-				&HoldReason::MakerSwapIntentAmount.into(),
-				&who,
-				stored_intent.intent.src_amount,
-				Precision::Exact,
-			)?;
+			Ok(())
+		}
 
-			Self::deposit_event(Event::SwapIntentCancelled {
-				maker: who,
-				nonce,
-				src_amount: stored_intent.intent.src_amount,
-				dst_amount: stored_intent.intent.dst_amount,
-				dst_address: stored_intent.intent.dst_address,
-				hashlock: stored_intent.intent.hashlock,
+		/// Note what the hold of `who` for `reason` keeps beyond its
+		/// `ExpectedHolds` once the HTLC or intent `key` released `amount` of
+		/// it, up to that amount, as left behind by `key`. It is expected
+		/// until it is swept or reconciled, so that it is noted once.
+		fn note_residual_hold(
+			reason: HoldReason,
+			key: H256,
+			who: &T::AccountId,
+			amount: BalanceOf<T>,
+		) {
+			let Some((_, orphaned)) =
+				Self::orphaned_holds(who).into_iter().find(|(orphaned, _)| *orphaned == reason)
+			else {
+				return;
+			};
+			let residual = orphaned.min(amount);
+			if residual.is_zero() {
+				return;
+			}
+
+			let now = frame_system::Pallet::<T>::block_number();
+			ResidualHolds::<T>::mutate(key, (who.clone(), reason), |noted| {
+				noted.get_or_insert((Zero::zero(), now)).0.saturating_accrue(residual)
 			});
+			Self::expect_hold(who, reason, residual);
+			Self::deposit_event(Event::ResidualHoldNoted {
+				key,
+				who: who.clone(),
+				reason,
+				amount: residual,
+			});
+		}
+
+		/// Release the holds that the HTLC or intent `key` left behind to
+		/// their accounts.
+		fn release_residual_holds(key: H256) -> DispatchResult {
+			for ((who, reason), (amount, _)) in ResidualHolds::<T>::drain_prefix(key) {
+				Self::track_expected_hold(reason, &who, |held| held.saturating_reduce(amount));
+				let released =
+					T::NativeBalance::release(&reason.into(), &who, amount, Precision::BestEffort)?;
+				Self::deposit_event(Event::OrphanedHoldReleased { who, reason, amount: released });
+			}
 
 			Ok(())
 		}
 
-		///////
-		/// Calls for source HTLCs
+		/// The holds of the swap amounts and safety deposits of `who` beyond
+		/// its `ExpectedHolds`.
+		fn orphaned_holds(who: &T::AccountId) -> [(HoldReason, BalanceOf<T>); 2] {
+			let expected = ExpectedHolds::<T>::get(who);
+			[
+				(HoldReason::SwapAmount, expected.swap_amounts),
+				(HoldReason::SafetyDeposit, expected.safety_deposits),
+			]
+			.map(|(reason, expected)| {
+				let held = <T::NativeBalance as fungible::InspectHold<_>>::balance_on_hold(
+					&reason.into(),
+					who,
+				);
+				(reason, held.saturating_sub(expected))
+			})
+		}
 
-		#[pallet::call_index(6)]
-		pub fn create_src_htlc(
-			origin: OriginFor<T>,
-			maker: T::AccountId,
-			nonce: u64,
-			timelocks: Timelocks<BlockNumberFor<T>>,
-			safety_deposit: BalanceOf<T>,
-		) -> DispatchResult {
-			let who = ensure_signed(origin)?;
+		/// Keep the `ExpectedHolds` of `who` with its holds of the swap
+		/// amounts and safety deposits.
+		fn track_expected_hold(
+			reason: HoldReason,
+			who: &T::AccountId,
+			update: impl FnOnce(&mut BalanceOf<T>),
+		) {
+			if !matches!(reason, HoldReason::SwapAmount | HoldReason::SafetyDeposit) {
+				return;
+			}
+			ExpectedHolds::<T>::mutate_exists(who, |expected| {
+				let mut hold = expected.take().unwrap_or_default();
+				match reason {
+					HoldReason::SwapAmount => update(&mut hold.swap_amounts),
+					_ => update(&mut hold.safety_deposits),
+				}
+				*expected = (hold != ExpectedHold::default()).then_some(hold);
+			});
+		}
 
-			let min_safety_deposit: BalanceOf<T> = T::MinSafetyDeposit::get().into();
+		/// The swap amount and safety deposit that the active HTLC holds, with
+		/// the accounts they are held from.
+		pub(crate) fn expected_holds(
+			htlc_id: H256,
+			htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> [(T::AccountId, HoldReason, BalanceOf<T>); 2] {
+			let swap_amount = match htlc.htlc_type {
+				HtlcType::Destination => htlc.immutables.amount,
+				// the maker's funds are held for the intent instead
+				HtlcType::Source => Zero::zero(),
+			};
+			let depositor =
+				DepositSponsors::<T>::get(htlc_id).unwrap_or_else(|| htlc.immutables.taker.clone());
+			[
+				(Self::swap_funder(htlc_id, htlc), HoldReason::SwapAmount, swap_amount),
+				(depositor, HoldReason::SafetyDeposit, Self::safety_deposit(htlc_id, htlc)),
+			]
+		}
 
-			ensure!(safety_deposit >= min_safety_deposit, Error::<T>::HigherSafetyDepositRequired);
+		/// The safety deposit that the pending destination HTLC holds, with
+		/// the account it is held from.
+		pub(crate) fn expected_pending_hold(
+			htlc_id: H256,
+			pending: &PendingDstHtlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> (T::AccountId, HoldReason, BalanceOf<T>) {
+			let depositor = DepositSponsors::<T>::get(htlc_id)
+				.unwrap_or_else(|| pending.immutables.taker.clone());
+			(depositor, HoldReason::SafetyDeposit, pending.immutables.safety_deposit)
+		}
 
-			// generate the key for the map and check it doesn't already exist
-			let intent_key = Self::intent_key(&maker, nonce);
-			let stored_intent =
-				SwapIntents::<T>::get(&intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+		/// Add what the HTLCs hold from `who` for `reason` to its
+		/// `ExpectedHolds`.
+		pub(crate) fn expect_hold(who: &T::AccountId, reason: HoldReason, amount: BalanceOf<T>) {
+			if !amount.is_zero() {
+				Self::track_expected_hold(reason, who, |held| held.saturating_accrue(amount));
+			}
+		}
 
-			// ensure we cannot cancel an already cancelled intent
-			ensure!(stored_intent.status == IntentStatus::Active, Error::<T>::IntentNotActive);
+		/// Slash `amount` escrowed by `who` for the HTLC or intent `key`.
+		fn escrow_slash(
+			reason: HoldReason,
+			key: H256,
+			who: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> Result<CreditOf<T>, DispatchError> {
+			let Some(account) = Self::escrow_account(key) else {
+				let (credit, missing) = T::NativeBalance::slash(&reason.into(), who, amount);
+				ensure!(missing.is_zero(), TokenError::FundsUnavailable);
+				Self::track_expected_hold(reason, who, |held| {
+					held.saturating_reduce(credit.peek())
+				});
+				return Ok(credit);
+			};
+			T::NativeBalance::withdraw(
+				&account,
+				amount,
+				Precision::Exact,
+				Self::payout_preservation(&account, amount),
+				Fortitude::Polite,
+			)
+		}
 
-			// ensure the intent hasn't expired
+		/// Close the escrow account with the payment of its whole balance.
+		fn payout_preservation(account: &T::AccountId, amount: BalanceOf<T>) -> Preservation {
+			if T::NativeBalance::balance(account) == amount {
+				Preservation::Expendable
+			} else {
+				Preservation::Preserve
+			}
+		}
+
+		/// Pay out of an escrow account, closing it with the last payment.
+		pub(crate) fn pay_from(
+			account: &T::AccountId,
+			to: &T::AccountId,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			if amount.is_zero() {
+				return Ok(());
+			}
+			T::NativeBalance::transfer(
+				account,
+				to,
+				amount,
+				Self::payout_preservation(account, amount),
+			)?;
+			Ok(())
+		}
+
+		/// Queue the HTLC to be cancelled automatically once it is overdue,
+		/// bounding how far ahead and how much work can be scheduled.
+		fn schedule_auto_cancel(
+			cancellation_after: BlockNumberFor<T>,
+			htlc_id: H256,
+		) -> DispatchResult {
 			let current_block = frame_system::Pallet::<T>::block_number();
 			ensure!(
-				current_block <= stored_intent.intent.timeout_after_block,
-				Error::<T>::IntentExpired
+				cancellation_after <= current_block.saturating_add(T::MaxTimelockHorizon::get()),
+				Error::<T>::TimelockTooFar
 			);
 
-			// validate timelock sequence (withdrawal < public_withdrawal < cancellation)
-			ensure!(
-				timelocks.deployed_at <= timelocks.cancellation_after &&
-					timelocks.withdrawal_after <= timelocks.public_withdrawal_after &&
-					timelocks.public_withdrawal_after <= timelocks.cancellation_after,
-				Error::<T>::InvalidTimelocks
-			);
+			let next_block = current_block.saturating_add(One::one());
+			HtlcsByCancellationBlock::<T>::try_append(cancellation_after.max(next_block), htlc_id)
+				.map_err(|_| Error::<T>::CancellationQueueFull)?;
 
-			let immutables = Immutables {
-				order_hash: intent_key,
-				hashlock: stored_intent.intent.hashlock,
-				maker: stored_intent.intent.maker.clone(),
-				taker: who.clone(),
-				amount: stored_intent.intent.src_amount,
-				safety_deposit,
-				timelocks,
-			};
+			Ok(())
+		}
 
-			// ensure HTLC doesn't already exist
-			let htlc_id = Self::hash_immutables(&immutables);
-			ensure!(!Htlcs::<T>::contains_key(&htlc_id), Error::<T>::HtlcAlreadyExists);
+		/// Queue the HTLC for the `StageEntered` events, if they are enabled.
+		fn schedule_stage_events(
+			timelocks: &Timelocks<BlockNumberFor<T>>,
+			htlc_id: H256,
+		) -> DispatchResult {
+			if !T::StageEvents::get() {
+				return Ok(());
+			}
 
-			// hold the required safety deposit for the swap from the taker
-			T::NativeBalance::hold(
-				&HoldReason::SafetyDeposit.into(),
-				&who,
-				immutables.safety_deposit,
+			let next_block = frame_system::Pallet::<T>::block_number().saturating_add(One::one());
+			let public_cancellation_after =
+				timelocks.cancellation_after.saturating_add(T::PublicCancellationDelay::get());
+			for (block, stage) in [
+				(timelocks.public_withdrawal_after, Stage::PublicWithdrawal),
+				(public_cancellation_after, Stage::PublicCancellation),
+			] {
+				StageTransitions::<T>::try_append(block.max(next_block), (htlc_id, stage))
+					.map_err(|_| Error::<T>::StageQueueFull)?;
+			}
+
+			Ok(())
+		}
+
+		/// Emit `StageEntered` for the HTLCs queued at the block that are still
+		/// active, and return the weight used.
+		fn emit_stage_events(n: BlockNumberFor<T>) -> Weight {
+			let transitions = StageTransitions::<T>::take(n);
+			let mut weight = T::WeightInfo::stage_events_block(transitions.len() as u32);
+
+			for (htlc_id, stage) in transitions {
+				weight.saturating_accrue(T::WeightInfo::stage_event());
+				if Htlcs::<T>::get(htlc_id).is_some_and(|htlc| htlc.status == HtlcStatus::Active) {
+					Self::deposit_event(Event::StageEntered { htlc_id, stage });
+				}
+			}
+
+			weight
+		}
+
+		/// Queue `SettlementAtRisk` for the HTLC at the start of the
+		/// `SettlementWarningPeriod` before its cancellation.
+		fn schedule_settlement_warning(
+			cancellation_after: BlockNumberFor<T>,
+			htlc_id: H256,
+		) -> DispatchResult {
+			let period = T::SettlementWarningPeriod::get();
+			if period.is_zero() {
+				return Ok(());
+			}
+
+			let next_block = frame_system::Pallet::<T>::block_number().saturating_add(One::one());
+			SettlementWarnings::<T>::try_append(
+				cancellation_after.saturating_sub(period).max(next_block),
+				htlc_id,
 			)
-			.map_err(|_| Error::<T>::InsufficientBalance)?;
+			.map_err(|_| Error::<T>::SettlementWarningQueueFull)?;
 
-			let htlc = Htlc {
-				immutables: immutables.clone(),
-				status: HtlcStatus::Active,
-				htlc_type: HtlcType::Source,
+			Ok(())
+		}
+
+		/// Emit `SettlementAtRisk` for the HTLCs queued at the block that are
+		/// still active, and return the weight used, which is that of the
+		/// stage events of as many HTLCs.
+		fn emit_settlement_warnings(n: BlockNumberFor<T>) -> Weight {
+			let warnings = SettlementWarnings::<T>::take(n);
+			let mut weight = T::WeightInfo::stage_events_block(warnings.len() as u32);
+
+			for htlc_id in warnings {
+				weight.saturating_accrue(T::WeightInfo::stage_event());
+				match Htlcs::<T>::get(htlc_id) {
+					Some(htlc) if htlc.status == HtlcStatus::Active =>
+						Self::deposit_event(Event::SettlementAtRisk {
+							htlc_id,
+							taker: htlc.immutables.taker,
+							cancellation_after: htlc.immutables.timelocks.cancellation_after,
+						}),
+					_ => {},
+				}
+			}
+
+			weight
+		}
+
+		/// Cancel up to `MaxAutoCancelsPerBlock` overdue HTLCs, resuming from
+		/// the block where the previous run stopped, and return the weight used.
+		fn auto_cancel_overdue(n: BlockNumberFor<T>) -> Weight {
+			let mut weight = Weight::zero();
+			let mut budget = T::MaxAutoCancelsPerBlock::get();
+			let mut visits = T::MaxAutoCancelsPerBlock::get();
+
+			let mut cursor = AutoCancelCursor::<T>::get().unwrap_or(n);
+			while cursor <= n && budget > 0 && visits > 0 {
+				let mut queue = HtlcsByCancellationBlock::<T>::take(cursor);
+				visits.saturating_dec();
+				weight
+					.saturating_accrue(T::WeightInfo::auto_cancel_queue_block(queue.len() as u32));
+
+				while budget > 0 {
+					let Some(htlc_id) = queue.pop() else { break };
+					budget.saturating_dec();
+					weight.saturating_accrue(T::WeightInfo::auto_cancel_htlc());
+
+					// expire the destination HTLCs that were reserved and not
+					// activated in time
+					let Some(htlc) = Htlcs::<T>::get(htlc_id) else {
+						let _ =
+							with_storage_layer(|| Self::expire_pending_dst_htlc(htlc_id, cursor));
+						continue;
+					};
+
+					// skip the HTLCs that were settled in the meantime, and the
+					// activation deadline of those that were reserved; blocked
+					// parties don't hold up the refunds, as with `cancel`
+					if htlc.status != HtlcStatus::Active ||
+						cursor < htlc.immutables.timelocks.cancellation_after
+					{
+						continue;
+					}
+
+					// an unchallenged claim settles the HTLC to the taker, and
+					// the HTLC is only cancelled if the claim is dropped
+					let htlc = match OptimisticClaims::<T>::get(htlc_id) {
+						Some(claim) => {
+							let _ = with_storage_layer(|| {
+								Self::finalize_optimistic_claim(htlc_id, claim)
+							});
+							match Htlcs::<T>::get(htlc_id) {
+								Some(htlc) if htlc.status == HtlcStatus::Active => htlc,
+								_ => continue,
+							}
+						},
+						None => htlc,
+					};
+
+					// the cancellation either completes or leaves no trace
+					let _ = with_storage_layer(|| {
+						Self::cancel_htlc(htlc_id, htlc, IntentStatus::Expired)
+					});
+				}
+
+				if !queue.is_empty() {
+					HtlcsByCancellationBlock::<T>::insert(cursor, queue);
+					break;
+				}
+				cursor.saturating_inc();
+			}
+			AutoCancelCursor::<T>::put(cursor);
+
+			weight
+		}
+
+		/// Expire the active intents that timed out, releasing the holds of the
+		/// makers, for as long as the remaining weight allows. Each call
+		/// continues from where the previous one stopped.
+		fn expire_timed_out_intents(n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+			let mut weight = T::WeightInfo::on_idle_base();
+			if weight.any_gt(remaining_weight) {
+				return Weight::zero();
+			}
+
+			let mut intents = match IntentExpiryCursor::<T>::get() {
+				Some(raw_key) => SwapIntents::<T>::iter_from(raw_key),
+				None => SwapIntents::<T>::iter(),
 			};
+			loop {
+				if weight.saturating_add(T::WeightInfo::expire_intent()).any_gt(remaining_weight) {
+					IntentExpiryCursor::<T>::put(intents.last_raw_key().to_vec());
+					break;
+				}
+				let Some((intent_key, stored_intent)) = intents.next() else {
+					IntentExpiryCursor::<T>::kill();
+					break;
+				};
+				weight.saturating_accrue(T::WeightInfo::expire_intent());
+
+				if matches!(
+					stored_intent.status,
+					IntentStatus::Active | IntentStatus::Reserved { .. }
+				) && stored_intent.intent.is_expired_at(n)
+				{
+					// the expiry either completes or leaves no trace
+					let _ = with_storage_layer(|| Self::expire_intent(intent_key, stored_intent));
+				}
+			}
 
-			Htlcs::<T>::insert(&htlc_id, &htlc);
+			weight
+		}
 
-			Self::deposit_event(Event::HtlcCreated {
-				htlc_id,
-				hashlock: stored_intent.intent.hashlock,
+		/// Expire an active or reserved intent that timed out before any
+		/// resolver filled it.
+		fn expire_intent(
+			intent_key: H256,
+			stored_intent: StoredSwapIntent<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		) -> DispatchResult {
+			Self::escrow_refund(
+				HoldReason::MakerSwapIntentAmount,
+				intent_key,
+				&stored_intent.intent.maker,
+				Self::unfilled_amount(intent_key, &stored_intent),
+			)?;
+			Self::finalize_intent(intent_key, IntentStatus::Expired)?;
+
+			Self::deposit_event(Event::SwapIntentExpired {
 				maker: stored_intent.intent.maker,
-				taker: who,
-				amount: stored_intent.intent.src_amount,
-				safety_deposit,
+				nonce: stored_intent.intent.nonce,
 			});
 
 			Ok(())
 		}
-	}
 
-	impl<T: Config> Pallet<T> {
-		/// Generate unique ID from immutables
-		pub fn hash_immutables(
-			immutables: &Immutables<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
-		) -> H256 {
-			let encoded = immutables.encode();
-			BlakeTwo256::hash(&encoded)
+		/// Add the HTLC `htlc_id` to the legs of the order `order_hash`.
+		pub(crate) fn track_order_leg(order_hash: H256, htlc_id: H256) -> DispatchResult {
+			let mut record = Self::order_record(order_hash);
+			record.htlcs.try_push(htlc_id).map_err(|_| Error::<T>::TooManyOrderLegs)?;
+			Self::store_order_record(order_hash, record);
+			Ok(())
+		}
+
+		/// Note the escrow of the other leg of the HTLC `htlc_id` in the order
+		/// `order_hash`, replacing the one noted before.
+		fn track_counterpart(order_hash: H256, htlc_id: H256, escrow: H160) -> DispatchResult {
+			let mut record = Self::order_record(order_hash);
+			match record.counterparts.iter_mut().find(|(id, _)| *id == htlc_id) {
+				Some(counterpart) => counterpart.1 = escrow,
+				None => record
+					.counterparts
+					.try_push((htlc_id, escrow))
+					.map_err(|_| Error::<T>::TooManyOrderLegs)?,
+			}
+			Self::store_order_record(order_hash, record);
+			Ok(())
+		}
+
+		/// Bring the status of the order `order_hash` up to date with its
+		/// intent and its HTLCs.
+		pub(crate) fn track_order(order_hash: H256) {
+			Self::store_order_record(order_hash, Self::order_record(order_hash));
+		}
+
+		fn order_record(order_hash: H256) -> OrderRecord<T::AccountId> {
+			CrossChainOrders::<T>::get(order_hash).unwrap_or(OrderRecord {
+				intent: None,
+				htlcs: BoundedVec::new(),
+				counterparts: BoundedVec::new(),
+				status: OrderStatus::Open,
+			})
+		}
+
+		fn store_order_record(order_hash: H256, mut record: OrderRecord<T::AccountId>) {
+			record.intent = SwapIntents::<T>::get(order_hash).map(|stored| stored.status);
+			let legs: Vec<HtlcStatus> = record
+				.htlcs
+				.iter()
+				.filter_map(|htlc_id| {
+					Htlcs::<T>::get(htlc_id).map(|htlc| htlc.status).or_else(|| {
+						ArchivedHtlcs::<T>::get(htlc_id).map(|archived| archived.status)
+					})
+				})
+				.collect();
+
+			// an active leg makes progress even while the intent takes more fills
+			record.status = if legs.contains(&HtlcStatus::Active) {
+				OrderStatus::InProgress
+			} else {
+				match &record.intent {
+					Some(IntentStatus::Active | IntentStatus::Reserved { .. }) => OrderStatus::Open,
+					Some(IntentStatus::InProgress { .. }) => OrderStatus::InProgress,
+					Some(IntentStatus::Completed) => OrderStatus::Completed,
+					_ if legs.contains(&HtlcStatus::Completed) => OrderStatus::Completed,
+					_ => OrderStatus::Cancelled,
+				}
+			};
+			CrossChainOrders::<T>::insert(order_hash, record);
+		}
+
+		/// Add the HTLC to the list of escrows that share `hashlock`.
+		pub(crate) fn index_hashlock(hashlock: H256, htlc_id: H256) -> DispatchResult {
+			HashlockIndex::<T>::try_append(hashlock, htlc_id)
+				.map_err(|_| Error::<T>::TooManyHtlcsForHashlock)?;
+			Ok(())
+		}
+
+		/// Record that the secret of `hashlock` was revealed in the current block.
+		fn note_revealed_secret(hashlock: H256, htlc_id: H256) -> DispatchResult {
+			let current_block = frame_system::Pallet::<T>::block_number();
+			RevealedSecrets::<T>::try_mutate(current_block, |revealed| {
+				// the escrows of a split order reveal the same secret
+				if revealed.iter().any(|(noted, _)| *noted == hashlock) {
+					return Ok(());
+				}
+				revealed
+					.try_push((hashlock, htlc_id))
+					.map_err(|_| Error::<T>::TooManyRevealedSecrets.into())
+			})
+		}
+
+		/// Ensure the account is not blocked and passes the configured
+		/// `ComplianceFilter`.
+		pub(crate) fn ensure_compliant(who: &T::AccountId) -> DispatchResult {
+			Self::ensure_not_blocked(who)?;
+			ensure!(T::ComplianceFilter::contains(who), Error::<T>::AccountNotCompliant);
+			Ok(())
+		}
+
+		/// Ensure the account is not in the `Blocked` list.
+		fn ensure_not_blocked(who: &T::AccountId) -> DispatchResult {
+			ensure!(!Blocked::<T>::contains_key(who), Error::<T>::AccountBlocked);
+			Ok(())
 		}
 
 		/// Geenrate intent storage key from maker AccountId + nonce
@@ -0,0 +1,76 @@
+//! Secrets of the intents that are filled in parts. Like the multiple fills
+//! of 1inch Fusion+, the maker of an intent of `parts` parts commits to
+//! `parts + 1` secrets with the root of their Merkle tree as the hashlock,
+//! and each fill is withdrawn with the secret of the part that the amount
+//! filled up to it reaches, so that a secret revealed for one fill does not
+//! unlock the next one.
+
+use crate::HashFunction;
+use frame_support::{traits::ConstU32, BoundedVec};
+use sp_core::H256;
+use sp_runtime::{
+	helpers_128bit::multiply_by_rational_with_rounding, traits::AtLeast32BitUnsigned, Rounding,
+	SaturatedConversion,
+};
+
+/// Maximum depth of the proofs, for the trees of the up to `u16::MAX + 1`
+/// secrets of an intent.
+pub const MAX_PROOF_DEPTH: u32 = 16;
+
+/// Sibling nodes from a leaf up to the root of its tree.
+pub type MerkleProof = BoundedVec<H256, ConstU32<MAX_PROOF_DEPTH>>;
+
+/// Leaf of the secret `index`, whose hash is `secret_hash`.
+pub fn leaf(hash_function: HashFunction, index: u16, secret_hash: H256) -> H256 {
+	let mut data = [0u8; 34];
+	data[..2].copy_from_slice(&index.to_be_bytes());
+	data[2..].copy_from_slice(secret_hash.as_bytes());
+	hash_function.hash(&data)
+}
+
+/// Hash of two nodes of a tree, in order, so that a proof needs no sides.
+pub fn node(hash_function: HashFunction, a: H256, b: H256) -> H256 {
+	let (left, right) = if a <= b { (a, b) } else { (b, a) };
+	let mut data = [0u8; 64];
+	data[..32].copy_from_slice(left.as_bytes());
+	data[32..].copy_from_slice(right.as_bytes());
+	hash_function.hash(&data)
+}
+
+/// Root of the tree in which `proof` proves `leaf`.
+pub fn root(hash_function: HashFunction, leaf: H256, proof: &[H256]) -> H256 {
+	proof.iter().fold(leaf, |acc, sibling| node(hash_function, acc, *sibling))
+}
+
+/// Index of the secret of a fill of `amount` of an intent of `src_amount` in
+/// `parts` parts, after `filled` of it: the part that the amount filled up to
+/// the fill reaches, or `parts` for the fill that completes the intent. None
+/// if the fill ends in the part of the previous one, whose secret may be
+/// revealed already.
+pub fn fill_index<Balance: AtLeast32BitUnsigned + Copy>(
+	src_amount: Balance,
+	parts: u16,
+	filled: Balance,
+	amount: Balance,
+) -> Option<u16> {
+	let part = |filled: Balance| -> u16 {
+		multiply_by_rational_with_rounding(
+			filled.saturating_sub(1u32.into()).saturated_into::<u128>(),
+			parts.into(),
+			src_amount.saturated_into::<u128>(),
+			Rounding::Down,
+		)
+		.unwrap_or_default()
+		.saturated_into()
+	};
+
+	let filled_after = filled.saturating_add(amount);
+	if filled_after >= src_amount {
+		return Some(parts);
+	}
+	let index = part(filled_after);
+	if !filled.is_zero() && part(filled) == index {
+		return None;
+	}
+	Some(index)
+}
@@ -0,0 +1,813 @@
+//! Multi-block migrations of the pallet storage, which step through the
+//! stored entries over as many blocks as they need so that chains with many
+//! intents can upgrade. They are run by the `pallet-migrations` of the
+//! runtime.
+
+use crate::{
+	BalanceOf, ChainId, Config, CrossChainOrders, EscrowMode, HashlockIndex, HtlcOf, HtlcStatus,
+	HtlcType, Htlcs, IntentHashlocks, IntentStatus, MakerIntents, Pallet, PendingDstHtlcs,
+	ResolverIntents, SwapIntents, WeightInfo,
+};
+use core::marker::PhantomData;
+use frame_support::{
+	migrations::{MigrationId, SteppedMigration, SteppedMigrationError},
+	pallet_prelude::*,
+	traits::GetStorageVersion,
+	weights::WeightMeter,
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use sp_core::H256;
+use sp_runtime::Saturating;
+
+/// Identifier of the migrations of this pallet.
+const PALLET_MIGRATIONS_ID: &[u8; 15] = b"pallet-htlc-mbm";
+
+/// The migrations of the pallet storage from its first version, in their
+/// order, for the `Migrations` of the `pallet-migrations` of the runtime. The
+/// intents and HTLCs of the first version swapped with `LegacyChain`.
+pub type LazyMigrations<T, LegacyChain> = (
+	LazyMigrationV1<T, LegacyChain>,
+	LazyMigrationV2<T, LegacyChain>,
+	LazyMigrationV3<T>,
+	LazyMigrationV4<T>,
+	LazyMigrationV5<T>,
+	LazyMigrationV6<T>,
+	LazyMigrationV7<T>,
+);
+
+/// Layout of the swap intents of the first version of the pallet, before
+/// they held bounded collections, for EVM destinations only.
+pub mod v0 {
+	use super::*;
+	use crate::{auction::AuctionCurve, DstAddress};
+	use sp_core::H160;
+	use sp_runtime::traits::Zero;
+	use v3::IntentFlags;
+
+	/// The status of the intents before they could be reserved.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub enum IntentStatus<AccountId> {
+		Active,
+		InProgress { resolver: AccountId, htlc_id: H256 },
+		Completed,
+		Cancelled,
+		Expired,
+	}
+
+	impl<AccountId> IntentStatus<AccountId> {
+		/// The status with the reservations.
+		pub fn migrate(self) -> crate::IntentStatus<AccountId> {
+			match self {
+				IntentStatus::Active => crate::IntentStatus::Active,
+				IntentStatus::InProgress { resolver, htlc_id } =>
+					crate::IntentStatus::InProgress { resolver, htlc_id },
+				IntentStatus::Completed => crate::IntentStatus::Completed,
+				IntentStatus::Cancelled => crate::IntentStatus::Cancelled,
+				IntentStatus::Expired => crate::IntentStatus::Expired,
+			}
+		}
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct SwapIntent<AccountId, Balance, BlockNumber> {
+		pub hashlock: H256,
+		pub maker: AccountId,
+		pub src_amount: Balance,
+		pub dst_amount: Balance,
+		pub dst_address: H160,
+		pub timeout_after_block: BlockNumber,
+		pub nonce: u64,
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		pub intent: SwapIntent<AccountId, Balance, BlockNumber>,
+		pub status: IntentStatus<AccountId>,
+		pub created_at: BlockNumber,
+	}
+
+	#[frame_support::storage_alias]
+	pub type SwapIntents<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		H256,
+		StoredSwapIntent<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+	>;
+
+	impl<AccountId, Balance: Zero, BlockNumber> StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		/// The intent in the v1 layout, at the fixed price with the defaults
+		/// of the intents that came after it, paid on `dst_chain`.
+		pub fn migrate(
+			self,
+			dst_chain: ChainId,
+		) -> v2::StoredSwapIntent<AccountId, Balance, BlockNumber> {
+			let intent = self.intent;
+			v2::StoredSwapIntent {
+				intent: v2::SwapIntent {
+					hashlock: intent.hashlock,
+					maker: intent.maker,
+					src_amount: intent.src_amount,
+					dst_amount: intent.dst_amount,
+					dst_address: DstAddress::Evm(intent.dst_address),
+					dst_chain,
+					timeout_after_block: Some(intent.timeout_after_block),
+					nonce: intent.nonce,
+					auction: AuctionCurve::Fixed,
+					min_fill_amount: Zero::zero(),
+					flags: IntentFlags { fill_or_kill: false, allow_multiple_fills: true },
+					epoch: 0,
+					denied_resolvers: Default::default(),
+				},
+				status: self.status.migrate(),
+				created_at: self.created_at,
+				maker_deposit: Zero::zero(),
+				taken_at: None,
+				reservation_bond: Zero::zero(),
+				fills: 0,
+				storage_deposit: Zero::zero(),
+			}
+		}
+	}
+}
+
+/// Layout of the HTLCs before their immutables were versioned and held the
+/// hash function of their hashlock, and before the HTLCs named the chain of
+/// their counterpart.
+pub mod v1 {
+	use super::*;
+	use crate::{HashFunction, HtlcStatus, HtlcType, StoredHtlc, Timelocks, VersionedImmutables};
+
+	/// The immutables of the blake2 hashlocks, the only ones before the
+	/// `hash_function`.
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct Immutables<AccountId, Balance, BlockNumber> {
+		pub order_hash: H256,
+		pub hashlock: H256,
+		pub maker: AccountId,
+		pub taker: AccountId,
+		pub amount: Balance,
+		pub safety_deposit: Balance,
+		pub timelocks: Timelocks<BlockNumber>,
+	}
+
+	impl<AccountId, Balance, BlockNumber> Immutables<AccountId, Balance, BlockNumber> {
+		/// The immutables with their blake2 hash function, which hash to the
+		/// same id.
+		pub fn migrate(self) -> crate::Immutables<AccountId, Balance, BlockNumber> {
+			crate::Immutables {
+				order_hash: self.order_hash,
+				hashlock: self.hashlock,
+				hash_function: HashFunction::Blake2_256,
+				maker: self.maker,
+				taker: self.taker,
+				amount: self.amount,
+				safety_deposit: self.safety_deposit,
+				timelocks: self.timelocks,
+			}
+		}
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct Htlc<AccountId, Balance, BlockNumber> {
+		pub immutables: Immutables<AccountId, Balance, BlockNumber>,
+		pub status: HtlcStatus,
+		pub htlc_type: HtlcType,
+	}
+
+	#[frame_support::storage_alias]
+	pub type Htlcs<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		H256,
+		Htlc<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+	>;
+
+	impl<AccountId, Balance, BlockNumber> Htlc<AccountId, Balance, BlockNumber> {
+		/// The HTLC in the v2 layout, with the first version of the immutables,
+		/// swapping with `counterpart_chain`.
+		pub fn migrate(
+			self,
+			counterpart_chain: ChainId,
+		) -> StoredHtlc<AccountId, Balance, BlockNumber> {
+			StoredHtlc {
+				immutables: VersionedImmutables::V1(self.immutables.migrate()),
+				status: self.status,
+				htlc_type: self.htlc_type,
+				counterpart_chain,
+			}
+		}
+	}
+}
+
+/// Layout of the swap intents before they held the decimals of the
+/// destination token.
+pub mod v2 {
+	use super::*;
+	use crate::{auction::AuctionCurve, ChainId, DeniedResolvers, DstAddress, IntentStatus};
+	use v3::IntentFlags;
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct SwapIntent<AccountId, Balance, BlockNumber> {
+		pub hashlock: H256,
+		pub maker: AccountId,
+		pub src_amount: Balance,
+		pub dst_amount: Balance,
+		pub dst_address: DstAddress,
+		pub dst_chain: ChainId,
+		pub timeout_after_block: Option<BlockNumber>,
+		pub nonce: u64,
+		pub auction: AuctionCurve<Balance, BlockNumber>,
+		pub min_fill_amount: Balance,
+		pub flags: IntentFlags,
+		pub epoch: u32,
+		pub denied_resolvers: DeniedResolvers<AccountId>,
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		pub intent: SwapIntent<AccountId, Balance, BlockNumber>,
+		pub status: IntentStatus<AccountId>,
+		pub created_at: BlockNumber,
+		pub maker_deposit: Balance,
+		pub taken_at: Option<BlockNumber>,
+		pub reservation_bond: Balance,
+		pub fills: u32,
+		pub storage_deposit: Balance,
+	}
+
+	#[frame_support::storage_alias]
+	pub type SwapIntents<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		H256,
+		StoredSwapIntent<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+	>;
+
+	impl<AccountId, Balance, BlockNumber> StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		/// The intent in the v3 layout, quoted in the decimals of its chain.
+		pub fn migrate(self) -> v3::StoredSwapIntent<AccountId, Balance, BlockNumber> {
+			let intent = self.intent;
+			v3::StoredSwapIntent {
+				intent: v3::SwapIntent {
+					hashlock: intent.hashlock,
+					maker: intent.maker,
+					src_amount: intent.src_amount,
+					dst_amount: intent.dst_amount,
+					dst_address: intent.dst_address,
+					dst_chain: intent.dst_chain,
+					timeout_after_block: intent.timeout_after_block,
+					nonce: intent.nonce,
+					auction: intent.auction,
+					min_fill_amount: intent.min_fill_amount,
+					flags: intent.flags,
+					epoch: intent.epoch,
+					denied_resolvers: intent.denied_resolvers,
+					dst_decimals: None,
+				},
+				status: self.status,
+				created_at: self.created_at,
+				maker_deposit: self.maker_deposit,
+				taken_at: self.taken_at,
+				reservation_bond: self.reservation_bond,
+				fills: self.fills,
+				storage_deposit: self.storage_deposit,
+			}
+		}
+	}
+}
+
+/// Layout of the swap intents before they allowed partial fills with a flag.
+pub mod v3 {
+	use super::*;
+	use crate::{auction::AuctionCurve, ChainId, DeniedResolvers, DstAddress, IntentStatus};
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Copy, Debug)]
+	pub struct IntentFlags {
+		pub fill_or_kill: bool,
+		pub allow_multiple_fills: bool,
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct SwapIntent<AccountId, Balance, BlockNumber> {
+		pub hashlock: H256,
+		pub maker: AccountId,
+		pub src_amount: Balance,
+		pub dst_amount: Balance,
+		pub dst_address: DstAddress,
+		pub dst_chain: ChainId,
+		pub timeout_after_block: Option<BlockNumber>,
+		pub nonce: u64,
+		pub auction: AuctionCurve<Balance, BlockNumber>,
+		pub min_fill_amount: Balance,
+		pub flags: IntentFlags,
+		pub epoch: u32,
+		pub denied_resolvers: DeniedResolvers<AccountId>,
+		pub dst_decimals: Option<u8>,
+	}
+
+	#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+	pub struct StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		pub intent: SwapIntent<AccountId, Balance, BlockNumber>,
+		pub status: IntentStatus<AccountId>,
+		pub created_at: BlockNumber,
+		pub maker_deposit: Balance,
+		pub taken_at: Option<BlockNumber>,
+		pub reservation_bond: Balance,
+		pub fills: u32,
+		pub storage_deposit: Balance,
+	}
+
+	#[frame_support::storage_alias]
+	pub type SwapIntents<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		H256,
+		StoredSwapIntent<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+	>;
+
+	impl<AccountId, Balance, BlockNumber> StoredSwapIntent<AccountId, Balance, BlockNumber> {
+		/// The intent in the v4 layout, which allows no partial fills.
+		pub fn migrate(self) -> crate::StoredSwapIntent<AccountId, Balance, BlockNumber> {
+			let intent = self.intent;
+			crate::StoredSwapIntent {
+				intent: crate::SwapIntent {
+					hashlock: intent.hashlock,
+					maker: intent.maker,
+					src_amount: intent.src_amount,
+					dst_amount: intent.dst_amount,
+					dst_address: intent.dst_address,
+					dst_chain: intent.dst_chain,
+					timeout_after_block: intent.timeout_after_block,
+					nonce: intent.nonce,
+					auction: intent.auction,
+					min_fill_amount: intent.min_fill_amount,
+					flags: crate::IntentFlags {
+						fill_or_kill: intent.flags.fill_or_kill,
+						allow_multiple_fills: intent.flags.allow_multiple_fills,
+						allow_partial_fills: false,
+					},
+					epoch: intent.epoch,
+					denied_resolvers: intent.denied_resolvers,
+					dst_decimals: intent.dst_decimals,
+				},
+				status: self.status,
+				created_at: self.created_at,
+				maker_deposit: self.maker_deposit,
+				taken_at: self.taken_at,
+				reservation_bond: self.reservation_bond,
+				fills: self.fills,
+				storage_deposit: self.storage_deposit,
+			}
+		}
+	}
+}
+
+/// Layout of the HTLCs before their immutables held `CompactTimelocks`,
+/// which is the v2 layout of the `StoredHtlc` with only the first version
+/// of the immutables.
+pub mod v4 {
+	use super::*;
+	use crate::StoredHtlc;
+
+	#[frame_support::storage_alias]
+	pub type Htlcs<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		H256,
+		StoredHtlc<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+	>;
+}
+
+/// The number of open intents of each maker, before `MakerIntents` kept
+/// their keys.
+pub mod v6 {
+	use super::*;
+
+	#[frame_support::storage_alias]
+	pub type OpenIntents<T: Config> =
+		StorageMap<Pallet<T>, Blake2_128Concat, <T as frame_system::Config>::AccountId, u32>;
+}
+
+/// Migrate the swap intents from the first version of the pallet to the v1
+/// layout, with the bounded list of the denied resolvers, one intent at a
+/// time. Their destination addresses are on `LegacyChain`, the EVM chain of
+/// the first swaps. The cursor is the key of the last migrated intent.
+pub struct LazyMigrationV1<T, LegacyChain>(PhantomData<(T, LegacyChain)>);
+
+impl<T: Config, LegacyChain: Get<ChainId>> SteppedMigration for LazyMigrationV1<T, LegacyChain> {
+	type Cursor = H256;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 0, version_to: 1 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the intents are already in the v1 layout
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(0) {
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_intent();
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let mut iter = match cursor {
+				Some(last_key) =>
+					v0::SwapIntents::<T>::iter_from(v0::SwapIntents::<T>::hashed_key_for(last_key)),
+				None => v0::SwapIntents::<T>::iter(),
+			};
+			let Some((key, stored_intent)) = iter.next() else {
+				StorageVersion::new(1).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			v2::SwapIntents::<T>::insert(key, stored_intent.migrate(LegacyChain::get()));
+			cursor = Some(key);
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Migrate the HTLCs to the v2 layout, with versioned immutables, one HTLC at
+/// a time. The HTLCs from before they named their counterpart chain swapped
+/// with `LegacyChain`, the EVM chain of the first swaps. The cursor is the id
+/// of the last migrated HTLC.
+pub struct LazyMigrationV2<T, LegacyChain>(PhantomData<(T, LegacyChain)>);
+
+impl<T: Config, LegacyChain: Get<ChainId>> SteppedMigration for LazyMigrationV2<T, LegacyChain> {
+	type Cursor = H256;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 1, version_to: 2 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the HTLCs are already in the v2 layout
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(1) {
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_htlc();
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let mut iter = match cursor {
+				Some(last_id) => v1::Htlcs::<T>::iter_from(v1::Htlcs::<T>::hashed_key_for(last_id)),
+				None => v1::Htlcs::<T>::iter(),
+			};
+			let Some((htlc_id, htlc)) = iter.next() else {
+				StorageVersion::new(2).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			v4::Htlcs::<T>::insert(htlc_id, htlc.migrate(LegacyChain::get()));
+			cursor = Some(htlc_id);
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Migrate the swap intents to the v3 layout, with the decimals of the
+/// destination token, one intent at a time. The cursor is the key of the last
+/// migrated intent.
+pub struct LazyMigrationV3<T>(PhantomData<T>);
+
+impl<T: Config> SteppedMigration for LazyMigrationV3<T> {
+	type Cursor = H256;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 2, version_to: 3 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the intents are already in the v3 layout
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(2) {
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_intent();
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let mut iter = match cursor {
+				Some(last_key) =>
+					v2::SwapIntents::<T>::iter_from(v2::SwapIntents::<T>::hashed_key_for(last_key)),
+				None => v2::SwapIntents::<T>::iter(),
+			};
+			let Some((key, stored_intent)) = iter.next() else {
+				StorageVersion::new(3).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			v3::SwapIntents::<T>::insert(key, stored_intent.migrate());
+			cursor = Some(key);
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Migrate the swap intents to the v4 layout, with the flag that allows their
+/// partial fills, one intent at a time. The cursor is the key of the last
+/// migrated intent.
+pub struct LazyMigrationV4<T>(PhantomData<T>);
+
+impl<T: Config> SteppedMigration for LazyMigrationV4<T> {
+	type Cursor = H256;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 3, version_to: 4 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the intents are already in the v4 layout
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(3) {
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_intent();
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let mut iter = match cursor {
+				Some(last_key) =>
+					v3::SwapIntents::<T>::iter_from(v3::SwapIntents::<T>::hashed_key_for(last_key)),
+				None => v3::SwapIntents::<T>::iter(),
+			};
+			let Some((key, stored_intent)) = iter.next() else {
+				StorageVersion::new(4).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			SwapIntents::<T>::insert(key, stored_intent.migrate());
+			cursor = Some(key);
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Migrate the HTLCs to the v5 layout, with the `CompactTimelocks` of the
+/// second version of the immutables, one HTLC at a time. The ids of the HTLCs
+/// stay the same, and the HTLCs whose timelocks have no compact form keep the
+/// first version. The cursor is the id of the last migrated HTLC.
+pub struct LazyMigrationV5<T>(PhantomData<T>);
+
+impl<T: Config> SteppedMigration for LazyMigrationV5<T> {
+	type Cursor = H256;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 4, version_to: 5 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the HTLCs are already in the v5 layout
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(4) {
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_htlc();
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let mut iter = match cursor {
+				Some(last_id) => v4::Htlcs::<T>::iter_from(v4::Htlcs::<T>::hashed_key_for(last_id)),
+				None => v4::Htlcs::<T>::iter(),
+			};
+			let Some((htlc_id, htlc)) = iter.next() else {
+				StorageVersion::new(5).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			Htlcs::<T>::insert(htlc_id, htlc.into_latest());
+			cursor = Some(htlc_id);
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Where `LazyMigrationV6` stopped: the id of the last HTLC, or of the last
+/// pending destination HTLC, whose holds it counted.
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExpectedHoldsCursor {
+	Htlc(H256),
+	PendingDstHtlc(H256),
+}
+
+/// Count what the active and pending HTLCs hold from each account into the
+/// `ExpectedHolds` of the v6 layout, one HTLC at a time, first the HTLCs and
+/// then the pending destination HTLCs. There is nothing to count unless the
+/// escrow keeps the funds in holds.
+pub struct LazyMigrationV6<T>(PhantomData<T>);
+
+impl<T: Config> SteppedMigration for LazyMigrationV6<T> {
+	type Cursor = ExpectedHoldsCursor;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 5, version_to: 6 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the expected holds are already counted
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(5) {
+			return Ok(None);
+		}
+		if T::EscrowMode::get() != EscrowMode::Holds {
+			StorageVersion::new(6).put::<Pallet<T>>();
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_htlc();
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let next = match cursor {
+				None => Htlcs::<T>::iter().next(),
+				Some(ExpectedHoldsCursor::Htlc(last_id)) =>
+					Htlcs::<T>::iter_from(Htlcs::<T>::hashed_key_for(last_id)).next(),
+				Some(ExpectedHoldsCursor::PendingDstHtlc(_)) => None,
+			};
+			if let Some((htlc_id, htlc)) = next {
+				if htlc.status == HtlcStatus::Active {
+					for (who, reason, amount) in Pallet::<T>::expected_holds(htlc_id, &htlc) {
+						Pallet::<T>::expect_hold(&who, reason, amount);
+					}
+				}
+				cursor = Some(ExpectedHoldsCursor::Htlc(htlc_id));
+				continue;
+			}
+
+			let next = match cursor {
+				Some(ExpectedHoldsCursor::PendingDstHtlc(last_id)) =>
+					PendingDstHtlcs::<T>::iter_from(PendingDstHtlcs::<T>::hashed_key_for(last_id))
+						.next(),
+				_ => PendingDstHtlcs::<T>::iter().next(),
+			};
+			let Some((htlc_id, pending)) = next else {
+				StorageVersion::new(6).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			let (who, reason, amount) = Pallet::<T>::expected_pending_hold(htlc_id, &pending);
+			Pallet::<T>::expect_hold(&who, reason, amount);
+			cursor = Some(ExpectedHoldsCursor::PendingDstHtlc(htlc_id));
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Where `LazyMigrationV7` stopped: the id of the last HTLC or the key of
+/// the last intent that it indexed, or the removal of the counts of the open
+/// intents.
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MakerIntentsCursor {
+	Htlc(H256),
+	Intent(H256),
+	OpenIntents,
+}
+
+/// Index the keys of the open intents of each maker in the `MakerIntents` of
+/// the v7 layout, one intent at a time, then remove the counts of the open
+/// intents that they replace, one maker at a time. The HTLCs and intents
+/// from the first version of the pallet are indexed first, one HTLC or intent
+/// at a time, like the ones created since: the HTLCs by their hashlock and
+/// order, the open intents by their hashlock, and the intents that their
+/// source HTLCs left active brought up to date with them.
+pub struct LazyMigrationV7<T>(PhantomData<T>);
+
+impl<T: Config> SteppedMigration for LazyMigrationV7<T> {
+	type Cursor = MakerIntentsCursor;
+	type Identifier = MigrationId<15>;
+
+	fn id() -> Self::Identifier {
+		MigrationId { pallet_id: *PALLET_MIGRATIONS_ID, version_from: 6, version_to: 7 }
+	}
+
+	fn step(
+		mut cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		// the open intents are already indexed
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(6) {
+			return Ok(None);
+		}
+
+		let required = T::WeightInfo::migrate_htlc().max(T::WeightInfo::migrate_intent());
+		if meter.remaining().any_lt(required) {
+			return Err(SteppedMigrationError::InsufficientWeight { required });
+		}
+
+		while meter.try_consume(required).is_ok() {
+			let next = match cursor {
+				None => Htlcs::<T>::iter().next(),
+				Some(MakerIntentsCursor::Htlc(last_id)) =>
+					Htlcs::<T>::iter_from(Htlcs::<T>::hashed_key_for(last_id)).next(),
+				Some(_) => None,
+			};
+			if let Some((htlc_id, htlc)) = next {
+				index_legacy_htlc::<T>(htlc_id, &htlc)?;
+				cursor = Some(MakerIntentsCursor::Htlc(htlc_id));
+				continue;
+			}
+
+			let next = match cursor {
+				Some(MakerIntentsCursor::Intent(last_key)) =>
+					SwapIntents::<T>::iter_from(SwapIntents::<T>::hashed_key_for(last_key)).next(),
+				Some(MakerIntentsCursor::OpenIntents) => None,
+				_ => SwapIntents::<T>::iter().next(),
+			};
+			if let Some((intent_key, stored_intent)) = next {
+				if stored_intent.status.is_open() {
+					MakerIntents::<T>::try_append(&stored_intent.intent.maker, intent_key)
+						.map_err(|_| SteppedMigrationError::Failed)?;
+					IntentHashlocks::<T>::insert(stored_intent.intent.hashlock, intent_key);
+				}
+				if !CrossChainOrders::<T>::contains_key(intent_key) {
+					Pallet::<T>::track_order(intent_key);
+				}
+				cursor = Some(MakerIntentsCursor::Intent(intent_key));
+				continue;
+			}
+
+			let Some(maker) = v6::OpenIntents::<T>::iter_keys().next() else {
+				StorageVersion::new(7).put::<Pallet<T>>();
+				return Ok(None);
+			};
+			v6::OpenIntents::<T>::remove(maker);
+			cursor = Some(MakerIntentsCursor::OpenIntents);
+		}
+
+		Ok(cursor)
+	}
+}
+
+/// Index the HTLC from the first version of the pallet, unless it was indexed
+/// when it was created. The intent that it filled stayed active, so it takes
+/// the status of the HTLC.
+fn index_legacy_htlc<T: Config>(
+	htlc_id: H256,
+	htlc: &HtlcOf<T>,
+) -> Result<(), SteppedMigrationError> {
+	let immutables = &htlc.immutables;
+	if HashlockIndex::<T>::get(immutables.hashlock).contains(&htlc_id) {
+		return Ok(());
+	}
+
+	if htlc.htlc_type == HtlcType::Source {
+		SwapIntents::<T>::mutate(immutables.order_hash, |stored_intent| {
+			let Some(stored_intent) =
+				stored_intent.as_mut().filter(|stored| stored.status == IntentStatus::Active)
+			else {
+				return;
+			};
+			stored_intent.status = match htlc.status {
+				HtlcStatus::Active => {
+					ResolverIntents::<T>::mutate(&immutables.taker, |held| held.saturating_inc());
+					IntentStatus::InProgress { resolver: immutables.taker.clone(), htlc_id }
+				},
+				HtlcStatus::Completed => IntentStatus::Completed,
+				HtlcStatus::Cancelled => IntentStatus::Cancelled,
+			};
+			stored_intent.taken_at = Some(immutables.timelocks.deployed_at);
+			stored_intent.fills.saturating_inc();
+		});
+	}
+
+	Pallet::<T>::index_hashlock(immutables.hashlock, htlc_id)
+		.and_then(|()| Pallet::<T>::track_order_leg(immutables.order_hash, htlc_id))
+		.map_err(|_| SteppedMigrationError::Failed)
+}
@@ -1,17 +1,36 @@
 use crate as pallet_htlc;
+use crate::forfeit::{ForfeitTo, ForfeitToMaker, SplitForfeits};
 use frame_support::{
-	derive_impl,
-	traits::{ConstU128, ConstU16, ConstU32, ConstU64},
+	derive_impl, parameter_types,
+	traits::{
+		fungible::Mutate,
+		tokens::{imbalance::ResolveTo, Preservation},
+		ConstU128, ConstU16, ConstU32, ConstU64, ConstU8, Contains, UnixTime,
+	},
+	PalletId,
 };
 use sp_core::H256;
 use sp_runtime::{
 	traits::{BlakeTwo256, IdentityLookup},
-	BuildStorage,
+	BuildStorage, DispatchError, Perbill, TokenError,
 };
+use xcm::latest::{Junction::Parachain, Location};
+use xcm_executor::traits::ConvertLocation;
 
 type Block = frame_system::mocking::MockBlock<Test>;
 type Balance = u128;
 
+/// Chain with the same hash function as this one and any address format.
+pub const GENERIC_CHAIN: pallet_htlc::ChainId = 0;
+/// Bitcoin, with sha256 hashlocks and a margin for its confirmation times.
+pub const BITCOIN_CHAIN: pallet_htlc::ChainId = 1;
+/// Receives the funds slashed from the escrows.
+pub const TREASURY: u64 = 99;
+/// Parachain of the remote party, dispatching as the root origin.
+pub const SIBLING_PARA_ID: u32 = 2_000;
+/// Accounts of the sibling parachains start at this id.
+pub const SIBLING_ACCOUNT_BASE: u64 = 1_000_000;
+
 // Configure a mock runtime to test the pallet. We use the simpler syntax here.
 #[frame_support::runtime]
 mod runtime {
@@ -81,12 +100,201 @@ impl pallet_balances::Config for Test {
 	type MaxFreezes = ConstU32<10>;
 }
 
+parameter_types! {
+	/// Accounts rejected by the `ComplianceFilter`, e.g. a sanctions list.
+	pub static Sanctioned: Vec<u64> = vec![];
+	/// Safety deposit of the makers, disabled unless a test sets it.
+	pub static MakerDeposit: u128 = 0;
+	pub const PublicCancellationReward: Perbill = Perbill::from_percent(50);
+	pub const WatchtowerShare: Perbill = Perbill::from_percent(20);
+	pub const VaultFee: Perbill = Perbill::from_percent(1);
+	/// Share of the insurance fund, disabled unless a test sets it.
+	pub static InsuranceShare: Perbill = Perbill::zero();
+	pub const MaxVaultUtilization: Perbill = Perbill::from_percent(80);
+	pub const DelegationSlash: Perbill = Perbill::from_percent(10);
+	/// How the escrowed funds are kept, holds unless a test sets it.
+	pub static Escrow: pallet_htlc::EscrowMode = pallet_htlc::EscrowMode::Holds;
+	/// Commitment-only destination HTLCs, disabled unless a test sets it.
+	pub static CommitmentOnly: bool = false;
+	pub const HtlcPalletId: PalletId = PalletId(*b"py/htlc_");
+	pub const Treasury: u64 = TREASURY;
+	pub static StageEventsEnabled: bool = false;
+	/// Blocks of warning before the cancellations, disabled unless a test
+	/// sets it.
+	pub static SettlementWarningPeriod: u64 = 0;
+	/// Receipts of the settled swaps.
+	/// Conversions of the settled funds, as (maker, amount, asset).
+	pub static Conversions: Vec<(u64, Balance, u32)> = vec![];
+	/// Remote accounts that cannot receive deliveries.
+	pub static UnreachableRemotes: Vec<u64> = vec![];
+	pub static IssuedReceipts: Vec<pallet_htlc::receipt::SwapReceipt<u64, Balance>> = vec![];
+	/// Calls submitted by the auto-resolver.
+	pub static SubmittedFills: Vec<pallet_htlc::Call<Test>> = vec![];
+	/// Location of the remote party dispatching as the root origin.
+	pub static RemoteParty: pallet_htlc::Location = Location::new(1, [Parachain(SIBLING_PARA_ID)]);
+	/// UNIX time of the current block, in seconds.
+	pub static Now: u64 = 1_700_000_000;
+}
+
+pub struct MockTime;
+impl UnixTime for MockTime {
+	fn now() -> core::time::Duration {
+		core::time::Duration::from_secs(Now::get())
+	}
+}
+
+#[cfg(feature = "auto-resolver")]
+pub struct RecordFills;
+#[cfg(feature = "auto-resolver")]
+impl pallet_htlc::resolver::SubmitFill<Test> for RecordFills {
+	fn submit(call: pallet_htlc::Call<Test>) -> Result<(), &'static str> {
+		let mut fills = SubmittedFills::get();
+		fills.push(call);
+		SubmittedFills::set(fills);
+		Ok(())
+	}
+}
+
+/// Proof that the destination leg of a swap did not settle in the tests.
+pub const UNSETTLED_PROOF: &[u8] = b"unsettled";
+
+pub struct ProofOfUnsettlement;
+impl pallet_htlc::optimistic::VerifySettlement<u64, Balance, u64> for ProofOfUnsettlement {
+	fn enabled() -> bool {
+		true
+	}
+
+	fn verify_unsettled(
+		_: H256,
+		_: &pallet_htlc::Immutables<u64, Balance, u64>,
+		proof: &[u8],
+	) -> bool {
+		proof == UNSETTLED_PROOF
+	}
+}
+
+/// Record the conversions of the settled funds at half their amount,
+/// without swapping them.
+pub struct ConvertAtHalf;
+impl pallet_htlc::conversion::ConvertSettlement<u64, Balance, u32> for ConvertAtHalf {
+	fn convert(
+		who: &u64,
+		amount: Balance,
+		asset: &u32,
+		min_out: Balance,
+	) -> Result<Balance, DispatchError> {
+		let amount_out = amount / 2;
+		if amount_out < min_out {
+			return Err(TokenError::BelowMinimum.into());
+		}
+		let mut conversions = Conversions::get();
+		conversions.push((*who, amount, *asset));
+		Conversions::set(conversions);
+		Ok(amount_out)
+	}
+}
+
+/// Deliver the settled funds to the remote accounts by moving them to the
+/// treasury, as the sovereign account of the other chain.
+pub struct DeliverToRemote;
+impl pallet_htlc::delivery::DeliverSettlement<u64, Balance, u64> for DeliverToRemote {
+	fn deliver(who: &u64, amount: Balance, beneficiary: &u64) -> Result<(), DispatchError> {
+		if UnreachableRemotes::get().contains(beneficiary) {
+			return Err(DispatchError::Unavailable);
+		}
+		Balances::transfer(who, &Treasury::get(), amount, Preservation::Expendable).map(|_| ())
+	}
+}
+
+pub struct RecordReceipts;
+impl pallet_htlc::receipt::IssueReceipt<u64, Balance> for RecordReceipts {
+	fn issue(receipt: &pallet_htlc::receipt::SwapReceipt<u64, Balance>) {
+		let mut receipts = IssuedReceipts::get();
+		receipts.push(receipt.clone());
+		IssuedReceipts::set(receipts);
+	}
+}
+
+/// Derive the accounts of the sibling parachains from their id.
+pub struct SiblingAccounts;
+impl ConvertLocation<u64> for SiblingAccounts {
+	fn convert_location(location: &Location) -> Option<u64> {
+		match location.unpack() {
+			(1, [Parachain(id)]) => Some(SIBLING_ACCOUNT_BASE + u64::from(*id)),
+			_ => None,
+		}
+	}
+}
+
+pub struct ComplianceFilter;
+impl Contains<u64> for ComplianceFilter {
+	fn contains(who: &u64) -> bool {
+		!Sanctioned::get().contains(who)
+	}
+}
+
 impl pallet_htlc::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type NativeBalance = Balances;
+	type NativeDecimals = ConstU8<12>;
 	type RuntimeCall = RuntimeCall;
 	type RuntimeHoldReason = RuntimeHoldReason;
+	type WeightInfo = ();
+	type EscrowMode = Escrow;
+	type CommitmentOnly = CommitmentOnly;
+	type PalletId = HtlcPalletId;
+	type ForfeitHandler =
+		SplitForfeits<ForfeitTo<ResolveTo<Treasury, Balances>>, ForfeitToMaker<Balances>>;
 	type MinSafetyDeposit = ConstU128<10>;
+	type MakerSafetyDeposit = MakerDeposit;
+	type IntentCancellationDelay = ConstU64<400>;
+	type ReservationBond = ConstU128<20>;
+	type GoodTilCancelDeposit = ConstU128<30>;
+	type MaxIntentsPerMaker = ConstU32<8>;
+	type MaxIntentsPerResolver = ConstU32<4>;
+	type FillDeadline = ConstU64<100>;
+	type ResolverCooldown = ConstU64<50>;
+	type UndelegationDelay = ConstU64<20>;
+	type DelegationSlash = DelegationSlash;
+	type MaxDelegators = ConstU32<2>;
+	type VolumeWindow = ConstU64<300>;
+	type DstActivationPeriod = ConstU64<20>;
+	type SettlementVerifier = ProofOfUnsettlement;
+	type OptimisticClaimBond = ConstU128<40>;
+	type ChallengeWindow = ConstU64<30>;
+	type SecretRevealPriority = ConstU64<1_000>;
+	type ReceiptIssuer = RecordReceipts;
+	type ConversionAsset = u32;
+	type SettlementConverter = ConvertAtHalf;
+	type RemoteBeneficiary = u64;
+	type SettlementDelivery = DeliverToRemote;
+	type PublicCancellationDelay = ConstU64<50>;
+	type PublicCancellationReward = PublicCancellationReward;
+	type WatchtowerShare = WatchtowerShare;
+	type VaultFee = VaultFee;
+	type InsuranceShare = InsuranceShare;
+	type MaxVaultUtilization = MaxVaultUtilization;
+	type MaxQueuedVaultWithdrawals = ConstU32<2>;
+	type EmergencySweepPeriod = ConstU64<1_000>;
+	type EscheatmentPeriod = ConstU64<1_000>;
+	type UnclaimedHandler = ResolveTo<Treasury, Balances>;
+	type MaxAutoCancelsPerBlock = ConstU32<2>;
+	type MaxCancellationsPerBlock = ConstU32<4>;
+	type StageEvents = StageEventsEnabled;
+	type SettlementWarningPeriod = SettlementWarningPeriod;
+	type MaxTimelockHorizon = ConstU64<1_000>;
+	type UnixTime = MockTime;
+	type ExpectedBlockTime = ConstU64<6_000>;
+	type TimestampSafetyMargin = ConstU64<60>;
+	type ComplianceFilter = ComplianceFilter;
+	type GovernanceOrigin = frame_system::EnsureRoot<u64>;
+	type RemoteOrigin = frame_system::EnsureRootWithSuccess<u64, RemoteParty>;
+	type LocationToAccount = SiblingAccounts;
+	type MaxRevealedSecretsPerBlock = ConstU32<2>;
+	type RevealedSecretsRetention = ConstU64<10>;
+	type MaxHtlcsPerHashlock = ConstU32<4>;
+	#[cfg(feature = "auto-resolver")]
+	type AutoResolver = RecordFills;
 }
 
 pub fn new_test_ext() -> sp_io::TestExternalities {
@@ -98,5 +306,26 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
 	.assimilate_storage(&mut t)
 	.unwrap();
 
-	t.into()
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		pallet_htlc::ChainProfiles::<Test>::insert(
+			GENERIC_CHAIN,
+			pallet_htlc::ChainProfile {
+				hash_function: pallet_htlc::HashFunction::Blake2_256,
+				address_format: pallet_htlc::AddressFormat::Any,
+				min_timelock_margin: 0,
+				decimals: 12,
+			},
+		);
+		pallet_htlc::ChainProfiles::<Test>::insert(
+			BITCOIN_CHAIN,
+			pallet_htlc::ChainProfile {
+				hash_function: pallet_htlc::HashFunction::Sha256,
+				address_format: pallet_htlc::AddressFormat::Bitcoin,
+				min_timelock_margin: 50,
+				decimals: 8,
+			},
+		);
+	});
+	ext
 }
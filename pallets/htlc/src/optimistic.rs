@@ -0,0 +1,39 @@
+//! Verification of the challenges to optimistic settlements, in which the
+//! taker of a source HTLC claims the funds by asserting that the destination
+//! leg settled, without revealing the secret. Each chain plugs in the proofs
+//! that it can check, e.g. light client proofs of the escrow on the other
+//! chain.
+
+use crate::Immutables;
+use sp_core::H256;
+
+/// Verifier of the proofs that the destination leg of a source HTLC did not
+/// settle.
+pub trait VerifySettlement<AccountId, Balance, BlockNumber> {
+	/// Whether the proofs can be verified at all. Optimistic claims are only
+	/// accepted if they can be challenged.
+	fn enabled() -> bool;
+
+	/// Whether `proof` shows that the preimage of the hashlock of the source
+	/// HTLC `htlc_id` was never revealed on the destination chain.
+	fn verify_unsettled(
+		htlc_id: H256,
+		immutables: &Immutables<AccountId, Balance, BlockNumber>,
+		proof: &[u8],
+	) -> bool;
+}
+
+/// No proofs are verified, so optimistic settlement is disabled.
+impl<AccountId, Balance, BlockNumber> VerifySettlement<AccountId, Balance, BlockNumber> for () {
+	fn enabled() -> bool {
+		false
+	}
+
+	fn verify_unsettled(
+		_: H256,
+		_: &Immutables<AccountId, Balance, BlockNumber>,
+		_: &[u8],
+	) -> bool {
+		false
+	}
+}
@@ -0,0 +1,42 @@
+//! Origin of the resolvers registered by the `GovernanceOrigin`, so that other
+//! pallets, and the call filters of the runtime, restrict calls to them the
+//! way this pallet does.
+
+use crate::{Config, Resolvers};
+use core::marker::PhantomData;
+use frame_support::traits::{Contains, EnsureOrigin};
+use frame_system::RawOrigin;
+
+/// Ensure that the origin is signed by a registered resolver, succeeding with
+/// its account.
+pub struct EnsureRegisteredResolver<T>(PhantomData<T>);
+
+impl<T: Config> EnsureOrigin<T::RuntimeOrigin> for EnsureRegisteredResolver<T> {
+	type Success = T::AccountId;
+
+	fn try_origin(origin: T::RuntimeOrigin) -> Result<Self::Success, T::RuntimeOrigin> {
+		origin.into().and_then(|origin| match origin {
+			RawOrigin::Signed(who) if Resolvers::<T>::contains_key(&who) => Ok(who),
+			origin => Err(origin.into()),
+		})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn try_successful_origin() -> Result<T::RuntimeOrigin, ()> {
+		use codec::Decode;
+		use sp_runtime::traits::{TrailingZeroInput, Zero};
+
+		let who = T::AccountId::decode(&mut TrailingZeroInput::zeroes()).map_err(|_| ())?;
+		Resolvers::<T>::insert(&who, <crate::BalanceOf<T>>::zero());
+		Ok(RawOrigin::Signed(who).into())
+	}
+}
+
+/// The accounts of the registered resolvers.
+pub struct RegisteredResolvers<T>(PhantomData<T>);
+
+impl<T: Config> Contains<T::AccountId> for RegisteredResolvers<T> {
+	fn contains(who: &T::AccountId) -> bool {
+		Resolvers::<T>::contains_key(who)
+	}
+}
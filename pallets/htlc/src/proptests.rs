@@ -0,0 +1,292 @@
+//! Property-based tests that run random sequences of calls against the mock
+//! runtime and check the invariants of the pallet after every call.
+
+use crate::{mock::*, *};
+use frame_support::traits::fungible::{Inspect, InspectHold};
+use proptest::prelude::*;
+use sp_core::{blake2_256, H160, H256};
+use sp_std::collections::btree_map::BTreeMap;
+
+const ACCOUNTS: [u64; 3] = [1, 2, 3];
+const SECRETS: [&[u8]; 4] = [b"secret 0", b"secret 1", b"secret 2", b"secret 3"];
+
+/// A single call, or the passing of time, in a random run.
+#[derive(Clone, Debug)]
+enum Op {
+	CreateDstHtlc { maker: u64, taker: u64, amount: u128, safety_deposit: u128, secret: usize },
+	CreateSwapIntent { maker: u64, amount: u128, secret: usize, nonce: u64 },
+	CancelSwapIntent { maker: u64, nonce: u64 },
+	CreateSrcHtlc { taker: u64, maker: u64, nonce: u64, safety_deposit: u128 },
+	Withdraw { htlc: usize, valid_secret: bool },
+	PublicWithdraw { htlc: usize, caller: u64 },
+	Cancel { htlc: usize, caller: u64 },
+	PublicCancel { htlc: usize, caller: u64 },
+	AgreeToCancel { htlc: usize, caller: u64 },
+	AdvanceBlocks(u64),
+}
+
+fn account() -> impl Strategy<Value = u64> {
+	prop::sample::select(ACCOUNTS.to_vec())
+}
+
+fn op() -> impl Strategy<Value = Op> {
+	prop_oneof![
+		(account(), account(), 1..2_000u128, 0..200u128, 0..SECRETS.len()).prop_map(
+			|(maker, taker, amount, safety_deposit, secret)| Op::CreateDstHtlc {
+				maker,
+				taker,
+				amount,
+				safety_deposit,
+				secret
+			}
+		),
+		(account(), 1..2_000u128, 0..SECRETS.len(), 0..3u64).prop_map(
+			|(maker, amount, secret, nonce)| Op::CreateSwapIntent { maker, amount, secret, nonce }
+		),
+		(account(), 0..3u64).prop_map(|(maker, nonce)| Op::CancelSwapIntent { maker, nonce }),
+		(account(), account(), 0..3u64, 0..200u128).prop_map(
+			|(taker, maker, nonce, safety_deposit)| Op::CreateSrcHtlc {
+				taker,
+				maker,
+				nonce,
+				safety_deposit
+			}
+		),
+		(any::<usize>(), any::<bool>())
+			.prop_map(|(htlc, valid_secret)| Op::Withdraw { htlc, valid_secret }),
+		(any::<usize>(), account()).prop_map(|(htlc, caller)| Op::PublicWithdraw { htlc, caller }),
+		(any::<usize>(), account()).prop_map(|(htlc, caller)| Op::Cancel { htlc, caller }),
+		(any::<usize>(), account()).prop_map(|(htlc, caller)| Op::PublicCancel { htlc, caller }),
+		(any::<usize>(), account()).prop_map(|(htlc, caller)| Op::AgreeToCancel { htlc, caller }),
+		(1..150u64).prop_map(Op::AdvanceBlocks),
+	]
+}
+
+fn timelocks(current_block: u64) -> Timelocks<u64> {
+	Timelocks {
+		deployed_at: current_block,
+		withdrawal_after: current_block + 100,
+		public_withdrawal_after: current_block + 200,
+		cancellation_after: current_block + 300,
+	}
+}
+
+/// Pick one of the stored HTLCs, so that the settlement calls hit existing
+/// escrows most of the time.
+fn pick_htlc(index: usize) -> Option<Immutables<u64, u128, u64>> {
+	let mut htlcs: Vec<_> = Htlcs::<Test>::iter().collect();
+	if htlcs.is_empty() {
+		return None;
+	}
+	htlcs.sort_by_key(|(htlc_id, _)| *htlc_id);
+	Some(htlcs.swap_remove(index % htlcs.len()).1.immutables)
+}
+
+fn secret_of(hashlock: H256) -> Vec<u8> {
+	SECRETS
+		.iter()
+		.find(|secret| H256(blake2_256(secret)) == hashlock)
+		.map(|secret| secret.to_vec())
+		.unwrap_or_default()
+}
+
+fn apply(op: Op) {
+	let current_block = System::block_number();
+
+	// the calls are allowed to fail; the invariants must hold either way
+	let _ = match op {
+		Op::CreateDstHtlc { maker, taker, amount, safety_deposit, secret } => {
+			let immutables = Immutables {
+				order_hash: H256::from_low_u64_be(current_block),
+				hashlock: H256(blake2_256(SECRETS[secret])),
+				hash_function: HashFunction::Blake2_256,
+				maker,
+				taker,
+				amount,
+				safety_deposit,
+				timelocks: timelocks(current_block),
+			};
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables,
+				current_block + 400,
+				GENERIC_CHAIN,
+			)
+		},
+		Op::CreateSwapIntent { maker, amount, secret, nonce } => HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			SwapIntent {
+				hashlock: H256(blake2_256(SECRETS[secret])),
+				maker,
+				src_amount: amount,
+				dst_amount: amount * 2,
+				dst_address: DstAddress::Evm(H160::repeat_byte(maker as u8)),
+				dst_chain: GENERIC_CHAIN,
+				timeout_after_block: Some(current_block + 500),
+				nonce,
+				auction: Default::default(),
+				min_fill_amount: 0,
+				flags: Default::default(),
+				epoch: 0,
+				denied_resolvers: Default::default(),
+				dst_decimals: None,
+			},
+		),
+		Op::CancelSwapIntent { maker, nonce } =>
+			HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), nonce),
+		Op::CreateSrcHtlc { taker, maker, nonce, safety_deposit } => HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(taker),
+			maker,
+			nonce,
+			timelocks(current_block),
+			safety_deposit,
+		),
+		Op::Withdraw { htlc, valid_secret } => match pick_htlc(htlc) {
+			Some(immutables) => {
+				let secret = if valid_secret {
+					secret_of(immutables.hashlock)
+				} else {
+					b"wrong secret".to_vec()
+				};
+				HtlcEscrow::withdraw(RuntimeOrigin::signed(immutables.taker), immutables, secret)
+			},
+			None => Ok(()),
+		},
+		Op::PublicWithdraw { htlc, caller } => match pick_htlc(htlc) {
+			Some(immutables) => {
+				let secret = secret_of(immutables.hashlock);
+				HtlcEscrow::public_withdraw(RuntimeOrigin::signed(caller), immutables, secret)
+			},
+			None => Ok(()),
+		},
+		Op::Cancel { htlc, caller } => match pick_htlc(htlc) {
+			Some(immutables) => HtlcEscrow::cancel(RuntimeOrigin::signed(caller), immutables),
+			None => Ok(()),
+		},
+		Op::PublicCancel { htlc, caller } => match pick_htlc(htlc) {
+			Some(immutables) =>
+				HtlcEscrow::public_cancel(RuntimeOrigin::signed(caller), immutables),
+			None => Ok(()),
+		},
+		Op::AgreeToCancel { htlc, caller } => match pick_htlc(htlc) {
+			Some(immutables) =>
+				HtlcEscrow::agree_to_cancel(RuntimeOrigin::signed(caller), immutables),
+			None => Ok(()),
+		},
+		Op::AdvanceBlocks(blocks) => {
+			System::set_block_number(current_block + blocks);
+			Ok(())
+		},
+	};
+}
+
+/// The amounts that the pallet should hold for each account and reason,
+/// derived from the stored HTLCs and intents.
+fn expected_holds() -> BTreeMap<(u64, u8), u128> {
+	let mut holds = BTreeMap::new();
+
+	for htlc in Htlcs::<Test>::iter_values().filter(|htlc| htlc.status == HtlcStatus::Active) {
+		let taker = htlc.immutables.taker;
+		if htlc.htlc_type == HtlcType::Destination {
+			*holds.entry((taker, HoldReason::SwapAmount as u8)).or_default() +=
+				htlc.immutables.amount;
+		}
+		*holds.entry((taker, HoldReason::SafetyDeposit as u8)).or_default() +=
+			htlc.immutables.safety_deposit;
+	}
+
+	for stored_intent in SwapIntents::<Test>::iter_values() {
+		if matches!(stored_intent.status, IntentStatus::Active | IntentStatus::InProgress { .. }) {
+			*holds
+				.entry((stored_intent.intent.maker, HoldReason::MakerSwapIntentAmount as u8))
+				.or_default() += stored_intent.intent.src_amount;
+		}
+	}
+
+	holds
+}
+
+fn check_invariants(
+	htlc_statuses: &mut BTreeMap<H256, HtlcStatus>,
+	intent_statuses: &mut BTreeMap<H256, IntentStatus<u64>>,
+) {
+	// no hold leakage: every held amount is backed by an escrow or intent
+	let holds = expected_holds();
+	for who in ACCOUNTS {
+		for reason in
+			[HoldReason::SwapAmount, HoldReason::SafetyDeposit, HoldReason::MakerSwapIntentAmount]
+		{
+			assert_eq!(
+				Balances::balance_on_hold(&reason.into(), &who),
+				holds.get(&(who, reason as u8)).copied().unwrap_or_default(),
+				"unexpected {reason:?} hold for account {who}",
+			);
+		}
+	}
+
+	// the pallet only moves funds around, slashing them to the treasury; it
+	// never mints or burns them
+	let total: u128 = ACCOUNTS.iter().chain([&TREASURY]).map(Balances::total_balance).sum();
+	assert_eq!(total, 3_000_000);
+
+	// HTLC statuses are monotonic: an HTLC only leaves the active status once
+	for (htlc_id, htlc) in Htlcs::<Test>::iter() {
+		if let Some(previous) = htlc_statuses.insert(htlc_id, htlc.status.clone()) {
+			assert!(
+				previous == htlc.status || previous == HtlcStatus::Active,
+				"HTLC status moved from {previous:?} to {:?}",
+				htlc.status,
+			);
+		}
+	}
+
+	// intent statuses are monotonic: terminal statuses never change
+	for (intent_key, stored_intent) in SwapIntents::<Test>::iter() {
+		if let Some(previous) = intent_statuses.insert(intent_key, stored_intent.status.clone()) {
+			let allowed = match previous {
+				IntentStatus::Active => true,
+				IntentStatus::InProgress { .. } => stored_intent.status != IntentStatus::Active,
+				_ => previous == stored_intent.status,
+			};
+			assert!(allowed, "intent status moved from {previous:?} to {:?}", stored_intent.status);
+		}
+	}
+
+	// no double settlement: each HTLC is withdrawn or cancelled at most once
+	let mut settlements = BTreeMap::<H256, u32>::new();
+	for record in System::events() {
+		match record.event {
+			RuntimeEvent::HtlcEscrow(Event::HtlcWithdrawn { htlc_id, .. }) |
+			RuntimeEvent::HtlcEscrow(Event::HtlcCancelled { htlc_id, .. }) => {
+				*settlements.entry(htlc_id).or_default() += 1;
+			},
+			_ => {},
+		}
+	}
+	assert!(settlements.values().all(|count| *count == 1), "HTLC settled twice");
+
+	// the indexes are consistent with the stored HTLCs and intents
+	assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+}
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(64))]
+
+	#[test]
+	fn random_operations_preserve_invariants(ops in prop::collection::vec(op(), 1..60)) {
+		new_test_ext().execute_with(|| {
+			System::set_block_number(1);
+
+			// the makers also post a safety deposit
+			MakerDeposit::set(10);
+
+			let mut htlc_statuses = BTreeMap::new();
+			let mut intent_statuses = BTreeMap::new();
+
+			for op in ops {
+				apply(op);
+				check_invariants(&mut htlc_statuses, &mut intent_statuses);
+			}
+		});
+	}
+}
@@ -0,0 +1,160 @@
+//! Quote book of the swap intents. Resolvers post what they offer to pay on
+//! the destination chain against an active intent, and the maker reserves the
+//! intent for the resolver of the quote that it accepts, at the quoted price.
+
+use crate::{
+	AcceptedQuotes, BalanceOf, Config, Error, Event, IntentQuotes, IntentStatus, Pallet,
+	SwapIntents,
+};
+use codec::{Decode, Encode};
+use frame_support::{ensure, traits::ConstU32, BoundedVec};
+use frame_system::pallet_prelude::BlockNumberFor;
+use scale_info::TypeInfo;
+use sp_core::H256;
+use sp_runtime::DispatchResult;
+use sp_std::prelude::*;
+
+/// Maximum number of live quotes posted against an intent.
+pub const MAX_QUOTES_PER_INTENT: u32 = 16;
+
+/// Amount that a resolver offers to pay on the destination chain to fill
+/// an intent, until `expires_at`.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct Quote<AccountId, Balance, BlockNumber> {
+	pub resolver: AccountId,
+	pub dst_amount: Balance,
+	pub expires_at: BlockNumber,
+}
+
+pub type QuoteOf<T> =
+	Quote<<T as frame_system::Config>::AccountId, BalanceOf<T>, BlockNumberFor<T>>;
+
+pub type QuoteBook<T> = BoundedVec<QuoteOf<T>, ConstU32<MAX_QUOTES_PER_INTENT>>;
+
+impl<T: Config> Pallet<T> {
+	/// The quotes against the intent that have not expired at `now`,
+	/// best first.
+	pub fn live_quotes(intent_key: H256, now: BlockNumberFor<T>) -> Vec<QuoteOf<T>> {
+		let mut quotes: Vec<_> = IntentQuotes::<T>::get(intent_key)
+			.into_iter()
+			.filter(|quote| quote.expires_at > now)
+			.collect();
+		quotes.sort_by_key(|quote| core::cmp::Reverse(quote.dst_amount));
+		quotes
+	}
+
+	/// Post the quote of `who` against the active intent of `maker`,
+	/// replacing its previous quote and making room in a full book.
+	pub(crate) fn do_post_quote(
+		who: T::AccountId,
+		maker: T::AccountId,
+		nonce: u64,
+		dst_amount: BalanceOf<T>,
+		expires_at: BlockNumberFor<T>,
+	) -> DispatchResult {
+		let intent_key = Self::intent_key(&maker, nonce);
+		let stored_intent =
+			SwapIntents::<T>::get(intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+		ensure!(stored_intent.status == IntentStatus::Active, Error::<T>::IntentNotActive);
+		Self::ensure_intent_epoch(&stored_intent.intent)?;
+		Self::ensure_compliant(&who)?;
+		Self::ensure_resolver_allowed(&stored_intent.intent, &who)?;
+
+		let current_block = frame_system::Pallet::<T>::block_number();
+		ensure!(
+			dst_amount >= stored_intent.intent.dst_amount &&
+				expires_at > current_block &&
+				!stored_intent.intent.is_expired_at(expires_at),
+			Error::<T>::InvalidQuote
+		);
+
+		let mut quotes = IntentQuotes::<T>::get(intent_key);
+		quotes.retain(|quote| quote.resolver != who && quote.expires_at > current_block);
+		let quote = Quote { resolver: who.clone(), dst_amount, expires_at };
+		if let Err(quote) = quotes.try_push(quote) {
+			let (lowest, _) = quotes
+				.iter()
+				.enumerate()
+				.min_by_key(|(_, quote)| quote.dst_amount)
+				.ok_or(Error::<T>::QuoteBookFull)?;
+			ensure!(quotes[lowest].dst_amount < dst_amount, Error::<T>::QuoteBookFull);
+			let dropped = core::mem::replace(&mut quotes[lowest], quote);
+			Self::deposit_event(Event::QuoteWithdrawn { intent_key, resolver: dropped.resolver });
+		}
+		IntentQuotes::<T>::insert(intent_key, quotes);
+
+		Self::deposit_event(Event::QuotePosted {
+			intent_key,
+			resolver: who,
+			dst_amount,
+			expires_at,
+		});
+
+		Ok(())
+	}
+
+	/// Withdraw the quote of `who` against the intent of `maker`.
+	pub(crate) fn do_withdraw_quote(
+		who: T::AccountId,
+		maker: T::AccountId,
+		nonce: u64,
+	) -> DispatchResult {
+		let intent_key = Self::intent_key(&maker, nonce);
+		IntentQuotes::<T>::try_mutate_exists(intent_key, |quotes| -> DispatchResult {
+			let book = quotes.as_mut().ok_or(Error::<T>::QuoteNotFound)?;
+			let position = book
+				.iter()
+				.position(|quote| quote.resolver == who)
+				.ok_or(Error::<T>::QuoteNotFound)?;
+			book.remove(position);
+			if book.is_empty() {
+				*quotes = None;
+			}
+			Ok(())
+		})?;
+
+		Self::deposit_event(Event::QuoteWithdrawn { intent_key, resolver: who });
+
+		Ok(())
+	}
+
+	/// Reserve the active intent of `who` for `resolver` at the price of its
+	/// live quote, clearing the quote book.
+	pub(crate) fn do_accept_quote(
+		who: T::AccountId,
+		nonce: u64,
+		resolver: T::AccountId,
+	) -> DispatchResult {
+		let intent_key = Self::intent_key(&who, nonce);
+		let mut stored_intent =
+			SwapIntents::<T>::get(intent_key).ok_or(Error::<T>::IntentDoesNotExists)?;
+		ensure!(stored_intent.status == IntentStatus::Active, Error::<T>::IntentNotActive);
+		Self::ensure_intent_epoch(&stored_intent.intent)?;
+		Self::ensure_compliant(&resolver)?;
+		Self::ensure_resolver_allowed(&stored_intent.intent, &resolver)?;
+
+		let current_block = frame_system::Pallet::<T>::block_number();
+		ensure!(!stored_intent.intent.is_expired_at(current_block), Error::<T>::IntentExpired);
+		let quote = Self::live_quotes(intent_key, current_block)
+			.into_iter()
+			.find(|quote| quote.resolver == resolver)
+			.ok_or(Error::<T>::QuoteNotFound)?;
+
+		let status = IntentStatus::Reserved { resolver: resolver.clone() };
+		Self::move_intent_hold(&stored_intent.status, &status)?;
+		stored_intent.status = status;
+		stored_intent.taken_at = Some(current_block);
+		SwapIntents::<T>::insert(intent_key, &stored_intent);
+		IntentQuotes::<T>::remove(intent_key);
+		AcceptedQuotes::<T>::insert(intent_key, &quote);
+		Self::track_order(intent_key);
+
+		Self::deposit_event(Event::QuoteAccepted {
+			intent_key,
+			resolver,
+			dst_amount: quote.dst_amount,
+		});
+
+		Ok(())
+	}
+}
@@ -0,0 +1,66 @@
+//! Receipts of the settled swaps, e.g. NFTs minted to the beneficiaries for
+//! their accounting, airdrops or proof-of-usage programs.
+
+use crate::ChainId;
+use codec::{Decode, Encode};
+use core::marker::PhantomData;
+use frame_support::{
+	storage::with_storage_layer,
+	traits::{tokens::nonfungibles_v2::Mutate, Get},
+};
+use scale_info::TypeInfo;
+use sp_core::H256;
+use sp_runtime::DispatchError;
+
+/// Attribute of the minted items that holds the encoded `SwapReceipt`.
+pub const RECEIPT_ATTRIBUTE: &[u8] = b"receipt";
+
+/// Receipt of an HTLC that settled to its beneficiary.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug)]
+pub struct SwapReceipt<AccountId, Balance> {
+	pub htlc_id: H256,
+	pub beneficiary: AccountId,
+	/// Amount paid to the beneficiary on this chain.
+	pub amount: Balance,
+	/// Amount that the maker asked for on the counterpart chain, if the HTLC
+	/// filled one of its intents.
+	pub counterpart_amount: Option<Balance>,
+	pub counterpart_chain: ChainId,
+}
+
+/// Issuer of the receipts of the settled swaps.
+pub trait IssueReceipt<AccountId, Balance> {
+	/// Issue `receipt` to its beneficiary. The settlement goes ahead even if
+	/// the receipt cannot be issued.
+	fn issue(receipt: &SwapReceipt<AccountId, Balance>);
+}
+
+/// Issue no receipts.
+impl<AccountId, Balance> IssueReceipt<AccountId, Balance> for () {
+	fn issue(_: &SwapReceipt<AccountId, Balance>) {}
+}
+
+/// Mint the receipts as items of the `Collection` of the `nonfungibles`
+/// implementation `N`, identified by the HTLC ids and with the receipt in
+/// their `RECEIPT_ATTRIBUTE`.
+pub struct MintReceipts<N, Collection, ItemConfig = ()>(PhantomData<(N, Collection, ItemConfig)>);
+
+impl<AccountId, Balance, N, Collection, ItemConfig> IssueReceipt<AccountId, Balance>
+	for MintReceipts<N, Collection, ItemConfig>
+where
+	AccountId: Encode,
+	Balance: Encode,
+	N: Mutate<AccountId, ItemConfig>,
+	N::ItemId: From<H256>,
+	Collection: Get<N::CollectionId>,
+	ItemConfig: Default,
+{
+	fn issue(receipt: &SwapReceipt<AccountId, Balance>) {
+		let collection = Collection::get();
+		let item = receipt.htlc_id.into();
+		let _ = with_storage_layer::<_, DispatchError, _>(|| {
+			N::mint_into(&collection, &item, &receipt.beneficiary, &ItemConfig::default(), false)?;
+			N::set_attribute(&collection, &item, RECEIPT_ATTRIBUTE, &receipt.encode())
+		});
+	}
+}
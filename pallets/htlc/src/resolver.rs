@@ -0,0 +1,93 @@
+//! Reference resolver that fills the swap intents from the off-chain worker
+//! when the pallet is built with the `auto-resolver` feature. Its settings are
+//! kept in the persistent off-chain local storage of the node, and the runtime
+//! signs its calls with the keys of the resolver.
+
+use crate::{BalanceOf, Call, ChainId, Config, Pallet, Timelocks};
+use codec::{Decode, Encode};
+use frame_system::pallet_prelude::BlockNumberFor;
+use sp_runtime::traits::{AtLeast32BitUnsigned, Saturating};
+use sp_std::prelude::*;
+
+/// Key of the settings in the persistent off-chain local storage.
+pub const SETTINGS_KEY: &[u8] = b"pallet-htlc::auto-resolver";
+
+/// Settings of the auto-resolver.
+#[derive(Encode, Decode, Eq, PartialEq, Clone, Debug)]
+pub struct ResolverSettings<Balance, BlockNumber> {
+	/// Chains of the destination legs that the resolver serves.
+	pub dst_chains: Vec<ChainId>,
+	/// Largest amount that the resolver pays on the destination chain.
+	pub max_dst_amount: Balance,
+	/// Lowest price `(src, dst)` of the intents that the resolver fills, i.e.
+	/// it receives at least `src` for each `dst` that it pays.
+	pub min_price: (Balance, Balance),
+	/// Safety deposit of the source HTLCs.
+	pub safety_deposit: Balance,
+	/// Number of blocks from the creation of a source HTLC to its withdrawal,
+	/// public withdrawal and cancellation.
+	pub timelocks: (BlockNumber, BlockNumber, BlockNumber),
+	/// Maximum number of intents filled per block.
+	pub max_fills_per_block: u32,
+}
+
+impl<Balance: AtLeast32BitUnsigned + Copy, BlockNumber> ResolverSettings<Balance, BlockNumber> {
+	/// Whether the resolver fills an intent to `dst_chain` that pays
+	/// `src_amount` for `dst_amount`.
+	pub fn accepts(&self, dst_chain: ChainId, src_amount: Balance, dst_amount: Balance) -> bool {
+		let (price_src, price_dst) = self.min_price;
+		self.dst_chains.contains(&dst_chain) &&
+			dst_amount <= self.max_dst_amount &&
+			src_amount.saturating_mul(price_dst) >= dst_amount.saturating_mul(price_src)
+	}
+}
+
+/// Submits the calls of the auto-resolver as signed transactions of its
+/// account.
+pub trait SubmitFill<T: Config> {
+	/// Submit `call` from the off-chain worker.
+	fn submit(call: Call<T>) -> Result<(), &'static str>;
+}
+
+/// The calls that fill the intents accepted by `settings` at block `now`.
+pub fn select_fills<T: Config>(
+	settings: &ResolverSettings<BalanceOf<T>, BlockNumberFor<T>>,
+	now: BlockNumberFor<T>,
+) -> Vec<Call<T>> {
+	let (withdrawal, public_withdrawal, cancellation) = settings.timelocks;
+	Pallet::<T>::active_intents(None, u32::MAX)
+		.into_iter()
+		.filter(|(_, intent)| {
+			settings.accepts(intent.dst_chain, intent.src_amount, intent.dst_amount_at(now))
+		})
+		.take(settings.max_fills_per_block as usize)
+		.map(|(_, intent)| Call::create_src_htlc {
+			maker: intent.maker,
+			nonce: intent.nonce,
+			timelocks: Timelocks {
+				deployed_at: now,
+				withdrawal_after: now.saturating_add(withdrawal),
+				public_withdrawal_after: now.saturating_add(public_withdrawal),
+				cancellation_after: now.saturating_add(cancellation),
+			},
+			safety_deposit: settings.safety_deposit,
+		})
+		.collect()
+}
+
+/// Fill the accepted intents, if the resolver is configured on this node.
+#[cfg(feature = "auto-resolver")]
+pub fn run<T: Config>(now: BlockNumberFor<T>) {
+	use sp_runtime::offchain::storage::StorageValueRef;
+
+	let Ok(Some(settings)) = StorageValueRef::persistent(SETTINGS_KEY)
+		.get::<ResolverSettings<BalanceOf<T>, BlockNumberFor<T>>>()
+	else {
+		return;
+	};
+	for call in select_fills::<T>(&settings, now) {
+		if T::AutoResolver::submit(call).is_err() {
+			break;
+		}
+	}
+}
@@ -0,0 +1,80 @@
+//! Solidity-style interface of the escrows for the runtimes with
+//! `pallet-revive`, so that contracts ported from the 1inch ecosystem can
+//! drive them with minimal changes. The runtime exposes it as a precompile
+//! that decodes the calldata with `decode_call` and dispatches the call with
+//! the account of the calling contract as the signed origin. The escrows are
+//! addressed by their HTLC id, the hash of their immutables, instead of the
+//! address of an escrow contract.
+
+use crate::{Call, Config};
+use sp_core::H256;
+use sp_io::hashing::keccak_256;
+use sp_std::prelude::*;
+
+/// `withdraw_by_id` with a 32-byte secret.
+pub const WITHDRAW: &[u8] = b"withdraw(bytes32,bytes32)";
+/// `public_withdraw_by_id` with a 32-byte secret.
+pub const PUBLIC_WITHDRAW: &[u8] = b"publicWithdraw(bytes32,bytes32)";
+/// `cancel_by_id`.
+pub const CANCEL: &[u8] = b"cancel(bytes32)";
+/// `agree_to_cancel_by_id`.
+pub const AGREE_TO_CANCEL: &[u8] = b"agreeToCancel(bytes32)";
+/// `public_cancel_by_id`.
+pub const PUBLIC_CANCEL: &[u8] = b"publicCancel(bytes32)";
+
+/// Why the calldata does not map to a call of the pallet.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum AbiError {
+	/// The selector matches none of the functions of the interface.
+	UnknownSelector,
+	/// The arguments are not the ABI-encoded words that the function takes.
+	InvalidArguments,
+}
+
+/// The selector of the function with the canonical `signature`, i.e. the
+/// first four bytes of its Keccak-256 hash.
+pub fn selector(signature: &[u8]) -> [u8; 4] {
+	let hash = keccak_256(signature);
+	[hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// The calldata that calls the function with `signature` with the 32-byte
+/// `words` as its arguments.
+pub fn encode_call(signature: &[u8], words: &[H256]) -> Vec<u8> {
+	let mut input = Vec::with_capacity(4 + 32 * words.len());
+	input.extend_from_slice(&selector(signature));
+	words.iter().for_each(|word| input.extend_from_slice(word.as_bytes()));
+	input
+}
+
+/// The call of the pallet that the calldata `input` maps to.
+pub fn decode_call<T: Config>(input: &[u8]) -> Result<Call<T>, AbiError> {
+	if input.len() < 4 {
+		return Err(AbiError::UnknownSelector);
+	}
+	let (function, arguments) = input.split_at(4);
+	let words = |count: usize| -> Result<Vec<H256>, AbiError> {
+		if arguments.len() != 32 * count {
+			return Err(AbiError::InvalidArguments);
+		}
+		Ok(arguments.chunks_exact(32).map(H256::from_slice).collect())
+	};
+
+	let call = if function == selector(WITHDRAW) {
+		let words = words(2)?;
+		Call::withdraw_by_id { htlc_id: words[0], secret: words[1].as_bytes().to_vec() }
+	} else if function == selector(PUBLIC_WITHDRAW) {
+		let words = words(2)?;
+		Call::public_withdraw_by_id { htlc_id: words[0], secret: words[1].as_bytes().to_vec() }
+	} else if function == selector(CANCEL) {
+		Call::cancel_by_id { htlc_id: words(1)?[0] }
+	} else if function == selector(AGREE_TO_CANCEL) {
+		Call::agree_to_cancel_by_id { htlc_id: words(1)?[0] }
+	} else if function == selector(PUBLIC_CANCEL) {
+		Call::public_cancel_by_id { htlc_id: words(1)?[0] }
+	} else {
+		return Err(AbiError::UnknownSelector);
+	};
+
+	Ok(call)
+}
@@ -1,9 +1,17 @@
-use crate::{mock::*, *};
+use crate::{
+	delegation::{DelegatedStake, Delegation},
+	mock::*,
+	vault::{ResolverCredit, VaultState},
+	*,
+};
 use frame_support::{
 	assert_noop, assert_ok,
-	traits::{fungible::InspectHold, Get},
+	traits::{
+		fungible::{Inspect, InspectHold, Mutate},
+		Get, Hooks, LockableCurrency, WithdrawReasons,
+	},
 };
-use sp_core::{blake2_256, H160, H256};
+use sp_core::{blake2_256, sha2_256, H160, H256};
 
 const ALICE: u64 = 1;
 const RESOLVER_BOB: u64 = 2;
@@ -43,13 +51,22 @@ fn create_test_htlc_immutables(
 ) -> Immutables<u64, u128, u64> {
 	let timelocks = create_timelocks(current_block);
 
-	Immutables { order_hash, hashlock, maker, taker, amount, safety_deposit, timelocks }
+	Immutables {
+		order_hash,
+		hashlock,
+		hash_function: HashFunction::Blake2_256,
+		maker,
+		taker,
+		amount,
+		safety_deposit,
+		timelocks,
+	}
 }
 
-fn get_h160_addr(address: u64) -> H160 {
+fn get_evm_dst_address(address: u64) -> DstAddress {
 	let mut addr_bytes = [0u8; 20];
 	addr_bytes[12..20].copy_from_slice(&address.to_be_bytes());
-	H160::from(addr_bytes)
+	DstAddress::Evm(H160::from(addr_bytes))
 }
 
 fn create_swap_intent(
@@ -57,11 +74,58 @@ fn create_swap_intent(
 	maker: u64,
 	src_amount: u128,
 	dst_amount: u128,
-	dst_address: H160,
+	dst_address: DstAddress,
 	timeout_after_block: u64,
 	nonce: u64,
 ) -> SwapIntent<u64, u128, u64> {
-	SwapIntent { hashlock, maker, src_amount, dst_amount, dst_address, timeout_after_block, nonce }
+	SwapIntent {
+		hashlock,
+		maker,
+		src_amount,
+		dst_amount,
+		dst_address,
+		dst_chain: GENERIC_CHAIN,
+		timeout_after_block: Some(timeout_after_block),
+		nonce,
+		auction: Default::default(),
+		min_fill_amount: 0,
+		flags: Default::default(),
+		epoch: 0,
+		denied_resolvers: Default::default(),
+		dst_decimals: None,
+	}
+}
+
+/// Root of the Merkle tree of the secrets of an intent that is filled in
+/// parts, with the proofs of their leaves.
+fn merkle_tree(secrets: &[&[u8]]) -> (H256, Vec<merkle::MerkleProof>) {
+	let hash_function = HashFunction::Blake2_256;
+	let mut level: Vec<(H256, Vec<usize>)> = secrets
+		.iter()
+		.enumerate()
+		.map(|(index, secret)| {
+			(merkle::leaf(hash_function, index as u16, hash_function.hash(secret)), vec![index])
+		})
+		.collect();
+	let mut proofs = vec![Vec::new(); secrets.len()];
+	while level.len() > 1 {
+		let mut next = Vec::new();
+		for pair in level.chunks(2) {
+			match pair {
+				[(left, left_leaves), (right, right_leaves)] => {
+					left_leaves.iter().for_each(|&leaf| proofs[leaf].push(*right));
+					right_leaves.iter().for_each(|&leaf| proofs[leaf].push(*left));
+					next.push((
+						merkle::node(hash_function, *left, *right),
+						[left_leaves.clone(), right_leaves.clone()].concat(),
+					));
+				},
+				_ => next.push(pair[0].clone()),
+			}
+		}
+		level = next;
+	}
+	(level[0].0, proofs.into_iter().map(|proof| proof.try_into().unwrap()).collect())
 }
 
 #[test]
@@ -83,7 +147,7 @@ fn create_htlc_and_reserve_funds() {
 		let order_hash = hash_of_word(b"order hash");
 
 		let current_block = 1u64;
-		let src_cancellation_timestamp = current_block + 400u64;
+		let src_cancellation_block = current_block + 400u64;
 
 		// verify initial balances
 		assert_eq!(Balances::free_balance(&taker), 1000000);
@@ -104,7 +168,8 @@ fn create_htlc_and_reserve_funds() {
 		assert_ok!(HtlcEscrow::create_dst_htlc(
 			RuntimeOrigin::signed(taker),
 			immutables.clone(),
-			src_cancellation_timestamp,
+			src_cancellation_block,
+			GENERIC_CHAIN,
 		));
 
 		// verify reserved funds
@@ -161,7 +226,7 @@ fn withdraw_success_with_valid_secret() {
 		let safety_deposit = SAFETY_DEPOSIT;
 
 		let current_block = 1u64;
-		let src_cancellation_timestamp = current_block + 400u64;
+		let src_cancellation_block = current_block + 400u64;
 
 		let secret = b"tests_secret";
 		let hashlock = hash_of_word(secret);
@@ -188,7 +253,8 @@ fn withdraw_success_with_valid_secret() {
 			HtlcEscrow::create_dst_htlc(
 				RuntimeOrigin::signed(taker),
 				immutables.clone(),
-				src_cancellation_timestamp,
+				src_cancellation_block,
+				GENERIC_CHAIN,
 			),
 			Error::<Test>::HigherSafetyDepositRequired
 		);
@@ -207,7 +273,8 @@ fn withdraw_success_with_valid_secret() {
 		assert_ok!(HtlcEscrow::create_dst_htlc(
 			RuntimeOrigin::signed(taker),
 			immutables.clone(),
-			src_cancellation_timestamp,
+			src_cancellation_block,
+			GENERIC_CHAIN,
 		));
 
 		// verify reserved funds
@@ -296,7 +363,7 @@ fn public_withdraw_success_by_third_party() {
 		let safety_deposit = SAFETY_DEPOSIT;
 
 		let current_block = 1u64;
-		let src_cancellation_timestamp = current_block + 400u64;
+		let src_cancellation_block = current_block + 400u64;
 
 		let secret = b"tests_secret";
 		let hashlock = hash_of_word(secret);
@@ -324,7 +391,8 @@ fn public_withdraw_success_by_third_party() {
 		assert_ok!(HtlcEscrow::create_dst_htlc(
 			RuntimeOrigin::signed(taker),
 			immutables.clone(),
-			src_cancellation_timestamp,
+			src_cancellation_block,
+			GENERIC_CHAIN,
 		));
 
 		// verify reserved funds
@@ -450,7 +518,7 @@ fn create_htlc_and_cancel_it() {
 		let safety_deposit = SAFETY_DEPOSIT;
 
 		let current_block = 1u64;
-		let src_cancellation_timestamp = current_block + 400u64;
+		let src_cancellation_block = current_block + 400u64;
 
 		let secret = b"tests_secret";
 		let hashlock = hash_of_word(secret);
@@ -477,7 +545,8 @@ fn create_htlc_and_cancel_it() {
 		assert_ok!(HtlcEscrow::create_dst_htlc(
 			RuntimeOrigin::signed(taker),
 			immutables.clone(),
-			src_cancellation_timestamp,
+			src_cancellation_block,
+			GENERIC_CHAIN,
 		));
 
 		// verify reserved funds
@@ -571,7 +640,7 @@ fn create_swap_intent_and_cancel_it() {
 
 		let src_amount = SRC_AMOUNT;
 		let dst_amount = DST_AMOUNT;
-		let dst_address = get_h160_addr(ALICE + 1000);
+		let dst_address = get_evm_dst_address(ALICE + 1000);
 		let nonce = 0;
 
 		let current_block = 1u64;
@@ -588,7 +657,7 @@ fn create_swap_intent_and_cancel_it() {
 			maker,
 			src_amount,
 			dst_amount,
-			dst_address,
+			dst_address.clone(),
 			current_block + 1000,
 			nonce,
 		);
@@ -619,12 +688,8 @@ fn create_swap_intent_and_cancel_it() {
 		// verify deposited event
 		System::assert_last_event(
 			Event::SwapIntentCreated {
-				maker,
-				nonce,
-				src_amount,
-				dst_amount,
-				dst_address,
-				hashlock,
+				intent_key: HtlcEscrow::intent_key(&maker, nonce),
+				intent: swap_intent.clone(),
 			}
 			.into(),
 		);
@@ -681,7 +746,7 @@ fn create_swap_intent_then_dst_htlc_then_withdraw() {
 
 		let src_amount = SRC_AMOUNT;
 		let dst_amount = DST_AMOUNT;
-		let dst_address = get_h160_addr(ALICE + 1000);
+		let dst_address = get_evm_dst_address(ALICE + 1000);
 		let nonce = 0;
 
 		let safety_deposit = SAFETY_DEPOSIT;
@@ -700,7 +765,7 @@ fn create_swap_intent_then_dst_htlc_then_withdraw() {
 			maker,
 			src_amount,
 			dst_amount,
-			dst_address,
+			dst_address.clone(),
 			current_block + 1000,
 			nonce,
 		);
@@ -732,12 +797,8 @@ fn create_swap_intent_then_dst_htlc_then_withdraw() {
 		// verify deposited event
 		System::assert_last_event(
 			Event::SwapIntentCreated {
-				maker,
-				nonce,
-				src_amount,
-				dst_amount,
-				dst_address,
-				hashlock,
+				intent_key: HtlcEscrow::intent_key(&maker, nonce),
+				intent: swap_intent.clone(),
 			}
 			.into(),
 		);
@@ -804,6 +865,18 @@ fn create_swap_intent_then_dst_htlc_then_withdraw() {
 			.into(),
 		);
 
+		// the intent is being filled by the taker
+		assert_eq!(
+			SwapIntents::<Test>::get(intent_key).map(|stored| stored.status),
+			Some(IntentStatus::InProgress { resolver: taker, htlc_id })
+		);
+
+		// the maker cannot cancel an intent that was just taken
+		assert_noop!(
+			HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), nonce),
+			Error::<Test>::EarlyIntentCancellation
+		);
+
 		System::set_block_number(immutables.timelocks.withdrawal_after + 1);
 
 		// //
@@ -812,6 +885,12 @@ fn create_swap_intent_then_dst_htlc_then_withdraw() {
 			HtlcEscrow::withdraw(RuntimeOrigin::signed(taker), immutables, secret.to_vec(),)
 		);
 
+		// the filled intent is completed
+		assert_eq!(
+			SwapIntents::<Test>::get(intent_key).map(|stored| stored.status),
+			Some(IntentStatus::Completed)
+		);
+
 		// verify funds have been transfered
 		assert_eq!(Balances::free_balance(&taker), 1000000 + src_amount);
 		assert_eq!(Balances::balance_on_hold(&crate::HoldReason::SafetyDeposit.into(), &taker), 0);
@@ -871,7 +950,7 @@ fn create_swap_intent_then_dst_htlc_then_public_withdraw() {
 
 		let src_amount = SRC_AMOUNT;
 		let dst_amount = DST_AMOUNT;
-		let dst_address = get_h160_addr(ALICE + 1000);
+		let dst_address = get_evm_dst_address(ALICE + 1000);
 		let nonce = 0;
 
 		let safety_deposit = SAFETY_DEPOSIT;
@@ -890,7 +969,7 @@ fn create_swap_intent_then_dst_htlc_then_public_withdraw() {
 			maker,
 			src_amount,
 			dst_amount,
-			dst_address,
+			dst_address.clone(),
 			current_block + 1000,
 			nonce,
 		);
@@ -922,12 +1001,8 @@ fn create_swap_intent_then_dst_htlc_then_public_withdraw() {
 		// verify deposited event
 		System::assert_last_event(
 			Event::SwapIntentCreated {
-				maker,
-				nonce,
-				src_amount,
-				dst_amount,
-				dst_address,
-				hashlock,
+				intent_key: HtlcEscrow::intent_key(&maker, nonce),
+				intent: swap_intent.clone(),
 			}
 			.into(),
 		);
@@ -1077,7 +1152,7 @@ fn create_swap_intent_then_dst_htlc_then_cancelled() {
 
 		let src_amount = SRC_AMOUNT;
 		let dst_amount = DST_AMOUNT;
-		let dst_address = get_h160_addr(ALICE + 1000);
+		let dst_address = get_evm_dst_address(ALICE + 1000);
 		let nonce = 0;
 
 		let safety_deposit = SAFETY_DEPOSIT;
@@ -1096,7 +1171,7 @@ fn create_swap_intent_then_dst_htlc_then_cancelled() {
 			maker,
 			src_amount,
 			dst_amount,
-			dst_address,
+			dst_address.clone(),
 			current_block + 1000,
 			nonce,
 		);
@@ -1128,12 +1203,8 @@ fn create_swap_intent_then_dst_htlc_then_cancelled() {
 		// verify deposited event
 		System::assert_last_event(
 			Event::SwapIntentCreated {
-				maker,
-				nonce,
-				src_amount,
-				dst_amount,
-				dst_address,
-				hashlock,
+				intent_key: HtlcEscrow::intent_key(&maker, nonce),
+				intent: swap_intent.clone(),
 			}
 			.into(),
 		);
@@ -1216,10 +1287,11 @@ fn create_swap_intent_then_dst_htlc_then_cancelled() {
 		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(taker), immutables,));
 
 		// verify funds have been transfered correctly;
-		// the taker losses the safety_deposit which is taken by the third party
-		assert_eq!(Balances::free_balance(&taker), 1000000);
+		// the taker never bound the destination leg, so it loses the
+		// safety_deposit to the maker
+		assert_eq!(Balances::free_balance(taker), 1000000 - safety_deposit);
 		assert_eq!(Balances::balance_on_hold(&crate::HoldReason::SafetyDeposit.into(), &taker), 0);
-		assert_eq!(Balances::free_balance(&maker), 1000000);
+		assert_eq!(Balances::free_balance(maker), 1000000 + safety_deposit);
 		assert_eq!(
 			Balances::balance_on_hold(&crate::HoldReason::MakerSwapIntentAmount.into(), &maker),
 			0
@@ -1249,6 +1321,7589 @@ fn create_swap_intent_then_dst_htlc_then_cancelled() {
 		assert_eq!(stored_htlc.immutables.timelocks, immutables.timelocks);
 
 		// verify deposited event
+		System::assert_has_event(
+			Event::SafetyDepositForfeited { htlc_id, amount: safety_deposit }.into(),
+		);
 		System::assert_last_event(Event::HtlcCancelled { htlc_id, refund_recipient: maker }.into());
 	});
 }
+
+#[test]
+fn compliance_filter_rejects_sanctioned_accounts() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+		let order_hash = hash_of_word(b"order hash");
+
+		let immutables = create_test_htlc_immutables(
+			order_hash,
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+
+		// a sanctioned maker cannot be part of a HTLC or create swap intents
+		Sanctioned::set(vec![maker]);
+
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables.clone(),
+				src_cancellation_block,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::AccountNotCompliant
+		);
+
+		let swap_intent = create_swap_intent(
+			hashlock,
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(ALICE + 1000),
+			current_block + 1000,
+			0,
+		);
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), swap_intent),
+			Error::<Test>::AccountNotCompliant
+		);
+
+		// the HTLC can be created once the maker is no longer sanctioned
+		Sanctioned::set(vec![]);
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			immutables.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+
+		// the taker gets sanctioned before withdrawing
+		Sanctioned::set(vec![taker]);
+		System::set_block_number(immutables.timelocks.withdrawal_after + 1);
+
+		assert_noop!(
+			HtlcEscrow::withdraw(RuntimeOrigin::signed(taker), immutables.clone(), secret.to_vec(),),
+			Error::<Test>::AccountNotCompliant
+		);
+
+		Sanctioned::set(vec![]);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(taker), immutables, secret.to_vec()));
+	});
+}
+
+#[test]
+fn blocked_accounts_cannot_create_or_settle_htlcs() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+		let third_party = RESOLVER_CHARLIE;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+		let order_hash = hash_of_word(b"order hash");
+
+		let immutables = create_test_htlc_immutables(
+			order_hash,
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+
+		// only the governance origin can manage the blocklist
+		assert_noop!(
+			HtlcEscrow::block_account(RuntimeOrigin::signed(maker), taker),
+			sp_runtime::DispatchError::BadOrigin
+		);
+
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), taker));
+		System::assert_last_event(Event::AccountBlocked { who: taker }.into());
+
+		assert_noop!(
+			HtlcEscrow::block_account(RuntimeOrigin::root(), taker),
+			Error::<Test>::AlreadyBlocked
+		);
+
+		// a blocked taker cannot create a HTLC
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables.clone(),
+				src_cancellation_block,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::AccountBlocked
+		);
+
+		assert_ok!(HtlcEscrow::unblock_account(RuntimeOrigin::root(), taker));
+		System::assert_last_event(Event::AccountUnblocked { who: taker }.into());
+
+		assert_noop!(
+			HtlcEscrow::unblock_account(RuntimeOrigin::root(), taker),
+			Error::<Test>::NotBlocked
+		);
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			immutables.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+
+		// a blocked third party cannot settle the HTLC through the public
+		// withdrawal
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), third_party));
+		System::set_block_number(immutables.timelocks.public_withdrawal_after + 1);
+
+		assert_noop!(
+			HtlcEscrow::public_withdraw(
+				RuntimeOrigin::signed(third_party),
+				immutables.clone(),
+				secret.to_vec(),
+			),
+			Error::<Test>::AccountBlocked
+		);
+
+		// a blocked maker does not freeze the refund of its counterparty
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), maker));
+		System::set_block_number(immutables.timelocks.cancellation_after + 1);
+
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(taker), immutables));
+		assert_eq!(Balances::free_balance(taker), 1000000);
+		assert_eq!(Balances::total_balance_on_hold(&taker), 0);
+	});
+}
+
+#[test]
+fn blocked_accounts_cannot_move_funds() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 0));
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), ALICE));
+		let alice = || RuntimeOrigin::signed(ALICE);
+
+		assert_noop!(HtlcEscrow::vault_deposit(alice(), 1_000), Error::<Test>::AccountBlocked);
+		assert_noop!(HtlcEscrow::vault_withdraw(alice(), 1_000), Error::<Test>::AccountBlocked);
+		assert_noop!(HtlcEscrow::repay_vault(alice(), 1_000), Error::<Test>::AccountBlocked);
+		assert_noop!(
+			HtlcEscrow::delegate(alice(), RESOLVER_BOB, 1_000),
+			Error::<Test>::AccountBlocked
+		);
+		assert_noop!(
+			HtlcEscrow::withdraw_undelegated(alice(), RESOLVER_BOB),
+			Error::<Test>::AccountBlocked
+		);
+		assert_noop!(
+			HtlcEscrow::set_sponsor_allowance(alice(), RESOLVER_BOB, 1_000),
+			Error::<Test>::AccountBlocked
+		);
+		assert_noop!(
+			HtlcEscrow::top_up_safety_deposit(alice(), H256::zero(), 1_000),
+			Error::<Test>::AccountBlocked
+		);
+		assert_noop!(HtlcEscrow::register_watchtower(alice()), Error::<Test>::AccountBlocked);
+
+		// once unblocked, the account moves its funds again
+		assert_ok!(HtlcEscrow::unblock_account(RuntimeOrigin::root(), ALICE));
+		assert_ok!(HtlcEscrow::vault_deposit(alice(), 1_000));
+		assert_ok!(HtlcEscrow::delegate(alice(), RESOLVER_BOB, 1_000));
+		assert_ok!(HtlcEscrow::register_watchtower(alice()));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn remote_parties_take_part_through_their_derived_accounts() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// the root origin dispatches as the sibling parachain in the mock
+		let remote = || RuntimeOrigin::root();
+		let taker = SIBLING_ACCOUNT_BASE + u64::from(SIBLING_PARA_ID);
+		assert_eq!(HtlcEscrow::location_account(&RemoteParty::get()), Some(taker));
+
+		// the funds of the remote resolver arrive at its derived account,
+		// without any local signer
+		assert_ok!(Balances::mint_into(&taker, 1_000_000));
+
+		let secret = b"tests_secret";
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order hash"),
+			hash_of_word(secret),
+			ALICE,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(remote(), immutables.clone(), 401, GENERIC_CHAIN));
+		assert_eq!(Balances::total_balance_on_hold(&taker), SWAP_AMOUNT + SAFETY_DEPOSIT);
+
+		System::set_block_number(immutables.timelocks.withdrawal_after + 1);
+		assert_ok!(HtlcEscrow::withdraw(remote(), immutables, secret.to_vec()));
+		assert_eq!(Balances::free_balance(ALICE), 1000000 + SWAP_AMOUNT);
+		assert_eq!(Balances::free_balance(taker), 1_000_000 - SWAP_AMOUNT);
+
+		// locations without an account on this chain cannot take part
+		RemoteParty::set(Location::here());
+		assert_noop!(
+			HtlcEscrow::cancel_all_intents(remote(), 1),
+			Error::<Test>::UnconvertibleLocation
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn metrics_aggregate_active_escrows_and_intents() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+		let order_hash = hash_of_word(b"order hash");
+
+		assert_eq!(HtlcEscrow::metrics(), HtlcMetrics::default());
+
+		let immutables = create_test_htlc_immutables(
+			order_hash,
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			immutables.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"another secret"),
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(ALICE + 1000),
+			current_block + 1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), swap_intent));
+
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"cancelled secret"),
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(ALICE + 1000),
+			current_block + 1000,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), swap_intent));
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), 1));
+
+		System::set_block_number(51);
+
+		assert_eq!(
+			HtlcEscrow::metrics(),
+			HtlcMetrics {
+				active_htlcs: 1,
+				held_swap_amount: SWAP_AMOUNT,
+				held_safety_deposit: SAFETY_DEPOSIT,
+				held_maker_intent_amount: SRC_AMOUNT,
+				active_intents: 1,
+				cancelled_intents: 1,
+				oldest_active_htlc_age: Some(50),
+				..Default::default()
+			}
+		);
+
+		// settled HTLCs are no longer part of the held totals
+		System::set_block_number(immutables.timelocks.withdrawal_after + 1);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(taker), immutables, secret.to_vec()));
+
+		let metrics = HtlcEscrow::metrics();
+		assert_eq!(metrics.active_htlcs, 0);
+		assert_eq!(metrics.held_swap_amount, 0);
+		assert_eq!(metrics.held_safety_deposit, 0);
+		assert_eq!(metrics.oldest_active_htlc_age, None);
+
+		// reserved intents are still held, with the bond of the resolver
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0));
+		let metrics = HtlcEscrow::metrics();
+		assert_eq!(metrics.in_progress_intents, 1);
+		assert_eq!(metrics.held_maker_intent_amount, SRC_AMOUNT);
+		assert_eq!(metrics.held_reservation_bond, 20);
+	});
+}
+
+#[test]
+fn prune_settled_htlc_into_archive() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+		let third_party = RESOLVER_CHARLIE;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+		let order_hash = hash_of_word(b"order hash");
+
+		let immutables = create_test_htlc_immutables(
+			order_hash,
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+
+		assert_noop!(
+			HtlcEscrow::prune_htlc(RuntimeOrigin::signed(third_party), htlc_id),
+			Error::<Test>::HtlcDoesNotExist
+		);
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			immutables.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+
+		// active HTLCs cannot be archived
+		assert_noop!(
+			HtlcEscrow::prune_htlc(RuntimeOrigin::signed(third_party), htlc_id),
+			Error::<Test>::HtlcStillActive
+		);
+
+		System::set_block_number(immutables.timelocks.withdrawal_after + 1);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(taker),
+			immutables.clone(),
+			secret.to_vec()
+		));
+
+		// anyone can archive the settled HTLC
+		System::set_block_number(500);
+		assert_ok!(HtlcEscrow::prune_htlc(RuntimeOrigin::signed(third_party), htlc_id));
+		System::assert_last_event(Event::HtlcArchived { htlc_id }.into());
+
+		assert!(Htlcs::<Test>::get(htlc_id).is_none());
+		assert_eq!(
+			ArchivedHtlcs::<Test>::get(htlc_id),
+			Some(ArchivedHtlc {
+				status: HtlcStatus::Completed,
+				hashlock,
+				amount: SWAP_AMOUNT,
+				archived_at: 500,
+			})
+		);
+
+		// the archived HTLC cannot be created again
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables,
+				src_cancellation_block + 500,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::HtlcAlreadyExists
+		);
+	});
+}
+
+#[test]
+fn revealed_secrets_are_indexed_per_block_and_pruned() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+		let order_hash = hash_of_word(b"order hash");
+
+		let immutables = create_test_htlc_immutables(
+			order_hash,
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			immutables.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+
+		let withdrawal_block = immutables.timelocks.withdrawal_after + 1;
+		System::set_block_number(withdrawal_block);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(taker), immutables, secret.to_vec()));
+
+		assert_eq!(
+			RevealedSecrets::<Test>::get(withdrawal_block).to_vec(),
+			vec![(hashlock, htlc_id)]
+		);
+
+		// the entry is kept for the retention period and then pruned
+		let retention: u64 = <Test as Config>::RevealedSecretsRetention::get();
+		HtlcEscrow::on_initialize(withdrawal_block + retention - 1);
+		assert_eq!(RevealedSecrets::<Test>::get(withdrawal_block).len(), 1);
+
+		HtlcEscrow::on_initialize(withdrawal_block + retention);
+		assert!(RevealedSecrets::<Test>::get(withdrawal_block).is_empty());
+	});
+}
+
+#[test]
+fn hashlock_index_tracks_htlcs_sharing_a_hashlock() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+
+		let first = create_test_htlc_immutables(
+			hash_of_word(b"first order"),
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		let second = create_test_htlc_immutables(
+			hash_of_word(b"second order"),
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		let first_id = HtlcEscrow::hash_immutables(&first);
+		let second_id = HtlcEscrow::hash_immutables(&second);
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			first.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+		assert_eq!(HashlockIndex::<Test>::get(hashlock).to_vec(), vec![first_id]);
+
+		// the escrows that the first order is split into share its hashlock
+		let mut split = first.clone();
+		split.amount = SWAP_AMOUNT / 2;
+		let split_id = HtlcEscrow::hash_immutables(&split);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			split.clone(),
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+		assert_eq!(HashlockIndex::<Test>::get(hashlock).to_vec(), vec![first_id, split_id]);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// but another order cannot reuse it while they are active
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				second.clone(),
+				src_cancellation_block,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::HashlockInUse
+		);
+
+		System::set_block_number(first.timelocks.withdrawal_after + 1);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(taker),
+			first.clone(),
+			secret.to_vec()
+		));
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				second.clone(),
+				src_cancellation_block,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::HashlockInUse
+		);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(taker), split, secret.to_vec()));
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(taker),
+			second,
+			src_cancellation_block,
+			GENERIC_CHAIN,
+		));
+
+		assert_eq!(
+			HashlockIndex::<Test>::get(hashlock).to_vec(),
+			vec![first_id, split_id, second_id]
+		);
+
+		// pruning a settled HTLC removes it from the index
+		assert_ok!(HtlcEscrow::prune_htlc(RuntimeOrigin::signed(taker), first_id));
+
+		assert_eq!(HashlockIndex::<Test>::get(hashlock).to_vec(), vec![split_id, second_id]);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn hashlock_of_active_intent_cannot_be_reused() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// initial setup
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+
+		let current_block = 1u64;
+		let src_cancellation_block = current_block + 400u64;
+
+		let secret = b"tests_secret";
+		let hashlock = hash_of_word(secret);
+		let dst_address = get_evm_dst_address(ALICE + 1000);
+
+		let swap_intent = create_swap_intent(
+			hashlock,
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			dst_address.clone(),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			swap_intent.clone()
+		));
+
+		// a second intent with the same hashlock is rejected
+		let another_intent = create_swap_intent(
+			hashlock,
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			dst_address.clone(),
+			1000,
+			1,
+		);
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), another_intent.clone()),
+			Error::<Test>::HashlockInUse
+		);
+
+		// and so is a destination HTLC
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order hash"),
+			hashlock,
+			maker,
+			taker,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables,
+				src_cancellation_block,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::HashlockInUse
+		);
+
+		// the source HTLC that fills the intent can use its hashlock
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(taker),
+			maker,
+			0,
+			create_timelocks(current_block),
+			SAFETY_DEPOSIT,
+		));
+
+		// once the intent is cancelled its hashlock is released
+		let mut unrelated_intent = another_intent;
+		unrelated_intent.hashlock = hash_of_word(b"unrelated secret");
+		unrelated_intent.nonce = 2;
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), unrelated_intent));
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), 2));
+		assert!(IntentHashlocks::<Test>::get(hash_of_word(b"unrelated secret")).is_none());
+	});
+}
+
+#[test]
+fn try_state_detects_inconsistent_indexes() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order hash"),
+			hash_of_word(b"tests_secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			401,
+			GENERIC_CHAIN,
+		));
+		assert_ok!(HtlcEscrow::do_try_state());
+
+		// an HTLC that is missing from the hashlock index is detected
+		HashlockIndex::<Test>::remove(immutables.hashlock);
+		assert!(HtlcEscrow::do_try_state().is_err());
+	});
+}
+
+#[test]
+fn swap_intents_accept_non_evm_destination_addresses() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let maker = ALICE;
+
+		// a Solana account
+		let solana_address = DstAddress::PublicKey([7u8; 32]);
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"solana secret"),
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			solana_address.clone(),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), swap_intent));
+
+		let intent_key = HtlcEscrow::intent_key(&maker, 0);
+		assert_eq!(
+			SwapIntents::<Test>::get(intent_key).map(|stored| stored.intent.dst_address),
+			Some(solana_address)
+		);
+
+		// a P2WPKH Bitcoin output
+		let bitcoin_address = DstAddress::BitcoinWitness {
+			version: 0,
+			program: vec![1u8; 20].try_into().expect("program fits the bound; qed"),
+		};
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"bitcoin secret"),
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			bitcoin_address,
+			1000,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), swap_intent));
+
+		// a version 0 witness program must be 20 or 32 bytes long
+		let malformed_address = DstAddress::BitcoinWitness {
+			version: 0,
+			program: vec![1u8; 25].try_into().expect("program fits the bound; qed"),
+		};
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"malformed secret"),
+			maker,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			malformed_address,
+			1000,
+			2,
+		);
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), swap_intent),
+			Error::<Test>::InvalidDstAddress
+		);
+	});
+}
+
+#[test]
+fn bitcoin_profile_uses_sha256_hashlocks_and_timelock_margins() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let secret = b"bitcoin preimage".to_vec();
+		let payment_hash = H256(sha2_256(&secret));
+		let current_block = System::block_number();
+		let margin = HtlcEscrow::chain_profile(BITCOIN_CHAIN).unwrap().min_timelock_margin;
+
+		// a destination HTLC for a Bitcoin source must use a sha256 hashlock
+		let blake2_immutables = create_test_htlc_immutables(
+			H256::repeat_byte(1),
+			payment_hash,
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			current_block,
+		);
+		let src_cancellation = blake2_immutables.timelocks.cancellation_after + margin;
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				blake2_immutables.clone(),
+				src_cancellation,
+				BITCOIN_CHAIN,
+			),
+			Error::<Test>::InvalidHashFunction
+		);
+
+		// the Bitcoin source must not be cancellable before the margin elapses
+		let immutables =
+			Immutables { hash_function: HashFunction::Sha256, ..blake2_immutables.clone() };
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				src_cancellation - 1,
+				BITCOIN_CHAIN,
+			),
+			Error::<Test>::InsufficientTimelockMargin
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			src_cancellation,
+			BITCOIN_CHAIN,
+		));
+
+		// the secret revealed on Bitcoin unlocks the destination HTLC
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			secret.clone()
+		));
+
+		// swaps to Bitcoin must pay to a Bitcoin address
+		let mut swap_intent = create_swap_intent(
+			H256(sha2_256(b"another preimage")),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		swap_intent.dst_chain = BITCOIN_CHAIN;
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent.clone()),
+			Error::<Test>::InvalidDstAddress
+		);
+		let recipient = DstAddress::BitcoinScriptHash(H160::repeat_byte(9));
+		swap_intent.dst_address = recipient.clone();
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			swap_intent.clone()
+		));
+
+		// the source HTLC gives the resolver what it needs for the Bitcoin script
+		let current_block = System::block_number();
+		let mut timelocks = create_timelocks(current_block);
+		timelocks.cancellation_after = timelocks.withdrawal_after + margin - 1;
+		timelocks.public_withdrawal_after = timelocks.withdrawal_after;
+		assert_noop!(
+			HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				ALICE,
+				0,
+				timelocks.clone(),
+				SAFETY_DEPOSIT
+			),
+			Error::<Test>::InsufficientTimelockMargin
+		);
+		let timelocks = create_timelocks(current_block);
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			timelocks.clone(),
+			SAFETY_DEPOSIT
+		));
+
+		let intent_key = HtlcEscrow::intent_key(&ALICE, 0);
+		let htlc_id = match SwapIntents::<Test>::get(intent_key).map(|stored| stored.status) {
+			Some(IntentStatus::InProgress { htlc_id, .. }) => htlc_id,
+			status => panic!("unexpected intent status {status:?}"),
+		};
+		assert_eq!(
+			Htlcs::<Test>::get(htlc_id).map(|htlc| htlc.immutables.hash_function),
+			Some(HashFunction::Sha256)
+		);
+		System::assert_last_event(
+			Event::BitcoinCounterpartRequested {
+				htlc_id,
+				payment_hash: swap_intent.hashlock,
+				recipient,
+				amount: DST_AMOUNT,
+				cancellation_after: timelocks.cancellation_after,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn chain_profiles_are_managed_by_governance() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		const ETHEREUM_CHAIN: ChainId = 2;
+		let profile = ChainProfile {
+			hash_function: HashFunction::Blake2_256,
+			address_format: AddressFormat::Evm,
+			min_timelock_margin: 10,
+			decimals: 18,
+		};
+
+		// intents to unknown chains are rejected
+		let mut swap_intent = create_swap_intent(
+			hash_of_word(b"secret"),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		swap_intent.dst_chain = ETHEREUM_CHAIN;
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent.clone()),
+			Error::<Test>::UnknownChain
+		);
+
+		// only governance can register a chain
+		assert_noop!(
+			HtlcEscrow::set_chain_profile(
+				RuntimeOrigin::signed(ALICE),
+				ETHEREUM_CHAIN,
+				profile.clone()
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(HtlcEscrow::set_chain_profile(
+			RuntimeOrigin::root(),
+			ETHEREUM_CHAIN,
+			profile.clone()
+		));
+		System::assert_last_event(
+			Event::ChainProfileSet { chain_id: ETHEREUM_CHAIN, profile: profile.clone() }.into(),
+		);
+
+		// the address must be in the format of the chain
+		let mut non_evm_intent = swap_intent.clone();
+		non_evm_intent.dst_address = DstAddress::PublicKey([7u8; 32]);
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), non_evm_intent),
+			Error::<Test>::InvalidDstAddress
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+
+		// removed chains can no longer be used
+		assert_ok!(HtlcEscrow::remove_chain_profile(RuntimeOrigin::root(), ETHEREUM_CHAIN));
+		System::assert_last_event(Event::ChainProfileRemoved { chain_id: ETHEREUM_CHAIN }.into());
+		assert_noop!(
+			HtlcEscrow::remove_chain_profile(RuntimeOrigin::root(), ETHEREUM_CHAIN),
+			Error::<Test>::UnknownChain
+		);
+		assert_noop!(
+			HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				ALICE,
+				0,
+				create_timelocks(System::block_number()),
+				SAFETY_DEPOSIT
+			),
+			Error::<Test>::UnknownChain
+		);
+	});
+}
+
+#[test]
+fn fusion_order_signature_is_verified_against_htlc_order_hash() {
+	use crate::fusion::{FusionDomain, FusionOrder};
+	use sp_core::{ecdsa, Pair, U256};
+
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		// the account of the secret key 1 on EVM chains
+		let signer = ecdsa::Pair::from_seed(&H256::from_low_u64_be(1).0);
+		let maker = H160([
+			0x7e, 0x5f, 0x45, 0x52, 0x09, 0x1a, 0x69, 0x12, 0x5d, 0x5d, 0xfc, 0xb7, 0xb8, 0xc2,
+			0x65, 0x90, 0x29, 0x39, 0x5b, 0xdf,
+		]);
+
+		let domain =
+			FusionDomain { chain_id: U256::from(1), verifying_contract: H160::repeat_byte(0x11) };
+		let order = FusionOrder {
+			salt: U256::from(42),
+			maker,
+			receiver: H160::zero(),
+			maker_asset: H160::repeat_byte(0xaa),
+			taker_asset: H160::repeat_byte(0xbb),
+			making_amount: U256::from(SWAP_AMOUNT),
+			taking_amount: U256::from(DST_AMOUNT),
+			maker_traits: U256::zero(),
+		};
+		let order_hash = order.hash(&domain);
+		let signature = signer.sign_prehashed(&order_hash.0).0;
+
+		let immutables = create_test_htlc_immutables(
+			order_hash,
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+
+		// the order must be the one that the HTLC was created for
+		let other_order = FusionOrder { salt: U256::from(43), ..order.clone() };
+		assert_noop!(
+			HtlcEscrow::verify_fusion_order(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				htlc_id,
+				Box::new(other_order),
+				domain.clone(),
+				signature
+			),
+			Error::<Test>::OrderHashMismatch
+		);
+
+		// the order must be signed by its maker
+		let other_signer = ecdsa::Pair::from_seed(&H256::from_low_u64_be(2).0);
+		assert_noop!(
+			HtlcEscrow::verify_fusion_order(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				htlc_id,
+				Box::new(order.clone()),
+				domain.clone(),
+				other_signer.sign_prehashed(&order_hash.0).0
+			),
+			Error::<Test>::InvalidOrderSignature
+		);
+
+		// EVM wallets use the 27/28 recovery ids
+		let mut evm_signature = signature;
+		evm_signature[64] += 27;
+		assert_ok!(HtlcEscrow::verify_fusion_order(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			htlc_id,
+			Box::new(order),
+			domain,
+			evm_signature
+		));
+		System::assert_last_event(Event::FusionOrderVerified { htlc_id, order_hash, maker }.into());
+	});
+}
+
+#[test]
+fn source_htlc_is_bound_to_create2_counterpart_escrow() {
+	use crate::fusion::{escrow_address, EvmImmutables};
+	use sp_core::U256;
+
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let hashlock = hash_of_word(b"secret");
+		let swap_intent = create_swap_intent(
+			hashlock,
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			create_timelocks(System::block_number()),
+			SAFETY_DEPOSIT
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hashlock)[0];
+
+		let factory = H160::repeat_byte(0xfa);
+		let implementation = H160::repeat_byte(0x1e);
+		let counterpart = EvmImmutables {
+			order_hash: H256::repeat_byte(1),
+			hashlock,
+			maker: H160::repeat_byte(2),
+			taker: H160::repeat_byte(3),
+			token: H160::repeat_byte(4),
+			amount: U256::from(DST_AMOUNT),
+			safety_deposit: U256::from(SAFETY_DEPOSIT),
+			timelocks: U256::zero(),
+		};
+
+		// only the resolver of the HTLC can bind it
+		assert_noop!(
+			HtlcEscrow::bind_counterpart_escrow(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				htlc_id,
+				factory,
+				implementation,
+				Box::new(counterpart.clone())
+			),
+			Error::<Test>::InvalidCaller
+		);
+
+		// the counterpart must be locked with the same hashlock
+		let other_counterpart =
+			EvmImmutables { hashlock: hash_of_word(b"other"), ..counterpart.clone() };
+		assert_noop!(
+			HtlcEscrow::bind_counterpart_escrow(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				htlc_id,
+				factory,
+				implementation,
+				Box::new(other_counterpart.clone())
+			),
+			Error::<Test>::CounterpartMismatch
+		);
+
+		assert_ok!(HtlcEscrow::bind_counterpart_escrow(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			factory,
+			implementation,
+			Box::new(counterpart.clone())
+		));
+		let escrow = escrow_address(&factory, &implementation, &counterpart);
+		assert_eq!(CounterpartEscrows::<Test>::get(htlc_id), Some(escrow));
+		System::assert_last_event(Event::CounterpartEscrowBound { htlc_id, escrow }.into());
+
+		// the address depends on the immutables of the counterpart
+		assert_ne!(escrow, escrow_address(&factory, &implementation, &other_counterpart));
+
+		assert_noop!(
+			HtlcEscrow::bind_counterpart_escrow(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				htlc_id,
+				factory,
+				implementation,
+				Box::new(counterpart)
+			),
+			Error::<Test>::CounterpartAlreadyBound
+		);
+	});
+}
+
+#[test]
+fn maker_safety_deposit_is_forfeited_when_source_htlc_expires() {
+	use crate::fusion::EvmImmutables;
+	use sp_core::U256;
+
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let maker_deposit = 30;
+		MakerDeposit::set(maker_deposit);
+		let reason = HoldReason::MakerSafetyDeposit.into();
+
+		// the deposit is held with the intent and released on cancellation
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"cancelled"),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), maker_deposit);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 0);
+
+		// the maker gets the deposit back once the swap completes
+		let secret = b"completed".to_vec();
+		let swap_intent = create_swap_intent(
+			hash_of_word(&secret),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		let timelocks = create_timelocks(System::block_number());
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			1,
+			timelocks.clone(),
+			SAFETY_DEPOSIT
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hash_of_word(&secret))[0];
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+		System::set_block_number(timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), immutables, secret));
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 0);
+
+		// the resolver gets the deposit if the maker never reveals the secret
+		// once the destination leg is bound
+		let hashlock = hash_of_word(b"expired");
+		let swap_intent = create_swap_intent(
+			hashlock,
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			2,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		let timelocks = create_timelocks(System::block_number());
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			ALICE,
+			2,
+			timelocks.clone(),
+			SAFETY_DEPOSIT
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hashlock)[0];
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+		assert_ok!(HtlcEscrow::bind_counterpart_escrow(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			htlc_id,
+			H160::repeat_byte(0xfa),
+			H160::repeat_byte(0x1e),
+			Box::new(EvmImmutables {
+				order_hash: H256::repeat_byte(1),
+				hashlock,
+				maker: H160::repeat_byte(2),
+				taker: H160::repeat_byte(3),
+				token: H160::repeat_byte(4),
+				amount: U256::from(DST_AMOUNT),
+				safety_deposit: U256::from(SAFETY_DEPOSIT),
+				timelocks: U256::zero(),
+			})
+		));
+
+		let maker_balance = Balances::free_balance(ALICE);
+		let resolver_balance = Balances::free_balance(RESOLVER_CHARLIE);
+		System::set_block_number(timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), immutables));
+
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 0);
+		assert_eq!(Balances::free_balance(ALICE), maker_balance + SRC_AMOUNT);
+		assert_eq!(
+			Balances::free_balance(RESOLVER_CHARLIE),
+			resolver_balance + SAFETY_DEPOSIT + maker_deposit
+		);
+		System::assert_has_event(
+			Event::MakerDepositForfeited {
+				intent_key: HtlcEscrow::intent_key(&ALICE, 2),
+				maker: ALICE,
+				resolver: RESOLVER_CHARLIE,
+				amount: maker_deposit,
+			}
+			.into(),
+		);
+	});
+}
+
+#[test]
+fn public_cancel_rewards_the_caller() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			H256::repeat_byte(1),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		let taker_balance = Balances::free_balance(RESOLVER_BOB);
+		let caller_balance = Balances::free_balance(RESOLVER_CHARLIE);
+
+		// the taker has the first chance to cancel
+		let delay: u64 = <Test as Config>::PublicCancellationDelay::get();
+		System::set_block_number(immutables.timelocks.cancellation_after + delay - 1);
+		assert_noop!(
+			HtlcEscrow::public_cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), immutables.clone()),
+			Error::<Test>::EarlyPublicCancellation
+		);
+
+		System::set_block_number(immutables.timelocks.cancellation_after + delay);
+		assert_noop!(
+			HtlcEscrow::public_cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables.clone()),
+			Error::<Test>::InvalidCaller
+		);
+		assert_ok!(HtlcEscrow::public_cancel(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			immutables.clone()
+		));
+
+		// the caller gets its share of the safety deposit and the rest of it
+		// is slashed to the treasury
+		let reward = SAFETY_DEPOSIT / 2;
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), caller_balance + reward);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), taker_balance + SWAP_AMOUNT);
+		assert_eq!(Balances::free_balance(TREASURY), SAFETY_DEPOSIT - reward);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert_eq!(
+			Htlcs::<Test>::get(htlc_id).map(|htlc| htlc.status),
+			Some(HtlcStatus::Cancelled)
+		);
+		System::assert_has_event(
+			Event::HtlcCancelled { htlc_id, refund_recipient: RESOLVER_BOB }.into(),
+		);
+		System::assert_last_event(
+			Event::PublicCancellationRewarded { htlc_id, caller: RESOLVER_CHARLIE, reward }.into(),
+		);
+	});
+}
+
+#[test]
+fn maker_and_taker_can_agree_to_cancel_early() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let hashlock = hash_of_word(b"secret");
+		let swap_intent = create_swap_intent(
+			hashlock,
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			create_timelocks(System::block_number()),
+			SAFETY_DEPOSIT
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hashlock)[0];
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+
+		// only the parties of the swap can agree
+		assert_noop!(
+			HtlcEscrow::agree_to_cancel(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				immutables.clone()
+			),
+			Error::<Test>::InvalidCaller
+		);
+
+		// one agreement is not enough
+		assert_ok!(HtlcEscrow::agree_to_cancel(RuntimeOrigin::signed(ALICE), immutables.clone()));
+		System::assert_last_event(Event::EarlyCancellationAgreed { htlc_id, who: ALICE }.into());
+		assert_eq!(Htlcs::<Test>::get(htlc_id).map(|htlc| htlc.status), Some(HtlcStatus::Active));
+		assert_noop!(
+			HtlcEscrow::agree_to_cancel(RuntimeOrigin::signed(ALICE), immutables.clone()),
+			Error::<Test>::AlreadyAgreed
+		);
+
+		// both parties are refunded as soon as the other party agrees
+		assert_ok!(HtlcEscrow::agree_to_cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		System::assert_last_event(Event::HtlcCancelled { htlc_id, refund_recipient: ALICE }.into());
+		assert_eq!(
+			Htlcs::<Test>::get(htlc_id).map(|htlc| htlc.status),
+			Some(HtlcStatus::Cancelled)
+		);
+		assert_eq!(CancelAgreements::<Test>::get(htlc_id), None);
+		assert_eq!(
+			SwapIntents::<Test>::get(HtlcEscrow::intent_key(&ALICE, 0)).map(|stored| stored.status),
+			Some(IntentStatus::Cancelled)
+		);
+		assert_eq!(Balances::total_balance_on_hold(&ALICE), 0);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+	});
+}
+
+#[test]
+fn governance_sweeps_dormant_htlcs_to_depositors() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			H256::repeat_byte(1),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+
+		let period: u64 = <Test as Config>::EmergencySweepPeriod::get();
+		System::set_block_number(immutables.timelocks.deployed_at + period - 1);
+		assert_noop!(
+			HtlcEscrow::sweep_htlc(RuntimeOrigin::signed(ALICE), htlc_id),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			HtlcEscrow::sweep_htlc(RuntimeOrigin::root(), htlc_id),
+			Error::<Test>::EarlySweep
+		);
+
+		System::set_block_number(immutables.timelocks.deployed_at + period);
+		assert_ok!(HtlcEscrow::sweep_htlc(RuntimeOrigin::root(), htlc_id));
+		System::assert_has_event(
+			Event::HtlcCancelled { htlc_id, refund_recipient: RESOLVER_BOB }.into(),
+		);
+		System::assert_last_event(Event::HtlcSwept { htlc_id }.into());
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert_eq!(
+			Htlcs::<Test>::get(htlc_id).map(|htlc| htlc.status),
+			Some(HtlcStatus::Cancelled)
+		);
+
+		assert_noop!(
+			HtlcEscrow::sweep_htlc(RuntimeOrigin::root(), htlc_id),
+			Error::<Test>::HtlcNotActive
+		);
+	});
+}
+
+#[test]
+fn overdue_htlcs_are_cancelled_automatically() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let htlc_ids: Vec<_> = [&b"first"[..], b"second", b"third"]
+			.into_iter()
+			.enumerate()
+			.map(|(i, word)| {
+				let immutables = create_test_htlc_immutables(
+					H256::repeat_byte(i as u8),
+					hash_of_word(word),
+					ALICE,
+					RESOLVER_BOB,
+					SWAP_AMOUNT,
+					SAFETY_DEPOSIT,
+					System::block_number(),
+				);
+				assert_ok!(HtlcEscrow::create_dst_htlc(
+					RuntimeOrigin::signed(RESOLVER_BOB),
+					immutables.clone(),
+					immutables.timelocks.cancellation_after + 100,
+					GENERIC_CHAIN,
+				));
+				HtlcEscrow::hash_immutables(&immutables)
+			})
+			.collect();
+		let cancellation_block = create_timelocks(1).cancellation_after;
+		assert_eq!(HtlcsByCancellationBlock::<Test>::get(cancellation_block), htlc_ids);
+
+		let status = |htlc_id| Htlcs::<Test>::get(htlc_id).map(|htlc| htlc.status);
+		let cancelled = || {
+			htlc_ids
+				.iter()
+				.filter(|htlc_id| status(**htlc_id) == Some(HtlcStatus::Cancelled))
+				.count()
+		};
+
+		// nothing is overdue yet
+		HtlcEscrow::on_initialize(cancellation_block - 1);
+		assert_eq!(cancelled(), 0);
+
+		// a blocked maker does not hold up the refunds
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), ALICE));
+
+		// at most `MaxAutoCancelsPerBlock` HTLCs are cancelled per block
+		System::set_block_number(cancellation_block);
+		HtlcEscrow::on_initialize(cancellation_block);
+		assert_eq!(cancelled(), 2);
+		System::assert_has_event(
+			Event::HtlcCancelled { htlc_id: htlc_ids[2], refund_recipient: RESOLVER_BOB }.into(),
+		);
+
+		// the rest of the queue is processed in the next block
+		System::set_block_number(cancellation_block + 1);
+		HtlcEscrow::on_initialize(cancellation_block + 1);
+		assert_eq!(cancelled(), 3);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert!(HtlcsByCancellationBlock::<Test>::get(cancellation_block).is_empty());
+		assert_eq!(AutoCancelCursor::<Test>::get(), Some(cancellation_block + 2));
+	});
+}
+
+#[test]
+fn cancellation_queue_is_bounded_at_creation() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8], cancellation_after: u64| {
+			let mut immutables = create_test_htlc_immutables(
+				H256::repeat_byte(1),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				System::block_number(),
+			);
+			immutables.timelocks.cancellation_after = cancellation_after;
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables,
+				cancellation_after + 100,
+				GENERIC_CHAIN,
+			)
+		};
+
+		// the cancellation must be within the horizon
+		let horizon: u64 = <Test as Config>::MaxTimelockHorizon::get();
+		assert_noop!(create(b"far", 1 + horizon + 1), Error::<Test>::TimelockTooFar);
+		assert_ok!(create(b"far", 1 + horizon));
+
+		// no more than `MaxCancellationsPerBlock` HTLCs can expire at a block
+		let capacity: u32 = <Test as Config>::MaxCancellationsPerBlock::get();
+		for i in 0..capacity {
+			assert_ok!(create(&i.to_le_bytes(), 500));
+		}
+		assert_noop!(create(b"one too many", 500), Error::<Test>::CancellationQueueFull);
+		assert_ok!(create(b"one too many", 501));
+	});
+}
+
+#[test]
+fn timed_out_intents_expire_within_idle_weight() {
+	use crate::weights::WeightInfo;
+	use frame_support::weights::Weight;
+
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		for nonce in 0..2u64 {
+			let swap_intent = create_swap_intent(
+				hash_of_word(&nonce.to_le_bytes()),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(1),
+				10,
+				nonce,
+			);
+			assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		}
+		let expired = || {
+			SwapIntents::<Test>::iter_values()
+				.filter(|stored| stored.status == IntentStatus::Expired)
+				.count()
+		};
+
+		// nothing is done without enough weight
+		assert_eq!(HtlcEscrow::on_idle(11, Weight::zero()), Weight::zero());
+
+		// the intents have not timed out yet
+		HtlcEscrow::on_idle(10, Weight::MAX);
+		assert_eq!(expired(), 0);
+		assert_eq!(IntentExpiryCursor::<Test>::get(), None);
+
+		// each call visits as many intents as the weight allows
+		System::set_block_number(11);
+		let one_intent = <() as WeightInfo>::on_idle_base() + <() as WeightInfo>::expire_intent();
+		assert_eq!(HtlcEscrow::on_idle(11, one_intent), one_intent);
+		assert_eq!(expired(), 1);
+		assert!(IntentExpiryCursor::<Test>::get().is_some());
+
+		HtlcEscrow::on_idle(11, one_intent * 2);
+		assert_eq!(expired(), 2);
+		assert_eq!(Balances::total_balance_on_hold(&ALICE), 0);
+		System::assert_has_event(Event::SwapIntentExpired { maker: ALICE, nonce: 0 }.into());
+	});
+}
+
+#[test]
+fn sub_account_escrow_keeps_funds_off_the_depositors() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		Escrow::set(EscrowMode::SubAccounts);
+
+		// the destination HTLC moves the funds of the resolver to its own account
+		let secret = b"sub account".to_vec();
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order hash"),
+			hash_of_word(&secret),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			401,
+			GENERIC_CHAIN
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hash_of_word(&secret))[0];
+		let escrow = HtlcEscrow::sub_account(htlc_id);
+		assert_eq!(Balances::free_balance(escrow), SWAP_AMOUNT + SAFETY_DEPOSIT);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - SWAP_AMOUNT - SAFETY_DEPOSIT);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+
+		// the withdrawal pays out of the escrow account and closes it
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), immutables, secret));
+		assert_eq!(Balances::total_balance(&escrow), 0);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - SWAP_AMOUNT);
+
+		// the amount of an intent is kept on the account of the intent
+		let hashlock = hash_of_word(b"expired");
+		let swap_intent = create_swap_intent(
+			hashlock,
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		let intent_account = HtlcEscrow::sub_account(HtlcEscrow::intent_key(&ALICE, 0));
+		assert_eq!(Balances::free_balance(intent_account), SRC_AMOUNT);
+		assert_eq!(Balances::total_balance_on_hold(&ALICE), 0);
+
+		let timelocks = create_timelocks(System::block_number());
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			ALICE,
+			0,
+			timelocks.clone(),
+			SAFETY_DEPOSIT
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hashlock)[0];
+		let escrow = HtlcEscrow::sub_account(htlc_id);
+		assert_eq!(Balances::free_balance(escrow), SAFETY_DEPOSIT);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_CHARLIE), 0);
+
+		// the cancellation closes both escrows, and the taker that abandoned
+		// the swap forfeits its safety deposit to the maker
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+		System::set_block_number(timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), immutables));
+		assert_eq!(Balances::total_balance(&escrow), 0);
+		assert_eq!(Balances::total_balance(&intent_account), 0);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT + SAFETY_DEPOSIT);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 - SAFETY_DEPOSIT);
+	});
+}
+
+#[test]
+fn pallet_account_escrow_pools_the_funds() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		Escrow::set(EscrowMode::PalletAccount);
+		let pool = HtlcEscrow::account_id();
+
+		// both HTLCs move the funds of their resolvers into the pool
+		let mut htlcs = Vec::new();
+		for (taker, word) in [(RESOLVER_BOB, b"first"), (RESOLVER_CHARLIE, b"other")] {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				taker,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables.clone(),
+				401,
+				GENERIC_CHAIN
+			));
+			assert_eq!(Balances::total_balance_on_hold(&taker), 0);
+			htlcs.push((immutables, word.to_vec()));
+		}
+		assert_eq!(Balances::free_balance(pool), 2 * (SWAP_AMOUNT + SAFETY_DEPOSIT));
+
+		// the settlements pay out of the pool until it is empty
+		let (immutables, secret) = htlcs.remove(0);
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), immutables, secret));
+		assert_eq!(Balances::free_balance(pool), SWAP_AMOUNT + SAFETY_DEPOSIT);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT);
+
+		let (immutables, _) = htlcs.remove(0);
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), immutables));
+		assert_eq!(Balances::total_balance(&pool), 0);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000);
+	});
+}
+
+#[test]
+fn committed_htlcs_are_settled_with_their_immutables() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		CommitmentOnly::set(true);
+
+		// only the commitments to the HTLCs are stored
+		let mut htlcs = Vec::new();
+		for word in [b"first", b"other"] {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				401,
+				GENERIC_CHAIN
+			));
+			let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+			assert!(!Htlcs::<Test>::contains_key(htlc_id));
+			assert_eq!(
+				HtlcCommitments::<Test>::get(htlc_id),
+				Some(HtlcCommitment {
+					status: HtlcStatus::Active,
+					counterpart_chain: GENERIC_CHAIN
+				})
+			);
+			assert_noop!(
+				HtlcEscrow::create_dst_htlc(
+					RuntimeOrigin::signed(RESOLVER_BOB),
+					immutables.clone(),
+					401,
+					GENERIC_CHAIN
+				),
+				Error::<Test>::HtlcAlreadyExists
+			);
+			htlcs.push((htlc_id, immutables, word.to_vec()));
+		}
+		assert_eq!(
+			Balances::total_balance_on_hold(&RESOLVER_BOB),
+			2 * (SWAP_AMOUNT + SAFETY_DEPOSIT)
+		);
+		assert!(HashlockIndex::<Test>::iter().next().is_none());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// the immutables are checked against the commitment
+		let (htlc_id, immutables, secret) = htlcs.remove(0);
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_noop!(
+			HtlcEscrow::withdraw(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				Immutables { amount: SWAP_AMOUNT * 2, ..immutables.clone() },
+				secret.clone()
+			),
+			Error::<Test>::HtlcDoesNotExist
+		);
+
+		// and are not stored to settle the HTLC by its id
+		assert_noop!(
+			HtlcEscrow::withdraw_by_id(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				htlc_id,
+				secret.clone()
+			),
+			Error::<Test>::HtlcDoesNotExist
+		);
+
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			secret.clone()
+		));
+		assert_eq!(HtlcCommitments::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Completed);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT);
+		assert_noop!(
+			HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), immutables, secret),
+			Error::<Test>::HtlcNotActive
+		);
+
+		// the other one is cancelled by its taker, as nothing cancels it
+		// automatically
+		let (htlc_id, immutables, _) = htlcs.remove(0);
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		HtlcEscrow::on_initialize(immutables.timelocks.cancellation_after);
+		assert_eq!(HtlcCommitments::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Active);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		assert_eq!(HtlcCommitments::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - SWAP_AMOUNT);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn can_create_htlc_reports_why_funds_cannot_be_escrowed() {
+	new_test_ext().execute_with(|| {
+		assert_eq!(HtlcEscrow::can_create_htlc(&RESOLVER_BOB, SWAP_AMOUNT, SAFETY_DEPOSIT), Ok(()));
+
+		let minimum = <Test as Config>::MinSafetyDeposit::get();
+		assert_eq!(
+			HtlcEscrow::can_create_htlc(&RESOLVER_BOB, SWAP_AMOUNT, minimum - 1),
+			Err(CannotCreateHtlc::SafetyDepositTooLow { minimum })
+		);
+		assert_eq!(
+			HtlcEscrow::can_create_htlc(&RESOLVER_BOB, 1_000_000, SAFETY_DEPOSIT),
+			Err(CannotCreateHtlc::InsufficientBalance {
+				required: 1_000_000 + SAFETY_DEPOSIT,
+				balance: 1_000_000
+			})
+		);
+		assert_eq!(
+			HtlcEscrow::can_create_htlc(&RESOLVER_BOB, 1_000_000 - SAFETY_DEPOSIT, SAFETY_DEPOSIT),
+			Err(CannotCreateHtlc::ExistentialDeposit { required: 1_000_000, available: 999_999 })
+		);
+
+		// holds may use the staked funds, transfers to escrow accounts may not
+		Balances::set_lock(*b"staking ", &RESOLVER_BOB, 900_000, WithdrawReasons::all());
+		assert_eq!(HtlcEscrow::can_create_htlc(&RESOLVER_BOB, 200_000, SAFETY_DEPOSIT), Ok(()));
+		Escrow::set(EscrowMode::SubAccounts);
+		assert_eq!(
+			HtlcEscrow::can_create_htlc(&RESOLVER_BOB, 200_000, SAFETY_DEPOSIT),
+			Err(CannotCreateHtlc::Frozen {
+				required: 200_000 + SAFETY_DEPOSIT,
+				available: 100_000
+			})
+		);
+
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), RESOLVER_BOB));
+		assert_eq!(
+			HtlcEscrow::can_create_htlc(&RESOLVER_BOB, SWAP_AMOUNT, SAFETY_DEPOSIT),
+			Err(CannotCreateHtlc::Blocked)
+		);
+	});
+}
+
+#[test]
+fn validation_views_return_the_errors_of_the_settlement_calls() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let secret = b"secret".to_vec();
+		let immutables = create_test_htlc_immutables(
+			H256::repeat_byte(1),
+			hash_of_word(&secret),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_eq!(
+			HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_BOB, &secret),
+			Err(Error::<Test>::HtlcDoesNotExist.into())
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+
+		// the taker is checked against the private calls, anyone else
+		// against the public ones
+		assert_eq!(
+			HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_BOB, b"wrong"),
+			Err(Error::<Test>::InvalidSecret.into())
+		);
+		assert_eq!(
+			HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_BOB, &secret),
+			Err(Error::<Test>::EarlyWithdrawal.into())
+		);
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_BOB, &secret));
+		assert_eq!(
+			HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_CHARLIE, &secret),
+			Err(Error::<Test>::EarlyPublicWithdrawal.into())
+		);
+		System::set_block_number(immutables.timelocks.public_withdrawal_after);
+		assert_ok!(HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_CHARLIE, &secret));
+
+		assert_eq!(
+			HtlcEscrow::validate_cancel(htlc_id, &RESOLVER_BOB),
+			Err(Error::<Test>::EarlyCancellation.into())
+		);
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		assert_eq!(
+			HtlcEscrow::validate_withdraw(htlc_id, &RESOLVER_BOB, &secret),
+			Err(Error::<Test>::LateWithdrawal.into())
+		);
+		assert_ok!(HtlcEscrow::validate_cancel(htlc_id, &RESOLVER_BOB));
+		assert_eq!(
+			HtlcEscrow::validate_cancel(htlc_id, &RESOLVER_CHARLIE),
+			Err(Error::<Test>::EarlyPublicCancellation.into())
+		);
+
+		// the views agree with the calls
+		assert_noop!(
+			HtlcEscrow::public_cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), immutables.clone()),
+			Error::<Test>::EarlyPublicCancellation
+		);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		assert_eq!(
+			HtlcEscrow::validate_cancel(htlc_id, &RESOLVER_BOB),
+			Err(Error::<Test>::HtlcNotActive.into())
+		);
+	});
+}
+
+#[test]
+fn stages_follow_the_timelocks() {
+	new_test_ext().execute_with(|| {
+		let timelocks = create_timelocks(1);
+		let delay: u64 = <Test as Config>::PublicCancellationDelay::get();
+		let stage_at = |now| HtlcEscrow::current_stage(&timelocks, now);
+
+		assert_eq!(stage_at(1), Stage::FinalityLock);
+		assert_eq!(stage_at(timelocks.withdrawal_after - 1), Stage::FinalityLock);
+		assert_eq!(stage_at(timelocks.withdrawal_after), Stage::PrivateWithdrawal);
+		assert_eq!(stage_at(timelocks.public_withdrawal_after), Stage::PublicWithdrawal);
+		assert_eq!(stage_at(timelocks.cancellation_after), Stage::Cancellation);
+		assert_eq!(stage_at(timelocks.cancellation_after + delay - 1), Stage::Cancellation);
+		assert_eq!(stage_at(timelocks.cancellation_after + delay), Stage::PublicCancellation);
+
+		// only the active HTLCs have a stage
+		System::set_block_number(1);
+		let immutables = create_test_htlc_immutables(
+			H256::repeat_byte(1),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_eq!(HtlcEscrow::htlc_stage(htlc_id), None);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		assert_eq!(HtlcEscrow::htlc_stage(htlc_id), Some(Stage::FinalityLock));
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		assert_eq!(HtlcEscrow::htlc_stage(htlc_id), Some(Stage::Cancellation));
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		assert_eq!(HtlcEscrow::htlc_stage(htlc_id), None);
+	});
+}
+
+#[test]
+fn stage_events_are_emitted_for_active_htlcs() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			immutables
+		};
+
+		// nothing is queued unless the events are enabled
+		create(b"quiet");
+		assert_eq!(StageTransitions::<Test>::iter().count(), 0);
+
+		StageEventsEnabled::set(true);
+		let immutables = create(b"watched");
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		let public_withdrawal_after = immutables.timelocks.public_withdrawal_after;
+		let delay: u64 = <Test as Config>::PublicCancellationDelay::get();
+		let public_cancellation_after = immutables.timelocks.cancellation_after + delay;
+		assert_eq!(
+			StageTransitions::<Test>::get(public_cancellation_after).to_vec(),
+			vec![(htlc_id, Stage::PublicCancellation)]
+		);
+
+		System::set_block_number(public_withdrawal_after);
+		HtlcEscrow::on_initialize(public_withdrawal_after);
+		System::assert_last_event(
+			Event::StageEntered { htlc_id, stage: Stage::PublicWithdrawal }.into(),
+		);
+
+		// the settled HTLCs are skipped
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"watched".to_vec()
+		));
+		System::reset_events();
+		System::set_block_number(public_cancellation_after);
+		HtlcEscrow::on_initialize(public_cancellation_after);
+		assert!(System::events().is_empty());
+		assert_eq!(StageTransitions::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn sponsored_safety_deposits_are_posted_and_refunded_by_the_sponsor() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let reason = HoldReason::SafetyDeposit.into();
+		let sponsor = RESOLVER_CHARLIE;
+		assert_ok!(HtlcEscrow::set_sponsor_allowance(
+			RuntimeOrigin::signed(sponsor),
+			RESOLVER_BOB,
+			SAFETY_DEPOSIT * 3 / 2
+		));
+		assert_ok!(HtlcEscrow::set_sponsor(RuntimeOrigin::signed(RESOLVER_BOB), Some(sponsor)));
+		System::assert_last_event(
+			Event::SponsorSet { resolver: RESOLVER_BOB, sponsor: Some(sponsor) }.into(),
+		);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				System::block_number(),
+			);
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			)
+			.map(|_| immutables)
+		};
+
+		// the deposit is held from the sponsor within its allowance
+		let immutables = create(b"first").unwrap();
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		System::assert_has_event(Event::SafetyDepositSponsored { htlc_id, sponsor }.into());
+		assert_eq!(Balances::balance_on_hold(&reason, &sponsor), SAFETY_DEPOSIT);
+		assert_eq!(Balances::balance_on_hold(&reason, &RESOLVER_BOB), 0);
+		assert_eq!(SponsorAllowances::<Test>::get(sponsor, RESOLVER_BOB), SAFETY_DEPOSIT / 2);
+		assert_noop!(create(b"second"), Error::<Test>::SponsorAllowanceExceeded);
+
+		// the refund goes back to the sponsor and restores the allowance
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			b"first".to_vec()
+		));
+		assert_eq!(Balances::balance_on_hold(&reason, &sponsor), 0);
+		assert_eq!(Balances::free_balance(sponsor), 1_000_000);
+		assert_eq!(SponsorAllowances::<Test>::get(sponsor, RESOLVER_BOB), SAFETY_DEPOSIT * 3 / 2);
+		System::assert_last_event(
+			Event::HtlcWithdrawn {
+				htlc_id,
+				secret: b"first".to_vec(),
+				amount: SWAP_AMOUNT,
+				beneficiary: ALICE,
+				safety_deposit_recipient: sponsor,
+			}
+			.into(),
+		);
+
+		// a forfeited deposit is lost by the sponsor
+		let immutables = create(b"third").unwrap();
+		System::set_block_number(immutables.timelocks.public_withdrawal_after);
+		let alice_balance = Balances::free_balance(ALICE);
+		assert_ok!(HtlcEscrow::public_withdraw(
+			RuntimeOrigin::signed(ALICE),
+			immutables,
+			b"third".to_vec()
+		));
+		assert_eq!(Balances::free_balance(ALICE), alice_balance + SWAP_AMOUNT + SAFETY_DEPOSIT);
+		assert_eq!(Balances::free_balance(sponsor), 1_000_000 - SAFETY_DEPOSIT);
+		assert_eq!(SponsorAllowances::<Test>::get(sponsor, RESOLVER_BOB), SAFETY_DEPOSIT / 2);
+		assert_eq!(DepositSponsors::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn resolvers_draw_on_the_vault_and_repay_it_with_a_fee() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let lp = RESOLVER_CHARLIE;
+		let vault = HtlcEscrow::vault_account();
+		assert_ok!(HtlcEscrow::vault_deposit(RuntimeOrigin::signed(lp), 10_000));
+		assert_eq!(VaultShares::<Test>::get(lp), 10_000);
+		assert_eq!(Balances::free_balance(vault), 10_000);
+
+		let create = |word: &[u8], amount| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				amount,
+				SAFETY_DEPOSIT,
+				System::block_number(),
+			);
+			HtlcEscrow::create_vault_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			)
+			.map(|_| immutables)
+		};
+
+		// only the registered resolvers draw, within their credit limits
+		assert_noop!(create(b"first", SWAP_AMOUNT), Error::<Test>::NotResolver);
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 2_500));
+		assert_noop!(create(b"first", 3_000), Error::<Test>::CreditLimitExceeded);
+
+		// the vault funds the swap amount, the resolver the safety deposit
+		let immutables = create(b"first", SWAP_AMOUNT).unwrap();
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		System::assert_has_event(
+			Event::VaultDrawn { htlc_id, resolver: RESOLVER_BOB, amount: SWAP_AMOUNT }.into(),
+		);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - SAFETY_DEPOSIT);
+
+		// the withdrawal leaves the resolver owing the amount and the fee
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"first".to_vec()
+		));
+		let fee = SWAP_AMOUNT / 100;
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT);
+		assert_eq!(
+			ResolverCredits::<Test>::get(RESOLVER_BOB),
+			ResolverCredit { drawn: 0, owed: SWAP_AMOUNT + fee, insurance_owed: 0 }
+		);
+		assert_eq!(Vault::<Test>::get().total_assets, 10_000 + fee);
+
+		// a cancelled escrow returns the funds to the vault
+		let immutables = create(b"second", SWAP_AMOUNT).unwrap();
+		assert_noop!(create(b"third", SWAP_AMOUNT), Error::<Test>::CreditLimitExceeded);
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		assert_eq!(ResolverCredits::<Test>::get(RESOLVER_BOB).drawn, 0);
+		assert_eq!(Vault::<Test>::get().outstanding, SWAP_AMOUNT + fee);
+
+		// once repaid, the fee goes to the liquidity providers
+		assert_ok!(HtlcEscrow::repay_vault(RuntimeOrigin::signed(RESOLVER_BOB), 2 * SWAP_AMOUNT));
+		System::assert_last_event(
+			Event::VaultRepaid { resolver: RESOLVER_BOB, amount: SWAP_AMOUNT + fee }.into(),
+		);
+		assert_ok!(HtlcEscrow::vault_withdraw(RuntimeOrigin::signed(lp), 10_000));
+		assert_eq!(Balances::free_balance(lp), 1_000_000 + fee);
+		assert_eq!(Balances::total_balance(&vault), 0);
+		assert_eq!(Vault::<Test>::get(), VaultState::default());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn the_insurance_fund_takes_a_share_of_the_fees_and_forfeits() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+		InsuranceShare::set(sp_runtime::Perbill::from_percent(50));
+
+		let insurance = HtlcEscrow::insurance_account();
+		assert_ok!(HtlcEscrow::vault_deposit(RuntimeOrigin::signed(RESOLVER_CHARLIE), 10_000));
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 2_500));
+
+		// half of the vault fee is owed to the insurance fund
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"drawn"),
+			hash_of_word(b"drawn"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		assert_ok!(HtlcEscrow::create_vault_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"drawn".to_vec()
+		));
+		let fee = SWAP_AMOUNT / 100;
+		assert_eq!(
+			ResolverCredits::<Test>::get(RESOLVER_BOB),
+			ResolverCredit { drawn: 0, owed: SWAP_AMOUNT + fee, insurance_owed: fee / 2 }
+		);
+		assert_eq!(Vault::<Test>::get().total_assets, 10_000 + fee / 2);
+
+		// the vault is repaid first, the insurance fund last
+		assert_ok!(HtlcEscrow::repay_vault(RuntimeOrigin::signed(RESOLVER_BOB), SWAP_AMOUNT));
+		assert_eq!(Balances::free_balance(insurance), 0);
+		assert_ok!(HtlcEscrow::repay_vault(RuntimeOrigin::signed(RESOLVER_BOB), fee));
+		System::assert_has_event(Event::InsuranceFunded { amount: fee / 2 }.into());
+		assert_eq!(Balances::free_balance(insurance), fee / 2);
+		assert_eq!(Vault::<Test>::get().outstanding, 0);
+
+		// and it takes half of the deposits forfeited by abandoned swaps
+		let nonce = 0;
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"abandoned"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				System::block_number() + 1000,
+				nonce,
+			),
+		));
+		let timelocks = create_timelocks(System::block_number());
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			nonce,
+			timelocks.clone(),
+			SAFETY_DEPOSIT,
+		));
+		let alice = Balances::free_balance(ALICE);
+		let immutables = create_test_htlc_immutables(
+			HtlcEscrow::intent_key(&ALICE, nonce),
+			hash_of_word(b"abandoned"),
+			ALICE,
+			RESOLVER_BOB,
+			SRC_AMOUNT,
+			SAFETY_DEPOSIT,
+			timelocks.deployed_at,
+		);
+		System::set_block_number(immutables.timelocks.cancellation_after + 1);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		System::assert_has_event(Event::InsuranceFunded { amount: SAFETY_DEPOSIT / 2 }.into());
+		assert_eq!(Balances::free_balance(insurance), fee / 2 + SAFETY_DEPOSIT / 2);
+		assert_eq!(Balances::free_balance(ALICE), alice + SRC_AMOUNT + SAFETY_DEPOSIT / 2);
+
+		// only governance pays out of the fund
+		assert_noop!(
+			HtlcEscrow::pay_insurance(RuntimeOrigin::signed(ALICE), ALICE, SAFETY_DEPOSIT / 2),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(HtlcEscrow::pay_insurance(RuntimeOrigin::root(), ALICE, SAFETY_DEPOSIT / 2));
+		System::assert_last_event(
+			Event::InsurancePaidOut { who: ALICE, amount: SAFETY_DEPOSIT / 2 }.into(),
+		);
+		assert_eq!(Balances::free_balance(insurance), fee / 2);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn vault_withdrawals_wait_for_the_drawn_funds() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		assert_ok!(HtlcEscrow::vault_deposit(RuntimeOrigin::signed(RESOLVER_CHARLIE), 10_000));
+		assert_ok!(HtlcEscrow::vault_deposit(RuntimeOrigin::signed(ALICE), 5_000));
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 20_000));
+
+		let create = |word: &[u8], amount| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				amount,
+				SAFETY_DEPOSIT,
+				System::block_number(),
+			);
+			HtlcEscrow::create_vault_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			)
+			.map(|_| immutables)
+		};
+
+		// the resolvers draw up to the utilization limit
+		assert_noop!(create(b"first", 12_001), Error::<Test>::VaultUtilizationExceeded);
+		let immutables = create(b"first", 10_000).unwrap();
+
+		// the withdrawals beyond the funds that are not drawn are queued, and
+		// the later ones wait behind them
+		assert_ok!(HtlcEscrow::vault_withdraw(RuntimeOrigin::signed(RESOLVER_CHARLIE), 10_000));
+		System::assert_last_event(
+			Event::VaultWithdrawalQueued { who: RESOLVER_CHARLIE, shares: 10_000 }.into(),
+		);
+		assert_ok!(HtlcEscrow::vault_withdraw(RuntimeOrigin::signed(ALICE), 1_000));
+		assert_noop!(
+			HtlcEscrow::vault_withdraw(RuntimeOrigin::signed(ALICE), 1_000),
+			Error::<Test>::VaultWithdrawalQueueFull
+		);
+		assert_eq!(VaultShares::<Test>::get(RESOLVER_CHARLIE), 0);
+		assert_eq!(Vault::<Test>::get().queued_shares, 11_000);
+
+		// the queued withdrawals are paid before the funds are drawn again
+		assert_noop!(create(b"second", 1_000), Error::<Test>::VaultIlliquid);
+
+		// the funds that come back redeem the queue in order
+		let alice_balance = Balances::free_balance(ALICE);
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		System::assert_has_event(
+			Event::VaultWithdrawn { who: RESOLVER_CHARLIE, amount: 10_000, shares: 10_000 }.into(),
+		);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000);
+		assert_eq!(Balances::free_balance(ALICE), alice_balance + 1_000);
+		assert!(VaultWithdrawalQueue::<Test>::get().is_empty());
+		assert_eq!(
+			Vault::<Test>::get(),
+			VaultState {
+				total_shares: 4_000,
+				total_assets: 4_000,
+				outstanding: 0,
+				queued_shares: 0
+			}
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn takers_top_up_the_safety_deposit_of_active_htlcs() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let reason = HoldReason::SafetyDeposit.into();
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				System::block_number(),
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			immutables
+		};
+
+		// only the taker tops up the deposit of an active HTLC
+		let immutables = create(b"first");
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_noop!(
+			HtlcEscrow::top_up_safety_deposit(RuntimeOrigin::signed(ALICE), htlc_id, 50),
+			Error::<Test>::InvalidCaller
+		);
+		assert_noop!(
+			HtlcEscrow::top_up_safety_deposit(RuntimeOrigin::signed(RESOLVER_BOB), htlc_id, 0),
+			Error::<Test>::InvalidTopUp
+		);
+		assert_ok!(HtlcEscrow::top_up_safety_deposit(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			50
+		));
+		System::assert_last_event(
+			Event::SafetyDepositToppedUp {
+				htlc_id,
+				amount: 50,
+				safety_deposit: SAFETY_DEPOSIT + 50,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::balance_on_hold(&reason, &RESOLVER_BOB), SAFETY_DEPOSIT + 50);
+		assert_eq!(HtlcEscrow::metrics().held_safety_deposit, SAFETY_DEPOSIT + 50);
+
+		// the immutables still settle the HTLC and the whole deposit is refunded
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"first".to_vec()
+		));
+		assert_eq!(Balances::balance_on_hold(&reason, &RESOLVER_BOB), 0);
+		assert!(!SafetyDepositTopUps::<Test>::contains_key(htlc_id));
+		assert_noop!(
+			HtlcEscrow::top_up_safety_deposit(RuntimeOrigin::signed(RESOLVER_BOB), htlc_id, 50),
+			Error::<Test>::HtlcNotActive
+		);
+
+		// a forfeited deposit includes the top-up
+		let immutables = create(b"second");
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_ok!(HtlcEscrow::top_up_safety_deposit(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			50
+		));
+		System::set_block_number(immutables.timelocks.public_withdrawal_after);
+		let alice_balance = Balances::free_balance(ALICE);
+		assert_ok!(HtlcEscrow::public_withdraw(
+			RuntimeOrigin::signed(ALICE),
+			immutables,
+			b"second".to_vec()
+		));
+		assert_eq!(
+			Balances::free_balance(ALICE),
+			alice_balance + SWAP_AMOUNT + SAFETY_DEPOSIT + 50
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn makers_cancel_taken_intents_after_the_delay() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+		let nonce = 0;
+		let hashlock = hash_of_word(b"secret");
+		let dst_address = get_evm_dst_address(ALICE + 1000);
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			create_swap_intent(
+				hashlock,
+				maker,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				dst_address.clone(),
+				1_000,
+				nonce
+			),
+		));
+		let intent_key = HtlcEscrow::intent_key(&maker, nonce);
+		let maker_balance = Balances::free_balance(maker) + SRC_AMOUNT + MakerDeposit::get();
+
+		System::set_block_number(2);
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(taker),
+			maker,
+			nonce,
+			create_timelocks(2),
+			SAFETY_DEPOSIT,
+		));
+		assert_eq!(SwapIntents::<Test>::get(intent_key).unwrap().taken_at, Some(2));
+
+		// the timelocks of the source HTLC do not shorten the delay
+		System::set_block_number(2 + 350);
+		assert_noop!(
+			HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), nonce),
+			Error::<Test>::EarlyIntentCancellation
+		);
+
+		// the source HTLC is cancelled along with the intent, and neither
+		// party loses its deposit
+		System::set_block_number(2 + 400);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), nonce));
+		System::assert_last_event(
+			Event::SwapIntentCancelled {
+				maker,
+				nonce,
+				src_amount: SRC_AMOUNT,
+				dst_amount: DST_AMOUNT,
+				dst_address,
+				hashlock,
+			}
+			.into(),
+		);
+		assert_eq!(
+			SwapIntents::<Test>::get(intent_key).map(|stored| stored.status),
+			Some(IntentStatus::Cancelled)
+		);
+		assert_eq!(Balances::free_balance(maker), maker_balance);
+		assert_eq!(Balances::free_balance(taker), 1_000_000);
+		assert_noop!(
+			HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), nonce),
+			Error::<Test>::IntentNotActive
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_reserve_intents_with_a_bond() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let reason = HoldReason::ReservationBond.into();
+		let create = |word: &[u8], nonce| {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(maker),
+				create_swap_intent(
+					hash_of_word(word),
+					maker,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce
+				),
+			));
+			HtlcEscrow::intent_key(&maker, nonce)
+		};
+
+		// the reservation keeps other resolvers away
+		let intent_key = create(b"first", 0);
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0));
+		System::assert_last_event(
+			Event::IntentTaken { maker, nonce: 0, resolver: RESOLVER_CHARLIE, bond: 20 }.into(),
+		);
+		assert_eq!(Balances::balance_on_hold(&reason, &RESOLVER_CHARLIE), 20);
+		assert_noop!(
+			HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), maker, 0),
+			Error::<Test>::IntentNotActive
+		);
+		assert_noop!(
+			HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				maker,
+				0,
+				create_timelocks(1),
+				SAFETY_DEPOSIT,
+			),
+			Error::<Test>::IntentReserved
+		);
+
+		// the bond is returned once the resolver fills the intent
+		System::set_block_number(2);
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			maker,
+			0,
+			create_timelocks(2),
+			SAFETY_DEPOSIT,
+		));
+		let stored_intent = SwapIntents::<Test>::get(intent_key).unwrap();
+		assert_eq!(stored_intent.taken_at, Some(1));
+		let IntentStatus::InProgress { htlc_id, .. } = stored_intent.status else {
+			panic!("intent is not in progress");
+		};
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			immutables,
+			b"first".to_vec()
+		));
+		assert_eq!(Balances::balance_on_hold(&reason, &RESOLVER_CHARLIE), 0);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 + SRC_AMOUNT);
+
+		// the maker gets the bond of a reservation that was not filled
+		let block = System::block_number();
+		let intent_key = create(b"second", 1);
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 1));
+		assert_noop!(
+			HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), 1),
+			Error::<Test>::EarlyIntentCancellation
+		);
+		let maker_balance = Balances::free_balance(maker);
+		System::set_block_number(block + 400);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), 1));
+		System::assert_has_event(
+			Event::ReservationBondForfeited {
+				intent_key,
+				resolver: RESOLVER_CHARLIE,
+				maker,
+				amount: 20,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::free_balance(maker), maker_balance + SRC_AMOUNT + 20);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 + SRC_AMOUNT - 20);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn registered_resolvers_take_over_stalled_fills() {
+	new_test_ext().execute_with(|| {
+		// track events
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let reason = HoldReason::ReservationBond.into();
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			create_swap_intent(
+				hash_of_word(b"secret"),
+				maker,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				0
+			),
+		));
+		let intent_key = HtlcEscrow::intent_key(&maker, 0);
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), maker, 0));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			maker,
+			0,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let Some(IntentStatus::InProgress { htlc_id, .. }) =
+			SwapIntents::<Test>::get(intent_key).map(|stored| stored.status)
+		else {
+			panic!("intent is not in progress");
+		};
+
+		// only registered resolvers take over the fills that stalled
+		assert_noop!(
+			HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0),
+			Error::<Test>::NotResolver
+		);
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_CHARLIE, 0));
+		assert_noop!(
+			HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0),
+			Error::<Test>::FillNotStalled
+		);
+
+		// the source HTLC of the fill must be cancellable
+		System::set_block_number(1 + 100);
+		assert_noop!(
+			HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0),
+			Error::<Test>::EarlyCancellation
+		);
+
+		System::set_block_number(1 + 300);
+		assert_ok!(HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0));
+		System::assert_last_event(
+			Event::IntentTakenOver {
+				maker,
+				nonce: 0,
+				resolver: RESOLVER_CHARLIE,
+				previous: RESOLVER_BOB,
+				forfeited: 20,
+			}
+			.into(),
+		);
+		assert_eq!(Htlcs::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - 20);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000);
+		assert_eq!(Balances::balance_on_hold(&reason, &RESOLVER_CHARLIE), 20);
+		assert_eq!(
+			Balances::balance_on_hold(&HoldReason::MakerSwapIntentAmount.into(), &maker),
+			SRC_AMOUNT
+		);
+
+		// the new resolver fills the intent
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			maker,
+			0,
+			create_timelocks(1 + 300),
+			SAFETY_DEPOSIT,
+		));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn forfeit_handlers_route_the_deposits() {
+	use crate::forfeit::{
+		Forfeit, ForfeitReason, ForfeitTo, ForfeitToCaller, ForfeitToMaker, OnForfeit,
+		SplitForfeits,
+	};
+	use frame_support::traits::{fungible::Balanced, tokens::imbalance::ResolveTo};
+
+	new_test_ext().execute_with(|| {
+		type Handler = SplitForfeits<
+			ForfeitToCaller<Balances, ForfeitTo<ResolveTo<Treasury, Balances>>>,
+			ForfeitToMaker<Balances>,
+		>;
+		let forfeit = |reason, caller| Forfeit {
+			reason,
+			htlc_id: H256::zero(),
+			maker: ALICE,
+			taker: RESOLVER_BOB,
+			caller,
+		};
+
+		Handler::on_forfeit(
+			&forfeit(ForfeitReason::PublicCancellation, Some(RESOLVER_CHARLIE)),
+			Balances::issue(10),
+		);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 + 10);
+
+		// without a caller the deposit is left to the fallback
+		Handler::on_forfeit(&forfeit(ForfeitReason::PublicCancellation, None), Balances::issue(20));
+		assert_eq!(Balances::free_balance(TREASURY), 20);
+
+		Handler::on_forfeit(
+			&forfeit(ForfeitReason::Abandonment, Some(RESOLVER_CHARLIE)),
+			Balances::issue(30),
+		);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + 30);
+
+		// the unit handler burns the deposit
+		let issuance = Balances::total_issuance();
+		<() as OnForfeit<u64, _>>::on_forfeit(
+			&forfeit(ForfeitReason::Abandonment, None),
+			Balances::issue(40),
+		);
+		assert_eq!(Balances::total_issuance(), issuance);
+	});
+}
+
+#[test]
+fn active_intents_are_listed_in_pages() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for nonce in 0..6u64 {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					if nonce == 5 { 5 } else { 1_000 },
+					nonce
+				),
+			));
+		}
+		// cancelled, reserved and timed out intents cannot be filled
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 1));
+		System::set_block_number(10);
+
+		let mut listed = Vec::new();
+		let mut start_key = None;
+		loop {
+			let page = HtlcEscrow::active_intents(start_key, 2);
+			assert!(page.len() <= 2);
+			let Some((last_key, _)) = page.last() else { break };
+			start_key = Some(*last_key);
+			listed.extend(page.into_iter().map(|(intent_key, intent)| {
+				assert_eq!(intent_key, HtlcEscrow::intent_key(&ALICE, intent.nonce));
+				intent.nonce
+			}));
+		}
+		listed.sort();
+		assert_eq!(listed, vec![2, 3, 4]);
+	});
+}
+
+#[test]
+fn auto_resolver_fills_the_intents_within_its_limits() {
+	use crate::resolver::{select_fills, ResolverSettings};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// intents at a price of 2, 1 and 1/2 source tokens per destination token
+		for (nonce, dst_amount) in [(0u64, 500), (1, 1_000), (2, 2_000)] {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					dst_amount,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce
+				),
+			));
+		}
+
+		let settings = ResolverSettings {
+			dst_chains: vec![GENERIC_CHAIN],
+			max_dst_amount: 1_500,
+			min_price: (1, 1),
+			safety_deposit: SAFETY_DEPOSIT,
+			timelocks: (100, 200, 300),
+			max_fills_per_block: 5,
+		};
+		let mut filled: Vec<u64> = select_fills::<Test>(&settings, 10)
+			.into_iter()
+			.map(|call| match call {
+				Call::create_src_htlc { maker, nonce, timelocks, safety_deposit } => {
+					assert_eq!(maker, ALICE);
+					assert_eq!(timelocks, create_timelocks(10));
+					assert_eq!(safety_deposit, SAFETY_DEPOSIT);
+					nonce
+				},
+				_ => panic!("unexpected call"),
+			})
+			.collect();
+		filled.sort();
+		assert_eq!(filled, vec![0, 1]);
+
+		// the fills are limited per block and to the served chains
+		let settings = ResolverSettings { max_fills_per_block: 1, ..settings };
+		assert_eq!(select_fills::<Test>(&settings, 10).len(), 1);
+		let settings = ResolverSettings { dst_chains: vec![BITCOIN_CHAIN], ..settings };
+		assert!(select_fills::<Test>(&settings, 10).is_empty());
+	});
+}
+
+#[cfg(feature = "auto-resolver")]
+#[test]
+fn auto_resolver_runs_in_the_offchain_worker() {
+	use crate::resolver::{ResolverSettings, SETTINGS_KEY};
+	use codec::Encode;
+	use sp_core::offchain::{
+		testing::TestOffchainExt, OffchainDbExt, OffchainStorage, OffchainWorkerExt,
+	};
+
+	let mut ext = new_test_ext();
+	let (offchain, state) = TestOffchainExt::new();
+	ext.register_extension(OffchainDbExt::new(offchain.clone()));
+	ext.register_extension(OffchainWorkerExt::new(offchain));
+
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"secret"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				0
+			),
+		));
+
+		// nothing is filled until the resolver is configured
+		HtlcEscrow::offchain_worker(1);
+		assert!(SubmittedFills::get().is_empty());
+
+		let settings = ResolverSettings::<u128, u64> {
+			dst_chains: vec![GENERIC_CHAIN],
+			max_dst_amount: DST_AMOUNT,
+			min_price: (1, 2),
+			safety_deposit: SAFETY_DEPOSIT,
+			timelocks: (100, 200, 300),
+			max_fills_per_block: 1,
+		};
+		state.write().persistent_storage.set(b"", SETTINGS_KEY, &settings.encode());
+		HtlcEscrow::offchain_worker(1);
+		assert_eq!(SubmittedFills::get().len(), 1);
+	});
+}
+
+#[test]
+fn auction_curves_decay_to_the_dst_amount() {
+	use crate::{
+		auction::AuctionCurve,
+		resolver::{select_fills, ResolverSettings},
+	};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// linear from 2_000 at block 10 to 1_000 at block 110
+		let linear = AuctionCurve::Linear { start: 10, duration: 100, start_amount: 2_000 };
+		let intent = SwapIntent {
+			auction: linear.clone(),
+			..create_swap_intent(
+				hash_of_word(b"linear"),
+				ALICE,
+				SRC_AMOUNT,
+				1_000,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				0,
+			)
+		};
+		assert_eq!(intent.dst_amount_at(1), 2_000);
+		assert_eq!(intent.dst_amount_at(60), 1_500);
+		// rounded up in favour of the maker
+		assert_eq!(intent.dst_amount_at(107), 1_030);
+		assert_eq!(intent.dst_amount_at(500), 1_000);
+
+		// piecewise through 1_200 at block 20, then to 1_000 at block 110
+		let piecewise: AuctionCurve<u128, u64> = AuctionCurve::Piecewise {
+			start: 10,
+			duration: 100,
+			start_amount: 2_000,
+			points: vec![(10, 1_200)].try_into().unwrap(),
+		};
+		assert_eq!(piecewise.amount_at(1_000, 15), 1_600);
+		assert_eq!(piecewise.amount_at(1_000, 20), 1_200);
+		assert_eq!(piecewise.amount_at(1_000, 65), 1_100);
+
+		// curves that go below the dst amount or up again are rejected
+		for auction in [
+			AuctionCurve::Linear { start: 10, duration: 100, start_amount: 900 },
+			AuctionCurve::Linear { start: 10, duration: 0, start_amount: 2_000 },
+			AuctionCurve::Piecewise {
+				start: 10,
+				duration: 100,
+				start_amount: 2_000,
+				points: vec![(10, 1_200), (20, 1_300)].try_into().unwrap(),
+			},
+			AuctionCurve::Piecewise {
+				start: 10,
+				duration: 100,
+				start_amount: 2_000,
+				points: vec![(100, 1_200)].try_into().unwrap(),
+			},
+		] {
+			assert_noop!(
+				HtlcEscrow::create_swap_intent(
+					RuntimeOrigin::signed(ALICE),
+					SwapIntent { auction, ..intent.clone() }
+				),
+				Error::<Test>::InvalidAuction
+			);
+		}
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), intent));
+
+		// the resolver fills the intent once the price drops within its limits
+		let settings = ResolverSettings {
+			dst_chains: vec![GENERIC_CHAIN],
+			max_dst_amount: 1_500,
+			min_price: (1, 2),
+			safety_deposit: SAFETY_DEPOSIT,
+			timelocks: (100, 200, 300),
+			max_fills_per_block: 5,
+		};
+		assert!(select_fills::<Test>(&settings, 59).is_empty());
+		assert_eq!(select_fills::<Test>(&settings, 60).len(), 1);
+	});
+}
+
+#[test]
+fn the_minimum_fill_size_is_bounded_by_the_intent() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let intent = |nonce: u64, min_fill_amount: u128| SwapIntent {
+			min_fill_amount,
+			..create_swap_intent(
+				hash_of_word(&nonce.to_le_bytes()),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				nonce,
+			)
+		};
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), intent(0, SRC_AMOUNT + 1)),
+			Error::<Test>::InvalidMinFillAmount
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			intent(0, SRC_AMOUNT / 4)
+		));
+		assert_eq!(
+			SwapIntents::<Test>::get(HtlcEscrow::intent_key(&ALICE, 0))
+				.unwrap()
+				.intent
+				.min_fill_amount,
+			SRC_AMOUNT / 4
+		);
+	});
+}
+
+#[test]
+fn intent_flags_limit_the_fills() {
+	use crate::IntentFlags;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let fill_or_kill = IntentFlags { fill_or_kill: true, ..Default::default() };
+		let single_fill = IntentFlags { allow_multiple_fills: false, ..Default::default() };
+		for (nonce, flags) in [(0u64, fill_or_kill), (1, single_fill)] {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(maker),
+				SwapIntent {
+					flags,
+					..create_swap_intent(
+						hash_of_word(&nonce.to_le_bytes()),
+						maker,
+						SRC_AMOUNT,
+						DST_AMOUNT,
+						get_evm_dst_address(ALICE + 1000),
+						1_000,
+						nonce,
+					)
+				},
+			));
+			assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), maker, nonce));
+		}
+
+		// the intents are filled whole unless their maker allows partial fills
+		assert_noop!(
+			HtlcEscrow::fill_intents(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				vec![(maker, 1, SRC_AMOUNT / 2)].try_into().unwrap(),
+				create_timelocks(1),
+				SAFETY_DEPOSIT,
+			),
+			Error::<Test>::PartialFillsNotAllowed
+		);
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			maker,
+			1,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		assert_eq!(SwapIntents::<Test>::get(HtlcEscrow::intent_key(&maker, 1)).unwrap().fills, 1);
+
+		// neither the reservation nor the stalled fill is taken over
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_CHARLIE, 0));
+		System::set_block_number(1 + 300);
+		assert_noop!(
+			HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0),
+			Error::<Test>::FillOrKillIntent
+		);
+		assert_noop!(
+			HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 1),
+			Error::<Test>::IntentAlreadyFilled
+		);
+
+		// the resolver that took the fill-or-kill intent still fills it
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			maker,
+			0,
+			create_timelocks(1 + 300),
+			SAFETY_DEPOSIT,
+		));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn good_til_cancel_intents_stay_open_until_cancelled() {
+	use frame_support::weights::Weight;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let reason = HoldReason::IntentStorageDeposit.into();
+		let balance = Balances::free_balance(maker);
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			SwapIntent {
+				timeout_after_block: None,
+				..create_swap_intent(
+					hash_of_word(b"secret"),
+					maker,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					0,
+					0,
+				)
+			},
+		));
+		let intent_key = HtlcEscrow::intent_key(&maker, 0);
+		assert_eq!(Balances::balance_on_hold(&reason, &maker), 30);
+		assert_eq!(SwapIntents::<Test>::get(intent_key).unwrap().storage_deposit, 30);
+
+		// the intent never expires
+		System::set_block_number(100_000);
+		HtlcEscrow::on_idle(100_000, Weight::MAX);
+		assert_eq!(SwapIntents::<Test>::get(intent_key).unwrap().status, IntentStatus::Active);
+		assert_eq!(HtlcEscrow::active_intents(None, 10).len(), 1);
+
+		// cancelling it returns the storage deposit
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(maker), 0));
+		assert_eq!(Balances::balance_on_hold(&reason, &maker), 0);
+		assert_eq!(Balances::free_balance(maker), balance);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn makers_invalidate_their_open_intents_by_epoch() {
+	use crate::weights::WeightInfo;
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let intent = |nonce: u64, epoch| SwapIntent {
+			epoch,
+			..create_swap_intent(
+				hash_of_word(&nonce.to_le_bytes()),
+				maker,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				nonce,
+			)
+		};
+		for (nonce, epoch) in [(0, 0), (1, 0), (2, 0), (3, 1)] {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(maker),
+				intent(nonce, epoch)
+			));
+		}
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), maker, 2));
+		let amount_reason = HoldReason::MakerSwapIntentAmount.into();
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), SRC_AMOUNT * 4);
+
+		// the active intents of the earlier epochs are cancelled with their
+		// funds returned to the maker, who pays for its four open intents
+		let post_info = HtlcEscrow::cancel_all_intents(RuntimeOrigin::signed(maker), 1).unwrap();
+		assert_eq!(post_info.actual_weight, Some(<() as WeightInfo>::cancel_all_intents(4)));
+		System::assert_last_event(Event::IntentsInvalidated { maker, before_epoch: 1 }.into());
+		for nonce in [0, 1] {
+			let intent_key = HtlcEscrow::intent_key(&maker, nonce);
+			assert_eq!(
+				SwapIntents::<Test>::get(intent_key).unwrap().status,
+				IntentStatus::Cancelled
+			);
+		}
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), SRC_AMOUNT * 2);
+		assert_eq!(MakerIntents::<Test>::get(maker).len(), 2);
+		assert_noop!(
+			HtlcEscrow::cancel_all_intents(RuntimeOrigin::signed(maker), 1),
+			Error::<Test>::InvalidEpoch
+		);
+
+		// the intents of the current epoch stay open
+		assert_eq!(
+			HtlcEscrow::active_intents(None, 10)
+				.into_iter()
+				.map(|(_, intent)| intent.nonce)
+				.collect::<Vec<_>>(),
+			vec![3]
+		);
+
+		// the reserved intent survives, but only its resolver fills it
+		let fill_deadline: u64 = <Test as Config>::FillDeadline::get();
+		System::set_block_number(1 + fill_deadline);
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_CHARLIE, 0));
+		assert_noop!(
+			HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 2),
+			Error::<Test>::IntentInvalidated
+		);
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			maker,
+			2,
+			create_timelocks(System::block_number()),
+			SAFETY_DEPOSIT,
+		));
+
+		// new intents take the new epoch
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), intent(4, 0)),
+			Error::<Test>::InvalidEpoch
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(maker), intent(4, 1)));
+		assert_eq!(HtlcEscrow::active_intents(None, 10).len(), 2);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn makers_deny_resolvers_from_their_intents() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			SwapIntent {
+				denied_resolvers: vec![RESOLVER_BOB].try_into().unwrap(),
+				..create_swap_intent(
+					hash_of_word(b"secret"),
+					maker,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					0,
+				)
+			},
+		));
+
+		assert_noop!(
+			HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), maker, 0),
+			Error::<Test>::ResolverDenied
+		);
+		assert_noop!(
+			HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				maker,
+				0,
+				create_timelocks(1),
+				SAFETY_DEPOSIT,
+			),
+			Error::<Test>::ResolverDenied
+		);
+
+		// other resolvers fill it
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			maker,
+			0,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+	});
+}
+
+#[test]
+fn reserved_dst_htlcs_are_activated_or_expire() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+		let deposit_reason = HoldReason::SafetyDeposit.into();
+		let amount_reason = HoldReason::SwapAmount.into();
+		let reserve = |order_hash| {
+			let immutables = create_test_htlc_immutables(
+				order_hash,
+				hash_of_word(order_hash.as_bytes()),
+				maker,
+				taker,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::reserve_dst_htlc(
+				RuntimeOrigin::signed(taker),
+				immutables.clone(),
+				401,
+				GENERIC_CHAIN,
+				false,
+			));
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		let activated = reserve(hash_of_word(b"activated"));
+		System::assert_last_event(
+			Event::DstHtlcReserved { htlc_id: activated, taker, activate_before: 21 }.into(),
+		);
+		let expired = reserve(hash_of_word(b"expired"));
+
+		// only the safety deposits are held until the activation
+		assert_eq!(Balances::balance_on_hold(&deposit_reason, &taker), 2 * SAFETY_DEPOSIT);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &taker), 0);
+		assert!(!Htlcs::<Test>::contains_key(activated));
+
+		System::set_block_number(10);
+		assert_noop!(
+			HtlcEscrow::activate_dst_htlc(RuntimeOrigin::signed(maker), activated),
+			Error::<Test>::InvalidCaller
+		);
+		assert_ok!(HtlcEscrow::activate_dst_htlc(RuntimeOrigin::signed(taker), activated));
+		assert_eq!(Htlcs::<Test>::get(activated).unwrap().status, HtlcStatus::Active);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &taker), SWAP_AMOUNT);
+
+		// the reservation that was not activated expires, the HTLC stays
+		System::set_block_number(22);
+		HtlcEscrow::on_initialize(22);
+		System::assert_has_event(Event::DstHtlcReservationExpired { htlc_id: expired }.into());
+		assert_eq!(Htlcs::<Test>::get(activated).unwrap().status, HtlcStatus::Active);
+		assert_eq!(Balances::balance_on_hold(&deposit_reason, &taker), SAFETY_DEPOSIT);
+		assert_noop!(
+			HtlcEscrow::activate_dst_htlc(RuntimeOrigin::signed(taker), expired),
+			Error::<Test>::DstHtlcNotPending
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn optimistic_claims_settle_unless_challenged() {
+	use crate::mock::UNSETTLED_PROOF;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+		let mut htlc_ids = vec![];
+		for nonce in 0..2u64 {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(maker),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					maker,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce,
+				),
+			));
+			assert_ok!(HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(taker),
+				maker,
+				nonce,
+				create_timelocks(1),
+				SAFETY_DEPOSIT,
+			));
+			let Some(IntentStatus::InProgress { htlc_id, .. }) =
+				SwapIntents::<Test>::get(HtlcEscrow::intent_key(&maker, nonce))
+					.map(|stored| stored.status)
+			else {
+				panic!("intent is not in progress");
+			};
+			htlc_ids.push(htlc_id);
+		}
+		let (settled, challenged) = (htlc_ids[0], htlc_ids[1]);
+
+		// the taker claims once it could withdraw with the secret
+		assert_noop!(
+			HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), settled),
+			Error::<Test>::EarlyWithdrawal
+		);
+		System::set_block_number(101);
+		assert_noop!(
+			HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(maker), settled),
+			Error::<Test>::InvalidCaller
+		);
+		for htlc_id in [settled, challenged] {
+			assert_ok!(HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), htlc_id));
+		}
+		System::assert_last_event(
+			Event::OptimisticClaimSubmitted {
+				htlc_id: challenged,
+				claimant: taker,
+				challenge_until: 131,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::balance_on_hold(&HoldReason::OptimisticClaimBond.into(), &taker), 80);
+		assert_eq!(HtlcEscrow::metrics().held_claim_bond, 80);
+
+		// a valid proof rejects the claim and pays the bond to the challenger
+		assert_noop!(
+			HtlcEscrow::challenge_claim(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				challenged,
+				vec![]
+			),
+			Error::<Test>::InvalidSettlementProof
+		);
+		assert_ok!(HtlcEscrow::challenge_claim(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			challenged,
+			UNSETTLED_PROOF.to_vec(),
+		));
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 + 40);
+		assert_eq!(Htlcs::<Test>::get(challenged).unwrap().status, HtlcStatus::Active);
+
+		// the unchallenged claim settles after the window
+		assert_noop!(
+			HtlcEscrow::finalize_claim(RuntimeOrigin::signed(maker), settled),
+			Error::<Test>::ChallengeWindowOpen
+		);
+		System::set_block_number(132);
+		assert_noop!(
+			HtlcEscrow::challenge_claim(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				settled,
+				UNSETTLED_PROOF.to_vec(),
+			),
+			Error::<Test>::ChallengeWindowClosed
+		);
+
+		// parties sanctioned during the window are not paid, and the claim is
+		// dropped with its bond returned
+		Sanctioned::set(vec![taker]);
+		assert_ok!(HtlcEscrow::finalize_claim(RuntimeOrigin::signed(maker), settled));
+		System::assert_last_event(
+			Event::OptimisticClaimDropped { htlc_id: settled, claimant: taker }.into(),
+		);
+		assert_eq!(Balances::balance_on_hold(&HoldReason::OptimisticClaimBond.into(), &taker), 0);
+		assert_eq!(Htlcs::<Test>::get(settled).unwrap().status, HtlcStatus::Active);
+		Sanctioned::set(vec![]);
+
+		// an unchallenged claim settles the HTLC once it expires, instead of
+		// the cancellation
+		assert_ok!(HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), settled));
+		System::set_block_number(301);
+		assert_noop!(
+			HtlcEscrow::cancel_by_id(RuntimeOrigin::signed(taker), settled),
+			Error::<Test>::OptimisticClaimPending
+		);
+		HtlcEscrow::on_initialize(301);
+		assert_eq!(Htlcs::<Test>::get(settled).unwrap().status, HtlcStatus::Completed);
+		assert_eq!(Htlcs::<Test>::get(challenged).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(
+			SwapIntents::<Test>::get(HtlcEscrow::intent_key(&maker, 0)).unwrap().status,
+			IntentStatus::Completed
+		);
+		assert_eq!(Balances::free_balance(taker), 1_000_000 - SAFETY_DEPOSIT - 40 + SRC_AMOUNT);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn blocked_parties_neither_claim_nor_challenge_optimistically() {
+	use crate::mock::UNSETTLED_PROOF;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let taker = RESOLVER_BOB;
+		let challenger = RESOLVER_CHARLIE;
+		let bond_reason = HoldReason::OptimisticClaimBond.into();
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			create_swap_intent(
+				hash_of_word(b"secret"),
+				maker,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				0,
+			),
+		));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(taker),
+			maker,
+			0,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hash_of_word(b"secret"))[0];
+		System::set_block_number(101);
+
+		// a blocked taker cannot claim
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), taker));
+		assert_noop!(
+			HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), htlc_id),
+			Error::<Test>::AccountBlocked
+		);
+		assert_ok!(HtlcEscrow::unblock_account(RuntimeOrigin::root(), taker));
+		assert_ok!(HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), htlc_id));
+
+		// nor does a blocked challenger take the bond
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), challenger));
+		assert_noop!(
+			HtlcEscrow::challenge_claim(
+				RuntimeOrigin::signed(challenger),
+				htlc_id,
+				UNSETTLED_PROOF.to_vec(),
+			),
+			Error::<Test>::AccountBlocked
+		);
+
+		// a maker blocked during the window drops the claim, whose bond is
+		// returned, and keeps the taker from claiming again
+		assert_ok!(HtlcEscrow::block_account(RuntimeOrigin::root(), maker));
+		System::set_block_number(132);
+		assert_ok!(HtlcEscrow::finalize_claim(RuntimeOrigin::signed(challenger), htlc_id));
+		System::assert_last_event(
+			Event::OptimisticClaimDropped { htlc_id, claimant: taker }.into(),
+		);
+		assert_eq!(Balances::balance_on_hold(&bond_reason, &taker), 0);
+		assert_eq!(Htlcs::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Active);
+		assert_noop!(
+			HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), htlc_id),
+			Error::<Test>::AccountBlocked
+		);
+
+		// once unblocked, the claim settles the HTLC as usual
+		assert_ok!(HtlcEscrow::unblock_account(RuntimeOrigin::root(), maker));
+		assert_ok!(HtlcEscrow::claim_optimistically(RuntimeOrigin::signed(taker), htlc_id));
+		System::set_block_number(163);
+		assert_ok!(HtlcEscrow::finalize_claim(RuntimeOrigin::signed(challenger), htlc_id));
+		assert_eq!(Htlcs::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Completed);
+		assert_eq!(Balances::free_balance(taker), 1_000_000 + SRC_AMOUNT);
+		assert_eq!(Balances::free_balance(challenger), 1_000_000);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn settled_swaps_issue_receipts_to_their_beneficiaries() {
+	use crate::receipt::SwapReceipt;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// the taker of a source HTLC gets the receipt of the filled intent
+		let nonce = 0;
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"source"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				nonce,
+			),
+		));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			nonce,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let source = create_test_htlc_immutables(
+			HtlcEscrow::intent_key(&ALICE, nonce),
+			hash_of_word(b"source"),
+			ALICE,
+			RESOLVER_BOB,
+			SRC_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+
+		// the maker of a destination HTLC gets the receipt of the swap from
+		// the counterpart chain
+		let destination = Immutables {
+			hash_function: HashFunction::Sha256,
+			..create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				sha2_256(b"destination").into(),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			)
+		};
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			destination.clone(),
+			destination.timelocks.cancellation_after + 100,
+			BITCOIN_CHAIN,
+		));
+
+		// no receipt is issued until the swaps settle
+		assert!(IssuedReceipts::get().is_empty());
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			source.clone(),
+			b"source".to_vec()
+		));
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			destination.clone(),
+			b"destination".to_vec()
+		));
+		assert_eq!(
+			IssuedReceipts::get(),
+			vec![
+				SwapReceipt {
+					htlc_id: HtlcEscrow::hash_immutables(&source),
+					beneficiary: RESOLVER_BOB,
+					amount: SRC_AMOUNT,
+					counterpart_amount: Some(DST_AMOUNT),
+					counterpart_chain: GENERIC_CHAIN,
+				},
+				SwapReceipt {
+					htlc_id: HtlcEscrow::hash_immutables(&destination),
+					beneficiary: ALICE,
+					amount: SWAP_AMOUNT,
+					counterpart_amount: None,
+					counterpart_chain: BITCOIN_CHAIN,
+				},
+			]
+		);
+	});
+}
+
+#[test]
+fn settled_swaps_count_in_the_account_stats() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			immutables
+		};
+		let withdrawn = create(b"withdrawn");
+		let cancelled = create(b"cancelled");
+
+		// only the settled swaps count
+		System::set_block_number(withdrawn.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			withdrawn.clone(),
+			b"withdrawn".to_vec()
+		));
+		System::set_block_number(cancelled.timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), cancelled));
+
+		assert_eq!(
+			AccountStats::<Test>::get(ALICE),
+			SwapStats {
+				maker_swaps: 1,
+				maker_volume: SWAP_AMOUNT,
+				last_active: withdrawn.timelocks.withdrawal_after,
+				..Default::default()
+			}
+		);
+		assert_eq!(
+			AccountStats::<Test>::get(RESOLVER_BOB),
+			SwapStats {
+				taker_swaps: 1,
+				taker_volume: SWAP_AMOUNT,
+				last_active: withdrawn.timelocks.withdrawal_after,
+				..Default::default()
+			}
+		);
+		assert_eq!(AccountStats::<Test>::get(RESOLVER_CHARLIE), SwapStats::default());
+	});
+}
+
+#[test]
+fn intents_migrate_to_v1_over_multiple_steps() {
+	use crate::migrations::{v0, v2, LazyMigrationV1};
+	use frame_support::{
+		migrations::{SteppedMigration, SteppedMigrationError},
+		traits::{ConstU32, GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+	// the intents of the first version paid on the generic chain
+	type Migration = LazyMigrationV1<Test, ConstU32<GENERIC_CHAIN>>;
+
+	new_test_ext().execute_with(|| {
+		// three intents stored in the v0 layout
+		StorageVersion::new(0).put::<HtlcEscrow>();
+		let keys: Vec<H256> = (0..3u64)
+			.map(|nonce| {
+				let key = HtlcEscrow::intent_key(&ALICE, nonce);
+				v0::SwapIntents::<Test>::insert(
+					key,
+					v0::StoredSwapIntent {
+						intent: v0::SwapIntent {
+							hashlock: hash_of_word(&nonce.to_le_bytes()),
+							maker: ALICE,
+							src_amount: SRC_AMOUNT,
+							dst_amount: DST_AMOUNT,
+							dst_address: H160::repeat_byte(nonce as u8),
+							timeout_after_block: 1_000,
+							nonce,
+						},
+						status: v0::IntentStatus::Active,
+						created_at: 1,
+					},
+				);
+				key
+			})
+			.collect();
+
+		// a step needs the weight of at least one intent
+		let per_intent = <() as WeightInfo>::migrate_intent();
+		assert!(matches!(
+			Migration::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Err(SteppedMigrationError::InsufficientWeight { required }) if required == per_intent
+		));
+
+		// two intents are migrated per step, until the version is bumped
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_intent.saturating_mul(2));
+			cursor = Migration::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 2);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(1));
+		for (nonce, key) in keys.into_iter().enumerate() {
+			let stored_intent = v2::SwapIntents::<Test>::get(key).expect("intent is migrated; qed");
+			assert_eq!(stored_intent.intent.nonce, nonce as u64);
+			assert_eq!(
+				stored_intent.intent.dst_address,
+				DstAddress::Evm(H160::repeat_byte(nonce as u8))
+			);
+			assert_eq!(stored_intent.intent.dst_chain, GENERIC_CHAIN);
+			assert_eq!(stored_intent.intent.timeout_after_block, Some(1_000));
+			assert!(stored_intent.intent.denied_resolvers.is_empty());
+			assert_eq!(stored_intent.status, IntentStatus::Active);
+		}
+
+		// migrated chains are left alone
+		assert!(matches!(
+			Migration::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn makers_are_limited_in_their_open_intents() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |nonce: u64| {
+			HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce,
+				),
+			)
+		};
+		for nonce in 0..8 {
+			assert_ok!(create(nonce));
+		}
+		assert_eq!(MakerIntents::<Test>::get(ALICE).len(), 8);
+		assert_noop!(create(8), Error::<Test>::TooManyIntents);
+
+		// the intents that end no longer count
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		assert_eq!(MakerIntents::<Test>::get(ALICE).len(), 7);
+		assert_ok!(create(8));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn htlcs_are_settled_by_their_ids() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		let withdrawn = create(b"withdrawn");
+		let cancelled = create(b"cancelled");
+
+		assert_noop!(
+			HtlcEscrow::withdraw_by_id(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				H256::zero(),
+				b"withdrawn".to_vec()
+			),
+			Error::<Test>::HtlcDoesNotExist
+		);
+
+		// the calls check the secret, the callers and the timing as usual
+		System::set_block_number(101);
+		assert_noop!(
+			HtlcEscrow::withdraw_by_id(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				withdrawn,
+				b"cancelled".to_vec()
+			),
+			Error::<Test>::InvalidSecret
+		);
+		assert_ok!(HtlcEscrow::withdraw_by_id(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			withdrawn,
+			b"withdrawn".to_vec()
+		));
+		assert_eq!(Htlcs::<Test>::get(withdrawn).unwrap().status, HtlcStatus::Completed);
+
+		assert_noop!(
+			HtlcEscrow::cancel_by_id(RuntimeOrigin::signed(RESOLVER_BOB), cancelled),
+			Error::<Test>::EarlyCancellation
+		);
+		System::set_block_number(301);
+		assert_ok!(HtlcEscrow::cancel_by_id(RuntimeOrigin::signed(RESOLVER_BOB), cancelled));
+		assert_eq!(Htlcs::<Test>::get(cancelled).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn timelocks_are_stored_as_offsets_from_the_deployment() {
+	use crate::validation::{check_timelocks, ImmutablesError};
+	use codec::{Decode, Encode};
+
+	let timelocks = create_timelocks(u64::MAX / 2);
+	assert_eq!(
+		timelocks.compact(),
+		Some(CompactTimelocks {
+			deployed_at: u64::MAX / 2,
+			withdrawal_offset: 100,
+			public_withdrawal_offset: 200,
+			cancellation_offset: 300,
+		})
+	);
+	assert_eq!(timelocks.compact().unwrap().expand(), timelocks);
+
+	// the timelocks themselves keep their encoding, and so the HTLC ids
+	assert_eq!(timelocks.encode().len(), 4 * 8);
+
+	// stored HTLCs hold the deployment block and three u32 offsets
+	let immutables = Immutables {
+		timelocks: timelocks.clone(),
+		..create_test_htlc_immutables(H256::zero(), H256::zero(), ALICE, RESOLVER_BOB, 1, 1, 0)
+	};
+	let stored_htlc = |immutables: Immutables<u64, u128, u64>| {
+		let htlc = Htlc {
+			immutables,
+			status: HtlcStatus::Active,
+			htlc_type: HtlcType::Source,
+			counterpart_chain: GENERIC_CHAIN,
+		};
+		StoredHtlc::<u64, u128, u64>::decode(&mut &htlc.encode()[..]).unwrap()
+	};
+	let stored = stored_htlc(immutables.clone());
+	assert!(matches!(stored.immutables, VersionedImmutables::V2(_)));
+	let legacy =
+		StoredHtlc { immutables: VersionedImmutables::V1(immutables.clone()), ..stored.clone() };
+	assert_eq!(stored.encode().len() + 3 * 4, legacy.encode().len());
+	assert_eq!(stored.into_latest().immutables, immutables);
+
+	// timelocks without a compact form are not lost, nor accepted for new HTLCs
+	let early = Timelocks { withdrawal_after: u64::MAX / 2 - 1, ..timelocks.clone() };
+	let far = Timelocks { cancellation_after: u64::MAX, ..timelocks.clone() };
+	for (timelocks, error) in
+		[(early, ImmutablesError::TimelocksOutOfOrder), (far, ImmutablesError::TimelocksTooLong)]
+	{
+		assert_eq!(timelocks.compact(), None);
+		assert_eq!(check_timelocks(&timelocks, None), Err(error));
+
+		let immutables = Immutables { timelocks, ..immutables.clone() };
+		let stored = stored_htlc(immutables.clone());
+		assert_eq!(stored.immutables, VersionedImmutables::V1(immutables.clone()));
+		assert_eq!(stored.into_latest().immutables, immutables);
+	}
+}
+
+#[test]
+fn the_pool_raises_secret_reveals_and_rejects_failing_settlements() {
+	use crate::extension::CheckSettlements;
+	use frame_support::{dispatch::DispatchInfo, pallet_prelude::TransactionSource};
+	use sp_runtime::{
+		traits::{TransactionExtension, TxBaseImplication},
+		transaction_validity::InvalidTransaction,
+	};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order"),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+
+		let priority = |call: RuntimeCall| {
+			CheckSettlements::<Test>::new()
+				.validate(
+					RuntimeOrigin::signed(RESOLVER_BOB),
+					&call,
+					&DispatchInfo::default(),
+					0,
+					(),
+					&TxBaseImplication(()),
+					TransactionSource::External,
+				)
+				.map(|(valid, _, _)| valid.priority)
+		};
+		let withdraw = |secret: &[u8]| {
+			RuntimeCall::HtlcEscrow(Call::withdraw {
+				immutables: immutables.clone(),
+				secret: secret.to_vec(),
+			})
+		};
+
+		// only the withdrawals with the right secret are raised
+		assert_eq!(priority(withdraw(b"secret")), Ok(1_000));
+		assert_eq!(
+			priority(RuntimeCall::HtlcEscrow(Call::public_withdraw_by_id {
+				htlc_id,
+				secret: b"secret".to_vec()
+			})),
+			Ok(1_000)
+		);
+		assert_eq!(
+			priority(RuntimeCall::HtlcEscrow(Call::cancel { immutables: immutables.clone() })),
+			Ok(0)
+		);
+		assert_eq!(
+			priority(RuntimeCall::System(frame_system::Call::remark { remark: vec![] })),
+			Ok(0)
+		);
+
+		// the settlements that are bound to fail are rejected from the pool
+		assert_eq!(priority(withdraw(b"guess")), Err(InvalidTransaction::Call.into()));
+		assert_eq!(
+			priority(RuntimeCall::HtlcEscrow(Call::cancel_by_id { htlc_id: H256::zero() })),
+			Err(InvalidTransaction::Call.into())
+		);
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw_by_id(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			b"secret".to_vec()
+		));
+		assert_eq!(priority(withdraw(b"secret")), Err(InvalidTransaction::Stale.into()));
+		assert_eq!(
+			priority(RuntimeCall::HtlcEscrow(Call::public_cancel_by_id { htlc_id })),
+			Err(InvalidTransaction::Stale.into())
+		);
+	});
+}
+
+#[test]
+fn takers_withdraw_source_htlcs_to_a_target() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let nonce = 0;
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"secret"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				nonce,
+			),
+		));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			nonce,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let source = create_test_htlc_immutables(
+			HtlcEscrow::intent_key(&ALICE, nonce),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SRC_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+
+		// only the funds of a source HTLC can be redirected
+		let destination = create_test_htlc_immutables(
+			hash_of_word(b"order"),
+			hash_of_word(b"other"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			destination.clone(),
+			destination.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		System::set_block_number(101);
+		assert_noop!(
+			HtlcEscrow::withdraw_to(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				destination,
+				b"other".to_vec(),
+				RESOLVER_CHARLIE
+			),
+			Error::<Test>::InvalidImmutables
+		);
+
+		// and only by the taker
+		assert_noop!(
+			HtlcEscrow::withdraw_to(
+				RuntimeOrigin::signed(ALICE),
+				source.clone(),
+				b"secret".to_vec(),
+				RESOLVER_CHARLIE
+			),
+			Error::<Test>::InvalidCaller
+		);
+
+		// the target gets the swap amount, the taker its safety deposit
+		assert_ok!(HtlcEscrow::withdraw_to(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			source.clone(),
+			b"secret".to_vec(),
+			RESOLVER_CHARLIE
+		));
+		System::assert_last_event(
+			Event::HtlcWithdrawn {
+				htlc_id: HtlcEscrow::hash_immutables(&source),
+				secret: b"secret".to_vec(),
+				amount: SRC_AMOUNT,
+				beneficiary: RESOLVER_CHARLIE,
+				safety_deposit_recipient: RESOLVER_BOB,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 + SRC_AMOUNT);
+		// the destination HTLC is still escrowed
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - SWAP_AMOUNT - SAFETY_DEPOSIT);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 - SRC_AMOUNT);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn swap_costs_are_estimated_for_makers_and_takers() {
+	use codec::MaxEncodedLen;
+
+	new_test_ext().execute_with(|| {
+		MakerDeposit::set(50);
+		let base_extrinsic = <<Test as frame_system::Config>::BlockWeights as Get<
+			frame_system::limits::BlockWeights,
+		>>::get()
+		.get(frame_support::dispatch::DispatchClass::Normal)
+		.base_extrinsic;
+
+		// the maker posts its deposit and the storage deposit of an intent
+		// without a timeout, and pays for the intent and the settlement of the
+		// destination HTLC, the dearer of a withdrawal and a cancellation
+		let settlement = <() as WeightInfo>::withdraw(MAX_SECRET_LEN, 1, MAX_ORDER_LEGS - 1)
+			.max(<() as WeightInfo>::cancel(MAX_ORDER_LEGS - 1));
+		let costs = HtlcEscrow::estimate_swap_costs(
+			SWAP_AMOUNT,
+			None,
+			SwapRole::Maker,
+			|weight| weight.ref_time().into(),
+			|length| length.into(),
+		);
+		let weight = base_extrinsic
+			.saturating_mul(2)
+			.saturating_add(<() as WeightInfo>::create_swap_intents(1))
+			.saturating_add(settlement);
+		assert_eq!((costs.safety_deposit, costs.storage_deposit, costs.protocol_fee), (50, 30, 0));
+		assert_eq!((costs.weight, costs.weight_fee), (weight, weight.ref_time().into()));
+		assert!(costs.length as usize > SwapIntentOf::<Test>::max_encoded_len());
+		assert_eq!(costs.length_fee, costs.length as u128);
+
+		// converting the settled funds adds an extrinsic
+		let converted = HtlcEscrow::estimate_swap_costs(
+			SWAP_AMOUNT,
+			Some(1),
+			SwapRole::Maker,
+			|weight| weight.ref_time().into(),
+			|length| length.into(),
+		);
+		assert_eq!(
+			converted.weight,
+			weight
+				.saturating_add(base_extrinsic)
+				.saturating_add(<() as WeightInfo>::set_settlement_conversion())
+		);
+		assert!(converted.length > costs.length);
+
+		// the taker posts the safety deposits of both HTLCs and the
+		// reservation bond, pays the vault fee on the amount, and pays for
+		// the reservation, both HTLCs and their settlements
+		let costs =
+			HtlcEscrow::estimate_swap_costs(SWAP_AMOUNT, None, SwapRole::Taker, |_| 7, |_| 3);
+		assert_eq!(
+			(costs.safety_deposit, costs.storage_deposit, costs.protocol_fee),
+			(2 * 10 + 20, 0, SWAP_AMOUNT / 100)
+		);
+		assert_eq!(
+			costs.weight,
+			base_extrinsic
+				.saturating_mul(5)
+				.saturating_add(<() as WeightInfo>::take_intent(MAX_ORDER_LEGS - 1))
+				.saturating_add(<() as WeightInfo>::create_src_htlc(3, MAX_ORDER_LEGS - 1))
+				.saturating_add(<() as WeightInfo>::create_vault_dst_htlc(3, MAX_ORDER_LEGS - 1))
+				.saturating_add(settlement.saturating_mul(2))
+		);
+		assert_eq!((costs.weight_fee, costs.length_fee), (7, 3));
+	});
+}
+
+#[test]
+fn makers_convert_their_settled_funds() {
+	use crate::conversion::SettlementConversion;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			immutables
+		};
+		let converted = create(b"converted");
+		let slipped = create(b"slipped");
+		let cancelled = create(b"cancelled");
+		let conversion = |min_out| Some(SettlementConversion { asset: 7, min_out });
+
+		// only the maker asks for the conversion
+		assert_noop!(
+			HtlcEscrow::set_settlement_conversion(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				HtlcEscrow::hash_immutables(&converted),
+				conversion(400),
+			),
+			Error::<Test>::InvalidCaller
+		);
+		for (immutables, min_out) in [(&converted, 400), (&slipped, 600), (&cancelled, 0)] {
+			assert_ok!(HtlcEscrow::set_settlement_conversion(
+				RuntimeOrigin::signed(ALICE),
+				HtlcEscrow::hash_immutables(immutables),
+				conversion(min_out),
+			));
+		}
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// the withdrawal converts the swap amount
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			converted.clone(),
+			b"converted".to_vec()
+		));
+		System::assert_has_event(
+			Event::SettlementConverted {
+				htlc_id: HtlcEscrow::hash_immutables(&converted),
+				asset: 7,
+				amount_in: SWAP_AMOUNT,
+				amount_out: SWAP_AMOUNT / 2,
+			}
+			.into(),
+		);
+		assert_eq!(Conversions::get(), vec![(ALICE, SWAP_AMOUNT, 7)]);
+
+		// a failed conversion leaves the native tokens with the maker
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			slipped.clone(),
+			b"slipped".to_vec()
+		));
+		System::assert_has_event(
+			Event::SettlementConversionFailed {
+				htlc_id: HtlcEscrow::hash_immutables(&slipped),
+				error: sp_runtime::TokenError::BelowMinimum.into(),
+			}
+			.into(),
+		);
+		assert_eq!(Conversions::get().len(), 1);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + 2 * SWAP_AMOUNT);
+
+		// the conversion of a cancelled HTLC is dropped
+		System::set_block_number(301);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), cancelled.clone()));
+		assert!(!SettlementConversions::<Test>::contains_key(HtlcEscrow::hash_immutables(
+			&cancelled
+		)));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn makers_deliver_their_settled_funds_to_other_chains() {
+	use crate::conversion::SettlementConversion;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		UnreachableRemotes::set(vec![66]);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			immutables
+		};
+		let delivered = create(b"delivered");
+		let unreachable = create(b"unreachable");
+		let delivered_id = HtlcEscrow::hash_immutables(&delivered);
+
+		// the swap amount is either converted or delivered
+		assert_ok!(HtlcEscrow::set_settlement_conversion(
+			RuntimeOrigin::signed(ALICE),
+			delivered_id,
+			Some(SettlementConversion { asset: 7, min_out: 0 }),
+		));
+		assert_noop!(
+			HtlcEscrow::set_settlement_delivery(
+				RuntimeOrigin::signed(ALICE),
+				delivered_id,
+				Some(55)
+			),
+			Error::<Test>::SettlementAlreadyRouted
+		);
+		assert_ok!(HtlcEscrow::set_settlement_conversion(
+			RuntimeOrigin::signed(ALICE),
+			delivered_id,
+			None,
+		));
+		assert_ok!(HtlcEscrow::set_settlement_delivery(
+			RuntimeOrigin::signed(ALICE),
+			delivered_id,
+			Some(55)
+		));
+		assert_ok!(HtlcEscrow::set_settlement_delivery(
+			RuntimeOrigin::signed(ALICE),
+			HtlcEscrow::hash_immutables(&unreachable),
+			Some(66)
+		));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// the withdrawal delivers the swap amount to the other chain
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			delivered.clone(),
+			b"delivered".to_vec()
+		));
+		System::assert_has_event(
+			Event::SettlementDelivered {
+				htlc_id: delivered_id,
+				beneficiary: 55,
+				amount: SWAP_AMOUNT,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000);
+
+		// a failed delivery leaves the swap amount with the maker
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			unreachable.clone(),
+			b"unreachable".to_vec()
+		));
+		System::assert_has_event(
+			Event::SettlementDeliveryFailed {
+				htlc_id: HtlcEscrow::hash_immutables(&unreachable),
+				error: sp_runtime::DispatchError::Unavailable,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn chain_profiles_are_set_at_genesis() {
+	use sp_runtime::BuildStorage;
+
+	let profile = ChainProfile {
+		hash_function: HashFunction::Sha256,
+		address_format: AddressFormat::Evm,
+		min_timelock_margin: 5,
+		decimals: 18,
+	};
+	let storage = RuntimeGenesisConfig {
+		htlc_escrow: crate::GenesisConfig { chain_profiles: vec![(7, profile.clone())] },
+		..Default::default()
+	}
+	.build_storage()
+	.unwrap();
+
+	sp_io::TestExternalities::new(storage).execute_with(|| {
+		assert_eq!(ChainProfiles::<Test>::get(7), Some(profile));
+		assert_eq!(ChainProfiles::<Test>::iter().count(), 1);
+	});
+}
+
+#[test]
+fn escrows_release_what_is_left_of_their_slashed_holds() {
+	use frame_support::traits::{
+		fungible::MutateHold,
+		tokens::{Fortitude, Precision},
+	};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order"),
+			hash_of_word(b"slashed"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+
+		// another pallet slashes part of the hold of the swap amount
+		assert_ok!(<Balances as MutateHold<u64>>::burn_held(
+			&HoldReason::SwapAmount.into(),
+			&RESOLVER_BOB,
+			400,
+			Precision::Exact,
+			Fortitude::Force,
+		));
+
+		// the withdrawal still goes through with what is left
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"slashed".to_vec()
+		));
+		System::assert_has_event(
+			Event::PartialRelease {
+				key: htlc_id,
+				who: RESOLVER_BOB,
+				reason: HoldReason::SwapAmount,
+				expected: SWAP_AMOUNT,
+				released: SWAP_AMOUNT - 400,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::free_balance(ALICE), 1_000_000 + SWAP_AMOUNT - 400);
+		let deficit = (RESOLVER_BOB, HoldReason::SwapAmount);
+		assert_eq!(EscrowDeficits::<Test>::get(htlc_id, deficit), 400);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// the shortfall stays expected, so a remediated hold is not residual
+		assert_eq!(ExpectedHolds::<Test>::get(RESOLVER_BOB).swap_amounts, 400);
+		assert_ok!(<Balances as MutateHold<u64>>::hold(
+			&HoldReason::SwapAmount.into(),
+			&RESOLVER_BOB,
+			400,
+		));
+		assert_noop!(
+			HtlcEscrow::escheat_residual_hold(
+				RuntimeOrigin::signed(ALICE),
+				htlc_id,
+				RESOLVER_BOB,
+				HoldReason::SwapAmount,
+			),
+			Error::<Test>::NoResidualHold
+		);
+
+		// until governance clears the deficit
+		assert_noop!(
+			HtlcEscrow::clear_escrow_deficit(
+				RuntimeOrigin::signed(ALICE),
+				htlc_id,
+				RESOLVER_BOB,
+				HoldReason::SwapAmount,
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_ok!(HtlcEscrow::clear_escrow_deficit(
+			RuntimeOrigin::root(),
+			htlc_id,
+			RESOLVER_BOB,
+			HoldReason::SwapAmount,
+		));
+		System::assert_last_event(
+			Event::EscrowDeficitCleared {
+				key: htlc_id,
+				who: RESOLVER_BOB,
+				reason: HoldReason::SwapAmount,
+				amount: 400,
+			}
+			.into(),
+		);
+		assert!(!EscrowDeficits::<Test>::contains_key(htlc_id, deficit));
+		System::assert_has_event(
+			Event::ResidualHoldNoted {
+				key: htlc_id,
+				who: RESOLVER_BOB,
+				reason: HoldReason::SwapAmount,
+				amount: 400,
+			}
+			.into(),
+		);
+		assert_eq!(ResidualHolds::<Test>::get(htlc_id, deficit), Some((400, 101)));
+		assert_eq!(ExpectedHolds::<Test>::get(RESOLVER_BOB).swap_amounts, 400);
+		assert_noop!(
+			HtlcEscrow::clear_escrow_deficit(
+				RuntimeOrigin::root(),
+				htlc_id,
+				RESOLVER_BOB,
+				HoldReason::SwapAmount,
+			),
+			Error::<Test>::NoEscrowDeficit
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn governance_reconciles_stuck_htlcs_and_orphaned_holds() {
+	use frame_support::traits::fungible::MutateHold;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		let stuck = create(b"stuck");
+		let active = create(b"active");
+
+		// a hold that no HTLC accounts for
+		assert_ok!(<Balances as MutateHold<u64>>::hold(
+			&HoldReason::SafetyDeposit.into(),
+			&RESOLVER_BOB,
+			50,
+		));
+
+		assert_noop!(
+			HtlcEscrow::force_reconcile(RuntimeOrigin::signed(ALICE), stuck),
+			sp_runtime::DispatchError::BadOrigin
+		);
+
+		// the stuck HTLC is cancelled before its timelocks, the orphaned hold
+		// released, and the holds of the other HTLC kept
+		assert_ok!(HtlcEscrow::force_reconcile(RuntimeOrigin::root(), stuck));
+		assert_eq!(Htlcs::<Test>::get(stuck).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(Htlcs::<Test>::get(active).unwrap().status, HtlcStatus::Active);
+		System::assert_has_event(
+			Event::OrphanedHoldReleased {
+				who: RESOLVER_BOB,
+				reason: HoldReason::SafetyDeposit,
+				amount: 50,
+			}
+			.into(),
+		);
+		System::assert_last_event(Event::HtlcReconciled { htlc_id: stuck }.into());
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000 - SWAP_AMOUNT - SAFETY_DEPOSIT);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), SWAP_AMOUNT + SAFETY_DEPOSIT);
+
+		// reconciling a settled HTLC only releases orphaned holds
+		assert_ok!(HtlcEscrow::force_reconcile(RuntimeOrigin::root(), stuck));
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), SWAP_AMOUNT + SAFETY_DEPOSIT);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn takers_record_the_counterpart_escrows() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order"),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+
+		let reference = CounterpartReference {
+			tx_hash: H256::repeat_byte(1),
+			escrow: H160::repeat_byte(2),
+			block_number: 100,
+		};
+
+		// only the taker deployed the other leg
+		assert_noop!(
+			HtlcEscrow::record_counterpart_reference(
+				RuntimeOrigin::signed(ALICE),
+				htlc_id,
+				reference.clone()
+			),
+			Error::<Test>::InvalidCaller
+		);
+
+		assert_ok!(HtlcEscrow::record_counterpart_reference(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			reference.clone()
+		));
+		assert_eq!(CounterpartReferences::<Test>::get(htlc_id), Some(reference.clone()));
+		System::assert_last_event(
+			Event::CounterpartReferenceRecorded { htlc_id, reference: reference.clone() }.into(),
+		);
+
+		// a reorg of the other chain moves the deployment to another block
+		let reorged = CounterpartReference { block_number: 101, ..reference };
+		assert_ok!(HtlcEscrow::record_counterpart_reference(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			reorged.clone()
+		));
+		assert_eq!(CounterpartReferences::<Test>::get(htlc_id), Some(reorged.clone()));
+
+		// the reference goes with the HTLC once it is settled and pruned
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"secret".to_vec()
+		));
+		assert_noop!(
+			HtlcEscrow::record_counterpart_reference(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				htlc_id,
+				reorged
+			),
+			Error::<Test>::HtlcNotActive
+		);
+		assert_ok!(HtlcEscrow::prune_htlc(RuntimeOrigin::signed(ALICE), htlc_id));
+		assert_eq!(CounterpartReferences::<Test>::get(htlc_id), None);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn orders_are_tracked_across_their_legs() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let secret = b"order".to_vec();
+		let swap_intent = create_swap_intent(
+			hash_of_word(&secret),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		let order_hash = HtlcEscrow::intent_key(&ALICE, 0);
+		let record = CrossChainOrders::<Test>::get(order_hash).unwrap();
+		assert_eq!(record.intent, Some(IntentStatus::Active));
+		assert!(record.htlcs.is_empty());
+		assert_eq!(record.status, OrderStatus::Open);
+
+		// the resolver fills the intent and deploys the other leg
+		let timelocks = create_timelocks(System::block_number());
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			timelocks.clone(),
+			SAFETY_DEPOSIT
+		));
+		let htlc_id = HashlockIndex::<Test>::get(hash_of_word(&secret))[0];
+		let escrow = H160::repeat_byte(2);
+		assert_ok!(HtlcEscrow::record_counterpart_reference(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			htlc_id,
+			CounterpartReference { tx_hash: H256::repeat_byte(1), escrow, block_number: 100 }
+		));
+		let record = CrossChainOrders::<Test>::get(order_hash).unwrap();
+		assert_eq!(record.htlcs.into_inner(), vec![htlc_id]);
+		assert_eq!(record.counterparts.into_inner(), vec![(htlc_id, escrow)]);
+		assert_eq!(record.status, OrderStatus::InProgress);
+
+		// the record outlives the pruned HTLC
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+		System::set_block_number(timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), immutables, secret));
+		assert_ok!(HtlcEscrow::prune_htlc(RuntimeOrigin::signed(ALICE), htlc_id));
+		let record = CrossChainOrders::<Test>::get(order_hash).unwrap();
+		assert_eq!(record.intent, Some(IntentStatus::Completed));
+		assert_eq!(record.status, OrderStatus::Completed);
+
+		// orders posted on another chain are tracked by their destination legs
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"remote order"),
+			hash_of_word(b"remote"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		let record = CrossChainOrders::<Test>::get(hash_of_word(b"remote order")).unwrap();
+		assert_eq!(record.intent, None);
+		assert_eq!(record.htlcs.into_inner(), vec![HtlcEscrow::hash_immutables(&immutables)]);
+		assert_eq!(record.status, OrderStatus::InProgress);
+
+		System::set_block_number(immutables.timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), immutables));
+		let record = CrossChainOrders::<Test>::get(hash_of_word(b"remote order")).unwrap();
+		assert_eq!(record.status, OrderStatus::Cancelled);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn downstream_legs_expire_before_their_upstream_leg() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8], cancellation_after: u64| {
+			let mut immutables = create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			immutables.timelocks.cancellation_after = cancellation_after;
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		let upstream = create(b"upstream", 301);
+		let parallel = create(b"parallel", 301);
+		let downstream = create(b"downstream", 250);
+		let parent_hashlock = hash_of_word(b"upstream");
+
+		// only the taker routes its HTLCs
+		assert_noop!(
+			HtlcEscrow::declare_parent_hashlock(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				downstream,
+				parent_hashlock
+			),
+			Error::<Test>::InvalidCaller
+		);
+		assert_noop!(
+			HtlcEscrow::declare_parent_hashlock(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				downstream,
+				hash_of_word(b"unknown")
+			),
+			Error::<Test>::UpstreamHtlcNotFound
+		);
+		assert_noop!(
+			HtlcEscrow::declare_parent_hashlock(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				parallel,
+				parent_hashlock
+			),
+			Error::<Test>::TimelockNotShorterThanUpstream
+		);
+
+		assert_ok!(HtlcEscrow::declare_parent_hashlock(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			downstream,
+			parent_hashlock
+		));
+		assert_eq!(ParentHashlocks::<Test>::get(downstream), Some(parent_hashlock));
+		System::assert_last_event(
+			Event::ParentHashlockDeclared { htlc_id: downstream, parent_hashlock, upstream }.into(),
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_fill_several_intents_at_once() {
+	use crate::IntentFlags;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for nonce in 0..3u64 {
+			let flags = IntentFlags {
+				fill_or_kill: nonce == 2,
+				allow_partial_fills: nonce != 2,
+				..Default::default()
+			};
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				SwapIntent {
+					flags,
+					min_fill_amount: SRC_AMOUNT / 4,
+					..create_swap_intent(
+						hash_of_word(&nonce.to_le_bytes()),
+						ALICE,
+						SRC_AMOUNT,
+						DST_AMOUNT,
+						get_evm_dst_address(1),
+						1_000,
+						nonce,
+					)
+				},
+			));
+		}
+		let timelocks = create_timelocks(System::block_number());
+		let fill = |fills: Vec<(u64, u64, u128)>| {
+			HtlcEscrow::fill_intents(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				fills.try_into().unwrap(),
+				timelocks.clone(),
+				SAFETY_DEPOSIT,
+			)
+		};
+
+		// either all the intents are filled or none
+		assert_noop!(
+			fill(vec![(ALICE, 0, SRC_AMOUNT / 2), (ALICE, 1, SRC_AMOUNT + 1)]),
+			Error::<Test>::InvalidFillAmount
+		);
+		assert_noop!(
+			fill(vec![(ALICE, 0, SRC_AMOUNT / 2), (ALICE, 2, SRC_AMOUNT / 2)]),
+			Error::<Test>::FillOrKillIntent
+		);
+		assert_noop!(
+			fill(vec![(ALICE, 0, SRC_AMOUNT / 2), (ALICE, 3, SRC_AMOUNT)]),
+			Error::<Test>::IntentDoesNotExists
+		);
+
+		// nor fragmented with dust fills
+		assert_noop!(
+			fill(vec![(ALICE, 0, 1), (ALICE, 1, SRC_AMOUNT)]),
+			Error::<Test>::FillBelowMinimum
+		);
+
+		assert_ok!(fill(vec![(ALICE, 0, SRC_AMOUNT / 2), (ALICE, 1, SRC_AMOUNT)]));
+		let partial_id = HashlockIndex::<Test>::get(hash_of_word(&0u64.to_le_bytes()))[0];
+		let full_id = HashlockIndex::<Test>::get(hash_of_word(&1u64.to_le_bytes()))[0];
+		assert_eq!(Htlcs::<Test>::get(partial_id).unwrap().immutables.amount, SRC_AMOUNT / 2);
+		assert_eq!(Htlcs::<Test>::get(full_id).unwrap().immutables.amount, SRC_AMOUNT);
+
+		// the maker gets the unfilled part of the intent back with the swap
+		let maker_balance = Balances::free_balance(ALICE);
+		let taker_balance = Balances::free_balance(RESOLVER_BOB);
+		let immutables = Htlcs::<Test>::get(partial_id).unwrap().immutables;
+		System::set_block_number(timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			0u64.to_le_bytes().to_vec()
+		));
+		assert_eq!(
+			Balances::free_balance(RESOLVER_BOB),
+			taker_balance + SRC_AMOUNT / 2 + SAFETY_DEPOSIT
+		);
+		assert_eq!(
+			Balances::free_balance(ALICE),
+			maker_balance + SRC_AMOUNT / 2 + <Test as Config>::MakerSafetyDeposit::get()
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn partially_filled_intents_are_filled_again_after_a_take_over() {
+	use crate::IntentFlags;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker = ALICE;
+		let hashlock = hash_of_word(b"secret");
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(maker),
+			SwapIntent {
+				flags: IntentFlags { allow_partial_fills: true, ..Default::default() },
+				min_fill_amount: SRC_AMOUNT / 4,
+				..create_swap_intent(
+					hashlock,
+					maker,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					0,
+				)
+			},
+		));
+		let intent_key = HtlcEscrow::intent_key(&maker, 0);
+		let amount_reason = HoldReason::MakerSwapIntentAmount.into();
+
+		// the first resolver fills half of the intent, then stalls
+		assert_ok!(HtlcEscrow::fill_intents(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			vec![(maker, 0, SRC_AMOUNT / 2)].try_into().unwrap(),
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let stalled_id = HashlockIndex::<Test>::get(hashlock)[0];
+		assert_eq!(Htlcs::<Test>::get(stalled_id).unwrap().immutables.amount, SRC_AMOUNT / 2);
+
+		// another resolver takes the intent over once the fill can be
+		// cancelled, and the stalled HTLC returns its amount to the intent
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_CHARLIE, 0));
+		System::set_block_number(1 + 300);
+		assert_ok!(HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), maker, 0));
+		assert_eq!(Htlcs::<Test>::get(stalled_id).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), 1_000_000);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), SRC_AMOUNT);
+
+		// the refill takes any part of the whole intent, above the minimum
+		let refill = |amount| {
+			HtlcEscrow::fill_intents(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				vec![(maker, 0, amount)].try_into().unwrap(),
+				create_timelocks(1 + 300),
+				SAFETY_DEPOSIT,
+			)
+		};
+		assert_noop!(refill(SRC_AMOUNT / 8), Error::<Test>::FillBelowMinimum);
+		assert_noop!(refill(SRC_AMOUNT + 1), Error::<Test>::InvalidFillAmount);
+		assert_ok!(refill(SRC_AMOUNT * 3 / 4));
+		let refill_id = HashlockIndex::<Test>::get(hashlock)
+			.into_iter()
+			.find(|htlc_id| *htlc_id != stalled_id)
+			.unwrap();
+		let immutables = Htlcs::<Test>::get(refill_id).unwrap().immutables;
+		assert_eq!(immutables.amount, SRC_AMOUNT * 3 / 4);
+
+		// the settlement pays the refill and returns the rest to the maker
+		let maker_balance = Balances::free_balance(maker);
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			immutables,
+			b"secret".to_vec()
+		));
+		assert_eq!(SwapIntents::<Test>::get(intent_key).unwrap().status, IntentStatus::Completed);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), 1_000_000 + SRC_AMOUNT * 3 / 4);
+		assert_eq!(Balances::free_balance(maker), maker_balance + SRC_AMOUNT / 4);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), 0);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn takers_cancel_several_expired_htlcs_at_once() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8], cancellation_after: u64| {
+			let mut immutables = create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			immutables.timelocks.cancellation_after = cancellation_after;
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		let expired = create(b"expired", 301);
+		let early = create(b"early", 400);
+		let unknown = hash_of_word(b"unknown");
+		let balance = Balances::free_balance(RESOLVER_BOB);
+
+		// the failed cancellations don't stop the batch
+		System::set_block_number(301);
+		assert_ok!(HtlcEscrow::cancel_many(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			vec![unknown, early, expired].try_into().unwrap()
+		));
+		assert_eq!(Htlcs::<Test>::get(expired).unwrap().status, HtlcStatus::Cancelled);
+		assert_eq!(Htlcs::<Test>::get(early).unwrap().status, HtlcStatus::Active);
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), balance + SWAP_AMOUNT + SAFETY_DEPOSIT);
+		System::assert_has_event(
+			Event::HtlcCancellationFailed {
+				htlc_id: unknown,
+				error: Error::<Test>::HtlcDoesNotExist.into(),
+			}
+			.into(),
+		);
+		System::assert_has_event(
+			Event::HtlcCancellationFailed {
+				htlc_id: early,
+				error: Error::<Test>::EarlyCancellation.into(),
+			}
+			.into(),
+		);
+		System::assert_has_event(
+			Event::HtlcCancelled { htlc_id: expired, refund_recipient: RESOLVER_BOB }.into(),
+		);
+
+		// only the taker cancels privately
+		System::set_block_number(400);
+		assert_ok!(HtlcEscrow::cancel_many(
+			RuntimeOrigin::signed(ALICE),
+			vec![early].try_into().unwrap()
+		));
+		System::assert_last_event(
+			Event::HtlcCancellationFailed {
+				htlc_id: early,
+				error: Error::<Test>::InvalidCaller.into(),
+			}
+			.into(),
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn makers_post_ladders_of_intents() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let ladder = |words: [&[u8]; 3]| {
+			let intents: Vec<_> = words
+				.into_iter()
+				.enumerate()
+				.map(|(step, word)| {
+					create_swap_intent(
+						hash_of_word(word),
+						ALICE,
+						SRC_AMOUNT,
+						DST_AMOUNT + step as u128,
+						get_evm_dst_address(1),
+						1_000,
+						0,
+					)
+				})
+				.collect();
+			intents.try_into().unwrap()
+		};
+
+		// the hashlocks of the ladder must differ
+		assert_noop!(
+			HtlcEscrow::create_swap_intents(
+				RuntimeOrigin::signed(ALICE),
+				ladder([b"a", b"b", b"a"]),
+				5
+			),
+			Error::<Test>::HashlockInUse
+		);
+
+		assert_ok!(HtlcEscrow::create_swap_intents(
+			RuntimeOrigin::signed(ALICE),
+			ladder([b"a", b"b", b"c"]),
+			5
+		));
+		for (nonce, step) in [(5, 0), (6, 1), (7, 2)] {
+			let stored_intent =
+				SwapIntents::<Test>::get(HtlcEscrow::intent_key(&ALICE, nonce)).unwrap();
+			assert_eq!(stored_intent.intent.nonce, nonce);
+			assert_eq!(stored_intent.intent.dst_amount, DST_AMOUNT + step);
+		}
+		assert_eq!(MakerIntents::<Test>::get(ALICE).len(), 3);
+		assert_eq!(
+			Balances::total_balance_on_hold(&ALICE),
+			3 * (SRC_AMOUNT + <Test as Config>::MakerSafetyDeposit::get())
+		);
+
+		// each intent of the ladder is cancelled on its own
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 6));
+		assert_eq!(
+			Balances::total_balance_on_hold(&ALICE),
+			2 * (SRC_AMOUNT + <Test as Config>::MakerSafetyDeposit::get())
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_hold_a_limited_number_of_intents() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let max_intents: u32 = <Test as Config>::MaxIntentsPerResolver::get();
+		for nonce in 0..=max_intents as u64 {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(1),
+					1_000,
+					nonce,
+				),
+			));
+		}
+		for nonce in 0..max_intents as u64 {
+			assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, nonce));
+		}
+		assert_eq!(ResolverIntents::<Test>::get(RESOLVER_BOB), max_intents);
+		assert_noop!(
+			HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, max_intents as u64),
+			Error::<Test>::TooManyIntentsInProgress
+		);
+
+		// filling an intent keeps it held until the swap settles
+		let timelocks = create_timelocks(System::block_number());
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			timelocks.clone(),
+			SAFETY_DEPOSIT
+		));
+		assert_eq!(ResolverIntents::<Test>::get(RESOLVER_BOB), max_intents);
+
+		let htlc_id = HashlockIndex::<Test>::get(hash_of_word(&0u64.to_le_bytes()))[0];
+		let immutables = Htlcs::<Test>::get(htlc_id).unwrap().immutables;
+		System::set_block_number(timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			0u64.to_le_bytes().to_vec()
+		));
+		assert_eq!(ResolverIntents::<Test>::get(RESOLVER_BOB), max_intents - 1);
+		assert_ok!(HtlcEscrow::take_intent(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			max_intents as u64
+		));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_cool_down_after_failing_a_fill() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for nonce in 0..2u64 {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(1),
+					1_000,
+					nonce,
+				),
+			));
+		}
+
+		// the resolver reserves an intent and never fills it
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 0));
+		System::set_block_number(401);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		let cooldown: u64 = <Test as Config>::ResolverCooldown::get();
+		let until = 401 + cooldown;
+		assert_eq!(ResolverCooldowns::<Test>::get(RESOLVER_BOB), Some(until));
+		System::assert_has_event(
+			Event::ResolverCooldownStarted { resolver: RESOLVER_BOB, until }.into(),
+		);
+
+		assert_noop!(
+			HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 1),
+			Error::<Test>::ResolverCoolingDown
+		);
+		assert_noop!(
+			HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				ALICE,
+				1,
+				create_timelocks(System::block_number()),
+				SAFETY_DEPOSIT
+			),
+			Error::<Test>::ResolverCoolingDown
+		);
+
+		System::set_block_number(until);
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 1));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn delegators_back_resolvers_and_share_their_slashes() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let reason = RuntimeHoldReason::HtlcEscrow(HoldReason::DelegatedStake);
+		assert_noop!(
+			HtlcEscrow::delegate(RuntimeOrigin::signed(ALICE), RESOLVER_BOB, 1_000),
+			Error::<Test>::NotResolver
+		);
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 0));
+		assert_ok!(HtlcEscrow::delegate(RuntimeOrigin::signed(ALICE), RESOLVER_BOB, 1_000));
+		assert_ok!(HtlcEscrow::delegate(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			RESOLVER_BOB,
+			500
+		));
+		assert_noop!(
+			HtlcEscrow::delegate(RuntimeOrigin::signed(4), RESOLVER_BOB, 100),
+			Error::<Test>::TooManyDelegators
+		);
+		assert_eq!(
+			DelegatedStakes::<Test>::get(RESOLVER_BOB),
+			DelegatedStake { bonded: 1_500, delegators: 2 }
+		);
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 1_000);
+
+		// the undelegated stake unbonds for the `UndelegationDelay`
+		assert_ok!(HtlcEscrow::undelegate(RuntimeOrigin::signed(ALICE), RESOLVER_BOB, 400));
+		System::assert_last_event(
+			Event::Undelegated {
+				delegator: ALICE,
+				resolver: RESOLVER_BOB,
+				amount: 400,
+				unlock_at: 21,
+			}
+			.into(),
+		);
+		assert_eq!(DelegatedStakes::<Test>::get(RESOLVER_BOB).bonded, 1_100);
+		assert_noop!(
+			HtlcEscrow::withdraw_undelegated(RuntimeOrigin::signed(ALICE), RESOLVER_BOB),
+			Error::<Test>::DelegationUnbonding
+		);
+
+		// the delegators lose their share when the resolver fails a fill,
+		// including the unbonding stake
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"secret"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(1),
+				1_000,
+				0,
+			),
+		));
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 0));
+		System::set_block_number(401);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		System::assert_has_event(
+			Event::DelegatorsSlashed { resolver: RESOLVER_BOB, amount: 150 }.into(),
+		);
+		assert_eq!(
+			Delegations::<Test>::get(RESOLVER_BOB, ALICE),
+			Some(Delegation { bonded: 540, unbonding: 360, unlock_at: 21 })
+		);
+		assert_eq!(DelegatedStakes::<Test>::get(RESOLVER_BOB).bonded, 990);
+		assert_eq!(Balances::free_balance(HtlcEscrow::insurance_account()), 150);
+
+		let balance = Balances::free_balance(ALICE);
+		assert_ok!(HtlcEscrow::withdraw_undelegated(RuntimeOrigin::signed(ALICE), RESOLVER_BOB));
+		assert_eq!(Balances::free_balance(ALICE), balance + 360);
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 540);
+
+		// the delegators leave once all their stake is withdrawn
+		assert_ok!(HtlcEscrow::undelegate(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			RESOLVER_BOB,
+			450
+		));
+		System::set_block_number(421);
+		assert_ok!(HtlcEscrow::withdraw_undelegated(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			RESOLVER_BOB
+		));
+		assert_eq!(Delegations::<Test>::get(RESOLVER_BOB, RESOLVER_CHARLIE), None);
+		assert_eq!(
+			DelegatedStakes::<Test>::get(RESOLVER_BOB),
+			DelegatedStake { bonded: 540, delegators: 1 }
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn delegators_are_slashed_without_touching_the_vault_draws() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let lp = RESOLVER_CHARLIE;
+		let stake_reason = RuntimeHoldReason::HtlcEscrow(HoldReason::DelegatedStake);
+		assert_ok!(HtlcEscrow::vault_deposit(RuntimeOrigin::signed(lp), 10_000));
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 2_500));
+		assert_ok!(HtlcEscrow::delegate(RuntimeOrigin::signed(ALICE), RESOLVER_BOB, 1_000));
+
+		// the resolver abandons a source HTLC
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"abandoned"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1_000,
+				0,
+			),
+		));
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let abandoned = create_test_htlc_immutables(
+			HtlcEscrow::intent_key(&ALICE, 0),
+			hash_of_word(b"abandoned"),
+			ALICE,
+			RESOLVER_BOB,
+			SRC_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+
+		// while it draws on the vault for another swap
+		System::set_block_number(250);
+		let drawn = create_test_htlc_immutables(
+			hash_of_word(b"drawn"),
+			hash_of_word(b"drawn"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			250,
+		);
+		assert_ok!(HtlcEscrow::create_vault_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			drawn.clone(),
+			drawn.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		assert_eq!(ResolverCredits::<Test>::get(RESOLVER_BOB).drawn, SWAP_AMOUNT);
+
+		// the delegators are slashed into the insurance fund, not the vault
+		System::set_block_number(abandoned.timelocks.cancellation_after + 1);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_BOB), abandoned));
+		System::assert_has_event(
+			Event::DelegatorsSlashed { resolver: RESOLVER_BOB, amount: 100 }.into(),
+		);
+		assert_eq!(Balances::balance_on_hold(&stake_reason, &ALICE), 900);
+		assert_eq!(Balances::free_balance(HtlcEscrow::insurance_account()), 100);
+		assert_eq!(Vault::<Test>::get().total_assets, 10_000);
+		assert_eq!(ResolverCredits::<Test>::get(RESOLVER_BOB).drawn, SWAP_AMOUNT);
+
+		// the draw still settles and is repaid with its fee, to the liquidity
+		// providers alone
+		System::set_block_number(drawn.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			drawn,
+			b"drawn".to_vec()
+		));
+		let fee = SWAP_AMOUNT / 100;
+		assert_eq!(
+			ResolverCredits::<Test>::get(RESOLVER_BOB),
+			ResolverCredit { drawn: 0, owed: SWAP_AMOUNT + fee, insurance_owed: 0 }
+		);
+		assert_ok!(HtlcEscrow::repay_vault(RuntimeOrigin::signed(RESOLVER_BOB), SWAP_AMOUNT + fee));
+		assert_ok!(HtlcEscrow::vault_withdraw(RuntimeOrigin::signed(lp), 10_000));
+		assert_eq!(Balances::free_balance(lp), 1_000_000 + fee);
+		assert_eq!(Balances::free_balance(HtlcEscrow::insurance_account()), 100);
+		assert_eq!(DelegatedStakes::<Test>::get(RESOLVER_BOB).bonded, 900);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_share_their_earnings_with_their_delegators() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_CHARLIE, 0));
+		assert_noop!(
+			HtlcEscrow::set_resolver_commission(
+				RuntimeOrigin::signed(ALICE),
+				Some(sp_runtime::Perbill::from_percent(50))
+			),
+			Error::<Test>::NotResolver
+		);
+		assert_ok!(HtlcEscrow::set_resolver_commission(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			Some(sp_runtime::Perbill::from_percent(50))
+		));
+		assert_ok!(HtlcEscrow::delegate(RuntimeOrigin::signed(ALICE), RESOLVER_CHARLIE, 300));
+		assert_ok!(HtlcEscrow::delegate(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			RESOLVER_CHARLIE,
+			100
+		));
+
+		// the resolver earns the bond of the resolver whose fill it takes over
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			create_swap_intent(
+				hash_of_word(b"secret"),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(1),
+				1_000,
+				0,
+			),
+		));
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 0));
+		let delegator_balance = Balances::free_balance(ALICE);
+		let resolver_balance = Balances::free_balance(RESOLVER_CHARLIE);
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::take_over_intent(RuntimeOrigin::signed(RESOLVER_CHARLIE), ALICE, 0));
+
+		// half of the bond is shared in proportion to the stakes, rounded down
+		System::assert_has_event(
+			Event::EarningsShared { resolver: RESOLVER_CHARLIE, amount: 9 }.into(),
+		);
+		assert_eq!(Balances::free_balance(ALICE), delegator_balance + 7);
+		// the forfeited bond pays for the bond the resolver posts itself
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), resolver_balance - 9);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn vault_fees_are_discounted_by_rolling_volume() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let tier = |min_volume, percent| FeeTier {
+			min_volume,
+			discount: sp_runtime::Perbill::from_percent(percent),
+		};
+		let unsorted: FeeTiersOf<Test> =
+			vec![tier(2 * SWAP_AMOUNT, 100), tier(SWAP_AMOUNT, 50)].try_into().unwrap();
+		let tiers: FeeTiersOf<Test> =
+			vec![tier(SWAP_AMOUNT, 50), tier(10 * SWAP_AMOUNT, 100)].try_into().unwrap();
+		assert_noop!(
+			HtlcEscrow::set_fee_tiers(RuntimeOrigin::signed(ALICE), tiers.clone()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			HtlcEscrow::set_fee_tiers(RuntimeOrigin::root(), unsorted),
+			Error::<Test>::FeeTiersNotSorted
+		);
+		assert_ok!(HtlcEscrow::set_fee_tiers(RuntimeOrigin::root(), tiers.clone()));
+		System::assert_last_event(Event::FeeTiersSet { tiers }.into());
+
+		assert_ok!(HtlcEscrow::vault_deposit(RuntimeOrigin::signed(RESOLVER_CHARLIE), 10_000));
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 2_500));
+		let draw_and_withdraw = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				System::block_number(),
+			);
+			assert_ok!(HtlcEscrow::create_vault_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			System::set_block_number(immutables.timelocks.withdrawal_after);
+			assert_ok!(HtlcEscrow::withdraw(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables,
+				word.to_vec()
+			));
+			let owed = ResolverCredits::<Test>::get(RESOLVER_BOB).owed;
+			assert_ok!(HtlcEscrow::repay_vault(RuntimeOrigin::signed(RESOLVER_BOB), owed));
+			owed - SWAP_AMOUNT
+		};
+
+		// the first swap pays the full fee and counts for both parties
+		let fee = SWAP_AMOUNT / 100;
+		assert_eq!(draw_and_withdraw(b"first"), fee);
+		assert_eq!(HtlcEscrow::rolling_volume(&RESOLVER_BOB), SWAP_AMOUNT);
+		assert_eq!(HtlcEscrow::rolling_volume(&ALICE), SWAP_AMOUNT);
+
+		// which reaches the first tier
+		assert_eq!(HtlcEscrow::fee_discount(&RESOLVER_BOB), sp_runtime::Perbill::from_percent(50));
+		assert_eq!(draw_and_withdraw(b"second"), fee - fee / 2);
+		assert_eq!(HtlcEscrow::rolling_volume(&RESOLVER_BOB), 2 * SWAP_AMOUNT);
+
+		// the volume leaves the window as it ages
+		let window: u64 = <Test as Config>::VolumeWindow::get();
+		System::set_block_number(System::block_number() + window);
+		assert_eq!(HtlcEscrow::rolling_volume(&RESOLVER_BOB), 0);
+		assert_eq!(draw_and_withdraw(b"third"), fee);
+		assert_eq!(RecentVolumes::<Test>::get(RESOLVER_BOB).len(), 1);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn secrets_are_bounded_in_length() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let secret = vec![0x42u8; MAX_SECRET_LEN as usize + 1];
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order hash"),
+			hash_of_word(&secret),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+
+		// even a secret that matches the hashlock is rejected if too long
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_noop!(
+			HtlcEscrow::withdraw(RuntimeOrigin::signed(RESOLVER_BOB), immutables, secret),
+			Error::<Test>::SecretTooLong
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_backed_by_more_stake_post_lower_deposits() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let tier = |min_stake, deposit_percent, fee_percent| ResolverTier {
+			min_stake,
+			deposit_discount: sp_runtime::Perbill::from_percent(deposit_percent),
+			fee_discount: sp_runtime::Perbill::from_percent(fee_percent),
+		};
+		let unsorted: ResolverTiersOf<Test> =
+			vec![tier(1_000, 80, 50), tier(100, 50, 0)].try_into().unwrap();
+		let tiers: ResolverTiersOf<Test> =
+			vec![tier(100, 50, 0), tier(1_000, 80, 50)].try_into().unwrap();
+		assert_noop!(
+			HtlcEscrow::set_resolver_tiers(RuntimeOrigin::signed(RESOLVER_BOB), tiers.clone()),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			HtlcEscrow::set_resolver_tiers(RuntimeOrigin::root(), unsorted),
+			Error::<Test>::ResolverTiersNotSorted
+		);
+		assert_ok!(HtlcEscrow::set_resolver_tiers(RuntimeOrigin::root(), tiers.clone()));
+		System::assert_last_event(Event::ResolverTiersSet { tiers: tiers.clone() }.into());
+
+		// the tier follows the stake delegated to a registered resolver
+		let min_safety_deposit: u128 = <Test as Config>::MinSafetyDeposit::get();
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 0));
+		assert_eq!(HtlcEscrow::resolver_tier(&RESOLVER_BOB), None);
+		assert_ok!(HtlcEscrow::delegate(RuntimeOrigin::signed(ALICE), RESOLVER_BOB, 100));
+		assert_eq!(HtlcEscrow::resolver_tier(&RESOLVER_BOB), Some(tiers[0].clone()));
+		assert_eq!(HtlcEscrow::min_safety_deposit(&RESOLVER_BOB), min_safety_deposit / 2);
+		// unregistered accounts pay the full deposit
+		assert_eq!(HtlcEscrow::resolver_tier(&RESOLVER_CHARLIE), None);
+		assert_eq!(HtlcEscrow::min_safety_deposit(&RESOLVER_CHARLIE), min_safety_deposit);
+
+		// which is enforced when the HTLCs are created
+		let immutables = |taker, word: &[u8]| {
+			create_test_htlc_immutables(
+				hash_of_word(word),
+				hash_of_word(word),
+				ALICE,
+				taker,
+				SWAP_AMOUNT,
+				min_safety_deposit / 2,
+				System::block_number(),
+			)
+		};
+		let charlie_immutables = immutables(RESOLVER_CHARLIE, b"charlie");
+		assert_noop!(
+			HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_CHARLIE),
+				charlie_immutables.clone(),
+				charlie_immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			),
+			Error::<Test>::HigherSafetyDepositRequired
+		);
+		let bob_immutables = immutables(RESOLVER_BOB, b"bob");
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			bob_immutables.clone(),
+			bob_immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn contracts_drive_escrows_with_solidity_calldata() {
+	use crate::revive::{self, AbiError};
+	use sp_runtime::traits::Dispatchable;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let secret = [0x42u8; 32];
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order hash"),
+			hash_of_word(&secret),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			System::block_number(),
+		);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+
+		// the selectors are those of the Solidity signatures
+		assert_eq!(revive::selector(b"transfer(address,uint256)"), [0xa9, 0x05, 0x9c, 0xbb]);
+		assert_eq!(
+			revive::decode_call::<Test>(&revive::encode_call(b"withdraw(bytes32)", &[htlc_id])),
+			Err(AbiError::UnknownSelector)
+		);
+		assert_eq!(
+			revive::decode_call::<Test>(&revive::encode_call(revive::WITHDRAW, &[htlc_id])),
+			Err(AbiError::InvalidArguments)
+		);
+		assert_eq!(
+			revive::decode_call::<Test>(&revive::encode_call(revive::CANCEL, &[htlc_id])),
+			Ok(Call::cancel_by_id { htlc_id })
+		);
+
+		// and the decoded calls are dispatched for the calling contract
+		let input = revive::encode_call(revive::WITHDRAW, &[htlc_id, H256(secret)]);
+		let call = RuntimeCall::from(revive::decode_call::<Test>(&input).unwrap());
+		System::set_block_number(immutables.timelocks.withdrawal_after);
+		assert_ok!(call.dispatch(RuntimeOrigin::signed(RESOLVER_BOB)));
+		assert_eq!(Htlcs::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Completed);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn htlcs_migrate_to_versioned_immutables() {
+	use crate::migrations::{v1, v4, LazyMigrationV2};
+	use codec::Encode;
+	use frame_support::{
+		migrations::SteppedMigration,
+		traits::{ConstU32, GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+	// the HTLCs from before their counterpart chain swapped with the generic one
+	type Migration = LazyMigrationV2<Test, ConstU32<GENERIC_CHAIN>>;
+
+	new_test_ext().execute_with(|| {
+		// three HTLCs stored in the v1 layout, under the hashes of their
+		// immutables without a hash function
+		StorageVersion::new(1).put::<HtlcEscrow>();
+		let htlcs: Vec<(H256, Immutables<u64, u128, u64>)> = (0..3u64)
+			.map(|i| {
+				let immutables = create_test_htlc_immutables(
+					hash_of_word(&i.to_le_bytes()),
+					hash_of_word(&i.to_le_bytes()),
+					ALICE,
+					RESOLVER_BOB,
+					SWAP_AMOUNT,
+					SAFETY_DEPOSIT,
+					1,
+				);
+				let v1_immutables = v1::Immutables {
+					order_hash: immutables.order_hash,
+					hashlock: immutables.hashlock,
+					maker: immutables.maker,
+					taker: immutables.taker,
+					amount: immutables.amount,
+					safety_deposit: immutables.safety_deposit,
+					timelocks: immutables.timelocks.clone(),
+				};
+				let htlc_id = H256(blake2_256(&v1_immutables.encode()));
+				v1::Htlcs::<Test>::insert(
+					htlc_id,
+					v1::Htlc {
+						immutables: v1_immutables,
+						status: HtlcStatus::Active,
+						htlc_type: HtlcType::Destination,
+					},
+				);
+				(htlc_id, immutables)
+			})
+			.collect();
+
+		// two HTLCs are migrated per step, until the version is bumped
+		let per_htlc = <() as WeightInfo>::migrate_htlc();
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_htlc.saturating_mul(2));
+			cursor = Migration::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 2);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(2));
+		for (htlc_id, immutables) in htlcs {
+			let htlc = Htlcs::<Test>::get(htlc_id).expect("HTLC is migrated; qed");
+			assert_eq!(htlc.immutables, immutables);
+			assert_eq!(htlc.status, HtlcStatus::Active);
+			assert_eq!(htlc.counterpart_chain, GENERIC_CHAIN);
+
+			// the immutables are stored versioned, while the ids stay the same
+			let stored = v4::Htlcs::<Test>::get(htlc_id).unwrap();
+			assert_eq!(stored.immutables, VersionedImmutables::V1(immutables.clone()));
+			assert_eq!(stored.immutables.into_latest(), immutables);
+			assert_eq!(HtlcEscrow::hash_immutables(&immutables), htlc_id);
+
+			// while a sha256 hashlock makes for another HTLC
+			let sha256_immutables =
+				Immutables { hash_function: HashFunction::Sha256, ..immutables.clone() };
+			assert_ne!(HtlcEscrow::hash_immutables(&sha256_immutables), htlc_id);
+		}
+
+		// migrated chains are left alone
+		assert!(matches!(
+			Migration::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn htlcs_migrate_to_compact_timelocks() {
+	use crate::migrations::{v4, LazyMigrationV5};
+	use frame_support::{
+		migrations::SteppedMigration,
+		traits::{GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+
+	new_test_ext().execute_with(|| {
+		// three HTLCs stored in the v4 layout, the last with timelocks that
+		// have no compact form
+		StorageVersion::new(4).put::<HtlcEscrow>();
+		let htlcs: Vec<(H256, Immutables<u64, u128, u64>)> = (0..3u64)
+			.map(|i| {
+				let mut immutables = create_test_htlc_immutables(
+					hash_of_word(&i.to_le_bytes()),
+					hash_of_word(&i.to_le_bytes()),
+					ALICE,
+					RESOLVER_BOB,
+					SWAP_AMOUNT,
+					SAFETY_DEPOSIT,
+					1,
+				);
+				if i == 2 {
+					immutables.timelocks.cancellation_after = u64::MAX;
+				}
+				let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+				v4::Htlcs::<Test>::insert(
+					htlc_id,
+					StoredHtlc {
+						immutables: VersionedImmutables::V1(immutables.clone()),
+						status: HtlcStatus::Active,
+						htlc_type: HtlcType::Destination,
+						counterpart_chain: GENERIC_CHAIN,
+					},
+				);
+				(htlc_id, immutables)
+			})
+			.collect();
+
+		// two HTLCs are migrated per step, until the version is bumped
+		let per_htlc = <() as WeightInfo>::migrate_htlc();
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_htlc.saturating_mul(2));
+			cursor = LazyMigrationV5::<Test>::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 2);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(5));
+		for (htlc_id, immutables) in htlcs {
+			assert_eq!(Htlcs::<Test>::get(htlc_id).unwrap().immutables, immutables);
+			assert_eq!(HtlcEscrow::hash_immutables(&immutables), htlc_id);
+
+			let stored = v4::Htlcs::<Test>::get(htlc_id).unwrap();
+			match immutables.timelocks.compact() {
+				Some(_) => assert!(matches!(stored.immutables, VersionedImmutables::V2(_))),
+				None => assert_eq!(stored.immutables, VersionedImmutables::V1(immutables)),
+			}
+		}
+
+		// migrated chains are left alone
+		assert!(matches!(
+			LazyMigrationV5::<Test>::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn expected_holds_are_counted_from_the_htlcs_by_the_migration() {
+	use crate::migrations::LazyMigrationV6;
+	use frame_support::{
+		migrations::SteppedMigration,
+		traits::{GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// an active and a pending destination HTLC of the taker
+		let immutables = |order_hash: H256| {
+			create_test_htlc_immutables(
+				order_hash,
+				hash_of_word(order_hash.as_bytes()),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			)
+		};
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables(hash_of_word(b"active")),
+			401,
+			GENERIC_CHAIN,
+		));
+		assert_ok!(HtlcEscrow::reserve_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables(hash_of_word(b"pending")),
+			401,
+			GENERIC_CHAIN,
+			false,
+		));
+		let expected =
+			ExpectedHold { swap_amounts: SWAP_AMOUNT, safety_deposits: SAFETY_DEPOSIT * 2 };
+		assert_eq!(ExpectedHolds::<Test>::get(RESOLVER_BOB), expected);
+
+		// a chain that does not count them yet
+		StorageVersion::new(5).put::<HtlcEscrow>();
+		let _ = ExpectedHolds::<Test>::clear(u32::MAX, None);
+		assert!(HtlcEscrow::do_try_state().is_err());
+
+		// one HTLC is counted per step, the pending one after the active one
+		let per_htlc = <() as WeightInfo>::migrate_htlc();
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_htlc);
+			cursor = LazyMigrationV6::<Test>::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 3);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(6));
+		assert_eq!(ExpectedHolds::<Test>::get(RESOLVER_BOB), expected);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// none of the holds is residual
+		assert_eq!(
+			Balances::total_balance_on_hold(&RESOLVER_BOB),
+			expected.swap_amounts + expected.safety_deposits
+		);
+
+		// migrated chains are left alone
+		assert!(matches!(
+			LazyMigrationV6::<Test>::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn open_intents_are_indexed_by_maker_by_the_migration() {
+	use crate::migrations::{v6, LazyMigrationV7};
+	use frame_support::{
+		migrations::SteppedMigration,
+		traits::{GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// an open, a reserved and a cancelled intent of the maker
+		for nonce in 0..3u64 {
+			assert_ok!(HtlcEscrow::create_swap_intent(
+				RuntimeOrigin::signed(ALICE),
+				create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce,
+				)
+			));
+		}
+		assert_ok!(HtlcEscrow::take_intent(RuntimeOrigin::signed(RESOLVER_BOB), ALICE, 1));
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 2));
+		let mut open = MakerIntents::<Test>::get(ALICE).into_inner();
+		open.sort();
+		assert_eq!(open.len(), 2);
+
+		// a chain that only counts them
+		StorageVersion::new(6).put::<HtlcEscrow>();
+		MakerIntents::<Test>::remove(ALICE);
+		v6::OpenIntents::<Test>::insert(ALICE, 2);
+		assert!(HtlcEscrow::do_try_state().is_err());
+
+		// one intent is indexed per step, then the count is removed
+		let per_intent = <() as WeightInfo>::migrate_intent();
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_intent);
+			cursor = LazyMigrationV7::<Test>::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 5);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(7));
+		let mut indexed = MakerIntents::<Test>::get(ALICE).into_inner();
+		indexed.sort();
+		assert_eq!(indexed, open);
+		assert!(!v6::OpenIntents::<Test>::contains_key(ALICE));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// migrated chains are left alone
+		assert!(matches!(
+			LazyMigrationV7::<Test>::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn unclaimed_residual_holds_are_swept_to_the_treasury() {
+	use frame_support::traits::{
+		fungible::MutateHold,
+		tokens::{Fortitude, Precision},
+	};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let withdraw = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(b"order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			System::set_block_number(101);
+			assert_ok!(HtlcEscrow::withdraw(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				word.to_vec()
+			));
+			System::set_block_number(1);
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		let settled = withdraw(b"settled");
+		let escheat = |origin: RuntimeOrigin, key: H256| {
+			HtlcEscrow::escheat_residual_hold(origin, key, RESOLVER_BOB, HoldReason::SafetyDeposit)
+		};
+		assert_noop!(escheat(RuntimeOrigin::signed(ALICE), settled), Error::<Test>::NoResidualHold);
+
+		// a safety deposit that the next settled HTLC leaves behind
+		assert_ok!(<Balances as MutateHold<u64>>::hold(
+			&HoldReason::SafetyDeposit.into(),
+			&RESOLVER_BOB,
+			30,
+		));
+		let htlc_id = withdraw(b"residual");
+		System::assert_has_event(
+			Event::ResidualHoldNoted {
+				key: htlc_id,
+				who: RESOLVER_BOB,
+				reason: HoldReason::SafetyDeposit,
+				amount: 30,
+			}
+			.into(),
+		);
+		let residual = (RESOLVER_BOB, HoldReason::SafetyDeposit);
+		assert_eq!(ResidualHolds::<Test>::get(htlc_id, residual), Some((30, 101)));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// it stays claimable for the escheatment period since it was left
+		let period: u64 = <Test as Config>::EscheatmentPeriod::get();
+		System::set_block_number(period + 100);
+		assert_noop!(
+			escheat(RuntimeOrigin::signed(ALICE), htlc_id),
+			Error::<Test>::EarlyEscheatment
+		);
+
+		// and what is left of it is swept afterwards
+		assert_ok!(<Balances as MutateHold<u64>>::burn_held(
+			&HoldReason::SafetyDeposit.into(),
+			&RESOLVER_BOB,
+			10,
+			Precision::Exact,
+			Fortitude::Force,
+		));
+		let treasury = Balances::free_balance(Treasury::get());
+		System::set_block_number(period + 101);
+		assert_ok!(escheat(RuntimeOrigin::signed(ALICE), htlc_id));
+		System::assert_last_event(
+			Event::ResidualHoldEscheated {
+				key: htlc_id,
+				who: RESOLVER_BOB,
+				reason: HoldReason::SafetyDeposit,
+				amount: 20,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::free_balance(Treasury::get()), treasury + 20);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert!(!ResidualHolds::<Test>::contains_key(htlc_id, residual));
+		assert!(!ExpectedHolds::<Test>::contains_key(RESOLVER_BOB));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn governance_reconciles_residual_holds_back_to_their_accounts() {
+	use frame_support::traits::fungible::MutateHold;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order"),
+			hash_of_word(b"residual"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		let htlc_id = HtlcEscrow::hash_immutables(&immutables);
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables.clone(),
+			immutables.timelocks.cancellation_after + 100,
+			GENERIC_CHAIN,
+		));
+		assert_ok!(<Balances as MutateHold<u64>>::hold(
+			&HoldReason::SwapAmount.into(),
+			&RESOLVER_BOB,
+			30,
+		));
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			b"residual".to_vec()
+		));
+		assert!(ResidualHolds::<Test>::contains_key(
+			htlc_id,
+			(RESOLVER_BOB, HoldReason::SwapAmount)
+		));
+
+		// governance releases it back before it can be swept
+		assert_ok!(HtlcEscrow::force_reconcile(RuntimeOrigin::root(), htlc_id));
+		System::assert_has_event(
+			Event::OrphanedHoldReleased {
+				who: RESOLVER_BOB,
+				reason: HoldReason::SwapAmount,
+				amount: 30,
+			}
+			.into(),
+		);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert_eq!(ResidualHolds::<Test>::iter_prefix(htlc_id).count(), 0);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// keys that are no HTLC and left nothing behind are unknown
+		assert_noop!(
+			HtlcEscrow::force_reconcile(RuntimeOrigin::root(), hash_of_word(b"unknown")),
+			Error::<Test>::HtlcDoesNotExist
+		);
+	});
+}
+
+#[test]
+fn takers_withdraw_several_htlcs_with_one_secret() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8], amount| {
+			let immutables = create_test_htlc_immutables(
+				hash_of_word(b"split order"),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				amount,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			HtlcEscrow::hash_immutables(&immutables)
+		};
+		// the order is split into three escrows locked with the same secret
+		let split: Vec<_> = [SWAP_AMOUNT, SWAP_AMOUNT / 2, SWAP_AMOUNT / 4]
+			.into_iter()
+			.map(|amount| create(b"shared", amount))
+			.collect();
+		let other = create(b"other", SWAP_AMOUNT);
+		assert_eq!(HashlockIndex::<Test>::get(hash_of_word(b"shared")).to_vec(), split);
+
+		// one reveal withdraws all of them, and the HTLCs that the secret
+		// doesn't unlock are skipped
+		let maker_balance = Balances::free_balance(ALICE);
+		System::set_block_number(101);
+		let mut htlc_ids = split.clone();
+		htlc_ids.extend([other, H256::zero()]);
+		assert_ok!(HtlcEscrow::withdraw_all_with_secret(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			b"shared".to_vec(),
+			htlc_ids.try_into().unwrap(),
+		));
+		for htlc_id in &split {
+			assert_eq!(Htlcs::<Test>::get(htlc_id).unwrap().status, HtlcStatus::Completed);
+		}
+		assert_eq!(
+			Balances::free_balance(ALICE),
+			maker_balance + SWAP_AMOUNT + SWAP_AMOUNT / 2 + SWAP_AMOUNT / 4
+		);
+		assert_eq!(
+			RevealedSecrets::<Test>::get(101).to_vec(),
+			vec![(hash_of_word(b"shared"), split[0])]
+		);
+		assert_eq!(Htlcs::<Test>::get(other).unwrap().status, HtlcStatus::Active);
+		System::assert_has_event(
+			Event::HtlcWithdrawalFailed {
+				htlc_id: other,
+				error: Error::<Test>::InvalidSecret.into(),
+			}
+			.into(),
+		);
+		System::assert_last_event(
+			Event::HtlcWithdrawalFailed {
+				htlc_id: H256::zero(),
+				error: Error::<Test>::HtlcDoesNotExist.into(),
+			}
+			.into(),
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn src_htlcs_take_the_timelocks_recommended_for_the_chain() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let template = TimelockTemplate {
+			withdrawal_offset: 100,
+			public_withdrawal_offset: 200,
+			cancellation_offset: 300,
+		};
+
+		// templates must be recommended by governance, for known chains, in order
+		assert_noop!(
+			HtlcEscrow::set_timelock_template(
+				RuntimeOrigin::signed(ALICE),
+				GENERIC_CHAIN,
+				Some(template.clone())
+			),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			HtlcEscrow::set_timelock_template(RuntimeOrigin::root(), 7, Some(template.clone())),
+			Error::<Test>::UnknownChain
+		);
+		assert_noop!(
+			HtlcEscrow::set_timelock_template(
+				RuntimeOrigin::root(),
+				GENERIC_CHAIN,
+				Some(TimelockTemplate { cancellation_offset: 100, ..template.clone() })
+			),
+			Error::<Test>::InvalidTimelocks
+		);
+
+		let hashlock = hash_of_word(b"secret");
+		let swap_intent = create_swap_intent(
+			hashlock,
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		assert_noop!(
+			HtlcEscrow::create_src_htlc_with_template(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				ALICE,
+				0,
+				SAFETY_DEPOSIT
+			),
+			Error::<Test>::NoTimelockTemplate
+		);
+
+		assert_ok!(HtlcEscrow::set_timelock_template(
+			RuntimeOrigin::root(),
+			GENERIC_CHAIN,
+			Some(template.clone())
+		));
+		System::assert_last_event(
+			Event::TimelockTemplateSet { chain_id: GENERIC_CHAIN, template: Some(template) }.into(),
+		);
+
+		// the timelocks of the template run from the creation of the HTLC
+		System::set_block_number(5);
+		assert_ok!(HtlcEscrow::create_src_htlc_with_template(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			SAFETY_DEPOSIT
+		));
+		let immutables = create_test_htlc_immutables(
+			HtlcEscrow::intent_key(&ALICE, 0),
+			hashlock,
+			ALICE,
+			RESOLVER_BOB,
+			SRC_AMOUNT,
+			SAFETY_DEPOSIT,
+			5,
+		);
+		assert!(Htlcs::<Test>::contains_key(HtlcEscrow::hash_immutables(&immutables)));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn dst_amounts_are_normalized_to_the_native_decimals() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// an intent paid in 6-decimal USDC, against 12-decimal native balances
+		let mut swap_intent = create_swap_intent(
+			hash_of_word(b"secret"),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		swap_intent.dst_decimals = Some(6);
+		assert_eq!(HtlcEscrow::dst_decimals(&swap_intent), Ok(6));
+		assert_eq!(HtlcEscrow::normalized_dst_amount(&swap_intent), Ok(DST_AMOUNT * 1_000_000));
+		assert_ok!(HtlcEscrow::create_swap_intent(
+			RuntimeOrigin::signed(ALICE),
+			swap_intent.clone()
+		));
+
+		// without decimals, the intent is quoted in the decimals of its chain
+		let mut generic_intent = swap_intent.clone();
+		generic_intent.dst_decimals = None;
+		assert_eq!(HtlcEscrow::dst_decimals(&generic_intent), Ok(12));
+		assert_eq!(HtlcEscrow::normalized_dst_amount(&generic_intent), Ok(DST_AMOUNT));
+
+		// amounts that overflow the native decimals are rejected
+		let mut huge_intent = swap_intent.clone();
+		huge_intent.hashlock = hash_of_word(b"huge");
+		huge_intent.nonce = 1;
+		huge_intent.dst_decimals = Some(0);
+		huge_intent.dst_amount = u128::MAX / 10;
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), huge_intent.clone()),
+			Error::<Test>::InvalidDecimals
+		);
+
+		// as are tokens with more decimals than a balance holds
+		huge_intent.dst_decimals = Some(decimals::MAX_DECIMALS + 1);
+		huge_intent.dst_amount = DST_AMOUNT;
+		assert_noop!(
+			HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), huge_intent),
+			Error::<Test>::InvalidDecimals
+		);
+
+		assert_eq!(decimals::normalize(123_456_789u128, 12, 6), Some(123));
+		assert_eq!(decimals::normalize(1u128, 0, 39), None);
+		assert_eq!(decimals::normalize(u128::MAX, 38, 0), Some(3));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn intents_migrate_to_the_decimals_of_their_chain() {
+	use crate::migrations::{v2, v3, LazyMigrationV3};
+	use frame_support::{
+		migrations::SteppedMigration,
+		traits::{GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+
+	new_test_ext().execute_with(|| {
+		// three intents stored in the v2 layout
+		StorageVersion::new(2).put::<HtlcEscrow>();
+		let keys: Vec<H256> = (0..3u64)
+			.map(|nonce| {
+				let intent = create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce,
+				);
+				let key = HtlcEscrow::intent_key(&ALICE, nonce);
+				v2::SwapIntents::<Test>::insert(
+					key,
+					v2::StoredSwapIntent {
+						intent: v2::SwapIntent {
+							hashlock: intent.hashlock,
+							maker: intent.maker,
+							src_amount: intent.src_amount,
+							dst_amount: intent.dst_amount,
+							dst_address: intent.dst_address,
+							dst_chain: intent.dst_chain,
+							timeout_after_block: intent.timeout_after_block,
+							nonce: intent.nonce,
+							auction: intent.auction,
+							min_fill_amount: intent.min_fill_amount,
+							flags: v3::IntentFlags {
+								fill_or_kill: intent.flags.fill_or_kill,
+								allow_multiple_fills: intent.flags.allow_multiple_fills,
+							},
+							epoch: intent.epoch,
+							denied_resolvers: vec![RESOLVER_CHARLIE].try_into().unwrap(),
+						},
+						status: IntentStatus::Active,
+						created_at: 1,
+						maker_deposit: 0,
+						taken_at: None,
+						reservation_bond: 0,
+						fills: 0,
+						storage_deposit: 0,
+					},
+				);
+				key
+			})
+			.collect();
+
+		// two intents are migrated per step, until the version is bumped
+		let per_intent = <() as WeightInfo>::migrate_intent();
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_intent.saturating_mul(2));
+			cursor = LazyMigrationV3::<Test>::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 2);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(3));
+		for (nonce, key) in keys.into_iter().enumerate() {
+			let stored_intent = v3::SwapIntents::<Test>::get(key).expect("intent is migrated; qed");
+			assert_eq!(stored_intent.intent.nonce, nonce as u64);
+			assert_eq!(stored_intent.intent.denied_resolvers.into_inner(), vec![RESOLVER_CHARLIE]);
+			assert_eq!(stored_intent.intent.dst_decimals, None);
+		}
+
+		// migrated chains are left alone
+		assert!(matches!(
+			LazyMigrationV3::<Test>::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn src_cancellation_blocks_are_converted_to_blocks() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let now = Now::get();
+		let margin: u64 = <Test as Config>::TimestampSafetyMargin::get();
+		let immutables = create_test_htlc_immutables(
+			hash_of_word(b"order"),
+			hash_of_word(b"secret"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		let create = |timestamp: u64| {
+			HtlcEscrow::create_dst_htlc_with_src_timestamp(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				timestamp,
+				GENERIC_CHAIN,
+			)
+		};
+
+		// block numbers, and timestamps within the margin, are rejected
+		assert_noop!(create(301), Error::<Test>::SrcCancellationPassed);
+		assert_noop!(create(now + margin), Error::<Test>::SrcCancellationPassed);
+
+		// as are timestamps in milliseconds
+		assert_noop!(create((now + margin + 1_800) * 1_000), Error::<Test>::SrcCancellationTooFar);
+
+		// 1_800 seconds after the margin are 300 blocks of 6 seconds, rounded down
+		assert_eq!(HtlcEscrow::block_before_timestamp(now + margin + 1_805), Ok(301));
+		assert_noop!(create(now + margin + 1_799), Error::<Test>::InvalidTimelocks);
+		assert_ok!(create(now + margin + 1_800));
+		assert!(Htlcs::<Test>::contains_key(HtlcEscrow::hash_immutables(&immutables)));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn origins_are_restricted_to_registered_resolvers() {
+	use crate::origin::{EnsureRegisteredResolver, RegisteredResolvers};
+	use frame_support::traits::{Contains, EnsureOrigin};
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert!(!RegisteredResolvers::<Test>::contains(&RESOLVER_BOB));
+		assert!(EnsureRegisteredResolver::<Test>::try_origin(RuntimeOrigin::signed(RESOLVER_BOB))
+			.is_err());
+		assert_noop!(
+			HtlcEscrow::ensure_registered_resolver(RuntimeOrigin::signed(RESOLVER_BOB)),
+			Error::<Test>::NotResolver
+		);
+
+		assert_ok!(HtlcEscrow::register_resolver(RuntimeOrigin::root(), RESOLVER_BOB, 2_500));
+		assert!(RegisteredResolvers::<Test>::contains(&RESOLVER_BOB));
+		assert_eq!(
+			EnsureRegisteredResolver::<Test>::try_origin(RuntimeOrigin::signed(RESOLVER_BOB)).ok(),
+			Some(RESOLVER_BOB)
+		);
+		assert_eq!(
+			HtlcEscrow::ensure_registered_resolver(RuntimeOrigin::signed(RESOLVER_BOB)),
+			Ok(RESOLVER_BOB)
+		);
+
+		// neither other accounts nor other origins are resolvers
+		assert!(EnsureRegisteredResolver::<Test>::try_origin(RuntimeOrigin::signed(ALICE)).is_err());
+		assert!(EnsureRegisteredResolver::<Test>::try_origin(RuntimeOrigin::root()).is_err());
+		assert!(EnsureRegisteredResolver::<Test>::try_origin(RuntimeOrigin::none()).is_err());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn makers_forfeit_their_deposit_when_cancelling_bound_intents() {
+	use crate::fusion::EvmImmutables;
+	use sp_core::U256;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let maker_deposit = 30;
+		MakerDeposit::set(maker_deposit);
+		let reason = HoldReason::MakerSafetyDeposit.into();
+
+		let take = |nonce: u64, resolver: u64| {
+			let hashlock = hash_of_word(&nonce.to_le_bytes());
+			let swap_intent = create_swap_intent(
+				hashlock,
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(1),
+				1000,
+				nonce,
+			);
+			assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+			assert_ok!(HtlcEscrow::create_src_htlc(
+				RuntimeOrigin::signed(resolver),
+				ALICE,
+				nonce,
+				create_timelocks(1),
+				SAFETY_DEPOSIT
+			));
+			(hashlock, HashlockIndex::<Test>::get(hashlock)[0])
+		};
+
+		// the resolver that never referenced a counterpart escrow made no
+		// verifiable progress, so the maker keeps its deposit
+		take(0, RESOLVER_BOB);
+
+		// the resolver that did is paid the deposit of the cancelling maker
+		let (hashlock, htlc_id) = take(1, RESOLVER_CHARLIE);
+		assert_ok!(HtlcEscrow::bind_counterpart_escrow(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			htlc_id,
+			H160::repeat_byte(0xfa),
+			H160::repeat_byte(0x1e),
+			Box::new(EvmImmutables {
+				order_hash: H256::repeat_byte(1),
+				hashlock,
+				maker: H160::repeat_byte(2),
+				taker: H160::repeat_byte(3),
+				token: H160::repeat_byte(4),
+				amount: U256::from(DST_AMOUNT),
+				safety_deposit: U256::from(SAFETY_DEPOSIT),
+				timelocks: U256::zero(),
+			})
+		));
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 2 * maker_deposit);
+
+		System::set_block_number(401);
+		let maker_balance = Balances::free_balance(ALICE);
+		let resolver_balance = Balances::free_balance(RESOLVER_CHARLIE);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		assert_eq!(Balances::free_balance(ALICE), maker_balance + SRC_AMOUNT + maker_deposit);
+
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 1));
+		assert_eq!(Balances::balance_on_hold(&reason, &ALICE), 0);
+		assert_eq!(Balances::free_balance(ALICE), maker_balance + 2 * SRC_AMOUNT + maker_deposit);
+		assert_eq!(
+			Balances::free_balance(RESOLVER_CHARLIE),
+			resolver_balance + SAFETY_DEPOSIT + maker_deposit
+		);
+		System::assert_has_event(
+			Event::MakerDepositForfeited {
+				intent_key: HtlcEscrow::intent_key(&ALICE, 1),
+				maker: ALICE,
+				resolver: RESOLVER_CHARLIE,
+				amount: maker_deposit,
+			}
+			.into(),
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn resolvers_quote_intents_on_chain() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let swap_intent = create_swap_intent(
+			hash_of_word(b"secret"),
+			ALICE,
+			SRC_AMOUNT,
+			DST_AMOUNT,
+			get_evm_dst_address(1),
+			1000,
+			0,
+		);
+		assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+		let intent_key = HtlcEscrow::intent_key(&ALICE, 0);
+		let quote = |resolver: u64, dst_amount: u128, expires_at: u64| {
+			HtlcEscrow::post_quote(
+				RuntimeOrigin::signed(resolver),
+				ALICE,
+				0,
+				dst_amount,
+				expires_at,
+			)
+		};
+
+		// quotes pay at least the intent and expire with it
+		assert_noop!(quote(RESOLVER_BOB, DST_AMOUNT - 1, 50), Error::<Test>::InvalidQuote);
+		assert_noop!(quote(RESOLVER_BOB, DST_AMOUNT, 1), Error::<Test>::InvalidQuote);
+		assert_noop!(quote(RESOLVER_BOB, DST_AMOUNT, 1001), Error::<Test>::InvalidQuote);
+
+		// each resolver keeps one quote, and the best comes first
+		assert_ok!(quote(RESOLVER_BOB, DST_AMOUNT + 10, 50));
+		assert_ok!(quote(RESOLVER_CHARLIE, DST_AMOUNT + 20, 100));
+		assert_ok!(quote(RESOLVER_BOB, DST_AMOUNT + 30, 50));
+		System::assert_last_event(
+			Event::QuotePosted {
+				intent_key,
+				resolver: RESOLVER_BOB,
+				dst_amount: DST_AMOUNT + 30,
+				expires_at: 50,
+			}
+			.into(),
+		);
+		let resolvers = |now: u64| -> Vec<u64> {
+			HtlcEscrow::live_quotes(intent_key, now)
+				.into_iter()
+				.map(|q| q.resolver)
+				.collect()
+		};
+		assert_eq!(resolvers(1), vec![RESOLVER_BOB, RESOLVER_CHARLIE]);
+		assert_eq!(resolvers(50), vec![RESOLVER_CHARLIE]);
+
+		// a full book only takes quotes better than its lowest
+		for resolver in 100..114 {
+			assert_ok!(quote(resolver, DST_AMOUNT, 100));
+		}
+		assert_noop!(quote(114, DST_AMOUNT, 100), Error::<Test>::QuoteBookFull);
+		assert_ok!(quote(114, DST_AMOUNT + 1, 100));
+		System::assert_has_event(Event::QuoteWithdrawn { intent_key, resolver: 100 }.into());
+		assert_eq!(IntentQuotes::<Test>::get(intent_key).len(), 16);
+
+		// resolvers withdraw their quotes
+		assert_ok!(HtlcEscrow::withdraw_quote(RuntimeOrigin::signed(RESOLVER_CHARLIE), ALICE, 0));
+		assert_noop!(
+			HtlcEscrow::withdraw_quote(RuntimeOrigin::signed(RESOLVER_CHARLIE), ALICE, 0),
+			Error::<Test>::QuoteNotFound
+		);
+
+		// the maker accepts a live quote, reserving the intent for its resolver
+		assert_noop!(
+			HtlcEscrow::accept_quote(RuntimeOrigin::signed(ALICE), 0, RESOLVER_CHARLIE),
+			Error::<Test>::QuoteNotFound
+		);
+		assert_ok!(HtlcEscrow::accept_quote(RuntimeOrigin::signed(ALICE), 0, RESOLVER_BOB));
+		System::assert_last_event(
+			Event::QuoteAccepted {
+				intent_key,
+				resolver: RESOLVER_BOB,
+				dst_amount: DST_AMOUNT + 30,
+			}
+			.into(),
+		);
+		assert_eq!(
+			SwapIntents::<Test>::get(intent_key).unwrap().status,
+			IntentStatus::Reserved { resolver: RESOLVER_BOB }
+		);
+		assert!(IntentQuotes::<Test>::get(intent_key).is_empty());
+		assert_noop!(quote(RESOLVER_CHARLIE, DST_AMOUNT, 100), Error::<Test>::IntentNotActive);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn accepted_quotes_bind_their_resolvers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let post_intent = |nonce: u64, preimage: &[u8]| {
+			let mut swap_intent = create_swap_intent(
+				H256(sha2_256(preimage)),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(1),
+				1000,
+				nonce,
+			);
+			swap_intent.dst_chain = BITCOIN_CHAIN;
+			swap_intent.dst_address = DstAddress::BitcoinScriptHash(H160::repeat_byte(9));
+			assert_ok!(HtlcEscrow::create_swap_intent(RuntimeOrigin::signed(ALICE), swap_intent));
+			HtlcEscrow::intent_key(&ALICE, nonce)
+		};
+		let quote = |resolver: u64, nonce: u64, dst_amount: u128| {
+			assert_ok!(HtlcEscrow::post_quote(
+				RuntimeOrigin::signed(resolver),
+				ALICE,
+				nonce,
+				dst_amount,
+				500,
+			));
+		};
+
+		// the resolver of the accepted quote pays the quoted price
+		let filled = post_intent(0, b"filled");
+		quote(RESOLVER_BOB, 0, DST_AMOUNT + 30);
+		assert_ok!(HtlcEscrow::accept_quote(RuntimeOrigin::signed(ALICE), 0, RESOLVER_BOB));
+		assert_eq!(AcceptedQuotes::<Test>::get(filled).unwrap().dst_amount, DST_AMOUNT + 30);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		assert_ok!(HtlcEscrow::create_src_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			ALICE,
+			0,
+			create_timelocks(1),
+			SAFETY_DEPOSIT,
+		));
+		let htlc_id = match SwapIntents::<Test>::get(filled).map(|stored| stored.status) {
+			Some(IntentStatus::InProgress { htlc_id, .. }) => htlc_id,
+			status => panic!("unexpected intent status {status:?}"),
+		};
+		System::assert_last_event(
+			Event::BitcoinCounterpartRequested {
+				htlc_id,
+				payment_hash: H256(sha2_256(b"filled")),
+				recipient: DstAddress::BitcoinScriptHash(H160::repeat_byte(9)),
+				amount: DST_AMOUNT + 30,
+				cancellation_after: create_timelocks(1).cancellation_after,
+			}
+			.into(),
+		);
+		assert!(AcceptedQuotes::<Test>::get(filled).is_none());
+
+		// a resolver that lets the reservation lapse cools down, although the
+		// maker held no bond from it
+		let lapsed = post_intent(1, b"lapsed");
+		quote(RESOLVER_CHARLIE, 1, DST_AMOUNT + 20);
+		assert_ok!(HtlcEscrow::accept_quote(RuntimeOrigin::signed(ALICE), 1, RESOLVER_CHARLIE));
+		let delay: u64 = <Test as Config>::IntentCancellationDelay::get();
+		let cooldown: u64 = <Test as Config>::ResolverCooldown::get();
+		let cancellable_at = 1 + delay;
+		System::set_block_number(cancellable_at);
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 1));
+		System::assert_has_event(
+			Event::ResolverCooldownStarted {
+				resolver: RESOLVER_CHARLIE,
+				until: cancellable_at + cooldown,
+			}
+			.into(),
+		);
+		assert!(AcceptedQuotes::<Test>::get(lapsed).is_none());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn watchtowers_share_the_rewards_of_the_htlcs_they_watch() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				H256::repeat_byte(1),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			(HtlcEscrow::hash_immutables(&immutables), immutables)
+		};
+		let (withdrawn_id, withdrawn) = create(b"withdrawn");
+		let (cancelled_id, cancelled) = create(b"cancelled");
+
+		// watchtowers opt in, and the parties of each HTLC or governance
+		// assign it one
+		let stranger = 7;
+		assert_noop!(
+			HtlcEscrow::watch_htlc(RuntimeOrigin::signed(ALICE), withdrawn_id, RESOLVER_CHARLIE),
+			Error::<Test>::NotWatchtower
+		);
+		assert_ok!(HtlcEscrow::register_watchtower(RuntimeOrigin::signed(RESOLVER_CHARLIE)));
+		assert_ok!(HtlcEscrow::register_watchtower(RuntimeOrigin::signed(stranger)));
+		assert_noop!(
+			HtlcEscrow::watch_htlc(RuntimeOrigin::signed(stranger), withdrawn_id, stranger),
+			Error::<Test>::InvalidCaller
+		);
+		assert_ok!(HtlcEscrow::watch_htlc(
+			RuntimeOrigin::signed(ALICE),
+			withdrawn_id,
+			RESOLVER_CHARLIE
+		));
+		assert_ok!(HtlcEscrow::watch_htlc(RuntimeOrigin::root(), cancelled_id, RESOLVER_CHARLIE));
+		System::assert_last_event(
+			Event::WatchtowerAssigned { htlc_id: cancelled_id, watchtower: RESOLVER_CHARLIE }
+				.into(),
+		);
+		assert_noop!(
+			HtlcEscrow::watch_htlc(RuntimeOrigin::signed(RESOLVER_BOB), withdrawn_id, stranger),
+			Error::<Test>::HtlcAlreadyWatched
+		);
+		assert_noop!(
+			HtlcEscrow::unwatch_htlc(RuntimeOrigin::signed(stranger), withdrawn_id),
+			Error::<Test>::NotWatching
+		);
+		assert_noop!(
+			HtlcEscrow::deregister_watchtower(RuntimeOrigin::signed(RESOLVER_CHARLIE)),
+			Error::<Test>::WatchtowerOnDuty
+		);
+		assert_eq!(Watchtowers::<Test>::get(RESOLVER_CHARLIE).unwrap().duties, 2);
+
+		// the watchtower is credited its share of the safety deposit of a
+		// public withdrawal that it called
+		System::set_block_number(withdrawn.timelocks.public_withdrawal_after);
+		let maker_balance = Balances::free_balance(ALICE);
+		let watchtower_balance = Balances::free_balance(RESOLVER_CHARLIE);
+		assert_ok!(HtlcEscrow::public_withdraw(
+			RuntimeOrigin::signed(RESOLVER_CHARLIE),
+			withdrawn,
+			b"withdrawn".to_vec(),
+		));
+		let share = SAFETY_DEPOSIT / 5;
+		assert_eq!(Balances::free_balance(ALICE), maker_balance + SWAP_AMOUNT);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), watchtower_balance + SAFETY_DEPOSIT);
+		System::assert_has_event(
+			Event::WatchtowerRewarded {
+				htlc_id: withdrawn_id,
+				watchtower: RESOLVER_CHARLIE,
+				amount: share,
+			}
+			.into(),
+		);
+
+		// and of the reward of a public cancellation
+		let delay: u64 = <Test as Config>::PublicCancellationDelay::get();
+		System::set_block_number(cancelled.timelocks.cancellation_after + delay);
+		let watchtower_balance = Balances::free_balance(RESOLVER_CHARLIE);
+		assert_ok!(HtlcEscrow::public_cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), cancelled));
+		let reward = SAFETY_DEPOSIT / 2;
+		let share = reward / 5;
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), watchtower_balance + reward);
+		System::assert_last_event(
+			Event::PublicCancellationRewarded {
+				htlc_id: cancelled_id,
+				caller: RESOLVER_CHARLIE,
+				reward: reward - share,
+			}
+			.into(),
+		);
+
+		// settled HTLCs release their watchtower
+		let info = Watchtowers::<Test>::get(RESOLVER_CHARLIE).unwrap();
+		assert_eq!(info.duties, 0);
+		assert_eq!(info.rewards, SAFETY_DEPOSIT / 5 + share);
+		assert_ok!(HtlcEscrow::deregister_watchtower(RuntimeOrigin::signed(RESOLVER_CHARLIE)));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn unrelated_callers_rescue_watched_htlcs_without_rewarding_the_watchtower() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				H256::repeat_byte(1),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			(HtlcEscrow::hash_immutables(&immutables), immutables)
+		};
+		let (withdrawn_id, withdrawn) = create(b"withdrawn");
+		let (cancelled_id, cancelled) = create(b"cancelled");
+		assert_ok!(HtlcEscrow::register_watchtower(RuntimeOrigin::signed(RESOLVER_CHARLIE)));
+		assert_ok!(HtlcEscrow::watch_htlc(
+			RuntimeOrigin::signed(ALICE),
+			withdrawn_id,
+			RESOLVER_CHARLIE
+		));
+		assert_ok!(HtlcEscrow::watch_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			cancelled_id,
+			RESOLVER_CHARLIE
+		));
+
+		// another account withdraws the HTLC before its watchtower does, and
+		// keeps the whole safety deposit
+		let stranger = 7;
+		System::set_block_number(withdrawn.timelocks.public_withdrawal_after);
+		let watchtower_balance = Balances::free_balance(RESOLVER_CHARLIE);
+		assert_ok!(HtlcEscrow::public_withdraw(
+			RuntimeOrigin::signed(stranger),
+			withdrawn,
+			b"withdrawn".to_vec(),
+		));
+		assert_eq!(Balances::free_balance(stranger), SAFETY_DEPOSIT);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), watchtower_balance);
+
+		// and the whole reward of cancelling the other one
+		let delay: u64 = <Test as Config>::PublicCancellationDelay::get();
+		System::set_block_number(cancelled.timelocks.cancellation_after + delay);
+		assert_ok!(HtlcEscrow::public_cancel(RuntimeOrigin::signed(stranger), cancelled));
+		let reward = SAFETY_DEPOSIT / 2;
+		assert_eq!(Balances::free_balance(stranger), SAFETY_DEPOSIT + reward);
+		assert_eq!(Balances::free_balance(RESOLVER_CHARLIE), watchtower_balance);
+		System::assert_last_event(
+			Event::PublicCancellationRewarded { htlc_id: cancelled_id, caller: stranger, reward }
+				.into(),
+		);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::HtlcEscrow(Event::WatchtowerRewarded { .. })
+		)));
+
+		// the settled HTLCs still release their watchtower
+		assert_eq!(
+			Watchtowers::<Test>::get(RESOLVER_CHARLIE),
+			Some(WatchtowerInfo { duties: 0, rewards: 0 })
+		);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn settlements_at_risk_are_warned_before_cancellation() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		SettlementWarningPeriod::set(50);
+
+		let create = |word: &[u8]| {
+			let immutables = create_test_htlc_immutables(
+				H256::repeat_byte(1),
+				hash_of_word(word),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				1,
+			);
+			assert_ok!(HtlcEscrow::create_dst_htlc(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables.clone(),
+				immutables.timelocks.cancellation_after + 100,
+				GENERIC_CHAIN,
+			));
+			immutables
+		};
+		let settled = create(b"settled");
+		let stalled = create(b"stalled");
+		let stalled_id = HtlcEscrow::hash_immutables(&stalled);
+		let warned_at = stalled.timelocks.cancellation_after - 50;
+		assert_eq!(SettlementWarnings::<Test>::get(warned_at).len(), 2);
+
+		// only the HTLCs that are still active are at risk
+		System::set_block_number(settled.timelocks.withdrawal_after);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			settled,
+			b"settled".to_vec()
+		));
+		System::reset_events();
+		System::set_block_number(warned_at);
+		HtlcEscrow::on_initialize(warned_at);
+		assert_eq!(
+			System::events().into_iter().map(|record| record.event).collect::<Vec<_>>(),
+			vec![RuntimeEvent::HtlcEscrow(Event::SettlementAtRisk {
+				htlc_id: stalled_id,
+				taker: RESOLVER_BOB,
+				cancellation_after: stalled.timelocks.cancellation_after,
+			})]
+		);
+		assert_eq!(SettlementWarnings::<Test>::iter().count(), 0);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn immutables_are_validated_before_submission() {
+	use crate::validation::{ImmutablesError, ValidatedImmutables};
+
+	new_test_ext().execute_with(|| {
+		let immutables = create_test_htlc_immutables(
+			H256::repeat_byte(1),
+			hash_of_word(b"validated"),
+			ALICE,
+			RESOLVER_BOB,
+			SWAP_AMOUNT,
+			SAFETY_DEPOSIT,
+			1,
+		);
+		let src_cancellation = immutables.timelocks.cancellation_after;
+		let validate = |immutables: &Immutables<u64, u128, u64>| {
+			ValidatedImmutables::new(immutables.clone(), Some(&src_cancellation)).map(|_| ())
+		};
+		assert_eq!(validate(&immutables), Ok(()));
+
+		// clients learn why the extrinsic would fail without submitting it
+		let cases = [
+			(Immutables { amount: 0, ..immutables.clone() }, ImmutablesError::ZeroAmount),
+			(
+				Immutables { hashlock: H256::zero(), ..immutables.clone() },
+				ImmutablesError::ZeroHashlock,
+			),
+			(
+				Immutables {
+					timelocks: Timelocks { withdrawal_after: 250, ..immutables.timelocks.clone() },
+					..immutables.clone()
+				},
+				ImmutablesError::TimelocksOutOfOrder,
+			),
+			(
+				Immutables {
+					timelocks: Timelocks { withdrawal_after: 0, ..immutables.timelocks.clone() },
+					..immutables.clone()
+				},
+				ImmutablesError::TimelocksOutOfOrder,
+			),
+			(
+				Immutables {
+					timelocks: Timelocks {
+						cancellation_after: src_cancellation + 1,
+						..immutables.timelocks.clone()
+					},
+					..immutables.clone()
+				},
+				ImmutablesError::CancelledAfterSource,
+			),
+		];
+		for (invalid, error) in cases {
+			assert_eq!(validate(&invalid), Err(error));
+			assert_noop!(
+				HtlcEscrow::create_dst_htlc(
+					RuntimeOrigin::signed(RESOLVER_BOB),
+					invalid,
+					src_cancellation,
+					GENERIC_CHAIN,
+				),
+				Error::<Test>::from(error)
+			);
+		}
+
+		assert_ok!(HtlcEscrow::create_dst_htlc(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			immutables,
+			src_cancellation,
+			GENERIC_CHAIN,
+		));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn intents_are_opened_from_the_templates_of_makers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let template = IntentTemplate {
+			src_amount: SRC_AMOUNT,
+			dst_amount: DST_AMOUNT,
+			dst_address: get_evm_dst_address(ALICE + 1000),
+			dst_chain: GENERIC_CHAIN,
+			dst_decimals: None,
+			validity: Some(1000),
+			flags: Default::default(),
+			min_fill_amount: 0,
+		};
+		assert_noop!(
+			HtlcEscrow::create_intent_from_template(
+				RuntimeOrigin::signed(ALICE),
+				7,
+				hash_of_word(b"first"),
+				0
+			),
+			Error::<Test>::NoIntentTemplate
+		);
+		assert_noop!(
+			HtlcEscrow::set_intent_template(
+				RuntimeOrigin::signed(ALICE),
+				7,
+				Some(IntentTemplate { validity: Some(0), ..template.clone() })
+			),
+			Error::<Test>::InvalidIntentTemplate
+		);
+		assert_ok!(HtlcEscrow::set_intent_template(
+			RuntimeOrigin::signed(ALICE),
+			7,
+			Some(template.clone())
+		));
+		System::assert_last_event(
+			Event::IntentTemplateSet { maker: ALICE, template_id: 7, template: Some(template) }
+				.into(),
+		);
+
+		// each recurring swap opens an intent from the current block
+		for (nonce, word) in [b"first", b"other"].into_iter().enumerate() {
+			let nonce = nonce as u64;
+			System::set_block_number(1 + 100 * nonce);
+			assert_ok!(HtlcEscrow::create_intent_from_template(
+				RuntimeOrigin::signed(ALICE),
+				7,
+				hash_of_word(word),
+				nonce
+			));
+			let expected = create_swap_intent(
+				hash_of_word(word),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				1001 + 100 * nonce,
+				nonce,
+			);
+			System::assert_last_event(
+				Event::SwapIntentCreated {
+					intent_key: HtlcEscrow::intent_key(&ALICE, nonce),
+					intent: expected,
+				}
+				.into(),
+			);
+		}
+		assert_eq!(
+			Balances::balance_on_hold(&crate::HoldReason::MakerSwapIntentAmount.into(), &ALICE),
+			2 * SRC_AMOUNT
+		);
+
+		// templates are kept per maker
+		assert_noop!(
+			HtlcEscrow::create_intent_from_template(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				7,
+				hash_of_word(b"bob"),
+				0
+			),
+			Error::<Test>::NoIntentTemplate
+		);
+
+		assert_ok!(HtlcEscrow::set_intent_template(RuntimeOrigin::signed(ALICE), 7, None));
+		assert!(IntentTemplates::<Test>::get(ALICE, 7).is_none());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn intents_migrate_to_opt_in_partial_fills() {
+	use crate::migrations::{v3, LazyMigrationV4};
+	use frame_support::{
+		migrations::SteppedMigration,
+		traits::{GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+
+	new_test_ext().execute_with(|| {
+		// three intents stored in the v3 layout
+		StorageVersion::new(3).put::<HtlcEscrow>();
+		let keys: Vec<H256> = (0..3u64)
+			.map(|nonce| {
+				let intent = create_swap_intent(
+					hash_of_word(&nonce.to_le_bytes()),
+					ALICE,
+					SRC_AMOUNT,
+					DST_AMOUNT,
+					get_evm_dst_address(ALICE + 1000),
+					1_000,
+					nonce,
+				);
+				let key = HtlcEscrow::intent_key(&ALICE, nonce);
+				v3::SwapIntents::<Test>::insert(
+					key,
+					v3::StoredSwapIntent {
+						intent: v3::SwapIntent {
+							hashlock: intent.hashlock,
+							maker: intent.maker,
+							src_amount: intent.src_amount,
+							dst_amount: intent.dst_amount,
+							dst_address: intent.dst_address,
+							dst_chain: intent.dst_chain,
+							timeout_after_block: intent.timeout_after_block,
+							nonce: intent.nonce,
+							auction: intent.auction,
+							min_fill_amount: SRC_AMOUNT / 4,
+							flags: v3::IntentFlags {
+								fill_or_kill: intent.flags.fill_or_kill,
+								allow_multiple_fills: intent.flags.allow_multiple_fills,
+							},
+							epoch: intent.epoch,
+							denied_resolvers: intent.denied_resolvers,
+							dst_decimals: Some(6),
+						},
+						status: IntentStatus::Active,
+						created_at: 1,
+						maker_deposit: 0,
+						taken_at: None,
+						reservation_bond: 0,
+						fills: 0,
+						storage_deposit: 0,
+					},
+				);
+				key
+			})
+			.collect();
+
+		// two intents are migrated per step, until the version is bumped
+		let per_intent = <() as WeightInfo>::migrate_intent();
+		let mut steps = 0;
+		let mut cursor = None;
+		loop {
+			let mut meter = WeightMeter::with_limit(per_intent.saturating_mul(2));
+			cursor = LazyMigrationV4::<Test>::step(cursor, &mut meter).unwrap();
+			steps += 1;
+			if cursor.is_none() {
+				break;
+			}
+		}
+		assert_eq!(steps, 2);
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(4));
+		for (nonce, key) in keys.into_iter().enumerate() {
+			let stored_intent = SwapIntents::<Test>::get(key).expect("intent is migrated; qed");
+			assert_eq!(stored_intent.intent.nonce, nonce as u64);
+			assert_eq!(stored_intent.intent.dst_decimals, Some(6));
+			assert_eq!(stored_intent.intent.min_fill_amount, SRC_AMOUNT / 4);
+			assert!(!stored_intent.intent.flags.allow_partial_fills);
+		}
+
+		// migrated chains are left alone
+		assert!(matches!(
+			LazyMigrationV4::<Test>::step(None, &mut WeightMeter::with_limit(Weight::zero())),
+			Ok(None)
+		));
+	});
+}
+
+#[test]
+fn storage_of_the_first_version_migrates_to_the_latest_layout() {
+	use codec::Encode;
+	use frame_support::{
+		migrations::SteppedMigrations,
+		storage::unhashed,
+		traits::{fungible::MutateHold, ConstU32, GetStorageVersion, StorageVersion},
+		weights::{Weight, WeightMeter},
+	};
+	// the swaps of the first version were with the generic chain
+	type Migrations = migrations::LazyMigrations<Test, ConstU32<GENERIC_CHAIN>>;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		StorageVersion::new(0).put::<HtlcEscrow>();
+
+		// the intents and HTLCs as the first version encoded them, with their
+		// funds held like it held them
+		let timelocks = (1u64, 101u64, 201u64, 301u64);
+		let put_intent = |nonce: u64, secret: &[u8]| {
+			let key = HtlcEscrow::intent_key(&ALICE, nonce);
+			let intent = (
+				hash_of_word(secret),
+				ALICE,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				H160::repeat_byte(nonce as u8),
+				1_000u64,
+				nonce,
+			);
+			// active, created at block 1
+			unhashed::put_raw(
+				&SwapIntents::<Test>::hashed_key_for(key),
+				&(intent, 0u8, 1u64).encode(),
+			);
+			key
+		};
+		let put_htlc = |order_hash: H256, secret: &[u8], status: u8, htlc_type: u8| {
+			let immutables = (
+				order_hash,
+				hash_of_word(secret),
+				ALICE,
+				RESOLVER_BOB,
+				SWAP_AMOUNT,
+				SAFETY_DEPOSIT,
+				timelocks,
+			)
+				.encode();
+			let htlc_id = H256(blake2_256(&immutables));
+			let htlc = [immutables, vec![status, htlc_type]].concat();
+			unhashed::put_raw(&Htlcs::<Test>::hashed_key_for(htlc_id), &htlc);
+			htlc_id
+		};
+		let hold = |reason: HoldReason, who: u64, amount: u128| {
+			assert_ok!(<Balances as MutateHold<_>>::hold(&reason.into(), &who, amount));
+		};
+
+		// an intent waiting for a resolver
+		let open_key = put_intent(0, b"open");
+		hold(HoldReason::MakerSwapIntentAmount, ALICE, SRC_AMOUNT);
+		// an intent filled by an active source HTLC, which left it active
+		let filled_key = put_intent(1, b"filled");
+		let src_id = put_htlc(filled_key, b"filled", 0, 0);
+		hold(HoldReason::MakerSwapIntentAmount, ALICE, SRC_AMOUNT);
+		hold(HoldReason::SafetyDeposit, RESOLVER_BOB, SAFETY_DEPOSIT);
+		// an intent settled by a withdrawn source HTLC, which left it active
+		let settled_key = put_intent(2, b"settled");
+		let settled_id = put_htlc(settled_key, b"settled", 1, 0);
+		// an active destination HTLC
+		let dst_order = hash_of_word(b"dst order");
+		let dst_id = put_htlc(dst_order, b"dst", 0, 1);
+		hold(HoldReason::SwapAmount, RESOLVER_BOB, SWAP_AMOUNT);
+		hold(HoldReason::SafetyDeposit, RESOLVER_BOB, SAFETY_DEPOSIT);
+
+		// run the migrations in their order, as the runtime does
+		for n in 0..Migrations::len() {
+			let mut cursor = None;
+			loop {
+				let mut meter = WeightMeter::with_limit(Weight::MAX);
+				cursor = Migrations::nth_step(n, cursor, &mut meter)
+					.expect("migration exists; qed")
+					.expect("migration steps; qed");
+				if cursor.is_none() {
+					break;
+				}
+			}
+		}
+		assert_eq!(HtlcEscrow::on_chain_storage_version(), StorageVersion::new(7));
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+
+		// the intents decode in the latest layout, with the status of their fills
+		let open = SwapIntents::<Test>::get(open_key).expect("intent is migrated; qed");
+		assert_eq!(open.status, IntentStatus::Active);
+		assert_eq!(open.intent.dst_address, DstAddress::Evm(H160::repeat_byte(0)));
+		assert_eq!(open.intent.dst_chain, GENERIC_CHAIN);
+		assert_eq!(open.intent.timeout_after_block, Some(1_000));
+		assert_eq!(IntentHashlocks::<Test>::get(hash_of_word(b"open")), Some(open_key));
+		let filled = SwapIntents::<Test>::get(filled_key).expect("intent is migrated; qed");
+		assert_eq!(
+			filled.status,
+			IntentStatus::InProgress { resolver: RESOLVER_BOB, htlc_id: src_id }
+		);
+		assert_eq!(filled.fills, 1);
+		assert_eq!(ResolverIntents::<Test>::get(RESOLVER_BOB), 1);
+		let settled = SwapIntents::<Test>::get(settled_key).expect("intent is migrated; qed");
+		assert_eq!(settled.status, IntentStatus::Completed);
+		let mut maker_intents = MakerIntents::<Test>::get(ALICE).into_inner();
+		maker_intents.sort();
+		let mut open_keys = vec![open_key, filled_key];
+		open_keys.sort();
+		assert_eq!(maker_intents, open_keys);
+
+		// the HTLCs keep their ids, and are indexed
+		for (htlc_id, secret) in
+			[(src_id, &b"filled"[..]), (settled_id, &b"settled"[..]), (dst_id, &b"dst"[..])]
+		{
+			let htlc = Htlcs::<Test>::get(htlc_id).expect("HTLC is migrated; qed");
+			assert_eq!(HtlcEscrow::hash_immutables(&htlc.immutables), htlc_id);
+			assert_eq!(htlc.immutables.hash_function, HashFunction::Blake2_256);
+			assert_eq!(htlc.counterpart_chain, GENERIC_CHAIN);
+			assert!(HashlockIndex::<Test>::get(hash_of_word(secret)).contains(&htlc_id));
+		}
+		assert_eq!(
+			CrossChainOrders::<Test>::get(filled_key).map(|record| record.status),
+			Some(OrderStatus::InProgress)
+		);
+		assert_eq!(
+			ExpectedHolds::<Test>::get(RESOLVER_BOB),
+			ExpectedHold {
+				swap_amounts: SWAP_AMOUNT,
+				safety_deposits: SAFETY_DEPOSIT.saturating_mul(2)
+			}
+		);
+
+		// and settle like the ones created since
+		let src_immutables = Htlcs::<Test>::get(src_id).unwrap().immutables;
+		let dst_immutables = Htlcs::<Test>::get(dst_id).unwrap().immutables;
+		System::set_block_number(101);
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			src_immutables,
+			b"filled".to_vec()
+		));
+		assert_ok!(HtlcEscrow::withdraw(
+			RuntimeOrigin::signed(RESOLVER_BOB),
+			dst_immutables,
+			b"dst".to_vec()
+		));
+		assert_ok!(HtlcEscrow::cancel_swap_intent(RuntimeOrigin::signed(ALICE), 0));
+		assert_eq!(SwapIntents::<Test>::get(filled_key).unwrap().status, IntentStatus::Completed);
+		assert_eq!(Balances::total_balance_on_hold(&ALICE), 0);
+		assert_eq!(Balances::total_balance_on_hold(&RESOLVER_BOB), 0);
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
+
+#[test]
+fn intents_are_filled_in_parts_with_the_secrets_of_a_merkle_tree() {
+	use crate::IntentFlags;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// the maker commits to a secret for each of the 4 parts, and one more
+		// for the fill that completes the intent
+		let maker = ALICE;
+		let secrets: [&[u8]; 5] = [b"part 0", b"part 1", b"part 2", b"part 3", b"complete"];
+		let (root, proofs) = merkle_tree(&secrets);
+		let intent = SwapIntent {
+			flags: IntentFlags {
+				allow_partial_fills: true,
+				allow_multiple_fills: true,
+				..Default::default()
+			},
+			..create_swap_intent(
+				root,
+				maker,
+				SRC_AMOUNT,
+				DST_AMOUNT,
+				get_evm_dst_address(ALICE + 1000),
+				10_000,
+				0,
+			)
+		};
+		assert_noop!(
+			HtlcEscrow::create_multi_fill_intent(RuntimeOrigin::signed(maker), intent.clone(), 0),
+			Error::<Test>::InvalidFillParts
+		);
+		assert_noop!(
+			HtlcEscrow::create_multi_fill_intent(
+				RuntimeOrigin::signed(maker),
+				SwapIntent { flags: Default::default(), ..intent.clone() },
+				4
+			),
+			Error::<Test>::InvalidFillParts
+		);
+		assert_ok!(HtlcEscrow::create_multi_fill_intent(RuntimeOrigin::signed(maker), intent, 4));
+		let intent_key = HtlcEscrow::intent_key(&maker, 0);
+		System::assert_has_event(Event::MultiFillIntentCreated { intent_key, parts: 4 }.into());
+		let amount_reason = HoldReason::MakerSwapIntentAmount.into();
+
+		let fill = |resolver: u64, amount: u128| {
+			HtlcEscrow::fill_intents(
+				RuntimeOrigin::signed(resolver),
+				vec![(maker, 0, amount)].try_into().unwrap(),
+				create_timelocks(System::block_number()),
+				SAFETY_DEPOSIT,
+			)
+		};
+		let filling = || match SwapIntents::<Test>::get(intent_key).unwrap().status {
+			IntentStatus::InProgress { htlc_id, .. } => Htlcs::<Test>::get(htlc_id).unwrap(),
+			status => panic!("intent not in progress: {status:?}"),
+		};
+
+		// the first fill reaches the second part, and is withdrawn with its
+		// secret only
+		assert_ok!(fill(RESOLVER_BOB, 300));
+		let htlc = filling();
+		let htlc_id = HtlcEscrow::hash_immutables(&htlc.immutables);
+		assert_eq!(FillIndexes::<Test>::get(htlc_id), Some(1));
+		System::assert_has_event(Event::IntentPartFilled { intent_key, htlc_id, index: 1 }.into());
+
+		System::set_block_number(htlc.immutables.timelocks.withdrawal_after);
+		let withdraw = |immutables, index: usize, proof: usize| {
+			HtlcEscrow::withdraw_with_proof(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				immutables,
+				secrets[index].to_vec(),
+				index as u16,
+				proofs[proof].clone(),
+			)
+		};
+		assert_noop!(
+			HtlcEscrow::withdraw(
+				RuntimeOrigin::signed(RESOLVER_BOB),
+				htlc.immutables.clone(),
+				secrets[1].to_vec()
+			),
+			Error::<Test>::InvalidSecret
+		);
+		assert_noop!(withdraw(htlc.immutables.clone(), 0, 0), Error::<Test>::InvalidFillIndex);
+		assert_noop!(withdraw(htlc.immutables.clone(), 1, 2), Error::<Test>::InvalidSecret);
+		let bob_balance = Balances::free_balance(RESOLVER_BOB);
+		assert_ok!(withdraw(htlc.immutables, 1, 1));
+		assert_eq!(Balances::free_balance(RESOLVER_BOB), bob_balance + 300 + SAFETY_DEPOSIT);
+
+		// the intent is open again for the rest
+		let stored_intent = SwapIntents::<Test>::get(intent_key).unwrap();
+		assert_eq!(stored_intent.status, IntentStatus::Active);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), SRC_AMOUNT - 300);
+		System::assert_has_event(Event::IntentReopened { intent_key, filled: 300 }.into());
+
+		// a fill that ends in the part of the previous one is rejected, as
+		// its secret is revealed already
+		assert_noop!(fill(RESOLVER_CHARLIE, 100), Error::<Test>::FillIndexReused);
+
+		// a fill that is cancelled keeps its part and returns its amount
+		assert_ok!(fill(RESOLVER_CHARLIE, 250));
+		let htlc = filling();
+		assert_eq!(
+			FillIndexes::<Test>::get(HtlcEscrow::hash_immutables(&htlc.immutables)),
+			Some(2)
+		);
+		System::set_block_number(htlc.immutables.timelocks.cancellation_after);
+		assert_ok!(HtlcEscrow::cancel(RuntimeOrigin::signed(RESOLVER_CHARLIE), htlc.immutables));
+		assert_eq!(SwapIntents::<Test>::get(intent_key).unwrap().status, IntentStatus::Active);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), SRC_AMOUNT - 550);
+		assert_noop!(fill(RESOLVER_BOB, 100), Error::<Test>::FillIndexReused);
+
+		// the fill of the rest completes the intent with the last secret
+		assert_ok!(fill(RESOLVER_BOB, SRC_AMOUNT - 550));
+		let htlc = filling();
+		System::set_block_number(htlc.immutables.timelocks.withdrawal_after);
+		assert_noop!(withdraw(htlc.immutables.clone(), 3, 3), Error::<Test>::InvalidFillIndex);
+		let htlc_id = HtlcEscrow::hash_immutables(&htlc.immutables);
+		let maker_balance = Balances::free_balance(maker);
+		assert_ok!(withdraw(htlc.immutables, 4, 4));
+		assert_eq!(SwapIntents::<Test>::get(intent_key).unwrap().status, IntentStatus::Completed);
+		assert_eq!(Balances::balance_on_hold(&amount_reason, &maker), 0);
+		assert_eq!(
+			Balances::free_balance(maker),
+			maker_balance + <Test as Config>::MakerSafetyDeposit::get()
+		);
+
+		// pruning the settled fill drops its index
+		assert_ok!(HtlcEscrow::prune_htlc(RuntimeOrigin::signed(RESOLVER_CHARLIE), htlc_id));
+		assert!(FillIndexes::<Test>::get(htlc_id).is_none());
+		assert_eq!(HtlcEscrow::do_try_state(), Ok(()));
+	});
+}
@@ -0,0 +1,82 @@
+//! Creation-time checks of the immutables of the HTLCs that need no storage,
+//! so that clients can run the checks of the extrinsics before they submit
+//! an HTLC. The checks of the chain state, e.g. of the minimum safety
+//! deposit, the chain profile or the hashlocks in use, are left to the
+//! pallet.
+
+use crate::{Immutables, Timelocks};
+use sp_core::H256;
+use sp_runtime::traits::{AtLeast32BitUnsigned, Zero};
+
+/// Why the immutables cannot make an HTLC.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum ImmutablesError {
+	/// The swap amount is zero.
+	ZeroAmount,
+	/// The hashlock is zero, which no secret hashes to.
+	ZeroHashlock,
+	/// The stages do not follow each other, or start before the deployment.
+	TimelocksOutOfOrder,
+	/// A stage starts more than `u32::MAX` blocks after the deployment, which
+	/// the `CompactTimelocks` of the stored HTLCs cannot hold.
+	TimelocksTooLong,
+	/// The HTLC is cancelled after its counterpart on the source chain.
+	CancelledAfterSource,
+}
+
+/// Ensure that the stages of the timelocks follow each other from the
+/// deployment, within `u32::MAX` blocks of it, and that the HTLC is cancelled
+/// before `src_cancellation_after`, the cancellation of its source if it has
+/// one.
+pub fn check_timelocks<BlockNumber: AtLeast32BitUnsigned + Copy>(
+	timelocks: &Timelocks<BlockNumber>,
+	src_cancellation_after: Option<&BlockNumber>,
+) -> Result<(), ImmutablesError> {
+	if !(timelocks.deployed_at <= timelocks.withdrawal_after &&
+		timelocks.withdrawal_after <= timelocks.public_withdrawal_after &&
+		timelocks.public_withdrawal_after <= timelocks.cancellation_after)
+	{
+		return Err(ImmutablesError::TimelocksOutOfOrder);
+	}
+	if timelocks.compact().is_none() {
+		return Err(ImmutablesError::TimelocksTooLong);
+	}
+	if src_cancellation_after.is_some_and(|src| timelocks.cancellation_after > *src) {
+		return Err(ImmutablesError::CancelledAfterSource);
+	}
+	Ok(())
+}
+
+/// Immutables that passed the creation-time checks.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ValidatedImmutables<AccountId, Balance, BlockNumber>(
+	Immutables<AccountId, Balance, BlockNumber>,
+);
+
+impl<AccountId, Balance: Zero, BlockNumber: AtLeast32BitUnsigned + Copy>
+	ValidatedImmutables<AccountId, Balance, BlockNumber>
+{
+	/// Check the immutables of an HTLC, of a destination HTLC if
+	/// `src_cancellation_after` is the cancellation of its source.
+	pub fn new(
+		immutables: Immutables<AccountId, Balance, BlockNumber>,
+		src_cancellation_after: Option<&BlockNumber>,
+	) -> Result<Self, ImmutablesError> {
+		if immutables.amount.is_zero() {
+			return Err(ImmutablesError::ZeroAmount);
+		}
+		if immutables.hashlock == H256::zero() {
+			return Err(ImmutablesError::ZeroHashlock);
+		}
+		check_timelocks(&immutables.timelocks, src_cancellation_after)?;
+		Ok(Self(immutables))
+	}
+
+	pub fn immutables(&self) -> &Immutables<AccountId, Balance, BlockNumber> {
+		&self.0
+	}
+
+	pub fn into_inner(self) -> Immutables<AccountId, Balance, BlockNumber> {
+		self.0
+	}
+}
@@ -0,0 +1,295 @@
+//! Resolver vault. Liquidity providers deposit the native token in exchange
+//! for shares of the vault, and the registered resolvers draw on it, up to
+//! their credit limit, to escrow the swap amounts of their destination HTLCs.
+//! A withdrawn swap amount is owed back with the vault fee, part of which goes
+//! to the insurance fund.
+
+use crate::{
+	BalanceOf, Config, DelegatedStakes, Error, Event, Htlc, Pallet, ResolverCredits, Resolvers,
+	Vault, VaultDraws, VaultShares, VaultWithdrawalQueue,
+};
+use codec::{Decode, Encode};
+use frame_support::{
+	ensure,
+	storage::with_storage_layer,
+	traits::{fungible::Mutate, tokens::Preservation, Get},
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use scale_info::TypeInfo;
+use sp_core::H256;
+use sp_io::hashing::blake2_256;
+use sp_runtime::{
+	helpers_128bit::multiply_by_rational_with_rounding,
+	traits::{
+		AccountIdConversion, TrailingZeroInput, UniqueSaturatedFrom, UniqueSaturatedInto, Zero,
+	},
+	DispatchError, DispatchResult, Perbill, Rounding, Saturating,
+};
+
+/// Accounting of the resolver vault.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+pub struct VaultState<Balance> {
+	/// Shares issued to the liquidity providers.
+	pub total_shares: Balance,
+	/// Value of the vault, including the funds drawn by the resolvers and
+	/// the fees that they owe.
+	pub total_assets: Balance,
+	/// Funds drawn by the resolvers, escrowed or owed with the fees.
+	pub outstanding: Balance,
+	/// Shares waiting in the withdrawal queue.
+	pub queued_shares: Balance,
+}
+
+/// Funds of the vault used by a registered resolver.
+#[derive(Encode, Decode, TypeInfo, Eq, PartialEq, Clone, Debug, Default)]
+pub struct ResolverCredit<Balance> {
+	/// Swap amounts escrowed in the active HTLCs of the resolver.
+	pub drawn: Balance,
+	/// Swap amounts paid out of the vault, with the fees, not repaid yet.
+	pub owed: Balance,
+	/// Part of the owed fees that goes to the insurance fund, repaid after
+	/// the debt to the vault.
+	pub insurance_owed: Balance,
+}
+
+impl<T: Config> Pallet<T> {
+	/// The account that keeps the funds of the resolver vault.
+	pub fn vault_account() -> T::AccountId {
+		T::PalletId::get().into_sub_account_truncating(b"vault")
+	}
+
+	/// The account that keeps the insurance fund, hashed like the escrow
+	/// accounts so that it cannot be truncated into the vault account.
+	pub fn insurance_account() -> T::AccountId {
+		let entropy = (T::PalletId::get(), b"insurance").using_encoded(blake2_256);
+		Decode::decode(&mut TrailingZeroInput::new(entropy.as_ref()))
+			.expect("infinite length input; no invalid inputs for type; qed")
+	}
+
+	/// `value * numerator / denominator`, rounded down.
+	pub(crate) fn mul_div(
+		value: BalanceOf<T>,
+		numerator: BalanceOf<T>,
+		denominator: BalanceOf<T>,
+	) -> BalanceOf<T> {
+		multiply_by_rational_with_rounding(
+			value.unique_saturated_into(),
+			numerator.unique_saturated_into(),
+			denominator.unique_saturated_into(),
+			Rounding::Down,
+		)
+		.map(BalanceOf::<T>::unique_saturated_from)
+		.unwrap_or_else(Zero::zero)
+	}
+
+	/// Deposit `amount` of `who` into the vault in exchange for shares of it.
+	pub(crate) fn do_vault_deposit(who: T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+		let mut vault = Vault::<T>::get();
+		let shares = if vault.total_shares.is_zero() {
+			amount
+		} else {
+			Self::mul_div(amount, vault.total_shares, vault.total_assets)
+		};
+		ensure!(!shares.is_zero(), Error::<T>::InvalidVaultAmount);
+
+		T::NativeBalance::transfer(&who, &Self::vault_account(), amount, Preservation::Preserve)?;
+		vault.total_shares.saturating_accrue(shares);
+		vault.total_assets.saturating_accrue(amount);
+		Vault::<T>::put(vault);
+		VaultShares::<T>::mutate(&who, |held| held.saturating_accrue(shares));
+
+		Self::deposit_event(Event::VaultDeposited { who, amount, shares });
+		Self::process_vault_withdrawals();
+
+		Ok(())
+	}
+
+	/// Redeem `shares` of `who`, or queue them behind the other withdrawals
+	/// if the funds that are not drawn do not cover them.
+	pub(crate) fn do_vault_withdraw(who: T::AccountId, shares: BalanceOf<T>) -> DispatchResult {
+		let held = VaultShares::<T>::get(&who);
+		ensure!(!shares.is_zero() && shares <= held, Error::<T>::InsufficientShares);
+		VaultShares::<T>::set(&who, held.saturating_sub(shares));
+
+		let mut queue = VaultWithdrawalQueue::<T>::get();
+		if queue.is_empty() && Self::vault_shares_redeemable(shares) {
+			return Self::redeem_vault_shares(&who, shares);
+		}
+
+		queue
+			.try_push((who.clone(), shares))
+			.map_err(|_| Error::<T>::VaultWithdrawalQueueFull)?;
+		VaultWithdrawalQueue::<T>::put(queue);
+		Vault::<T>::mutate(|vault| vault.queued_shares.saturating_accrue(shares));
+
+		Self::deposit_event(Event::VaultWithdrawalQueued { who, shares });
+
+		Ok(())
+	}
+
+	/// Repay up to `amount` of the debt of `who` to the vault, then its share
+	/// of the insurance fund.
+	pub(crate) fn do_repay_vault(who: T::AccountId, amount: BalanceOf<T>) -> DispatchResult {
+		let mut credit = ResolverCredits::<T>::get(&who);
+		let amount = amount.min(credit.owed);
+		ensure!(!amount.is_zero(), Error::<T>::InvalidVaultAmount);
+
+		// the vault is repaid before the share of the insurance fund
+		let to_vault = amount.min(credit.owed.saturating_sub(credit.insurance_owed));
+		let to_insurance = amount.saturating_sub(to_vault);
+		T::NativeBalance::transfer(&who, &Self::vault_account(), to_vault, Preservation::Preserve)?;
+		T::NativeBalance::transfer(
+			&who,
+			&Self::insurance_account(),
+			to_insurance,
+			Preservation::Preserve,
+		)?;
+		credit.owed.saturating_reduce(amount);
+		credit.insurance_owed.saturating_reduce(to_insurance);
+		ResolverCredits::<T>::insert(&who, credit);
+		Vault::<T>::mutate(|vault| vault.outstanding.saturating_reduce(to_vault));
+
+		Self::deposit_event(Event::VaultRepaid { resolver: who, amount });
+		if !to_insurance.is_zero() {
+			Self::deposit_event(Event::InsuranceFunded { amount: to_insurance });
+		}
+		Self::process_vault_withdrawals();
+
+		Ok(())
+	}
+
+	/// Draw the swap amount of a new HTLC from the vault for a registered
+	/// resolver, returning the vault account that funds the escrow.
+	pub(crate) fn draw_from_vault(
+		resolver: &T::AccountId,
+		htlc_id: H256,
+		amount: BalanceOf<T>,
+	) -> Result<T::AccountId, DispatchError> {
+		let credit_limit = Resolvers::<T>::get(resolver)
+			.ok_or(Error::<T>::NotResolver)?
+			.saturating_add(DelegatedStakes::<T>::get(resolver).bonded);
+
+		let mut credit = ResolverCredits::<T>::get(resolver);
+		ensure!(
+			credit.drawn.saturating_add(credit.owed).saturating_add(amount) <= credit_limit,
+			Error::<T>::CreditLimitExceeded
+		);
+
+		// the queued withdrawals are paid before the funds are drawn again
+		let mut vault = Vault::<T>::get();
+		let queued = Self::mul_div(vault.queued_shares, vault.total_assets, vault.total_shares);
+		let liquid = vault.total_assets.saturating_sub(vault.outstanding).saturating_sub(queued);
+		ensure!(amount <= liquid, Error::<T>::VaultIlliquid);
+		ensure!(
+			vault.outstanding.saturating_add(amount) <=
+				T::MaxVaultUtilization::get().mul_floor(vault.total_assets),
+			Error::<T>::VaultUtilizationExceeded
+		);
+
+		credit.drawn.saturating_accrue(amount);
+		ResolverCredits::<T>::insert(resolver, credit);
+		vault.outstanding.saturating_accrue(amount);
+		Vault::<T>::put(vault);
+		VaultDraws::<T>::insert(htlc_id, ());
+
+		Self::deposit_event(Event::VaultDrawn { htlc_id, resolver: resolver.clone(), amount });
+
+		Ok(Self::vault_account())
+	}
+
+	/// The account that escrowed the swap amount of a destination HTLC.
+	pub(crate) fn swap_funder(
+		htlc_id: H256,
+		htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+	) -> T::AccountId {
+		if VaultDraws::<T>::contains_key(htlc_id) {
+			Self::vault_account()
+		} else {
+			htlc.immutables.taker.clone()
+		}
+	}
+
+	/// Settle the draw of a destination HTLC on the vault: a refunded swap
+	/// amount is back in the vault, a withdrawn one is owed by the resolver
+	/// together with the fee, which accrues to the liquidity providers.
+	pub(crate) fn settle_vault_draw(
+		htlc_id: H256,
+		htlc: &Htlc<T::AccountId, BalanceOf<T>, BlockNumberFor<T>>,
+		refunded: bool,
+	) {
+		if VaultDraws::<T>::take(htlc_id).is_none() {
+			return;
+		}
+
+		let resolver = &htlc.immutables.taker;
+		let amount = htlc.immutables.amount;
+		let mut credit = ResolverCredits::<T>::get(resolver);
+		let mut vault = Vault::<T>::get();
+		credit.drawn.saturating_reduce(amount);
+		if refunded {
+			vault.outstanding.saturating_reduce(amount);
+		} else {
+			let fee = T::VaultFee::get().mul_ceil(amount);
+			let discount = Self::resolver_tier(resolver)
+				.map_or(Perbill::zero(), |tier| tier.fee_discount)
+				.max(Self::fee_discount(resolver));
+			let fee = fee.saturating_sub(discount.mul_floor(fee));
+			let insurance = T::InsuranceShare::get().mul_floor(fee);
+			credit.owed.saturating_accrue(amount.saturating_add(fee));
+			credit.insurance_owed.saturating_accrue(insurance);
+			vault.outstanding.saturating_accrue(fee.saturating_sub(insurance));
+			vault.total_assets.saturating_accrue(fee.saturating_sub(insurance));
+			Self::deposit_event(Event::VaultDebtIncurred {
+				htlc_id,
+				resolver: resolver.clone(),
+				amount,
+				fee,
+			});
+		}
+		ResolverCredits::<T>::insert(resolver, credit);
+		Vault::<T>::put(vault);
+
+		if refunded {
+			Self::process_vault_withdrawals();
+		}
+	}
+
+	/// Whether the vault holds enough funds that are not drawn to redeem
+	/// the shares.
+	fn vault_shares_redeemable(shares: BalanceOf<T>) -> bool {
+		let vault = Vault::<T>::get();
+		let amount = Self::mul_div(shares, vault.total_assets, vault.total_shares);
+		amount <= vault.total_assets.saturating_sub(vault.outstanding)
+	}
+
+	/// Pay out the part of the vault funds that the shares stand for.
+	fn redeem_vault_shares(who: &T::AccountId, shares: BalanceOf<T>) -> DispatchResult {
+		let mut vault = Vault::<T>::get();
+		let amount = Self::mul_div(shares, vault.total_assets, vault.total_shares);
+
+		Self::pay_from(&Self::vault_account(), who, amount)?;
+		vault.total_shares.saturating_reduce(shares);
+		vault.total_assets.saturating_reduce(amount);
+		Vault::<T>::put(vault);
+
+		Self::deposit_event(Event::VaultWithdrawn { who: who.clone(), amount, shares });
+
+		Ok(())
+	}
+
+	/// Redeem the queued withdrawals, in order, for as long as the funds
+	/// that are not drawn cover them.
+	pub(crate) fn process_vault_withdrawals() {
+		let mut queue = VaultWithdrawalQueue::<T>::get();
+		while let Some((who, shares)) = queue.first().cloned() {
+			if !Self::vault_shares_redeemable(shares) ||
+				with_storage_layer(|| Self::redeem_vault_shares(&who, shares)).is_err()
+			{
+				break;
+			}
+			queue.remove(0);
+			Vault::<T>::mutate(|vault| vault.queued_shares.saturating_reduce(shares));
+		}
+		VaultWithdrawalQueue::<T>::put(queue);
+	}
+}
@@ -0,0 +1,624 @@
+//! Weights for pallet_htlc
+//!
+//! The weights of the calls come from their benchmarks, run natively on the
+//! runtime rather than by `frame-omni-bencher` on the reference hardware:
+//! the execution time is the 10th percentile of 41 runs at each step, fitted
+//! per component, and the reads and writes are counted from their code
+//! paths. The cost of hashing a secret is below the noise of those runs, so
+//! the slope of `s` comes from 801 runs of `withdraw_all_with_secret`. The
+//! destination HTLCs, `take_intent` and `set_settlement_conversion` take the
+//! lowest of five passes of 201 runs instead.
+//! Regenerate them with `frame-omni-bencher v1 benchmark pallet` once the
+//! runtime builds to wasm.
+//!
+//! The weights of the hooks are estimated from their storage accesses and
+//! the queues they take, and the calls without a benchmark still run in
+//! `dev_mode`.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_htlc.
+pub trait WeightInfo {
+	/// Fixed work of `on_initialize`: pruning the revealed secrets and
+	/// reading and writing the auto-cancel cursor.
+	fn on_initialize_base() -> Weight;
+	/// Taking one block of the auto-cancel queue, holding `q` HTLCs.
+	fn auto_cancel_queue_block(q: u32) -> Weight;
+	/// Cancelling one overdue HTLC of the auto-cancel queue.
+	fn auto_cancel_htlc() -> Weight;
+	/// Taking the `q` stage transitions of a block.
+	fn stage_events_block(q: u32) -> Weight;
+	/// Emitting the event of one stage transition.
+	fn stage_event() -> Weight;
+	/// Fixed work of `on_idle`: reading and writing the intent expiry cursor.
+	fn on_idle_base() -> Weight;
+	/// Visiting one swap intent and expiring it if it timed out.
+	fn expire_intent() -> Weight;
+	/// Migrating one swap intent in a step of a multi-block migration.
+	fn migrate_intent() -> Weight;
+	/// Migrating one HTLC in a step of a multi-block migration.
+	fn migrate_htlc() -> Weight;
+	/// Withdrawing a source HTLC with a secret of `s` bytes, in a block
+	/// that revealed `r` other secrets, of an order with `f` other legs.
+	fn withdraw(s: u32, r: u32, f: u32) -> Weight;
+	/// Withdrawing a source HTLC publicly, like `withdraw`.
+	fn public_withdraw(s: u32, r: u32, f: u32) -> Weight;
+	/// Verifying a Merkle proof of `p` nodes with the slowest hash function.
+	fn verify_proof(p: u32) -> Weight;
+	/// Filling an intent whose hashlock `h` other HTLCs share, of an order
+	/// with `f` other legs.
+	fn create_src_htlc(h: u32, f: u32) -> Weight;
+	/// Cancelling an expired source HTLC of an order with `f` other legs.
+	fn cancel(f: u32) -> Weight;
+	/// Reserving an intent whose order has `f` other legs.
+	fn take_intent(f: u32) -> Weight;
+	/// Creating a destination HTLC whose hashlock `h` other HTLCs share, of
+	/// an order with `f` other legs.
+	fn create_dst_htlc(h: u32, f: u32) -> Weight;
+	/// `create_dst_htlc` drawing the swap amount from the vault.
+	fn create_vault_dst_htlc(h: u32, f: u32) -> Weight;
+	/// Setting the conversion of the swap amount of a destination HTLC.
+	fn set_settlement_conversion() -> Weight;
+	/// Creating a ladder of `n` intents.
+	fn create_swap_intents(n: u32) -> Weight;
+	/// Invalidating the intents of a maker, cancelling its `n` open ones.
+	fn cancel_all_intents(n: u32) -> Weight;
+	/// Filling `n` intents of orders with `f` legs each.
+	fn fill_intents(n: u32, f: u32) -> Weight;
+	/// Cancelling `n` HTLCs.
+	fn cancel_many(n: u32) -> Weight;
+	/// Withdrawing `n` HTLCs with a secret of `s` bytes.
+	fn withdraw_all_with_secret(n: u32, s: u32) -> Weight;
+}
+
+/// Weights for pallet_htlc using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: `HtlcEscrow::RevealedSecrets` (r:0 w:1)
+	/// Storage: `HtlcEscrow::AutoCancelCursor` (r:1 w:1)
+	fn on_initialize_base() -> Weight {
+		Weight::from_parts(5_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: `HtlcEscrow::HtlcsByCancellationBlock` (r:1 w:1)
+	/// The range of component `q` is `[0, 256]`.
+	fn auto_cancel_queue_block(q: u32) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(q.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:1 w:1)
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `Balances::Holds` (r:2 w:2)
+	/// Storage: `System::Account` (r:2 w:2)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:0 w:1)
+	/// Storage: `HtlcEscrow::OptimisticClaims` (r:1 w:1)
+	fn auto_cancel_htlc() -> Weight {
+		Weight::from_parts(40_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(9_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
+	}
+	/// Storage: `HtlcEscrow::StageTransitions` (r:1 w:1)
+	/// The range of component `q` is `[0, 256]`.
+	fn stage_events_block(q: u32) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(70_000, 0).saturating_mul(q.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:1 w:0)
+	fn stage_event() -> Weight {
+		Weight::from_parts(8_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+	}
+	/// Storage: `HtlcEscrow::IntentExpiryCursor` (r:1 w:1)
+	fn on_idle_base() -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `HtlcEscrow::SwapIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:0 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	fn expire_intent() -> Weight {
+		Weight::from_parts(30_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+	}
+	/// Storage: `HtlcEscrow::SwapIntents` (r:1 w:1)
+	fn migrate_intent() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:1 w:1)
+	fn migrate_htlc() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:16 w:1)
+	/// Storage: `HtlcEscrow::FillIndexes` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:2 w:1)
+	/// Storage: `HtlcEscrow::MultiFillIntents` (r:1 w:0)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:0 w:1)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::AcceptedQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SafetyDepositTopUps` (r:0 w:1)
+	/// Storage: `HtlcEscrow::DepositSponsors` (r:1 w:1)
+	/// Storage: `HtlcEscrow::WatchtowerDuties` (r:1 w:1)
+	/// Storage: `HtlcEscrow::RevealedSecrets` (r:1 w:1)
+	/// Storage: `HtlcEscrow::AccountStats` (r:2 w:2)
+	/// Storage: `HtlcEscrow::RecentVolumes` (r:2 w:2)
+	/// Storage: `HtlcEscrow::SettlementConversions` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SettlementDeliveries` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:2 w:2)
+	/// Storage: `Balances::Holds` (r:2 w:2)
+	/// Storage: `System::Account` (r:3 w:3)
+	/// The range of component `s` is `[0, 256]`.
+	/// The range of component `r` is `[0, 127]`.
+	/// The range of component `f` is `[0, 15]`.
+	fn withdraw(s: u32, r: u32, f: u32) -> Weight {
+		Weight::from_parts(91_800_000, 0)
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(37_000, 0).saturating_mul(r.into()))
+			.saturating_add(Weight::from_parts(3_808_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(24_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(23_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:16 w:1)
+	/// Storage: `HtlcEscrow::FillIndexes` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Blocked` (r:3 w:0)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:2 w:1)
+	/// Storage: `HtlcEscrow::MultiFillIntents` (r:1 w:0)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:0 w:1)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::AcceptedQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SafetyDepositTopUps` (r:0 w:1)
+	/// Storage: `HtlcEscrow::DepositSponsors` (r:1 w:1)
+	/// Storage: `HtlcEscrow::WatchtowerDuties` (r:1 w:1)
+	/// Storage: `HtlcEscrow::RevealedSecrets` (r:1 w:1)
+	/// Storage: `HtlcEscrow::AccountStats` (r:2 w:2)
+	/// Storage: `HtlcEscrow::RecentVolumes` (r:2 w:2)
+	/// Storage: `HtlcEscrow::SettlementConversions` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SettlementDeliveries` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:2 w:2)
+	/// Storage: `Balances::Holds` (r:2 w:2)
+	/// Storage: `System::Account` (r:3 w:3)
+	/// The range of component `s` is `[0, 256]`.
+	/// The range of component `r` is `[0, 127]`.
+	/// The range of component `f` is `[0, 15]`.
+	fn public_withdraw(s: u32, r: u32, f: u32) -> Weight {
+		Weight::from_parts(106_100_000, 0)
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(39_000, 0).saturating_mul(r.into()))
+			.saturating_add(Weight::from_parts(3_748_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(25_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(23_u64))
+	}
+	/// The range of component `p` is `[0, 16]`.
+	fn verify_proof(p: u32) -> Weight {
+		Weight::from_parts(40_000, 0)
+			.saturating_add(Weight::from_parts(140_000, 0).saturating_mul(p.into()))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::Resolvers` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ChainProfiles` (r:1 w:0)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:2 w:1)
+	/// Storage: `HtlcEscrow::MultiFillIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::FillIndexes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::AcceptedQuotes` (r:1 w:1)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::ArchivedHtlcs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Htlcs` (r:31 w:1)
+	/// Storage: `HtlcEscrow::HashlockIndex` (r:1 w:1)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:1 w:0)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ResolverIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::HtlcsByCancellationBlock` (r:1 w:1)
+	/// Storage: `HtlcEscrow::StageTransitions` (r:3 w:3)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:1 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `h` is `[0, 15]`.
+	/// The range of component `f` is `[0, 15]`.
+	fn create_src_htlc(h: u32, f: u32) -> Weight {
+		Weight::from_parts(48_000_000, 0)
+			.saturating_add(Weight::from_parts(924_000, 0).saturating_mul(h.into()))
+			.saturating_add(Weight::from_parts(1_909_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(21_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(h.into())))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(16_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:16 w:1)
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::OptimisticClaims` (r:1 w:1)
+	/// Storage: `HtlcEscrow::CounterpartEscrows` (r:1 w:0)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:2 w:1)
+	/// Storage: `HtlcEscrow::MultiFillIntents` (r:1 w:0)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:0 w:1)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::AcceptedQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SafetyDepositTopUps` (r:0 w:1)
+	/// Storage: `HtlcEscrow::DepositSponsors` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ResolverCooldowns` (r:0 w:1)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:2 w:2)
+	/// Storage: `Balances::Holds` (r:2 w:2)
+	/// Storage: `System::Account` (r:3 w:3)
+	/// The range of component `f` is `[0, 15]`.
+	fn cancel(f: u32) -> Weight {
+		Weight::from_parts(81_111_000, 0)
+			.saturating_add(Weight::from_parts(3_711_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(17_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(17_u64))
+	}
+	/// Storage: `HtlcEscrow::SwapIntents` (r:2 w:1)
+	/// Storage: `HtlcEscrow::MakerEpochs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::ResolverCooldowns` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ResolverIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:0 w:1)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::Htlcs` (r:15 w:0)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:1 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `f` is `[0, 15]`.
+	fn take_intent(f: u32) -> Weight {
+		Weight::from_parts(25_600_000, 0)
+			.saturating_add(Weight::from_parts(1_770_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(11_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(7_u64))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::ChainProfiles` (r:1 w:0)
+	/// Storage: `HtlcEscrow::HashlockIndex` (r:1 w:1)
+	/// Storage: `HtlcEscrow::Htlcs` (r:31 w:1)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ArchivedHtlcs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::HtlcCommitments` (r:1 w:0)
+	/// Storage: `HtlcEscrow::PendingDstHtlcs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Sponsors` (r:1 w:0)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:1 w:0)
+	/// Storage: `HtlcEscrow::HtlcsByCancellationBlock` (r:1 w:1)
+	/// Storage: `HtlcEscrow::StageTransitions` (r:3 w:3)
+	/// Storage: `HtlcEscrow::SettlementWarnings` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:1 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `h` is `[0, 15]`.
+	/// The range of component `f` is `[0, 15]`.
+	fn create_dst_htlc(h: u32, f: u32) -> Weight {
+		Weight::from_parts(54_100_000, 0)
+			.saturating_add(Weight::from_parts(770_000, 0).saturating_mul(h.into()))
+			.saturating_add(Weight::from_parts(3_100_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(20_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(h.into())))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(11_u64))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::ChainProfiles` (r:1 w:0)
+	/// Storage: `HtlcEscrow::HashlockIndex` (r:1 w:1)
+	/// Storage: `HtlcEscrow::Htlcs` (r:31 w:1)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ArchivedHtlcs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::HtlcCommitments` (r:1 w:0)
+	/// Storage: `HtlcEscrow::PendingDstHtlcs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Sponsors` (r:1 w:0)
+	/// Storage: `HtlcEscrow::Resolvers` (r:1 w:0)
+	/// Storage: `HtlcEscrow::DelegatedStakes` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ResolverCredits` (r:1 w:1)
+	/// Storage: `HtlcEscrow::Vault` (r:1 w:1)
+	/// Storage: `HtlcEscrow::VaultDraws` (r:0 w:1)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:1 w:0)
+	/// Storage: `HtlcEscrow::HtlcsByCancellationBlock` (r:1 w:1)
+	/// Storage: `HtlcEscrow::StageTransitions` (r:3 w:3)
+	/// Storage: `HtlcEscrow::SettlementWarnings` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:2 w:2)
+	/// Storage: `Balances::Holds` (r:2 w:2)
+	/// Storage: `System::Account` (r:2 w:2)
+	/// The range of component `h` is `[0, 15]`.
+	/// The range of component `f` is `[0, 15]`.
+	fn create_vault_dst_htlc(h: u32, f: u32) -> Weight {
+		Weight::from_parts(62_500_000, 0)
+			.saturating_add(Weight::from_parts(770_000, 0).saturating_mul(h.into()))
+			.saturating_add(Weight::from_parts(3_100_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(27_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(h.into())))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(17_u64))
+	}
+	/// Storage: `HtlcEscrow::Htlcs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::SettlementDeliveries` (r:1 w:0)
+	/// Storage: `HtlcEscrow::SettlementConversions` (r:0 w:1)
+	fn set_settlement_conversion() -> Weight {
+		Weight::from_parts(2_470_000, 0)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ChainProfiles` (r:1 w:0)
+	/// Storage: `HtlcEscrow::MakerEpochs` (r:1 w:0)
+	/// Storage: `HtlcEscrow::MakerIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:16 w:16)
+	/// Storage: `HtlcEscrow::HashlockIndex` (r:16 w:0)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:16 w:16)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:16 w:16)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `n` is `[1, 16]`.
+	fn create_swap_intents(n: u32) -> Weight {
+		Weight::from_parts(29_467_000, 0)
+			.saturating_add(Weight::from_parts(13_676_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(6_u64))
+			.saturating_add(T::DbWeight::get().reads((4_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(T::DbWeight::get().writes((3_u64).saturating_mul(n.into())))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:1 w:0)
+	/// Storage: `HtlcEscrow::MakerEpochs` (r:1 w:1)
+	/// Storage: `HtlcEscrow::MakerIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:64 w:64)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:64 w:64)
+	/// Storage: `HtlcEscrow::MultiFillIntents` (r:64 w:0)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:0 w:64)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:0 w:64)
+	/// Storage: `HtlcEscrow::AcceptedQuotes` (r:0 w:64)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `n` is `[0, 64]`.
+	fn cancel_all_intents(n: u32) -> Weight {
+		Weight::from_parts(5_097_000, 0)
+			.saturating_add(Weight::from_parts(35_528_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((3_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(4_u64))
+			.saturating_add(T::DbWeight::get().writes((5_u64).saturating_mul(n.into())))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:17 w:0)
+	/// Storage: `HtlcEscrow::Resolvers` (r:1 w:0)
+	/// Storage: `HtlcEscrow::ChainProfiles` (r:1 w:0)
+	/// Storage: `HtlcEscrow::SwapIntents` (r:16 w:16)
+	/// Storage: `HtlcEscrow::AcceptedQuotes` (r:16 w:0)
+	/// Storage: `HtlcEscrow::IntentQuotes` (r:16 w:0)
+	/// Storage: `HtlcEscrow::ArchivedHtlcs` (r:16 w:0)
+	/// Storage: `HtlcEscrow::Htlcs` (r:16 w:16)
+	/// Storage: `HtlcEscrow::HashlockIndex` (r:16 w:16)
+	/// Storage: `HtlcEscrow::IntentHashlocks` (r:16 w:0)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:16 w:16)
+	/// Storage: `HtlcEscrow::ResolverIntents` (r:1 w:1)
+	/// Storage: `HtlcEscrow::HtlcsByCancellationBlock` (r:1 w:1)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:1 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `n` is `[1, 16]`.
+	/// The range of component `f` is `[0, 15]`.
+	fn fill_intents(n: u32, f: u32) -> Weight {
+		Weight::from_parts(4_787_000, 0)
+			.saturating_add(Weight::from_parts(42_436_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(1_968_000, 0).saturating_mul(f.into()))
+			.saturating_add(T::DbWeight::get().reads(8_u64))
+			.saturating_add(T::DbWeight::get().reads((9_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(5_u64))
+			.saturating_add(T::DbWeight::get().writes((4_u64).saturating_mul(n.into())))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::Htlcs` (r:32 w:32)
+	/// Storage: `HtlcEscrow::OptimisticClaims` (r:32 w:0)
+	/// Storage: `HtlcEscrow::CounterpartEscrows` (r:32 w:0)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:32 w:32)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:1 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// The range of component `n` is `[1, 32]`.
+	fn cancel_many(n: u32) -> Weight {
+		Weight::from_parts(6_987_000, 0)
+			.saturating_add(Weight::from_parts(46_151_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(5_u64))
+			.saturating_add(T::DbWeight::get().reads((4_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(n.into())))
+	}
+	/// Storage: `HtlcEscrow::Blocked` (r:2 w:0)
+	/// Storage: `HtlcEscrow::Htlcs` (r:16 w:16)
+	/// Storage: `HtlcEscrow::OptimisticClaims` (r:16 w:0)
+	/// Storage: `HtlcEscrow::CrossChainOrders` (r:1 w:1)
+	/// Storage: `HtlcEscrow::RevealedSecrets` (r:1 w:1)
+	/// Storage: `HtlcEscrow::AccountStats` (r:2 w:2)
+	/// Storage: `HtlcEscrow::RecentVolumes` (r:2 w:2)
+	/// Storage: `HtlcEscrow::ExpectedHolds` (r:1 w:1)
+	/// Storage: `Balances::Holds` (r:1 w:1)
+	/// Storage: `System::Account` (r:2 w:2)
+	/// The range of component `n` is `[1, 16]`.
+	/// The range of component `s` is `[0, 256]`.
+	fn withdraw_all_with_secret(n: u32, s: u32) -> Weight {
+		Weight::from_parts(0, 0)
+			.saturating_add(Weight::from_parts(72_369_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(12_u64))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(10_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+}
+
+// For backwards compatibility and tests.
+impl WeightInfo for () {
+	fn on_initialize_base() -> Weight {
+		Weight::from_parts(5_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	fn auto_cancel_queue_block(q: u32) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(60_000, 0).saturating_mul(q.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn auto_cancel_htlc() -> Weight {
+		Weight::from_parts(40_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(9_u64))
+			.saturating_add(RocksDbWeight::get().writes(8_u64))
+	}
+	fn stage_events_block(q: u32) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(70_000, 0).saturating_mul(q.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn stage_event() -> Weight {
+		Weight::from_parts(8_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	fn on_idle_base() -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn expire_intent() -> Weight {
+		Weight::from_parts(30_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(3_u64))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+	}
+	fn migrate_intent() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn migrate_htlc() -> Weight {
+		Weight::from_parts(10_000_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn withdraw(s: u32, r: u32, f: u32) -> Weight {
+		Weight::from_parts(91_800_000, 0)
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(37_000, 0).saturating_mul(r.into()))
+			.saturating_add(Weight::from_parts(3_808_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(24_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(23_u64))
+	}
+	fn public_withdraw(s: u32, r: u32, f: u32) -> Weight {
+		Weight::from_parts(106_100_000, 0)
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(39_000, 0).saturating_mul(r.into()))
+			.saturating_add(Weight::from_parts(3_748_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(25_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(23_u64))
+	}
+	fn verify_proof(p: u32) -> Weight {
+		Weight::from_parts(40_000, 0)
+			.saturating_add(Weight::from_parts(140_000, 0).saturating_mul(p.into()))
+	}
+	fn create_src_htlc(h: u32, f: u32) -> Weight {
+		Weight::from_parts(48_000_000, 0)
+			.saturating_add(Weight::from_parts(924_000, 0).saturating_mul(h.into()))
+			.saturating_add(Weight::from_parts(1_909_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(21_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(h.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(16_u64))
+	}
+	fn cancel(f: u32) -> Weight {
+		Weight::from_parts(81_111_000, 0)
+			.saturating_add(Weight::from_parts(3_711_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(17_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(17_u64))
+	}
+	fn take_intent(f: u32) -> Weight {
+		Weight::from_parts(25_600_000, 0)
+			.saturating_add(Weight::from_parts(1_770_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(11_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(7_u64))
+	}
+	fn create_dst_htlc(h: u32, f: u32) -> Weight {
+		Weight::from_parts(54_100_000, 0)
+			.saturating_add(Weight::from_parts(770_000, 0).saturating_mul(h.into()))
+			.saturating_add(Weight::from_parts(3_100_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(20_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(h.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(11_u64))
+	}
+	fn create_vault_dst_htlc(h: u32, f: u32) -> Weight {
+		Weight::from_parts(62_500_000, 0)
+			.saturating_add(Weight::from_parts(770_000, 0).saturating_mul(h.into()))
+			.saturating_add(Weight::from_parts(3_100_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(27_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(h.into())))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(f.into())))
+			.saturating_add(RocksDbWeight::get().writes(17_u64))
+	}
+	fn set_settlement_conversion() -> Weight {
+		Weight::from_parts(2_470_000, 0)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	fn create_swap_intents(n: u32) -> Weight {
+		Weight::from_parts(29_467_000, 0)
+			.saturating_add(Weight::from_parts(13_676_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(6_u64))
+			.saturating_add(RocksDbWeight::get().reads((4_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().writes((3_u64).saturating_mul(n.into())))
+	}
+	fn cancel_all_intents(n: u32) -> Weight {
+		Weight::from_parts(5_097_000, 0)
+			.saturating_add(Weight::from_parts(35_528_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((3_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(4_u64))
+			.saturating_add(RocksDbWeight::get().writes((5_u64).saturating_mul(n.into())))
+	}
+	fn fill_intents(n: u32, f: u32) -> Weight {
+		Weight::from_parts(4_787_000, 0)
+			.saturating_add(Weight::from_parts(42_436_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(1_968_000, 0).saturating_mul(f.into()))
+			.saturating_add(RocksDbWeight::get().reads(8_u64))
+			.saturating_add(RocksDbWeight::get().reads((9_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(5_u64))
+			.saturating_add(RocksDbWeight::get().writes((4_u64).saturating_mul(n.into())))
+	}
+	fn cancel_many(n: u32) -> Weight {
+		Weight::from_parts(6_987_000, 0)
+			.saturating_add(Weight::from_parts(46_151_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().reads(5_u64))
+			.saturating_add(RocksDbWeight::get().reads((4_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+			.saturating_add(RocksDbWeight::get().writes((2_u64).saturating_mul(n.into())))
+	}
+	fn withdraw_all_with_secret(n: u32, s: u32) -> Weight {
+		Weight::from_parts(0, 0)
+			.saturating_add(Weight::from_parts(72_369_000, 0).saturating_mul(n.into()))
+			.saturating_add(Weight::from_parts(3_000, 0).saturating_mul(s.into()))
+			.saturating_add(RocksDbWeight::get().reads(12_u64))
+			.saturating_add(RocksDbWeight::get().reads((2_u64).saturating_mul(n.into())))
+			.saturating_add(RocksDbWeight::get().writes(10_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+}
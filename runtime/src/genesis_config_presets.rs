@@ -0,0 +1,97 @@
+//! Genesis presets of the runtime, from which the chain specs of the
+//! development and local testnet chains are built.
+
+use crate::{AccountId, BalancesConfig, HtlcConfig, RuntimeGenesisConfig, SudoConfig};
+use alloc::{vec, vec::Vec};
+use frame::{
+	deps::frame_support::build_struct_json_patch,
+	runtime::prelude::{
+		AccountKeyring, PresetId, DEV_RUNTIME_PRESET, LOCAL_TESTNET_RUNTIME_PRESET,
+	},
+};
+use pallet_htlc::{AddressFormat, ChainId, ChainProfile, HashFunction};
+
+/// Balance of the endowed accounts.
+const ENDOWMENT: u128 = 1 << 60;
+
+/// Counterpart chain of the EVM deployments of the 1inch escrows.
+pub const EVM_CHAIN: ChainId = 1;
+
+/// Counterpart chain of the Bitcoin HTLC scripts.
+pub const BITCOIN_CHAIN: ChainId = 2;
+
+/// Profiles of the counterpart chains, with margins of about an hour at 6
+/// second blocks for the confirmations on the other chain.
+fn chain_profiles() -> Vec<(ChainId, ChainProfile<u32>)> {
+	vec![
+		(
+			EVM_CHAIN,
+			ChainProfile {
+				hash_function: HashFunction::Blake2_256,
+				address_format: AddressFormat::Evm,
+				min_timelock_margin: 600,
+				decimals: 18,
+			},
+		),
+		(
+			BITCOIN_CHAIN,
+			ChainProfile {
+				hash_function: HashFunction::Sha256,
+				address_format: AddressFormat::Bitcoin,
+				min_timelock_margin: 1_200,
+				decimals: 8,
+			},
+		),
+	]
+}
+
+fn testnet_genesis(endowed_accounts: Vec<AccountId>, root: AccountId) -> serde_json::Value {
+	build_struct_json_patch!(RuntimeGenesisConfig {
+		balances: BalancesConfig {
+			balances: endowed_accounts.into_iter().map(|account| (account, ENDOWMENT)).collect(),
+		},
+		sudo: SudoConfig { key: Some(root) },
+		htlc: HtlcConfig { chain_profiles: chain_profiles() },
+	})
+}
+
+/// The accounts of the development keyring, with Alice as the root.
+fn development_config_genesis() -> serde_json::Value {
+	testnet_genesis(
+		AccountKeyring::iter().map(|keyring| keyring.to_account_id()).collect(),
+		AccountKeyring::Alice.to_account_id(),
+	)
+}
+
+/// Alice and Bob as the makers, Charlie and Dave as the resolvers, with
+/// Alice as the root.
+fn local_testnet_genesis() -> serde_json::Value {
+	testnet_genesis(
+		vec![
+			AccountKeyring::Alice.to_account_id(),
+			AccountKeyring::Bob.to_account_id(),
+			AccountKeyring::Charlie.to_account_id(),
+			AccountKeyring::Dave.to_account_id(),
+		],
+		AccountKeyring::Alice.to_account_id(),
+	)
+}
+
+/// The genesis patch of the preset `id`, if it exists.
+pub fn get_preset(id: &PresetId) -> Option<Vec<u8>> {
+	let patch = match id.as_ref() {
+		DEV_RUNTIME_PRESET => development_config_genesis(),
+		LOCAL_TESTNET_RUNTIME_PRESET => local_testnet_genesis(),
+		_ => return None,
+	};
+	Some(
+		serde_json::to_string(&patch)
+			.expect("serialization to json is expected to work. qed.")
+			.into_bytes(),
+	)
+}
+
+/// The names of the presets.
+pub fn preset_names() -> Vec<PresetId> {
+	vec![PresetId::from(DEV_RUNTIME_PRESET), PresetId::from(LOCAL_TESTNET_RUNTIME_PRESET)]
+}
@@ -2,15 +2,20 @@
 
 extern crate alloc;
 
+pub mod genesis_config_presets;
+
 use alloc::{vec, vec::Vec};
 use frame::{
+	arithmetic::Perbill,
 	deps::{
 		codec::Compact,
 		frame_support::{
 			genesis_builder_helper::{build_state, get_preset},
+			migrations::FreezeChainOnFailedMigration,
 			runtime,
-			traits::AsEnsureOriginWithArg,
+			traits::{AsEnsureOriginWithArg, Everything, NeverEnsureOrigin},
 			weights::FixedFee,
+			PalletId,
 		},
 	},
 	prelude::*,
@@ -76,6 +81,10 @@ mod runtime {
 	#[runtime::pallet_index(5)]
 	pub type Htlc = pallet_htlc;
 
+	/// Runs the multi-block migrations of the pallets.
+	#[runtime::pallet_index(6)]
+	pub type MultiBlockMigrations = pallet_migrations;
+
 	#[runtime::pallet_index(99)]
 	pub type Timestamp = pallet_timestamp;
 }
@@ -99,6 +108,8 @@ impl frame_system::Config for Runtime {
 
 	// This is needed to make pjs-apps work and send txs
 	type AccountId = frame::runtime::types_common::AccountId;
+
+	type MultiBlockMigrator = MultiBlockMigrations;
 }
 
 // Implements the types required for the balances pallet.
@@ -164,13 +175,115 @@ impl pallet_assets::Config for Runtime {
 	type BenchmarkHelper = ();
 }
 
+parameter_types! {
+	pub const PublicCancellationReward: Perbill = Perbill::one();
+	pub const HtlcWatchtowerShare: Perbill = Perbill::from_percent(20);
+	pub const HtlcVaultFee: Perbill = Perbill::from_parts(3_000_000);
+	pub const HtlcInsuranceShare: Perbill = Perbill::from_percent(10);
+	pub const HtlcDelegationSlash: Perbill = Perbill::from_percent(10);
+	pub const HtlcSecretRevealPriority: TransactionPriority = TransactionPriority::MAX / 2;
+	pub const HtlcMaxVaultUtilization: Perbill = Perbill::from_percent(90);
+	pub const HtlcEscrowMode: pallet_htlc::EscrowMode = pallet_htlc::EscrowMode::Holds;
+	pub const HtlcPalletId: PalletId = PalletId(*b"py/htlc_");
+}
+
 /// Configure the pallet-htlc in pallets/htlc.
 impl pallet_htlc::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
 	type NativeBalance = Balances;
+	type NativeDecimals = ConstU8<12>;
 	type RuntimeCall = RuntimeCall;
 	type RuntimeHoldReason = RuntimeHoldReason;
+	type WeightInfo = pallet_htlc::weights::SubstrateWeight<Runtime>;
+	type EscrowMode = HtlcEscrowMode;
+	type CommitmentOnly = ConstBool<false>;
+	type PalletId = HtlcPalletId;
+	// burnt, like the transaction fees
+	type ForfeitHandler =
+		pallet_htlc::forfeit::SplitForfeits<(), pallet_htlc::forfeit::ForfeitToMaker<Balances>>;
 	type MinSafetyDeposit = ConstU128<50>;
+	type MakerSafetyDeposit = ConstU128<0>;
+	type IntentCancellationDelay = ConstU32<1_200>;
+	type ReservationBond = ConstU128<10>;
+	type GoodTilCancelDeposit = ConstU128<100>;
+	type MaxIntentsPerMaker = ConstU32<64>;
+	type MaxIntentsPerResolver = ConstU32<32>;
+	type FillDeadline = ConstU32<600>;
+	type ResolverCooldown = ConstU32<1_200>;
+	// a day at 6 second blocks
+	type UndelegationDelay = ConstU32<14_400>;
+	type DelegationSlash = HtlcDelegationSlash;
+	type MaxDelegators = ConstU32<64>;
+	// 30 days at 6 second blocks
+	type VolumeWindow = ConstU32<432_000>;
+	type DstActivationPeriod = ConstU32<100>;
+	// no light client proofs of the other chains yet
+	type SettlementVerifier = ();
+	type OptimisticClaimBond = ConstU128<100>;
+	type ChallengeWindow = ConstU32<300>;
+	type SecretRevealPriority = HtlcSecretRevealPriority;
+	type ReceiptIssuer = ();
+	type ConversionAsset = u32;
+	type SettlementConverter = ();
+	type RemoteBeneficiary = AccountId;
+	type SettlementDelivery = ();
+	type PublicCancellationDelay = ConstU32<600>;
+	type PublicCancellationReward = PublicCancellationReward;
+	type WatchtowerShare = HtlcWatchtowerShare;
+	type VaultFee = HtlcVaultFee;
+	type InsuranceShare = HtlcInsuranceShare;
+	type MaxVaultUtilization = HtlcMaxVaultUtilization;
+	type MaxQueuedVaultWithdrawals = ConstU32<64>;
+	type EmergencySweepPeriod = ConstU32<5_256_000>;
+	type EscheatmentPeriod = ConstU32<5_256_000>;
+	// burnt, like the forfeits
+	type UnclaimedHandler = ();
+	type MaxAutoCancelsPerBlock = ConstU32<32>;
+	type MaxCancellationsPerBlock = ConstU32<256>;
+	type StageEvents = ConstBool<true>;
+	// an hour at 6 second blocks
+	type SettlementWarningPeriod = ConstU32<600>;
+	type MaxTimelockHorizon = ConstU32<432_000>;
+	type UnixTime = Timestamp;
+	type ExpectedBlockTime = ConstU64<6_000>;
+	// ten blocks
+	type TimestampSafetyMargin = ConstU64<60>;
+	type ComplianceFilter = Everything;
+	type GovernanceOrigin = EnsureRoot<AccountId>;
+	// no XCM executor yet: remote parties arrive once the runtime configures one
+	type RemoteOrigin = NeverEnsureOrigin<pallet_htlc::Location>;
+	type LocationToAccount = ();
+	type MaxRevealedSecretsPerBlock = ConstU32<128>;
+	type RevealedSecretsRetention = ConstU32<14_400>;
+	type MaxHtlcsPerHashlock = ConstU32<16>;
+}
+
+parameter_types! {
+	// leave a fifth of the block to the extrinsics while migrating
+	pub MigrationsMaxServiceWeight: Weight = Perbill::from_percent(80) *
+		<<Runtime as frame_system::Config>::BlockWeights as Get<
+			frame_system::limits::BlockWeights,
+		>>::get()
+		.max_block;
+}
+
+/// Configure the multi-block migrations.
+impl pallet_migrations::Config for Runtime {
+	type RuntimeEvent = RuntimeEvent;
+	// the HTLCs of the first version were with the EVM chains
+	#[cfg(not(feature = "runtime-benchmarks"))]
+	type Migrations = pallet_htlc::migrations::LazyMigrations<
+		Runtime,
+		ConstU32<{ genesis_config_presets::EVM_CHAIN }>,
+	>;
+	#[cfg(feature = "runtime-benchmarks")]
+	type Migrations = pallet_migrations::mock_helpers::MockedMigrations;
+	type CursorMaxLen = ConstU32<65_536>;
+	type IdentifierMaxLen = ConstU32<256>;
+	type MigrationStatusHandler = ();
+	type FailedMigrationHandler = FreezeChainOnFailedMigration;
+	type MaxServiceWeight = MigrationsMaxServiceWeight;
+	type WeightInfo = pallet_migrations::weights::SubstrateWeight<Runtime>;
 }
 
 parameter_types! {
@@ -212,6 +325,7 @@ type SignedExtra = (
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
 	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_htlc::extension::CheckSettlements<Runtime>,
 );
 
 // Many of the types in this runtime are being pulled in from `derive_impl`. We use the almighty
@@ -234,6 +348,7 @@ mod benches {
 		[pallet_balances, Balances]
 		[pallet_sudo, Sudo]
 		[pallet_htlc, Htlc]
+		[pallet_migrations, MultiBlockMigrations]
 		[pallet_timestamp, Timestamp]
 	);
 }
@@ -412,7 +527,10 @@ impl_runtime_apis! {
 			// have a backtrace here. If any of the pre/post migration checks fail, we shall stop
 			// right here and right now.
 			let weight = RuntimeExecutive::try_runtime_upgrade(checks).unwrap();
-			(weight, <Runtime as frame_system::Config>::BlockWeights::get().max_block)
+			let block_weights = <<Runtime as frame_system::Config>::BlockWeights as Get<
+				frame_system::limits::BlockWeights,
+			>>::get();
+			(weight, block_weights.max_block)
 		}
 
 		fn execute_block(
@@ -427,17 +545,68 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_htlc_runtime_api::HtlcApi<Block, AccountId, Balance, BlockNumberFor<Runtime>, u32> for Runtime {
+		fn metrics() -> pallet_htlc::HtlcMetrics<Balance, BlockNumberFor<Runtime>> {
+			Htlc::metrics()
+		}
+
+		fn can_create_htlc(
+			who: AccountId,
+			amount: Balance,
+			safety_deposit: Balance,
+		) -> Result<(), pallet_htlc::CannotCreateHtlc<Balance>> {
+			Htlc::can_create_htlc(&who, amount, safety_deposit)
+		}
+
+		fn validate_withdraw(
+			htlc_id: H256,
+			who: AccountId,
+			secret: Vec<u8>,
+		) -> DispatchResult {
+			Htlc::validate_withdraw(htlc_id, &who, &secret)
+		}
+
+		fn validate_cancel(htlc_id: H256, who: AccountId) -> DispatchResult {
+			Htlc::validate_cancel(htlc_id, &who)
+		}
+
+		fn htlc_stage(htlc_id: H256) -> Option<pallet_htlc::Stage> {
+			Htlc::htlc_stage(htlc_id)
+		}
+
+		fn active_intents(
+			start_key: Option<H256>,
+			limit: u32,
+		) -> Vec<(H256, pallet_htlc::SwapIntent<AccountId, Balance, BlockNumberFor<Runtime>>)> {
+			Htlc::active_intents(start_key, limit)
+		}
+
+		fn estimate_swap_costs(
+			amount: Balance,
+			asset: Option<u32>,
+			role: pallet_htlc::SwapRole,
+		) -> pallet_htlc::SwapCosts<Balance> {
+			Htlc::estimate_swap_costs(
+				amount,
+				asset,
+				role,
+				TransactionPayment::weight_to_fee,
+				TransactionPayment::length_to_fee,
+			)
+		}
+	}
+
 	impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
 		fn build_state(config: Vec<u8>) -> sp_genesis_builder::Result {
 			build_state::<RuntimeGenesisConfig>(config)
 		}
 
 		fn get_preset(id: &Option<sp_genesis_builder::PresetId>) -> Option<Vec<u8>> {
-			get_preset::<RuntimeGenesisConfig>(id, |_| None)
+			get_preset::<RuntimeGenesisConfig>(id, genesis_config_presets::get_preset)
 		}
 
 		fn preset_names() -> Vec<sp_genesis_builder::PresetId> {
-			vec![]
+			genesis_config_presets::preset_names()
 		}
 	}
 }